@@ -0,0 +1,205 @@
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                                                                                            │ *
+ * │ This Source Code Form is subject to the terms of the Mozilla Public                        │ *
+ * │ License, v. 2.0. If a copy of the MPL was not distributed with this                        │ *
+ * │ file, You can obtain one at http://mozilla.org/MPL/2.0/.                                   │ *
+ * │                                                                                            │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                                       Documentation                                        │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+//! Attribute macros for `smol-timeout`, re-exported by it with the `macros` feature.
+
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                                          Imports                                           │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+extern crate proc_macro;
+
+use proc_macro2::TokenStream;
+use quote::{quote, quote_spanned};
+use syn::spanned::Spanned;
+use syn::{Expr, ExprLit, ItemFn, Lit, LitStr, ReturnType, Type};
+
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                                    attribute #[timeout]                                    │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+/// Wraps the body of an `async fn` in a timeout panicking when it expires.
+///
+/// See `smol_timeout::timeout`.
+#[proc_macro_attribute]
+pub fn timeout(
+    args: proc_macro::TokenStream,
+    item: proc_macro::TokenStream,
+) -> proc_macro::TokenStream {
+    expand_timeout(args.into(), item.into())
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
+
+fn expand_timeout(args: TokenStream, item: TokenStream) -> syn::Result<TokenStream> {
+    let after = after(syn::parse2(args)?)?;
+
+    let mut item: ItemFn = syn::parse2(item)?;
+    if item.sig.asyncness.is_none() {
+        return Err(syn::Error::new(
+            item.sig.fn_token.span(),
+            "the `#[timeout]` attribute can only be applied to `async fn`s",
+        ));
+    }
+
+    let name = item.sig.ident.to_string();
+    let body = body(&item);
+    let timeout = quote_spanned! { after.span()=>
+        ::smol_timeout::panic::PanicTimeoutExt::timeout_or_panic(#body, #after, #name)
+    };
+
+    // Plain `#[test]`s can't be `async`, so the body is run with `block_on`. Otherwise, the
+    // function is left `async` for another attribute (e.g. `#[smol_potat::test]`) to run it.
+    let block = if item.attrs.iter().any(|attr| attr.path().is_ident("test")) {
+        item.sig.asyncness = None;
+        quote! { ::smol_timeout::__block_on(#timeout) }
+    } else {
+        quote! { #timeout.await }
+    };
+
+    let ItemFn {
+        attrs, vis, sig, ..
+    } = item;
+
+    Ok(quote! {
+        #(#attrs)*
+        #vis #sig {
+            #block
+        }
+    })
+}
+
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                                  attribute #[with_timeout]                                 │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+/// Wraps the body of an `async fn` in a timeout, returning `Err(Elapsed)` when it expires.
+///
+/// See `smol_timeout::with_timeout`.
+#[proc_macro_attribute]
+pub fn with_timeout(
+    args: proc_macro::TokenStream,
+    item: proc_macro::TokenStream,
+) -> proc_macro::TokenStream {
+    expand_with_timeout(args.into(), item.into())
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
+
+fn expand_with_timeout(args: TokenStream, item: TokenStream) -> syn::Result<TokenStream> {
+    let after = after(syn::parse2(args)?)?;
+
+    let mut item: ItemFn = syn::parse2(item)?;
+    if item.sig.asyncness.is_none() {
+        return Err(syn::Error::new(
+            item.sig.fn_token.span(),
+            "the `#[with_timeout]` attribute can only be applied to `async fn`s",
+        ));
+    }
+
+    let body = body(&item);
+    item.sig.output = match &item.sig.output {
+        ReturnType::Default => syn::parse_quote! {
+            -> ::core::result::Result<(), ::smol_timeout::Elapsed>
+        },
+        ReturnType::Type(arrow, ty) => syn::parse_quote! {
+            #arrow ::core::result::Result<#ty, ::smol_timeout::Elapsed>
+        },
+    };
+
+    let ItemFn {
+        attrs, vis, sig, ..
+    } = item;
+
+    // The duration is evaluated before the body starts, so that it can use the arguments.
+    Ok(quote! {
+        #(#attrs)*
+        #vis #sig {
+            let __after: ::core::time::Duration = #after;
+            match ::smol_timeout::TimeoutExt::timeout(#body, __after).await {
+                ::core::option::Option::Some(__output) => ::core::result::Result::Ok(__output),
+                ::core::option::Option::None => {
+                    ::core::result::Result::Err(::smol_timeout::Elapsed::new())
+                }
+            }
+        }
+    })
+}
+
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                                          Helpers                                           │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+/// Returns the body of an `async fn` as an `async` block, annotated with the function's return
+/// type so that `?` and `return` inside it are inferred like in the function.
+fn body(item: &ItemFn) -> TokenStream {
+    let block = &item.block;
+
+    match &item.sig.output {
+        ReturnType::Type(_, ty) if !matches!(**ty, Type::ImplTrait(_)) => quote! {
+            async move {
+                let __output: #ty = #block;
+                __output
+            }
+        },
+        _ => quote! { async move #block },
+    }
+}
+
+/// Returns the expression of the duration provided to an attribute, parsing it when it is a
+/// string literal (e.g. `"5s"`).
+fn after(expr: Expr) -> syn::Result<Expr> {
+    match expr {
+        Expr::Lit(ExprLit {
+            lit: Lit::Str(lit), ..
+        }) => {
+            let nanos = parse_duration(&lit)?;
+            Ok(syn::parse_quote_spanned! { lit.span()=>
+                ::core::time::Duration::from_nanos(#nanos)
+            })
+        }
+        expr => Ok(expr),
+    }
+}
+
+/// Parses a duration written as an integer followed by a unit (`ns`, `us`, `ms`, `s`, `m` or
+/// `h`), returning it as a number of nanoseconds.
+fn parse_duration(lit: &LitStr) -> syn::Result<u64> {
+    let value = lit.value();
+    let error = |message: &str| syn::Error::new(lit.span(), message);
+
+    let split = value
+        .find(|c: char| !c.is_ascii_digit())
+        .ok_or_else(|| error("missing unit (expected one of `ns`, `us`, `ms`, `s`, `m` or `h`)"))?;
+    let (number, unit) = value.split_at(split);
+
+    let number: u64 = number
+        .parse()
+        .map_err(|_| error("expected a duration like `5s` or `500ms`"))?;
+    let unit: u64 = match unit {
+        "ns" => 1,
+        "us" => 1_000,
+        "ms" => 1_000_000,
+        "s" => 1_000_000_000,
+        "m" => 60_000_000_000,
+        "h" => 3_600_000_000_000,
+        _ => {
+            return Err(error(
+                "unknown unit (expected one of `ns`, `us`, `ms`, `s`, `m` or `h`)",
+            ))
+        }
+    };
+
+    number
+        .checked_mul(unit)
+        .ok_or_else(|| error("duration too long"))
+}
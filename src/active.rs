@@ -0,0 +1,171 @@
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                                                                                            │ *
+ * │ This Source Code Form is subject to the terms of the Mozilla Public                        │ *
+ * │ License, v. 2.0. If a copy of the MPL was not distributed with this                        │ *
+ * │ file, You can obtain one at http://mozilla.org/MPL/2.0/.                                   │ *
+ * │                                                                                            │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                                       Documentation                                        │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+//! Timeouts only charged for the time their caller is driving them.
+//!
+//! A regular timeout keeps running while its caller doesn't poll it, e.g. while it is parked in
+//! a branch of a `select!` whose task is busy elsewhere. An [`ActiveTimeout`] instead only
+//! charges its budget from every poll until the following wakeup: the time between a wakeup
+//! and the caller polling it again isn't charged, so that a future is only timed out once it
+//! actually had the whole budget to make progress.
+//!
+//! ## Example
+//!
+//! ```rust
+//! use async_io::Timer;
+//! # use futures_lite::future;
+//! use smol_timeout::active::ActiveTimeoutExt;
+//! use std::time::Duration;
+//!
+//! # future::block_on(async {
+//! #
+//! let foo = async {
+//!     Timer::after(Duration::from_millis(250)).await;
+//!     24
+//! };
+//!
+//! assert_eq!(foo.timeout_active(Duration::from_millis(100)).await, None);
+//!
+//! let bar = async {
+//!     Timer::after(Duration::from_millis(100)).await;
+//!     42
+//! };
+//!
+//! // The caller doesn't drive `bar` for the first 200ms, which isn't charged.
+//! let mut bar = Box::pin(bar.timeout_active(Duration::from_millis(150)));
+//! assert_eq!(future::poll_once(&mut bar).await, None);
+//! Timer::after(Duration::from_millis(200)).await;
+//!
+//! assert_eq!(bar.await, Some(42));
+//! #
+//! # });
+//! ```
+
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                                          Imports                                           │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+use crate::clock;
+use crate::stats::Counter;
+use crate::Timer;
+use core::fmt;
+use core::future::Future;
+use core::pin::Pin;
+use core::task::{Context, Poll, Waker};
+use core::time::Duration;
+use pin_project_lite::pin_project;
+use std::sync::Arc;
+use std::time::Instant;
+
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                                 struct ActiveTimeout<Fut>                                  │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+pin_project! {
+    /// A future polling both another future and a [`Timer`] armed for the remaining budget,
+    /// and returning the future's output or [`None`] if the budget is spent first, only
+    /// charging the budget for the time the future is driven.
+    #[must_use = "futures do nothing unless you `.await` or poll them"]
+    pub struct ActiveTimeout<Fut> {
+        #[pin]
+        future: Fut,
+        timer: Timer,
+        budget: Duration,
+        used: Duration,
+        last_poll: Option<Instant>,
+        counter: Arc<Counter>,
+        waker: Waker,
+    }
+}
+
+impl<Fut> ActiveTimeout<Fut> {
+    /// Returns the part of the budget charged so far.
+    pub fn used(&self) -> Duration {
+        self.used
+    }
+}
+
+impl<Fut> fmt::Debug for ActiveTimeout<Fut> {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        fmt.debug_struct("ActiveTimeout")
+            .field("budget", &self.budget)
+            .field("used", &self.used)
+            .finish()
+    }
+}
+
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                               trait ActiveTimeoutExt: Future                               │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+/// An extension trait for [`Future`]s that provides a way to create [`ActiveTimeout`]s.
+pub trait ActiveTimeoutExt: Future {
+    /// Given a budget, creates and returns a new [`ActiveTimeout`] that will poll the future and
+    /// return its output, or [`None`] once it was driven for longer than the budget.
+    fn timeout_active(self, budget: Duration) -> ActiveTimeout<Self>
+    where
+        Self: Sized,
+    {
+        let counter = Arc::new(Counter::default());
+
+        ActiveTimeout {
+            future: self,
+            timer: Timer::never(),
+            budget,
+            used: Duration::from_secs(0),
+            last_poll: None,
+            waker: Waker::from(counter.clone()),
+            counter,
+        }
+    }
+}
+
+impl<Fut: Future> ActiveTimeoutExt for Fut {}
+
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                             impl Future for ActiveTimeout<Fut>                             │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+impl<Fut: Future> Future for ActiveTimeout<Fut> {
+    type Output = Option<Fut::Output>;
+
+    fn poll(self: Pin<&mut Self>, ctx: &mut Context) -> Poll<Self::Output> {
+        let this = self.project();
+
+        let now = clock::now();
+        let latency = this.counter.register(ctx.waker());
+
+        // Charging the time between the last poll and the wakeup that followed it, or until now
+        // if the future is polled without having been woken up.
+        if let Some(last_poll) = *this.last_poll {
+            let woken = latency.map_or(now, |latency| now - latency);
+            *this.used += woken.saturating_duration_since(last_poll);
+        }
+
+        *this.last_poll = Some(now);
+        if *this.used >= *this.budget {
+            return Poll::Ready(None);
+        }
+
+        let mut ctx = Context::from_waker(this.waker);
+        if let Poll::Ready(output) = this.future.poll(&mut ctx) {
+            return Poll::Ready(Some(output));
+        }
+
+        this.timer.set_at(now + (*this.budget - *this.used));
+        if Pin::new(this.timer).poll(&mut ctx).is_ready() {
+            return Poll::Ready(None);
+        }
+
+        Poll::Pending
+    }
+}
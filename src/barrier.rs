@@ -0,0 +1,267 @@
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                                                                                            │ *
+ * │ This Source Code Form is subject to the terms of the Mozilla Public                        │ *
+ * │ License, v. 2.0. If a copy of the MPL was not distributed with this                        │ *
+ * │ file, You can obtain one at http://mozilla.org/MPL/2.0/.                                   │ *
+ * │                                                                                            │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                                       Documentation                                        │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+//! An asynchronous barrier whose waits can time out without leaving it in an inconsistent state.
+//!
+//! When a task stops waiting on a [`Barrier`] (because its wait timed out or was dropped), its
+//! arrival is withdrawn, so that the barrier still waits for the same number of tasks.
+//!
+//! ## Example
+//!
+//! ```rust
+//! # use futures_lite::future;
+//! use smol_timeout::barrier::Barrier;
+//! use std::time::Duration;
+//!
+//! # future::block_on(async {
+//! #
+//! let barrier = Barrier::new(2);
+//!
+//! // Only one task reaches the barrier, so the wait expires...
+//! assert!(barrier.wait_timeout(Duration::from_millis(100)).await.is_none());
+//!
+//! // ...and its arrival is withdrawn, so two tasks are still needed.
+//! let (foo, bar) = future::zip(barrier.wait(), barrier.wait()).await;
+//! assert!(foo.is_leader() != bar.is_leader());
+//! #
+//! # });
+//! ```
+
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                                          Imports                                           │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+use crate::Timer;
+use core::fmt;
+use core::future::Future;
+use core::pin::Pin;
+use core::task::{Context, Poll};
+use core::time::Duration;
+use event_listener::{Event, EventListener};
+use std::sync::Mutex;
+
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                                  struct BarrierWaitResult                                  │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+/// The result of a successful wait on a [`Barrier`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BarrierWaitResult {
+    leader: bool,
+}
+
+impl BarrierWaitResult {
+    /// Returns `true` if this task was the last one to reach the barrier. Exactly one task is
+    /// the leader every time the barrier is reached.
+    pub fn is_leader(&self) -> bool {
+        self.leader
+    }
+}
+
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                                       struct Barrier                                       │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+/// An asynchronous barrier that makes a number of tasks wait until all of them reached it, and
+/// whose waits can time out (see [`wait_timeout`](Barrier::wait_timeout)).
+///
+/// The barrier can be reused once it has been reached.
+pub struct Barrier {
+    n: usize,
+    state: Mutex<State>,
+    event: Event,
+}
+
+#[derive(Debug)]
+struct State {
+    /// The number of tasks currently waiting.
+    count: usize,
+    /// Incremented every time the barrier is reached.
+    generation: u64,
+}
+
+impl Barrier {
+    /// Creates a new [`Barrier`] waiting for `n` tasks.
+    ///
+    /// A barrier created with an `n` of `0` behaves like one created with an `n` of `1`.
+    pub fn new(n: usize) -> Self {
+        Barrier {
+            n,
+            state: Mutex::new(State {
+                count: 0,
+                generation: 0,
+            }),
+            event: Event::new(),
+        }
+    }
+
+    /// Waits until all tasks have reached the barrier.
+    ///
+    /// Dropping the returned future before it completes withdraws this task's arrival.
+    pub fn wait(&self) -> BarrierWait<'_> {
+        BarrierWait {
+            barrier: self,
+            generation: None,
+            listener: None,
+        }
+    }
+
+    /// Waits for at most the provided duration until all tasks have reached the barrier,
+    /// returning [`None`] and withdrawing this task's arrival if they didn't reach it in time.
+    ///
+    /// ## Example
+    ///
+    /// ```rust
+    /// # use futures_lite::future;
+    /// use smol_timeout::barrier::Barrier;
+    /// use std::time::Duration;
+    ///
+    /// # future::block_on(async {
+    /// #
+    /// let barrier = Barrier::new(1);
+    ///
+    /// let result = barrier.wait_timeout(Duration::from_millis(100)).await;
+    /// assert!(result.unwrap().is_leader());
+    /// #
+    /// # })
+    /// ```
+    pub fn wait_timeout(&self, after: Duration) -> BarrierWaitTimeout<'_> {
+        BarrierWaitTimeout {
+            wait: self.wait(),
+            timer: Timer::after(after),
+        }
+    }
+}
+
+impl fmt::Debug for Barrier {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        fmt.debug_struct("Barrier").field("n", &self.n).finish()
+    }
+}
+
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                                   struct BarrierWait<'a>                                   │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+/// A future waiting until all tasks have reached a [`Barrier`] (see [`Barrier::wait`]).
+#[derive(Debug)]
+#[must_use = "futures do nothing unless you `.await` or poll them"]
+pub struct BarrierWait<'a> {
+    barrier: &'a Barrier,
+    /// The generation this task arrived in, or [`None`] if it didn't arrive yet or is done.
+    generation: Option<u64>,
+    listener: Option<EventListener>,
+}
+
+impl BarrierWait<'_> {
+    /// Withdraws this task's arrival, unless the barrier was reached in the meantime, in which
+    /// case the result of the wait is returned.
+    fn withdraw(&mut self) -> Option<BarrierWaitResult> {
+        let generation = self.generation.take()?;
+        self.listener = None;
+
+        let mut state = self.barrier.state.lock().unwrap();
+        if state.generation != generation {
+            return Some(BarrierWaitResult { leader: false });
+        }
+
+        state.count -= 1;
+        None
+    }
+}
+
+impl Future for BarrierWait<'_> {
+    type Output = BarrierWaitResult;
+
+    fn poll(mut self: Pin<&mut Self>, ctx: &mut Context) -> Poll<Self::Output> {
+        let this = &mut *self;
+        let barrier = this.barrier;
+
+        let generation = match this.generation {
+            Some(generation) => generation,
+            None => {
+                let mut state = barrier.state.lock().unwrap();
+                state.count += 1;
+
+                if state.count >= barrier.n {
+                    state.count = 0;
+                    state.generation = state.generation.wrapping_add(1);
+                    barrier.event.notify(usize::MAX);
+
+                    return Poll::Ready(BarrierWaitResult { leader: true });
+                }
+
+                this.listener = Some(barrier.event.listen());
+                this.generation = Some(state.generation);
+                state.generation
+            }
+        };
+
+        loop {
+            if let Some(listener) = &mut this.listener {
+                if Pin::new(listener).poll(ctx).is_pending() {
+                    return Poll::Pending;
+                }
+            }
+
+            let state = barrier.state.lock().unwrap();
+            if state.generation != generation {
+                this.generation = None;
+                this.listener = None;
+                return Poll::Ready(BarrierWaitResult { leader: false });
+            }
+
+            // The listener is created while holding the lock, so that the leader can't notify
+            // the others before it is registered.
+            this.listener = Some(barrier.event.listen());
+        }
+    }
+}
+
+impl Drop for BarrierWait<'_> {
+    fn drop(&mut self) {
+        self.withdraw();
+    }
+}
+
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                               struct BarrierWaitTimeout<'a>                                │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+/// A future waiting with a timeout until all tasks have reached a [`Barrier`] (see
+/// [`Barrier::wait_timeout`]).
+#[derive(Debug)]
+#[must_use = "futures do nothing unless you `.await` or poll them"]
+pub struct BarrierWaitTimeout<'a> {
+    wait: BarrierWait<'a>,
+    timer: Timer,
+}
+
+impl Future for BarrierWaitTimeout<'_> {
+    type Output = Option<BarrierWaitResult>;
+
+    fn poll(mut self: Pin<&mut Self>, ctx: &mut Context) -> Poll<Self::Output> {
+        let this = &mut *self;
+
+        if let Poll::Ready(result) = Pin::new(&mut this.wait).poll(ctx) {
+            return Poll::Ready(Some(result));
+        }
+
+        if Pin::new(&mut this.timer).poll(ctx).is_pending() {
+            return Poll::Pending;
+        }
+
+        // The barrier might have been reached right before the timer completed, in which case
+        // the arrival isn't withdrawn and the wait succeeded.
+        Poll::Ready(this.wait.withdraw())
+    }
+}
@@ -0,0 +1,191 @@
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                                                                                            │ *
+ * │ This Source Code Form is subject to the terms of the Mozilla Public                        │ *
+ * │ License, v. 2.0. If a copy of the MPL was not distributed with this                        │ *
+ * │ file, You can obtain one at http://mozilla.org/MPL/2.0/.                                   │ *
+ * │                                                                                            │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                                       Documentation                                        │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+//! Suspend-aware timeouts, whose deadlines keep running while the system is suspended.
+//!
+//! A [`Timer`]'s deadline is an [`Instant`], which on most platforms doesn't advance while the
+//! system is suspended, so that a 30 seconds timeout spanning a 10 minutes suspend fires 30
+//! seconds of uptime after it was created. The timeouts of this module are instead based on a
+//! clock that keeps running during suspend:
+//!
+//! - On Linux and Android, they are based on `CLOCK_BOOTTIME`. As the reactor's timers can't
+//!   be armed on that clock, they wake up at least once every [`RECHECK`] to check it, so that
+//!   a timeout whose deadline passed during a suspend fires at most [`RECHECK`] after resuming.
+//! - On other platforms, they fall back to being based on [`Instant`], like a [`Timer`]. This is
+//!   already suspend-aware on Windows, but not on macOS and iOS, where [`Instant`] doesn't
+//!   advance during sleep.
+//!
+//! [`Instant`]: std::time::Instant
+//!
+//! ## Example
+//!
+//! ```rust
+//! use async_io::Timer;
+//! # use futures_lite::future;
+//! use smol_timeout::boottime;
+//! use std::time::Duration;
+//!
+//! # future::block_on(async {
+//! #
+//! let foo = async {
+//!     Timer::after(Duration::from_millis(250)).await;
+//!     24
+//! };
+//!
+//! assert_eq!(boottime::timeout(foo, Duration::from_millis(100)).await, None);
+//!
+//! let bar = async {
+//!     Timer::after(Duration::from_millis(100)).await;
+//!     42
+//! };
+//!
+//! assert_eq!(boottime::timeout(bar, Duration::from_millis(250)).await, Some(42));
+//! #
+//! # });
+//! ```
+
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                                          Imports                                           │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+use crate::Timer;
+use core::future::Future;
+use core::pin::Pin;
+use core::task::{Context, Poll};
+use core::time::Duration;
+use pin_project_lite::pin_project;
+
+#[cfg(not(any(target_os = "linux", target_os = "android")))]
+use std::time::Instant;
+
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                                          fn now()                                          │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+/// The maximum duration a suspend-aware timer waits for without checking the suspend-aware
+/// clock, on platforms where the reactor's timers can't be armed on it.
+pub const RECHECK: Duration = Duration::from_secs(1);
+
+/// Returns the current time of the suspend-aware clock.
+#[cfg(any(target_os = "linux", target_os = "android"))]
+fn now() -> Duration {
+    let mut ts = libc::timespec {
+        tv_sec: 0,
+        tv_nsec: 0,
+    };
+
+    // SAFETY: `ts` is a valid `timespec`, and `CLOCK_BOOTTIME` is supported since Linux 2.6.39.
+    let res = unsafe { libc::clock_gettime(libc::CLOCK_BOOTTIME, &mut ts) };
+    assert_eq!(res, 0, "failed to read CLOCK_BOOTTIME");
+
+    Duration::new(ts.tv_sec as u64, ts.tv_nsec as u32)
+}
+
+/// Returns the current time of the fallback clock.
+#[cfg(not(any(target_os = "linux", target_os = "android")))]
+fn now() -> Duration {
+    std::thread_local! {
+        static START: Instant = Instant::now();
+    }
+
+    START.with(|start| start.elapsed())
+}
+
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                                        struct Sleep                                        │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+/// A future completing once a duration elapsed on the suspend-aware clock.
+///
+/// Created by [`sleep`].
+#[derive(Debug)]
+#[must_use = "futures do nothing unless you `.await` or poll them"]
+pub struct Sleep {
+    /// The deadline, as a time of the suspend-aware clock.
+    deadline: Duration,
+    timer: Timer,
+}
+
+/// Creates and returns a new [`Sleep`] future that will complete once the provided duration
+/// elapsed on the suspend-aware clock.
+pub fn sleep(after: Duration) -> Sleep {
+    Sleep {
+        deadline: now().saturating_add(after),
+        timer: Timer::after(after.min(RECHECK)),
+    }
+}
+
+impl Future for Sleep {
+    type Output = ();
+
+    fn poll(mut self: Pin<&mut Self>, ctx: &mut Context) -> Poll<()> {
+        loop {
+            if Pin::new(&mut self.timer).poll(ctx).is_pending() {
+                return Poll::Pending;
+            }
+
+            let remaining = self.deadline.saturating_sub(now());
+            if remaining == Duration::from_secs(0) {
+                return Poll::Ready(());
+            }
+
+            self.timer.set_after(remaining.min(RECHECK));
+        }
+    }
+}
+
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                                    struct Timeout<Fut>                                     │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+pin_project! {
+    /// A future polling both another future and a suspend-aware [`Sleep`], and returning the
+    /// future's output or [`None`] if the sleep completes first.
+    ///
+    /// Created by [`timeout`].
+    #[derive(Debug)]
+    #[must_use = "futures do nothing unless you `.await` or poll them"]
+    pub struct Timeout<Fut> {
+        #[pin]
+        future: Fut,
+        sleep: Sleep,
+    }
+}
+
+/// Given a future and a [`Duration`], creates and returns a new [`Timeout`] that will poll both
+/// the future and a [`Sleep`] that will complete once the provided duration elapsed on the
+/// suspend-aware clock, and return the future's output or [`None`] if the sleep completes
+/// first.
+pub fn timeout<Fut: Future>(future: Fut, after: Duration) -> Timeout<Fut> {
+    Timeout {
+        future,
+        sleep: sleep(after),
+    }
+}
+
+impl<Fut: Future> Future for Timeout<Fut> {
+    type Output = Option<Fut::Output>;
+
+    fn poll(self: Pin<&mut Self>, ctx: &mut Context) -> Poll<Self::Output> {
+        let this = self.project();
+
+        if Pin::new(this.sleep).poll(ctx).is_ready() {
+            return Poll::Ready(None);
+        }
+
+        if let Poll::Ready(output) = this.future.poll(ctx) {
+            return Poll::Ready(Some(output));
+        }
+
+        Poll::Pending
+    }
+}
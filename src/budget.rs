@@ -0,0 +1,277 @@
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                                                                                            │ *
+ * │ This Source Code Form is subject to the terms of the Mozilla Public                        │ *
+ * │ License, v. 2.0. If a copy of the MPL was not distributed with this                        │ *
+ * │ file, You can obtain one at http://mozilla.org/MPL/2.0/.                                   │ *
+ * │                                                                                            │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                                       Documentation                                        │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+//! Error budgets for [`NamedTimeout`]s, calling a hook when too many of them expire.
+//!
+//! With the `budget` feature, a [`Budget`] watched with [`watch`] tracks the outcomes of the
+//! named timeouts with its name over a sliding window, and calls its hook with an [`Alert`]
+//! once the ratio of expired timeouts exceeds the budget (e.g. more than 1% over 5 minutes).
+//! The hook is called again only after the ratio went back within the budget.
+//!
+//! ## Example
+//!
+//! ```rust
+//! # use futures_lite::future;
+//! use smol_timeout::budget::{self, Budget};
+//! use smol_timeout::named::NamedTimeoutExt;
+//! use std::sync::atomic::{AtomicBool, Ordering};
+//! use std::sync::Arc;
+//! use std::time::Duration;
+//!
+//! # future::block_on(async {
+//! #
+//! let alerted = Arc::new(AtomicBool::new(false));
+//! let hook = {
+//!     let alerted = alerted.clone();
+//!     move |_| alerted.store(true, Ordering::Relaxed)
+//! };
+//!
+//! budget::watch(Budget::new("db.get_user", 0.5, Duration::from_secs(300), hook));
+//!
+//! let foo = future::ready(()).timeout_named(Duration::from_millis(100), "db.get_user");
+//! assert!(foo.await.is_ok());
+//! assert!(!alerted.load(Ordering::Relaxed));
+//!
+//! for _ in 0..2 {
+//!     let bar = future::pending::<()>().timeout_named(Duration::from_millis(10), "db.get_user");
+//!     assert!(bar.await.is_err());
+//! }
+//!
+//! assert!(alerted.load(Ordering::Relaxed));
+//! #
+//! # });
+//! ```
+//!
+//! [`NamedTimeout`]: crate::named::NamedTimeout
+
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                                          Imports                                           │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+use crate::clock;
+use core::fmt;
+use core::time::Duration;
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::Instant;
+use std::vec::Vec;
+
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                                        struct Alert                                        │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+/// The outcomes of the timeouts of a [`Budget`] over its window, passed to its hook when they
+/// exceed it.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct Alert {
+    name: &'static str,
+    completed: u64,
+    expired: u64,
+    window: Duration,
+    budget: f64,
+}
+
+impl Alert {
+    /// Returns the name of the timeouts.
+    pub fn name(&self) -> &'static str {
+        self.name
+    }
+
+    /// Returns the number of timeouts whose future completed in time over the window.
+    pub fn completed(&self) -> u64 {
+        self.completed
+    }
+
+    /// Returns the number of timeouts that expired over the window.
+    pub fn expired(&self) -> u64 {
+        self.expired
+    }
+
+    /// Returns the ratio of expired timeouts over the window.
+    pub fn ratio(&self) -> f64 {
+        self.expired as f64 / (self.completed + self.expired) as f64
+    }
+
+    /// Returns the duration of the window.
+    pub fn window(&self) -> Duration {
+        self.window
+    }
+
+    /// Returns the ratio of expired timeouts allowed by the budget.
+    pub fn budget(&self) -> f64 {
+        self.budget
+    }
+}
+
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                                       struct Budget                                        │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+/// The number of slots a window is divided into.
+const SLOTS: u32 = 10;
+
+type Hook = Arc<dyn Fn(Alert) + Send + Sync>;
+
+/// An error budget for the named timeouts with a given name, to watch with [`watch`].
+pub struct Budget {
+    name: &'static str,
+    budget: f64,
+    window: Duration,
+    min_samples: u64,
+    hook: Hook,
+}
+
+impl Budget {
+    /// Creates a new [`Budget`] allowing the provided ratio (between `0.` and `1.`) of the
+    /// timeouts named `name` to expire over a sliding window, and calling `hook` when more of
+    /// them do.
+    ///
+    /// ## Panics
+    ///
+    /// Panics if `window` is zero.
+    pub fn new<H>(name: &'static str, budget: f64, window: Duration, hook: H) -> Self
+    where
+        H: Fn(Alert) + Send + Sync + 'static,
+    {
+        assert!(
+            window > Duration::from_secs(0),
+            "a budget's window can't be zero"
+        );
+
+        Budget {
+            name,
+            budget,
+            window,
+            min_samples: 1,
+            hook: Arc::new(hook),
+        }
+    }
+
+    /// Sets the minimum number of outcomes over the window for the budget to be considered
+    /// exceeded, so that a single expiry doesn't trigger an alert on a quiet name. Defaults to
+    /// `1`.
+    pub fn min_samples(mut self, min_samples: u64) -> Self {
+        self.min_samples = min_samples;
+        self
+    }
+}
+
+impl fmt::Debug for Budget {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        fmt.debug_struct("Budget")
+            .field("name", &self.name)
+            .field("budget", &self.budget)
+            .field("window", &self.window)
+            .field("min_samples", &self.min_samples)
+            .finish()
+    }
+}
+
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                                         fn watch()                                         │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+struct Slot {
+    index: u64,
+    completed: u64,
+    expired: u64,
+}
+
+struct Watched {
+    budget: Budget,
+    /// The outcomes of the slots of the window, oldest first.
+    slots: VecDeque<Slot>,
+    alerting: bool,
+}
+
+static WATCHED: Mutex<Vec<Watched>> = Mutex::new(Vec::new());
+
+/// The instant slots are counted from.
+fn epoch() -> Instant {
+    static EPOCH: OnceLock<Instant> = OnceLock::new();
+    *EPOCH.get_or_init(clock::now)
+}
+
+/// Starts watching the provided budget.
+pub fn watch(budget: Budget) {
+    // Initializing the epoch before any outcome is recorded.
+    epoch();
+
+    WATCHED.lock().unwrap().push(Watched {
+        budget,
+        slots: VecDeque::new(),
+        alerting: false,
+    });
+}
+
+/// Records the outcome of the timeout with the provided name.
+pub(crate) fn record(name: &'static str, expired: bool) {
+    let now = clock::now();
+    let mut alerts = Vec::new();
+
+    {
+        let mut watched = WATCHED.lock().unwrap();
+        for watched in watched
+            .iter_mut()
+            .filter(|watched| watched.budget.name == name)
+        {
+            let width = watched.budget.window / SLOTS;
+            let index = (now.duration_since(epoch()).as_nanos() / width.as_nanos().max(1)) as u64;
+
+            while let Some(slot) = watched.slots.front() {
+                if slot.index + u64::from(SLOTS) > index {
+                    break;
+                }
+
+                watched.slots.pop_front();
+            }
+
+            if watched.slots.back().is_none_or(|slot| slot.index != index) {
+                watched.slots.push_back(Slot {
+                    index,
+                    completed: 0,
+                    expired: 0,
+                });
+            }
+
+            let slot = watched.slots.back_mut().unwrap();
+            if expired {
+                slot.expired += 1;
+            } else {
+                slot.completed += 1;
+            }
+
+            let alert = Alert {
+                name,
+                completed: watched.slots.iter().map(|slot| slot.completed).sum(),
+                expired: watched.slots.iter().map(|slot| slot.expired).sum(),
+                window: watched.budget.window,
+                budget: watched.budget.budget,
+            };
+
+            let exceeded = alert.completed + alert.expired >= watched.budget.min_samples
+                && alert.ratio() > watched.budget.budget;
+
+            if exceeded && !watched.alerting {
+                alerts.push((watched.budget.hook.clone(), alert));
+            }
+
+            watched.alerting = exceeded;
+        }
+    }
+
+    // Calling the hooks without holding the lock, since they might record outcomes.
+    for (hook, alert) in alerts {
+        hook(alert);
+    }
+}
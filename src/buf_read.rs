@@ -0,0 +1,278 @@
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                                                                                            │ *
+ * │ This Source Code Form is subject to the terms of the Mozilla Public                        │ *
+ * │ License, v. 2.0. If a copy of the MPL was not distributed with this                        │ *
+ * │ file, You can obtain one at http://mozilla.org/MPL/2.0/.                                   │ *
+ * │                                                                                            │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                                       Documentation                                        │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+//! Buffered reads of delimited data with a timeout, telling how much was read when it expires.
+//!
+//! With the `futures-io` feature, [`BufReadTimeoutExt`] provides variants of the `read_until`
+//! and `read_line` helpers of [`AsyncBufRead`] readers which return a [`ReadOutcome`]: either
+//! the delimiter was reached, or the timeout expired first, in both cases with the number of
+//! bytes read (and appended to the buffer) so far. This is what line-based protocol parsers
+//! need to evict slow clients without losing track of their input.
+//!
+//! ## Example
+//!
+//! ```rust
+//! use async_io::Async;
+//! # use futures_lite::future;
+//! use futures_lite::io::BufReader;
+//! use futures_lite::AsyncWriteExt;
+//! use smol_timeout::buf_read::{BufReadTimeoutExt, ReadOutcome};
+//! use std::io;
+//! use std::net::{TcpListener, TcpStream};
+//! use std::time::Duration;
+//!
+//! # future::block_on(async {
+//! #
+//! let listener = Async::<TcpListener>::bind(([127, 0, 0, 1], 0))?;
+//! let mut client = Async::<TcpStream>::connect(listener.get_ref().local_addr()?).await?;
+//! let (server, _) = listener.accept().await?;
+//! let mut server = BufReader::new(server);
+//!
+//! client.write_all(b"HELO\r\nMAIL FR").await?;
+//!
+//! let mut line = String::new();
+//! let outcome = server
+//!     .read_line_timeout(&mut line, Duration::from_millis(100))
+//!     .await?;
+//! assert_eq!(outcome, ReadOutcome::Done(6));
+//! assert_eq!(line, "HELO\r\n");
+//!
+//! line.clear();
+//! let outcome = server
+//!     .read_line_timeout(&mut line, Duration::from_millis(100))
+//!     .await?;
+//! assert_eq!(outcome, ReadOutcome::Elapsed(7));
+//! assert_eq!(line, "MAIL FR");
+//! #
+//! # io::Result::Ok(())
+//! # }).unwrap();
+//! ```
+
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                                          Imports                                           │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+use crate::Timer;
+use core::future::Future;
+use core::pin::Pin;
+use core::str;
+use core::task::{Context, Poll};
+use core::time::Duration;
+use futures_io::AsyncBufRead;
+use std::io;
+use std::string::String;
+use std::vec::Vec;
+
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                                      enum ReadOutcome                                      │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+/// The outcome of a delimited read with a timeout.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum ReadOutcome {
+    /// The delimiter (included) or the end of the reader was reached after reading this many
+    /// bytes.
+    Done(usize),
+    /// The timeout expired after reading this many bytes, without reaching the delimiter.
+    Elapsed(usize),
+}
+
+impl ReadOutcome {
+    /// Returns the number of bytes read.
+    pub fn read(&self) -> usize {
+        match *self {
+            ReadOutcome::Done(read) | ReadOutcome::Elapsed(read) => read,
+        }
+    }
+
+    /// Returns whether the timeout expired before the delimiter was reached.
+    pub fn is_elapsed(&self) -> bool {
+        matches!(self, ReadOutcome::Elapsed(_))
+    }
+}
+
+/// Reads into `buf` until the delimiter or the end of the reader is reached, or the timer
+/// completes, adding the number of bytes read to `read`.
+fn poll_read_until<R: AsyncBufRead + Unpin + ?Sized>(
+    reader: &mut R,
+    timer: &mut Timer,
+    delimiter: u8,
+    buf: &mut Vec<u8>,
+    read: &mut usize,
+    ctx: &mut Context,
+) -> Poll<io::Result<ReadOutcome>> {
+    loop {
+        let (done, used) = {
+            let available = match Pin::new(&mut *reader).poll_fill_buf(ctx) {
+                Poll::Ready(Ok(available)) => available,
+                Poll::Ready(Err(err)) => return Poll::Ready(Err(err)),
+                Poll::Pending if Pin::new(&mut *timer).poll(ctx).is_ready() => {
+                    return Poll::Ready(Ok(ReadOutcome::Elapsed(*read)));
+                }
+                Poll::Pending => return Poll::Pending,
+            };
+
+            match available.iter().position(|&byte| byte == delimiter) {
+                Some(index) => {
+                    buf.extend_from_slice(&available[..=index]);
+                    (true, index + 1)
+                }
+                None => {
+                    buf.extend_from_slice(available);
+                    (available.is_empty(), available.len())
+                }
+            }
+        };
+
+        Pin::new(&mut *reader).consume(used);
+        *read += used;
+
+        if done {
+            return Poll::Ready(Ok(ReadOutcome::Done(*read)));
+        }
+    }
+}
+
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                                  trait BufReadTimeoutExt                                   │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+/// An extension trait for [`AsyncBufRead`] readers that provides a way to read delimited data
+/// with a timeout.
+pub trait BufReadTimeoutExt: AsyncBufRead + Unpin {
+    /// Reads all the bytes until the delimiter (included) or the end of the reader, appending
+    /// them to `buf`, for at most the provided duration.
+    ///
+    /// The bytes read before the timeout expires are kept in `buf`, and counted by the returned
+    /// [`ReadOutcome::Elapsed`].
+    fn read_until_timeout<'a>(
+        &'a mut self,
+        delimiter: u8,
+        buf: &'a mut Vec<u8>,
+        after: Duration,
+    ) -> ReadUntilTimeout<'a, Self> {
+        ReadUntilTimeout {
+            reader: self,
+            timer: Timer::after(after),
+            delimiter,
+            buf,
+            read: 0,
+        }
+    }
+
+    /// Reads all the bytes until a newline (included) or the end of the reader, appending them
+    /// to `buf`, for at most the provided duration.
+    ///
+    /// The bytes read before the timeout expires are appended to `buf` and counted by the
+    /// returned [`ReadOutcome::Elapsed`], except those of an incomplete UTF-8 sequence at their
+    /// end, which are only counted. If the bytes read aren't valid UTF-8, an error of kind
+    /// [`InvalidData`](io::ErrorKind::InvalidData) is returned and `buf` is left untouched.
+    fn read_line_timeout<'a>(
+        &'a mut self,
+        buf: &'a mut String,
+        after: Duration,
+    ) -> ReadLineTimeout<'a, Self> {
+        ReadLineTimeout {
+            reader: self,
+            timer: Timer::after(after),
+            buf,
+            bytes: Vec::new(),
+            read: 0,
+        }
+    }
+}
+
+impl<R: AsyncBufRead + Unpin + ?Sized> BufReadTimeoutExt for R {}
+
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                               struct ReadUntilTimeout<'a, R>                               │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+/// A future reading bytes until a delimiter with a timeout (see
+/// [`BufReadTimeoutExt::read_until_timeout`]).
+#[derive(Debug)]
+#[must_use = "futures do nothing unless you `.await` or poll them"]
+pub struct ReadUntilTimeout<'a, R: ?Sized> {
+    reader: &'a mut R,
+    timer: Timer,
+    delimiter: u8,
+    buf: &'a mut Vec<u8>,
+    read: usize,
+}
+
+impl<R: AsyncBufRead + Unpin + ?Sized> Future for ReadUntilTimeout<'_, R> {
+    type Output = io::Result<ReadOutcome>;
+
+    fn poll(self: Pin<&mut Self>, ctx: &mut Context) -> Poll<Self::Output> {
+        let this = self.get_mut();
+        poll_read_until(
+            this.reader,
+            &mut this.timer,
+            this.delimiter,
+            this.buf,
+            &mut this.read,
+            ctx,
+        )
+    }
+}
+
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                               struct ReadLineTimeout<'a, R>                                │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+/// A future reading a line with a timeout (see [`BufReadTimeoutExt::read_line_timeout`]).
+#[derive(Debug)]
+#[must_use = "futures do nothing unless you `.await` or poll them"]
+pub struct ReadLineTimeout<'a, R: ?Sized> {
+    reader: &'a mut R,
+    timer: Timer,
+    buf: &'a mut String,
+    bytes: Vec<u8>,
+    read: usize,
+}
+
+impl<R: AsyncBufRead + Unpin + ?Sized> Future for ReadLineTimeout<'_, R> {
+    type Output = io::Result<ReadOutcome>;
+
+    fn poll(self: Pin<&mut Self>, ctx: &mut Context) -> Poll<Self::Output> {
+        let this = self.get_mut();
+        let outcome = match poll_read_until(
+            this.reader,
+            &mut this.timer,
+            b'\n',
+            &mut this.bytes,
+            &mut this.read,
+            ctx,
+        ) {
+            Poll::Ready(Ok(outcome)) => outcome,
+            poll => return poll,
+        };
+
+        let valid = match (str::from_utf8(&this.bytes), outcome) {
+            (Ok(valid), _) => valid,
+            // Only the end of the bytes, cut by the timeout, isn't valid.
+            (Err(err), ReadOutcome::Elapsed(_)) if err.error_len().is_none() => {
+                str::from_utf8(&this.bytes[..err.valid_up_to()]).unwrap()
+            }
+            (Err(_), _) => {
+                return Poll::Ready(Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    "stream did not contain valid UTF-8",
+                )));
+            }
+        };
+
+        this.buf.push_str(valid);
+        Poll::Ready(Ok(outcome))
+    }
+}
@@ -0,0 +1,263 @@
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                                                                                            │ *
+ * │ This Source Code Form is subject to the terms of the Mozilla Public                        │ *
+ * │ License, v. 2.0. If a copy of the MPL was not distributed with this                        │ *
+ * │ file, You can obtain one at http://mozilla.org/MPL/2.0/.                                   │ *
+ * │                                                                                            │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                                       Documentation                                        │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+//! Timeouts for sending and receiving messages over an [`async_channel`].
+//!
+//! ## Example
+//!
+//! ```rust
+//! # use futures_lite::future;
+//! use smol_timeout::channel::{ReceiverExt, RecvTimeoutError, SenderExt};
+//! use std::time::Duration;
+//!
+//! # future::block_on(async {
+//! #
+//! let (sender, receiver) = async_channel::bounded(1);
+//!
+//! assert_eq!(sender.send_timeout(24, Duration::from_millis(100)).await, Ok(()));
+//! assert!(sender.send_timeout(42, Duration::from_millis(100)).await.is_err());
+//!
+//! assert_eq!(receiver.recv_timeout(Duration::from_millis(100)).await, Ok(24));
+//! assert_eq!(
+//!     receiver.recv_timeout(Duration::from_millis(100)).await,
+//!     Err(RecvTimeoutError::Timeout),
+//! );
+//!
+//! drop(sender);
+//! assert_eq!(
+//!     receiver.recv_timeout(Duration::from_millis(100)).await,
+//!     Err(RecvTimeoutError::Closed),
+//! );
+//! #
+//! # });
+//! ```
+
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                                          Imports                                           │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+use crate::{Timeout, TimeoutExt};
+use async_channel::{Receiver, Recv, Send, Sender};
+use core::fmt;
+use core::future::Future;
+use core::pin::Pin;
+use core::task::{Context, Poll};
+use core::time::Duration;
+
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                                   enum RecvTimeoutError                                    │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+/// The error returned by [`ReceiverExt::recv_timeout`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum RecvTimeoutError {
+    /// No message was received before the timeout.
+    Timeout,
+    /// The channel is empty and closed.
+    Closed,
+}
+
+impl fmt::Display for RecvTimeoutError {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            RecvTimeoutError::Timeout => fmt.write_str("timed out receiving from the channel"),
+            RecvTimeoutError::Closed => fmt.write_str("receiving from an empty and closed channel"),
+        }
+    }
+}
+
+impl std::error::Error for RecvTimeoutError {}
+
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                                  enum SendTimeoutError<T>                                  │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+/// The error returned by [`SenderExt::send_timeout`].
+#[derive(Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum SendTimeoutError<T> {
+    /// The channel didn't have enough capacity before the timeout.
+    ///
+    /// The message was dropped: waiting for capacity hands it over to the channel's pending
+    /// [`Send`] future, which [`async_channel`] only gives back if the channel is closed.
+    Timeout,
+    /// The channel is closed. Contains the message that couldn't be sent.
+    Closed(T),
+}
+
+impl<T> SendTimeoutError<T> {
+    /// Returns the message that couldn't be sent, if any.
+    pub fn into_inner(self) -> Option<T> {
+        match self {
+            SendTimeoutError::Timeout => None,
+            SendTimeoutError::Closed(msg) => Some(msg),
+        }
+    }
+}
+
+impl<T> fmt::Debug for SendTimeoutError<T> {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SendTimeoutError::Timeout => fmt.write_str("Timeout"),
+            SendTimeoutError::Closed(_) => fmt.write_str("Closed(..)"),
+        }
+    }
+}
+
+impl<T> fmt::Display for SendTimeoutError<T> {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SendTimeoutError::Timeout => fmt.write_str("timed out sending into the channel"),
+            SendTimeoutError::Closed(_) => fmt.write_str("sending into a closed channel"),
+        }
+    }
+}
+
+impl<T> std::error::Error for SendTimeoutError<T> {}
+
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                                     trait SenderExt<T>                                     │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+/// An extension trait for [`Sender`]s that provides a way to send messages with a timeout.
+pub trait SenderExt<T> {
+    /// Sends a message into the channel, waiting for at most the provided duration for the
+    /// channel to have enough capacity.
+    ///
+    /// ## Example
+    ///
+    /// ```rust
+    /// # use futures_lite::future;
+    /// use smol_timeout::channel::{SendTimeoutError, SenderExt};
+    /// use std::time::Duration;
+    ///
+    /// # future::block_on(async {
+    /// #
+    /// let (sender, receiver) = async_channel::bounded(1);
+    ///
+    /// assert_eq!(sender.send_timeout(24, Duration::from_millis(100)).await, Ok(()));
+    /// assert_eq!(
+    ///     sender.send_timeout(42, Duration::from_millis(100)).await,
+    ///     Err(SendTimeoutError::Timeout),
+    /// );
+    ///
+    /// drop(receiver);
+    /// assert_eq!(
+    ///     sender.send_timeout(42, Duration::from_millis(100)).await,
+    ///     Err(SendTimeoutError::Closed(42)),
+    /// );
+    /// #
+    /// # })
+    /// ```
+    fn send_timeout(&self, msg: T, after: Duration) -> SendTimeout<'_, T>;
+}
+
+impl<T> SenderExt<T> for Sender<T> {
+    fn send_timeout(&self, msg: T, after: Duration) -> SendTimeout<'_, T> {
+        SendTimeout {
+            inner: self.send(msg).timeout(after),
+        }
+    }
+}
+
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                                    trait ReceiverExt<T>                                    │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+/// An extension trait for [`Receiver`]s that provides a way to receive messages with a timeout.
+pub trait ReceiverExt<T> {
+    /// Receives a message from the channel, waiting for at most the provided duration for one to
+    /// be sent.
+    ///
+    /// ## Example
+    ///
+    /// ```rust
+    /// # use futures_lite::future;
+    /// use smol_timeout::channel::{ReceiverExt, RecvTimeoutError};
+    /// use std::time::Duration;
+    ///
+    /// # future::block_on(async {
+    /// #
+    /// let (sender, receiver) = async_channel::unbounded::<i32>();
+    ///
+    /// assert_eq!(
+    ///     receiver.recv_timeout(Duration::from_millis(100)).await,
+    ///     Err(RecvTimeoutError::Timeout),
+    /// );
+    /// #
+    /// # })
+    /// ```
+    fn recv_timeout(&self, after: Duration) -> RecvTimeout<'_, T>;
+}
+
+impl<T> ReceiverExt<T> for Receiver<T> {
+    fn recv_timeout(&self, after: Duration) -> RecvTimeout<'_, T> {
+        RecvTimeout {
+            inner: self.recv().timeout(after),
+        }
+    }
+}
+
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                                 struct SendTimeout<'a, T>                                  │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+/// A future sending a message into a channel with a timeout (see [`SenderExt::send_timeout`]).
+///
+/// The future waits on the channel like [`Sender::send`], and is thus woken up as soon as it has
+/// capacity, in turn with the other senders waiting on it.
+#[derive(Debug)]
+#[must_use = "futures do nothing unless you `.await` or poll them"]
+pub struct SendTimeout<'a, T> {
+    inner: Timeout<Send<'a, T>>,
+}
+
+impl<T> Future for SendTimeout<'_, T> {
+    type Output = Result<(), SendTimeoutError<T>>;
+
+    fn poll(mut self: Pin<&mut Self>, ctx: &mut Context) -> Poll<Self::Output> {
+        Pin::new(&mut self.inner)
+            .poll(ctx)
+            .map(|output| match output {
+                Some(Ok(())) => Ok(()),
+                Some(Err(err)) => Err(SendTimeoutError::Closed(err.into_inner())),
+                None => Err(SendTimeoutError::Timeout),
+            })
+    }
+}
+
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                                 struct RecvTimeout<'a, T>                                  │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+/// A future receiving a message from a channel with a timeout (see
+/// [`ReceiverExt::recv_timeout`]).
+#[derive(Debug)]
+#[must_use = "futures do nothing unless you `.await` or poll them"]
+pub struct RecvTimeout<'a, T> {
+    inner: Timeout<Recv<'a, T>>,
+}
+
+impl<T> Future for RecvTimeout<'_, T> {
+    type Output = Result<T, RecvTimeoutError>;
+
+    fn poll(mut self: Pin<&mut Self>, ctx: &mut Context) -> Poll<Self::Output> {
+        Pin::new(&mut self.inner)
+            .poll(ctx)
+            .map(|output| match output {
+                Some(Ok(msg)) => Ok(msg),
+                Some(Err(_)) => Err(RecvTimeoutError::Closed),
+                None => Err(RecvTimeoutError::Timeout),
+            })
+    }
+}
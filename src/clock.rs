@@ -0,0 +1,89 @@
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                                                                                            │ *
+ * │ This Source Code Form is subject to the terms of the Mozilla Public                        │ *
+ * │ License, v. 2.0. If a copy of the MPL was not distributed with this                        │ *
+ * │ file, You can obtain one at http://mozilla.org/MPL/2.0/.                                   │ *
+ * │                                                                                            │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                                       Documentation                                        │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+//! The clock used to measure elapsed durations, e.g. the [`Winner::elapsed`] of a race or the
+//! time since a [`Heartbeat`] last ticked.
+//!
+//! By default, [`Stamp`]s are taken using [`std::time::Instant`]. With the `quanta` feature,
+//! they are taken using [`quanta`]'s clock instead, which reads the CPU's calibrated timestamp
+//! counter when available, avoiding a system call on hot paths. Deadlines are always expressed
+//! as [`std::time::Instant`]s, as that is what the reactor's timers use, and are computed from
+//! the virtual time when a simulated clock is installed on the current thread with the `sim`
+//! feature (see [`sim`](crate::sim)).
+//!
+//! [`Winner::elapsed`]: crate::join::Winner::elapsed
+//! [`Heartbeat`]: crate::watchdog::Heartbeat
+//!
+//! ## Example
+//!
+//! ```rust
+//! use smol_timeout::clock::Stamp;
+//! use std::time::Duration;
+//!
+//! let start = Stamp::now();
+//! std::thread::sleep(Duration::from_millis(10));
+//!
+//! assert!(start.elapsed() >= Duration::from_millis(10));
+//! ```
+
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                                          Imports                                           │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+use core::time::Duration;
+
+#[cfg(feature = "quanta")]
+use quanta::Instant;
+#[cfg(not(feature = "quanta"))]
+use std::time::Instant;
+
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                                        struct Stamp                                        │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+/// A timestamp taken using the crate's clock, only meant to measure elapsed durations.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Stamp(Instant);
+
+impl Stamp {
+    /// Returns a timestamp corresponding to now.
+    pub fn now() -> Self {
+        Stamp(Instant::now())
+    }
+
+    /// Returns the duration elapsed since this timestamp was taken.
+    pub fn elapsed(&self) -> Duration {
+        Stamp::now().saturating_duration_since(*self)
+    }
+
+    /// Returns the duration elapsed between `earlier` and this timestamp, or zero if `earlier`
+    /// is later.
+    pub fn saturating_duration_since(&self, earlier: Stamp) -> Duration {
+        self.0.saturating_duration_since(earlier.0)
+    }
+}
+
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                                          fn now()                                          │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+/// Returns the instant deadlines are computed from.
+#[cfg(all(feature = "sim", not(target_os = "wasi")))]
+pub(crate) fn now() -> std::time::Instant {
+    crate::sim::now()
+}
+
+/// Returns the instant deadlines are computed from.
+#[cfg(not(all(feature = "sim", not(target_os = "wasi"))))]
+pub(crate) fn now() -> std::time::Instant {
+    std::time::Instant::now()
+}
@@ -0,0 +1,320 @@
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                                                                                            │ *
+ * │ This Source Code Form is subject to the terms of the Mozilla Public                        │ *
+ * │ License, v. 2.0. If a copy of the MPL was not distributed with this                        │ *
+ * │ file, You can obtain one at http://mozilla.org/MPL/2.0/.                                   │ *
+ * │                                                                                            │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                                       Documentation                                        │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+//! Timers whose deadlines are rounded to a quantum, so that nearby timers share a single
+//! [`Timer`].
+//!
+//! A [`Coalescer`] rounds the deadline of every timer it creates up to the next multiple of its
+//! quantum, and all the timers whose deadline rounds to the same instant share a single
+//! [`Timer`], registered once with the reactor and waking all of them when it fires. This trades
+//! up to one quantum of precision for far fewer timers, wakeups and syscalls on busy servers.
+//!
+//! ## Example
+//!
+//! ```rust
+//! use async_io::Timer;
+//! # use futures_lite::future;
+//! use smol_timeout::coalesce::Coalescer;
+//! use std::time::Duration;
+//!
+//! # future::block_on(async {
+//! #
+//! let coalescer = Coalescer::new(Duration::from_millis(10));
+//!
+//! let foo = async {
+//!     Timer::after(Duration::from_millis(250)).await;
+//!     24
+//! };
+//!
+//! assert_eq!(coalescer.timeout(foo, Duration::from_millis(100)).await, None);
+//!
+//! let bar = async {
+//!     Timer::after(Duration::from_millis(100)).await;
+//!     42
+//! };
+//!
+//! assert_eq!(coalescer.timeout(bar, Duration::from_millis(250)).await, Some(42));
+//! #
+//! # });
+//! ```
+
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                                          Imports                                           │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+use crate::waker_set::WakerSet;
+use crate::{clock, deadline};
+use crate::{DeadlineSource, Timeout, Timer};
+use core::fmt;
+use core::future::Future;
+use core::pin::Pin;
+use core::task::{Context, Poll};
+use core::time::Duration;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
+
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                                      struct Coalescer                                      │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+/// A [`Timer`] shared by all the timers whose deadline rounds to the same instant.
+struct Bucket {
+    timer: Timer,
+    wakers: Arc<WakerSet>,
+    /// The number of [`CoalescedSleep`]s sharing the bucket.
+    sleeps: usize,
+    fired: bool,
+}
+
+struct Inner {
+    start: Instant,
+    quantum: Duration,
+    /// The buckets, indexed by their deadline as a number of quanta since `start`.
+    buckets: Mutex<HashMap<u64, Bucket>>,
+}
+
+impl Inner {
+    /// Returns the index of the bucket `deadline` rounds up to, and the bucket's deadline.
+    fn round(&self, deadline: Instant) -> (u64, Instant) {
+        let since = deadline.saturating_duration_since(self.start).as_nanos();
+        let index = since.div_ceil(self.quantum.as_nanos()) as u64;
+
+        let rounded = self.start
+            + Duration::from_nanos((self.quantum.as_nanos() as u64).saturating_mul(index));
+
+        (index, rounded)
+    }
+
+    /// Creates a new [`CoalescedSleep`] sharing the bucket `deadline` rounds up to.
+    fn sleep_until(self: &Arc<Self>, deadline: Instant) -> CoalescedSleep {
+        let (index, rounded) = self.round(deadline);
+
+        let mut buckets = self.buckets.lock().unwrap();
+        let bucket = buckets.entry(index).or_insert_with(|| Bucket {
+            timer: Timer::at(rounded),
+            wakers: WakerSet::new(),
+            sleeps: 0,
+            fired: false,
+        });
+
+        bucket.sleeps += 1;
+
+        CoalescedSleep {
+            inner: self.clone(),
+            index,
+            key: bucket.wakers.key(),
+            deadline: rounded,
+        }
+    }
+
+    /// Removes a sleep from its bucket, dropping the bucket if it was the last one sharing it.
+    fn release(&self, index: u64, key: u64) {
+        let mut buckets = self.buckets.lock().unwrap();
+        if let Some(bucket) = buckets.get_mut(&index) {
+            bucket.wakers.remove(key);
+            bucket.sleeps -= 1;
+            if bucket.sleeps == 0 {
+                buckets.remove(&index);
+            }
+        }
+    }
+}
+
+/// A factory of timers whose deadlines are rounded up to a multiple of a quantum, and which
+/// share a single [`Timer`] when they round to the same deadline.
+///
+/// Cloning a [`Coalescer`] returns a new handle to the same coalescer.
+#[derive(Clone)]
+pub struct Coalescer {
+    inner: Arc<Inner>,
+}
+
+impl Coalescer {
+    /// Creates a new [`Coalescer`] rounding deadlines up to a multiple of `quantum`.
+    ///
+    /// ## Panics
+    ///
+    /// Panics if `quantum` is zero.
+    pub fn new(quantum: Duration) -> Self {
+        assert!(
+            quantum > Duration::from_secs(0),
+            "a coalescer's quantum can't be zero"
+        );
+
+        Coalescer {
+            inner: Arc::new(Inner {
+                start: clock::now(),
+                quantum,
+                buckets: Mutex::new(HashMap::new()),
+            }),
+        }
+    }
+
+    /// Returns the quantum deadlines are rounded to.
+    pub fn quantum(&self) -> Duration {
+        self.inner.quantum
+    }
+
+    /// Returns the number of [`Timer`]s currently shared by the timers of this coalescer.
+    pub fn timers(&self) -> usize {
+        self.inner.buckets.lock().unwrap().len()
+    }
+
+    /// Creates and returns a new [`CoalescedSleep`] future that will complete after the provided
+    /// duration, rounded up to the coalescer's quantum.
+    pub fn sleep(&self, after: Duration) -> CoalescedSleep {
+        self.sleep_until(deadline::deadline_after(clock::now(), after))
+    }
+
+    /// Creates and returns a new [`CoalescedSleep`] future that will complete at the provided
+    /// deadline, rounded up to the coalescer's quantum.
+    ///
+    /// ## Example
+    ///
+    /// ```rust
+    /// # use futures_lite::future;
+    /// use smol_timeout::coalesce::Coalescer;
+    /// use std::time::{Duration, Instant};
+    ///
+    /// # future::block_on(async {
+    /// #
+    /// let coalescer = Coalescer::new(Duration::from_millis(100));
+    /// let deadline = Instant::now() + Duration::from_millis(50);
+    ///
+    /// let foo = coalescer.sleep_until(deadline);
+    /// let bar = coalescer.sleep_until(deadline + Duration::from_millis(1));
+    /// assert_eq!(coalescer.timers(), 1);
+    ///
+    /// future::zip(foo, bar).await;
+    /// assert_eq!(coalescer.timers(), 0);
+    /// #
+    /// # })
+    /// ```
+    pub fn sleep_until(&self, deadline: Instant) -> CoalescedSleep {
+        self.inner.sleep_until(deadline)
+    }
+
+    /// Given a future and a [`Duration`], creates and returns a new [`CoalescedTimeout`] that
+    /// will poll both the future and a [`CoalescedSleep`] that will complete after the provided
+    /// duration, and return the future's output or [`None`] if the sleep completes first.
+    #[track_caller]
+    pub fn timeout<Fut: Future>(&self, future: Fut, after: Duration) -> CoalescedTimeout<Fut> {
+        Timeout::armed(future, self.sleep(after), after)
+    }
+}
+
+impl fmt::Debug for Coalescer {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        fmt.debug_struct("Coalescer")
+            .field("quantum", &self.inner.quantum)
+            .field("timers", &self.timers())
+            .finish()
+    }
+}
+
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                                   struct CoalescedSleep                                    │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+/// A future completing once the [`Timer`] it shares with the other timers of a [`Coalescer`]
+/// rounding to the same deadline fires.
+///
+/// A [`CoalescedSleep`] is a [`DeadlineSource`], whose deadline is the rounded one: rearming it
+/// moves it to the bucket of its new deadline. The shared timer is dropped once all the
+/// [`CoalescedSleep`]s sharing it are dropped or rearmed.
+#[must_use = "futures do nothing unless you `.await` or poll them"]
+pub struct CoalescedSleep {
+    inner: Arc<Inner>,
+    index: u64,
+    key: u64,
+    /// The deadline of the bucket.
+    deadline: Instant,
+}
+
+impl Future for CoalescedSleep {
+    type Output = ();
+
+    fn poll(self: Pin<&mut Self>, ctx: &mut Context) -> Poll<()> {
+        let mut buckets = self.inner.buckets.lock().unwrap();
+        let bucket = buckets
+            .get_mut(&self.index)
+            .expect("a sleep's bucket is only removed once it is dropped");
+
+        if bucket.fired {
+            return Poll::Ready(());
+        }
+
+        bucket.wakers.register(self.key, ctx.waker());
+
+        // The shared timer is polled with a waker waking all the sleeps sharing it.
+        let waker = bucket.wakers.waker();
+        if Pin::new(&mut bucket.timer)
+            .poll(&mut Context::from_waker(&waker))
+            .is_pending()
+        {
+            return Poll::Pending;
+        }
+
+        bucket.fired = true;
+        let wakers = bucket.wakers.clone();
+        drop(buckets);
+
+        wakers.wake_all();
+        Poll::Ready(())
+    }
+}
+
+impl DeadlineSource for CoalescedSleep {
+    fn now(&self) -> Instant {
+        clock::now()
+    }
+
+    fn deadline(&self) -> Option<Instant> {
+        Some(self.deadline)
+    }
+
+    fn set_at(&mut self, at: Instant) {
+        // Joining the new bucket before leaving the old one, so that a sleep rearmed to the same
+        // bucket doesn't drop its timer.
+        let sleep = self.inner.sleep_until(at);
+        *self = sleep;
+    }
+
+    fn fork(&self) -> Self {
+        self.inner.sleep_until(self.deadline)
+    }
+}
+
+impl Drop for CoalescedSleep {
+    fn drop(&mut self) {
+        self.inner.release(self.index, self.key);
+    }
+}
+
+impl fmt::Debug for CoalescedSleep {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        fmt.debug_struct("CoalescedSleep")
+            .field("deadline", &self.deadline)
+            .finish()
+    }
+}
+
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                                 type CoalescedTimeout<Fut>                                 │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+/// A [`Timeout`] whose deadline is a [`CoalescedSleep`], and which can thus be shortened,
+/// restarted and cloned like any other.
+///
+/// Created by [`Coalescer::timeout`].
+pub type CoalescedTimeout<Fut> = Timeout<Fut, CoalescedSleep>;
@@ -0,0 +1,161 @@
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                                                                                            │ *
+ * │ This Source Code Form is subject to the terms of the Mozilla Public                        │ *
+ * │ License, v. 2.0. If a copy of the MPL was not distributed with this                        │ *
+ * │ file, You can obtain one at http://mozilla.org/MPL/2.0/.                                   │ *
+ * │                                                                                            │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                                       Documentation                                        │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+//! An asynchronous condition variable for [`async_lock`]'s [`Mutex`], with the ability to wait
+//! with a timeout.
+//!
+//! ## Example
+//!
+//! ```rust
+//! use async_lock::Mutex;
+//! # use futures_lite::future;
+//! use smol_timeout::condvar::Condvar;
+//! use std::time::Duration;
+//!
+//! # future::block_on(async {
+//! #
+//! let mutex = Mutex::new(false);
+//! let condvar = Condvar::new();
+//!
+//! let guard = mutex.lock().await;
+//! let (guard, result) = condvar.wait_timeout(guard, Duration::from_millis(100)).await;
+//!
+//! assert!(result.timed_out());
+//! assert!(!*guard);
+//! #
+//! # });
+//! ```
+
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                                          Imports                                           │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+use crate::event;
+use async_lock::{Mutex, MutexGuard};
+use core::time::Duration;
+use event_listener::Event;
+
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                                  struct WaitTimeoutResult                                  │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+/// Whether a wait on a [`Condvar`] timed out (see [`Condvar::wait_timeout`]).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct WaitTimeoutResult(bool);
+
+impl WaitTimeoutResult {
+    /// Returns `true` if the wait timed out instead of being notified.
+    pub fn timed_out(&self) -> bool {
+        self.0
+    }
+}
+
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                                       struct Condvar                                       │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+/// An asynchronous condition variable, mirroring [`std::sync::Condvar`]'s API for
+/// [`async_lock`]'s [`Mutex`].
+///
+/// Like with [`std::sync::Condvar`], waits can wake up spuriously, so the condition should be
+/// checked again in a loop after every wait.
+///
+/// ## Example
+///
+/// ```rust
+/// use async_executor::Executor;
+/// use async_lock::Mutex;
+/// # use futures_lite::future;
+/// use smol_timeout::condvar::Condvar;
+/// use std::sync::Arc;
+/// use std::time::Duration;
+///
+/// let ex = Executor::new();
+///
+/// # future::block_on(ex.run(async {
+/// #
+/// let pair = Arc::new((Mutex::new(false), Condvar::new()));
+///
+/// ex.spawn({
+///     let pair = pair.clone();
+///
+///     async move {
+///         let (mutex, condvar) = &*pair;
+///         *mutex.lock().await = true;
+///         condvar.notify_one();
+///     }
+/// })
+/// .detach();
+///
+/// let (mutex, condvar) = &*pair;
+/// let mut ready = mutex.lock().await;
+/// while !*ready {
+///     let (guard, result) = condvar.wait_timeout(ready, Duration::from_secs(1)).await;
+///     assert!(!result.timed_out());
+///     ready = guard;
+/// }
+/// #
+/// # }))
+/// ```
+#[derive(Debug, Default)]
+pub struct Condvar {
+    event: Event,
+}
+
+impl Condvar {
+    /// Creates a new [`Condvar`].
+    pub fn new() -> Self {
+        Condvar {
+            event: Event::new(),
+        }
+    }
+
+    /// Unlocks the mutex guarded by `guard` and waits for a notification, before locking the
+    /// mutex again and returning a new guard.
+    pub async fn wait<'a, T: ?Sized>(&self, guard: MutexGuard<'a, T>) -> MutexGuard<'a, T> {
+        let mutex: &'a Mutex<T> = MutexGuard::source(&guard);
+        let listener = self.event.listen();
+        drop(guard);
+
+        listener.await;
+        mutex.lock().await
+    }
+
+    /// Unlocks the mutex guarded by `guard` and waits for a notification for at most the provided
+    /// duration, before locking the mutex again and returning a new guard along with whether the
+    /// wait timed out.
+    ///
+    /// The timeout only bounds the wait for a notification: locking the mutex again afterwards
+    /// can take longer.
+    pub async fn wait_timeout<'a, T: ?Sized>(
+        &self,
+        guard: MutexGuard<'a, T>,
+        after: Duration,
+    ) -> (MutexGuard<'a, T>, WaitTimeoutResult) {
+        let mutex: &'a Mutex<T> = MutexGuard::source(&guard);
+        let listener = self.event.listen();
+        drop(guard);
+
+        let notified = event::wait_timeout(listener, after).await;
+        (mutex.lock().await, WaitTimeoutResult(!notified))
+    }
+
+    /// Wakes up one task waiting on this condition variable.
+    pub fn notify_one(&self) {
+        self.event.notify_additional(1);
+    }
+
+    /// Wakes up all the tasks waiting on this condition variable.
+    pub fn notify_all(&self) {
+        self.event.notify(usize::MAX);
+    }
+}
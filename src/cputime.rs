@@ -0,0 +1,151 @@
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                                                                                            │ *
+ * │ This Source Code Form is subject to the terms of the Mozilla Public                        │ *
+ * │ License, v. 2.0. If a copy of the MPL was not distributed with this                        │ *
+ * │ file, You can obtain one at http://mozilla.org/MPL/2.0/.                                   │ *
+ * │                                                                                            │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                                       Documentation                                        │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+//! Timeouts measured in CPU time, for compute-bound futures.
+//!
+//! A [`CpuTimeout`] measures the CPU time its thread consumes inside every `poll` of its
+//! future (using `CLOCK_THREAD_CPUTIME_ID`), and only completes without the future's output
+//! once their sum exceeds its budget. Unlike wall-clock timeouts, it doesn't fire because the
+//! machine is loaded or the future is waiting on I/O: only the future's own work is charged.
+//!
+//! Since CPU time is only consumed while the future is polled, the budget is checked after
+//! every poll rather than by a timer: a future blocking inside a single `poll` for longer than
+//! its budget is only interrupted once that `poll` returns.
+//!
+//! Only available on Unix platforms, with the `cputime` feature.
+//!
+//! ## Example
+//!
+//! ```rust
+//! use async_io::Timer;
+//! # use futures_lite::future;
+//! use smol_timeout::cputime::CpuTimeoutExt;
+//! use std::time::Duration;
+//!
+//! # future::block_on(async {
+//! #
+//! // Waiting doesn't consume CPU time.
+//! let foo = async {
+//!     Timer::after(Duration::from_millis(100)).await;
+//!     42
+//! };
+//!
+//! assert_eq!(foo.timeout_cpu(Duration::from_millis(50)).await, Some(42));
+//!
+//! // Spinning does.
+//! let bar = async {
+//!     loop {
+//!         future::yield_now().await;
+//!     }
+//! };
+//!
+//! assert_eq!(bar.timeout_cpu(Duration::from_millis(50)).await, None::<()>);
+//! #
+//! # });
+//! ```
+
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                                          Imports                                           │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+use core::future::Future;
+use core::pin::Pin;
+use core::task::{Context, Poll};
+use core::time::Duration;
+use pin_project_lite::pin_project;
+
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                                      fn thread_cpu()                                       │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+/// Returns the CPU time consumed by the current thread.
+fn thread_cpu() -> Duration {
+    let mut ts = libc::timespec {
+        tv_sec: 0,
+        tv_nsec: 0,
+    };
+
+    // SAFETY: `ts` is a valid `timespec`.
+    let res = unsafe { libc::clock_gettime(libc::CLOCK_THREAD_CPUTIME_ID, &mut ts) };
+    assert_eq!(res, 0, "failed to read CLOCK_THREAD_CPUTIME_ID");
+
+    Duration::new(ts.tv_sec as u64, ts.tv_nsec as u32)
+}
+
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                                   struct CpuTimeout<Fut>                                   │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+pin_project! {
+    /// A future polling another future, and returning its output or [`None`] once the CPU time
+    /// consumed inside its `poll` exceeds a budget.
+    #[derive(Debug)]
+    #[must_use = "futures do nothing unless you `.await` or poll them"]
+    pub struct CpuTimeout<Fut> {
+        #[pin]
+        future: Fut,
+        budget: Duration,
+        used: Duration,
+    }
+}
+
+impl<Fut> CpuTimeout<Fut> {
+    /// Returns the CPU time consumed by the future so far.
+    pub fn used(&self) -> Duration {
+        self.used
+    }
+}
+
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                                trait CpuTimeoutExt: Future                                 │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+/// An extension trait for [`Future`]s that provides a way to create [`CpuTimeout`]s.
+pub trait CpuTimeoutExt: Future {
+    /// Given a budget, creates and returns a new [`CpuTimeout`] that will poll the future and
+    /// return its output, or [`None`] once the CPU time consumed inside its `poll` exceeds the
+    /// budget.
+    fn timeout_cpu(self, budget: Duration) -> CpuTimeout<Self>
+    where
+        Self: Sized,
+    {
+        CpuTimeout {
+            future: self,
+            budget,
+            used: Duration::from_secs(0),
+        }
+    }
+}
+
+impl<Fut: Future> CpuTimeoutExt for Fut {}
+
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                              impl Future for CpuTimeout<Fut>                               │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+impl<Fut: Future> Future for CpuTimeout<Fut> {
+    type Output = Option<Fut::Output>;
+
+    fn poll(self: Pin<&mut Self>, ctx: &mut Context) -> Poll<Self::Output> {
+        let this = self.project();
+
+        let start = thread_cpu();
+        let poll = this.future.poll(ctx);
+        *this.used += thread_cpu().saturating_sub(start);
+
+        match poll {
+            Poll::Ready(output) => Poll::Ready(Some(output)),
+            Poll::Pending if *this.used >= *this.budget => Poll::Ready(None),
+            Poll::Pending => Poll::Pending,
+        }
+    }
+}
@@ -0,0 +1,383 @@
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                                                                                            │ *
+ * │ This Source Code Form is subject to the terms of the Mozilla Public                        │ *
+ * │ License, v. 2.0. If a copy of the MPL was not distributed with this                        │ *
+ * │ file, You can obtain one at http://mozilla.org/MPL/2.0/.                                   │ *
+ * │                                                                                            │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                                       Documentation                                        │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+//! Saturating arithmetic on deadlines, [`TimeBudget`]s to divide the time left before one, and
+//! [`Deadline`]s shared by several awaits.
+//!
+//! These helpers compute how much time is left before a deadline, how long ago it passed, or
+//! how to divide what is left, without the underflows of [`Instant`] subtraction. They read the
+//! same clock as the timeouts of this crate (i.e. the virtual clock inside a simulation, with
+//! the `sim` feature).
+//!
+//! ## Example
+//!
+//! ```rust
+//! use smol_timeout::deadline;
+//! use std::time::{Duration, Instant};
+//!
+//! let start = Instant::now();
+//! let deadline = start + Duration::from_secs(60);
+//!
+//! assert!(deadline::remaining_until(deadline) <= Duration::from_secs(60));
+//! assert_eq!(deadline::exceeded_by(deadline), Duration::from_secs(0));
+//! assert!(deadline::fraction_elapsed(start, deadline) < 0.5);
+//! assert!(deadline::split_evenly(deadline, 3) <= Duration::from_secs(20));
+//!
+//! let passed = start - Duration::from_secs(1);
+//! assert_eq!(deadline::remaining_until(passed), Duration::from_secs(0));
+//! assert!(deadline::exceeded_by(passed) >= Duration::from_secs(1));
+//! assert_eq!(deadline::fraction_elapsed(passed - Duration::from_secs(1), passed), 1.0);
+//! ```
+//!
+//! ## Redundant timeouts
+//!
+//! With the `log` feature, debug builds (i.e. with `debug_assertions`) keep track of the
+//! earliest deadline of the [`Timeout`]s polling their future on each thread,
+//! and log a warning (with the `smol_timeout` target) when a timeout created while polling
+//! them is set to complete strictly later: such a timeout can never fire first, and is likely
+//! dead configuration.
+
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                                          Imports                                           │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+use crate::{clock, Elapsed, Timeout};
+use core::future::Future;
+use core::pin::Pin;
+use core::task::{Context, Poll};
+use core::time::Duration;
+use pin_project_lite::pin_project;
+use std::time::Instant;
+
+#[cfg(all(feature = "log", debug_assertions))]
+use core::cell::Cell;
+#[cfg(all(feature = "log", debug_assertions))]
+use core::panic::Location;
+
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                                         Functions                                          │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+/// Returns the time left before the provided deadline, or zero if it passed.
+pub fn remaining_until(deadline: Instant) -> Duration {
+    deadline.saturating_duration_since(clock::now())
+}
+
+/// Returns how long ago the provided deadline passed, or zero if it didn't.
+pub fn exceeded_by(deadline: Instant) -> Duration {
+    clock::now().saturating_duration_since(deadline)
+}
+
+/// Returns the fraction of the time between `start` and `deadline` which elapsed, between `0.0`
+/// and `1.0`.
+///
+/// Returns `1.0` if `deadline` isn't later than `start`.
+pub fn fraction_elapsed(start: Instant, deadline: Instant) -> f64 {
+    let total = deadline.saturating_duration_since(start);
+    if total == Duration::from_secs(0) {
+        return 1.0;
+    }
+
+    let elapsed = clock::now().saturating_duration_since(start);
+    (elapsed.as_secs_f64() / total.as_secs_f64()).min(1.0)
+}
+
+/// Returns the duration of each of `parts` equal parts of the time left before the provided
+/// deadline, e.g. to give each of several sequential attempts the same share of it.
+///
+/// ## Panics
+///
+/// Panics if `parts` is zero.
+pub fn split_evenly(deadline: Instant, parts: u32) -> Duration {
+    assert!(parts > 0, "the time left can't be split in zero parts");
+    remaining_until(deadline) / parts
+}
+
+/// The duration after which a deadline is considered to never be reached, used as the largest
+/// [`Instant`] depends on the platform.
+const FAR_FUTURE: Duration = Duration::from_secs(30 * 365 * 24 * 60 * 60);
+
+/// Returns the deadline `after` past `now`, saturating at a deadline that is never reached
+/// (about 30 years later) instead of overflowing, e.g. for [`Duration::MAX`].
+pub(crate) fn deadline_after(now: Instant, after: Duration) -> Instant {
+    now.checked_add(after)
+        .or_else(|| now.checked_add(FAR_FUTURE))
+        .unwrap_or(now)
+}
+
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                                     struct TimeBudget                                      │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+/// The time left before a deadline, to be divided among the sequential steps of an operation
+/// (e.g. connecting, authenticating and querying within a single SLA).
+///
+/// A [`TimeBudget`] only stores its deadline, so that the time each step spends is naturally
+/// deducted from what is left for the following ones. Sub-budgets for a single step can be
+/// carved out with [`take`](TimeBudget::take), [`fraction`](TimeBudget::fraction) or
+/// [`split`](TimeBudget::split), and converted into timeouts with
+/// [`timeout`](TimeBudget::timeout).
+///
+/// ## Example
+///
+/// ```rust
+/// use async_io::Timer;
+/// # use futures_lite::future;
+/// use smol_timeout::deadline::TimeBudget;
+/// use std::time::Duration;
+///
+/// # future::block_on(async {
+/// #
+/// let budget = TimeBudget::new(Duration::from_millis(500));
+///
+/// let connect = Timer::after(Duration::from_millis(50));
+/// assert!(budget.take(Duration::from_millis(100)).timeout(connect).await.is_some());
+///
+/// let authenticate = Timer::after(Duration::from_millis(250));
+/// assert!(budget.fraction(0.3).timeout(authenticate).await.is_none());
+///
+/// let query = Timer::after(Duration::from_millis(100));
+/// assert!(budget.timeout(query).await.is_some());
+/// assert!(budget.remaining() < Duration::from_millis(300));
+/// #
+/// # })
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct TimeBudget {
+    deadline: Instant,
+}
+
+impl TimeBudget {
+    /// Creates a new [`TimeBudget`] of the provided duration, starting now.
+    pub fn new(total: Duration) -> Self {
+        TimeBudget::until(deadline_after(clock::now(), total))
+    }
+
+    /// Creates a new [`TimeBudget`] lasting until the provided deadline.
+    pub fn until(deadline: Instant) -> Self {
+        TimeBudget { deadline }
+    }
+
+    /// Returns the deadline of the budget.
+    pub fn deadline(&self) -> Instant {
+        self.deadline
+    }
+
+    /// Returns the time left in the budget, or zero if it is exhausted.
+    pub fn remaining(&self) -> Duration {
+        remaining_until(self.deadline)
+    }
+
+    /// Returns whether the deadline of the budget passed.
+    pub fn is_exhausted(&self) -> bool {
+        self.remaining() == Duration::from_secs(0)
+    }
+
+    /// Returns a sub-budget of at most `max`, i.e. ending after `max` or with this budget,
+    /// whichever comes first.
+    pub fn take(&self, max: Duration) -> TimeBudget {
+        TimeBudget::until(self.deadline.min(deadline_after(clock::now(), max)))
+    }
+
+    /// Returns a sub-budget of the provided fraction (between `0.0` and `1.0`) of the time
+    /// left in this budget.
+    ///
+    /// ## Panics
+    ///
+    /// Panics if `fraction` isn't between `0.0` and `1.0`.
+    pub fn fraction(&self, fraction: f64) -> TimeBudget {
+        assert!(
+            (0.0..=1.0).contains(&fraction),
+            "a budget's fraction must be between 0 and 1"
+        );
+
+        self.take(self.remaining().mul_f64(fraction))
+    }
+
+    /// Returns a sub-budget of one of `parts` equal parts of the time left in this budget, e.g.
+    /// for each of several sequential attempts.
+    ///
+    /// ## Panics
+    ///
+    /// Panics if `parts` is zero.
+    pub fn split(&self, parts: u32) -> TimeBudget {
+        self.take(split_evenly(self.deadline, parts))
+    }
+
+    /// Given a future, creates and returns a new [`Timeout`] that will poll both the future and
+    /// a [`Timer`](crate::Timer) that will complete at the budget's deadline, and return the
+    /// future's output or [`None`] if the timer completes first.
+    pub fn timeout<Fut: Future>(&self, future: Fut) -> Timeout<Fut> {
+        Timeout::at(future, self.deadline)
+    }
+}
+
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                                      struct Deadline                                       │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+/// A deadline shared by several awaits, e.g. all the reads of the frames of a request.
+///
+/// Every call to [`timeout`](Deadline::timeout) bounds its future to the time left before the
+/// deadline, and the returned [`DeadlineTimeout`] returns an [`Elapsed`] error without polling
+/// its future at all once the deadline passed, so that a loop awaiting them ends as soon as the
+/// deadline passes.
+///
+/// ## Example
+///
+/// ```rust
+/// use async_io::Timer;
+/// # use futures_lite::future;
+/// use smol_timeout::deadline::Deadline;
+/// use std::time::Duration;
+///
+/// # future::block_on(async {
+/// #
+/// let deadline = Deadline::new(Duration::from_millis(250));
+///
+/// let mut frames = 0;
+/// while deadline
+///     .timeout(Timer::after(Duration::from_millis(100)))
+///     .await
+///     .is_ok()
+/// {
+///     frames += 1;
+/// }
+///
+/// assert_eq!(frames, 2);
+/// assert!(deadline.is_exhausted());
+/// assert!(deadline.timeout(future::ready(42)).await.is_err());
+/// #
+/// # })
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Deadline {
+    at: Instant,
+}
+
+impl Deadline {
+    /// Creates a new [`Deadline`] passing after the provided duration.
+    pub fn new(after: Duration) -> Self {
+        Deadline::at(deadline_after(clock::now(), after))
+    }
+
+    /// Creates a new [`Deadline`] passing at the provided instant.
+    pub fn at(at: Instant) -> Self {
+        Deadline { at }
+    }
+
+    /// Returns the instant the deadline passes at.
+    pub fn instant(&self) -> Instant {
+        self.at
+    }
+
+    /// Returns the time left before the deadline, or zero if it passed.
+    pub fn remaining(&self) -> Duration {
+        remaining_until(self.at)
+    }
+
+    /// Returns whether the deadline passed.
+    pub fn is_exhausted(&self) -> bool {
+        self.remaining() == Duration::from_secs(0)
+    }
+
+    /// Given a future, creates and returns a new [`DeadlineTimeout`] that will poll both the
+    /// future and a [`Timer`](crate::Timer) that will complete at the deadline, and return the
+    /// future's output or an [`Elapsed`] error if the timer completes first, or if the deadline
+    /// already passed.
+    pub fn timeout<Fut: Future>(&self, future: Fut) -> DeadlineTimeout<Fut> {
+        DeadlineTimeout {
+            inner: if self.is_exhausted() {
+                None
+            } else {
+                Some(Timeout::at(future, self.at))
+            },
+        }
+    }
+}
+
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                                struct DeadlineTimeout<Fut>                                 │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+pin_project! {
+    /// A future polling both another future and a [`Timer`](crate::Timer) that will complete at
+    /// a [`Deadline`], and returning the future's output or an [`Elapsed`] error if the timer
+    /// completes first.
+    ///
+    /// Created by [`Deadline::timeout`].
+    #[derive(Debug)]
+    #[must_use = "futures do nothing unless you `.await` or poll them"]
+    pub struct DeadlineTimeout<Fut: Future> {
+        // `None` if the deadline had already passed when the timeout was created.
+        #[pin]
+        inner: Option<Timeout<Fut>>,
+    }
+}
+
+impl<Fut: Future> Future for DeadlineTimeout<Fut> {
+    type Output = Result<Fut::Output, Elapsed>;
+
+    fn poll(self: Pin<&mut Self>, ctx: &mut Context) -> Poll<Self::Output> {
+        match self.project().inner.as_pin_mut() {
+            Some(inner) => inner.poll(ctx).map(|output| output.ok_or(Elapsed::new())),
+            None => Poll::Ready(Err(Elapsed::new())),
+        }
+    }
+}
+
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                                      Ambient deadline                                      │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+#[cfg(all(feature = "log", debug_assertions))]
+std::thread_local! {
+    /// The earliest deadline of the timeouts currently polling their future on this thread.
+    static AMBIENT: Cell<Option<Instant>> = const { Cell::new(None) };
+}
+
+/// Restores the ambient deadline that was current before [`enter`] when dropped.
+#[cfg(all(feature = "log", debug_assertions))]
+pub(crate) struct Ambient(Option<Instant>);
+
+/// Makes the provided deadline the ambient one, if it is earlier than the current one, until
+/// the returned guard is dropped.
+#[cfg(all(feature = "log", debug_assertions))]
+pub(crate) fn enter(deadline: Option<Instant>) -> Ambient {
+    let previous = AMBIENT.with(Cell::get);
+    let current = match (previous, deadline) {
+        (Some(previous), Some(deadline)) => Some(previous.min(deadline)),
+        (previous, deadline) => previous.or(deadline),
+    };
+
+    AMBIENT.with(|ambient| ambient.set(current));
+    Ambient(previous)
+}
+
+#[cfg(all(feature = "log", debug_assertions))]
+impl Drop for Ambient {
+    fn drop(&mut self) {
+        AMBIENT.with(|ambient| ambient.set(self.0));
+    }
+}
+
+/// Logs a warning if the provided deadline, of a timeout configured for `after`, is later than
+/// the ambient one, and can thus never elapse first.
+#[cfg(all(feature = "log", debug_assertions))]
+#[track_caller]
+pub(crate) fn check(deadline: Instant, after: Duration) {
+    if let Some(ambient) = AMBIENT.with(Cell::get) {
+        if deadline > ambient {
+            crate::log::redundant(Location::caller(), after, remaining_until(ambient));
+        }
+    }
+}
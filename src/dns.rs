@@ -0,0 +1,142 @@
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                                                                                            │ *
+ * │ This Source Code Form is subject to the terms of the Mozilla Public                        │ *
+ * │ License, v. 2.0. If a copy of the MPL was not distributed with this                        │ *
+ * │ file, You can obtain one at http://mozilla.org/MPL/2.0/.                                   │ *
+ * │                                                                                            │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                                       Documentation                                        │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+//! Hostname resolutions with a timeout.
+//!
+//! Resolving a hostname with [`ToSocketAddrs`] blocks, for as long as the system's resolver
+//! takes, which is a classic source of unbounded waits. With the `blocking` feature,
+//! [`resolve_timeout`] runs it on the [`blocking`] thread pool for at most a duration, and
+//! [`resolve_with`] bounds any other resolver's future the same way, both returning a
+//! [`ResolveError::Timeout`] if the resolution doesn't complete in time.
+//!
+//! ## Example
+//!
+//! ```rust
+//! # use futures_lite::future;
+//! use smol_timeout::dns;
+//! use std::net::SocketAddr;
+//! use std::time::Duration;
+//!
+//! # future::block_on(async {
+//! #
+//! let addrs = dns::resolve_timeout(("127.0.0.1", 8080), Duration::from_secs(1)).await?;
+//! assert_eq!(addrs, vec![SocketAddr::from(([127, 0, 0, 1], 8080))]);
+//!
+//! let slow = async {
+//!     future::pending::<()>().await;
+//!     Ok(Vec::new())
+//! };
+//!
+//! let err = dns::resolve_with(slow, Duration::from_millis(100)).await.unwrap_err();
+//! assert!(matches!(err, dns::ResolveError::Timeout));
+//! #
+//! # Ok::<_, dns::ResolveError>(())
+//! # }).unwrap();
+//! ```
+
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                                          Imports                                           │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+use crate::unblock::unblock_with_timeout;
+use crate::{Elapsed, TimeoutExt};
+use core::fmt;
+use core::future::Future;
+use core::time::Duration;
+use std::io;
+use std::net::{SocketAddr, ToSocketAddrs};
+use std::vec::Vec;
+
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                                     enum ResolveError                                      │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+/// The error returned when a resolution failed or didn't complete in time.
+#[derive(Debug)]
+pub enum ResolveError {
+    /// The resolution didn't complete before the timeout.
+    Timeout,
+    /// The resolution failed.
+    Io(io::Error),
+}
+
+impl fmt::Display for ResolveError {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ResolveError::Timeout => fmt.write_str("resolution didn't complete in time"),
+            ResolveError::Io(err) => fmt::Display::fmt(err, fmt),
+        }
+    }
+}
+
+impl std::error::Error for ResolveError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            ResolveError::Timeout => None,
+            ResolveError::Io(err) => Some(err),
+        }
+    }
+}
+
+impl From<ResolveError> for io::Error {
+    fn from(err: ResolveError) -> Self {
+        match err {
+            ResolveError::Timeout => Elapsed::new().into(),
+            ResolveError::Io(err) => err,
+        }
+    }
+}
+
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                                    fn resolve_timeout()                                    │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+/// Resolves the address with [`ToSocketAddrs`] on the [`blocking`] thread pool, returning the
+/// resolved addresses or a [`ResolveError::Timeout`] if it doesn't complete before the provided
+/// duration.
+///
+/// When the timeout fires first, the resolution keeps running detached, and its result is
+/// dropped once it completes.
+pub async fn resolve_timeout<A>(addr: A, after: Duration) -> Result<Vec<SocketAddr>, ResolveError>
+where
+    A: ToSocketAddrs + Send + 'static,
+{
+    let resolve = move || addr.to_socket_addrs().map(Iterator::collect);
+
+    match unblock_with_timeout(after, resolve).await {
+        Some(res) => res.map_err(ResolveError::Io),
+        None => Err(ResolveError::Timeout),
+    }
+}
+
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                                     fn resolve_with()                                      │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+/// Awaits the resolution of a pluggable (e.g. asynchronous) resolver, returning the resolved
+/// addresses or a [`ResolveError::Timeout`] if it doesn't complete before the provided
+/// duration.
+pub async fn resolve_with<Fut, I>(
+    resolve: Fut,
+    after: Duration,
+) -> Result<Vec<SocketAddr>, ResolveError>
+where
+    Fut: Future<Output = io::Result<I>>,
+    I: IntoIterator<Item = SocketAddr>,
+{
+    match resolve.timeout(after).await {
+        Some(res) => res
+            .map(|addrs| addrs.into_iter().collect())
+            .map_err(ResolveError::Io),
+        None => Err(ResolveError::Timeout),
+    }
+}
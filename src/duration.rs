@@ -0,0 +1,149 @@
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                                                                                            │ *
+ * │ This Source Code Form is subject to the terms of the Mozilla Public                        │ *
+ * │ License, v. 2.0. If a copy of the MPL was not distributed with this                        │ *
+ * │ file, You can obtain one at http://mozilla.org/MPL/2.0/.                                   │ *
+ * │                                                                                            │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                                       Documentation                                        │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+//! Timeouts accepting the signed duration types of other crates.
+//!
+//! Durations read from configuration files or databases are often [`chrono::TimeDelta`]s (with
+//! the `chrono` feature) or [`time::Duration`]s (with the `time` feature), which can be
+//! negative. [`TryTimeoutExt::try_timeout`] accepts any [`TryIntoDuration`], and returns a
+//! [`NegativeDuration`] error instead of a timeout if the duration is negative. As both types
+//! hold at most [`i64::MAX`] seconds, their non-negative values always fit in a [`Duration`].
+//!
+//! [`chrono::TimeDelta`]: https://docs.rs/chrono/latest/chrono/struct.TimeDelta.html
+//! [`time::Duration`]: https://docs.rs/time/latest/time/struct.Duration.html
+//!
+//! ## Example
+//!
+//! ```rust
+//! use async_io::Timer;
+//! # use futures_lite::future;
+//! use smol_timeout::duration::TryTimeoutExt;
+//! use std::time::Duration;
+//!
+//! # future::block_on(async {
+//! #
+//! let foo = async {
+//!     Timer::after(Duration::from_millis(250)).await;
+//!     24
+//! };
+//!
+//! let foo = foo.try_timeout(Duration::from_millis(100)).unwrap();
+//! assert_eq!(foo.await, None);
+//! #
+//! # });
+//! ```
+
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                                          Imports                                           │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+use crate::{Timeout, TimeoutExt};
+use core::fmt;
+use core::future::Future;
+use core::time::Duration;
+
+#[cfg(feature = "time")]
+use core::convert::TryFrom;
+
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                                  struct NegativeDuration                                   │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+/// The error returned when a negative duration was provided as a timeout.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct NegativeDuration;
+
+impl fmt::Display for NegativeDuration {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        fmt.write_str("a timeout's duration can't be negative")
+    }
+}
+
+impl std::error::Error for NegativeDuration {}
+
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                                   trait TryIntoDuration                                    │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+/// A duration that can be converted into a [`Duration`], unless it is negative.
+pub trait TryIntoDuration {
+    /// Converts the duration into a [`Duration`], returning a [`NegativeDuration`] error if it
+    /// is negative.
+    fn try_into_duration(self) -> Result<Duration, NegativeDuration>;
+}
+
+impl TryIntoDuration for Duration {
+    fn try_into_duration(self) -> Result<Duration, NegativeDuration> {
+        Ok(self)
+    }
+}
+
+/// ## Example
+///
+/// ```rust
+/// use smol_timeout::duration::{NegativeDuration, TryIntoDuration};
+/// use std::time::Duration;
+///
+/// let after = chrono::TimeDelta::milliseconds(100);
+/// assert_eq!(after.try_into_duration(), Ok(Duration::from_millis(100)));
+///
+/// let after = chrono::TimeDelta::milliseconds(-100);
+/// assert_eq!(after.try_into_duration(), Err(NegativeDuration));
+/// ```
+#[cfg(feature = "chrono")]
+impl TryIntoDuration for chrono::TimeDelta {
+    fn try_into_duration(self) -> Result<Duration, NegativeDuration> {
+        self.to_std().map_err(|_| NegativeDuration)
+    }
+}
+
+/// ## Example
+///
+/// ```rust
+/// use smol_timeout::duration::{NegativeDuration, TryIntoDuration};
+/// use std::time::Duration;
+///
+/// let after = time::Duration::milliseconds(100);
+/// assert_eq!(after.try_into_duration(), Ok(Duration::from_millis(100)));
+///
+/// let after = time::Duration::milliseconds(-100);
+/// assert_eq!(after.try_into_duration(), Err(NegativeDuration));
+/// ```
+#[cfg(feature = "time")]
+impl TryIntoDuration for time::Duration {
+    fn try_into_duration(self) -> Result<Duration, NegativeDuration> {
+        Duration::try_from(self).map_err(|_| NegativeDuration)
+    }
+}
+
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                                trait TryTimeoutExt: Future                                 │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+/// An extension trait for [`Future`]s that provides a way to create [`Timeout`]s from durations
+/// which may be negative.
+pub trait TryTimeoutExt: Future {
+    /// Given a duration, creates and returns a new [`Timeout`] that will poll both the future
+    /// and a [`Timer`](crate::Timer) that will complete after the provided duration, and return
+    /// the future's output or [`None`] if the timer completes first, or returns a
+    /// [`NegativeDuration`] error if the duration is negative.
+    fn try_timeout<D>(self, after: D) -> Result<Timeout<Self>, NegativeDuration>
+    where
+        Self: Sized,
+        D: TryIntoDuration,
+    {
+        Ok(self.timeout(after.try_into_duration()?))
+    }
+}
+
+impl<Fut: Future> TryTimeoutExt for Fut {}
@@ -0,0 +1,138 @@
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                                                                                            │ *
+ * │ This Source Code Form is subject to the terms of the Mozilla Public                        │ *
+ * │ License, v. 2.0. If a copy of the MPL was not distributed with this                        │ *
+ * │ file, You can obtain one at http://mozilla.org/MPL/2.0/.                                   │ *
+ * │                                                                                            │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                                       Documentation                                        │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+//! Timeouts for awaiting [`event_listener`]'s [`EventListener`]s.
+//!
+//! [`EventListener::wait_timeout`] and [`EventListener::wait_deadline`] block the current
+//! thread; the functions of this module are their asynchronous counterparts.
+//!
+//! ## Example
+//!
+//! ```rust
+//! use event_listener::Event;
+//! # use futures_lite::future;
+//! use smol_timeout::event;
+//! use std::time::Duration;
+//!
+//! # future::block_on(async {
+//! #
+//! let ev = Event::new();
+//!
+//! let listener = ev.listen();
+//! assert!(!event::wait_timeout(listener, Duration::from_millis(100)).await);
+//!
+//! let listener = ev.listen();
+//! ev.notify(1);
+//! assert!(event::wait_timeout(listener, Duration::from_millis(100)).await);
+//! #
+//! # });
+//! ```
+
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                                          Imports                                           │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+use crate::Timeout;
+use core::future::Future;
+use core::pin::Pin;
+use core::task::{Context, Poll};
+use core::time::Duration;
+use event_listener::EventListener;
+use std::time::Instant;
+
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                                     fn wait_timeout()                                      │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+/// Waits for the listener to be notified for at most the provided duration, returning `true`
+/// if it was notified or `false` if the wait expired.
+///
+/// If the wait expires, the listener is dropped, which passes any notification it received
+/// concurrently on to another listener.
+///
+/// ## Example
+///
+/// ```rust
+/// use event_listener::Event;
+/// # use futures_lite::future;
+/// use smol_timeout::event;
+/// use std::time::Duration;
+///
+/// # future::block_on(async {
+/// #
+/// let ev = Event::new();
+///
+/// let listener = ev.listen();
+/// assert!(!event::wait_timeout(listener, Duration::from_millis(100)).await);
+/// #
+/// # })
+/// ```
+pub fn wait_timeout(listener: EventListener, after: Duration) -> WaitTimeout {
+    WaitTimeout {
+        inner: Timeout::after(listener, after),
+    }
+}
+
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                                     fn wait_deadline()                                     │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+/// Waits for the listener to be notified until the provided deadline, returning `true` if it
+/// was notified or `false` if the wait expired.
+///
+/// ## Example
+///
+/// ```rust
+/// use event_listener::Event;
+/// # use futures_lite::future;
+/// use smol_timeout::event;
+/// use std::time::{Duration, Instant};
+///
+/// # future::block_on(async {
+/// #
+/// let ev = Event::new();
+///
+/// let listener = ev.listen();
+/// ev.notify(1);
+///
+/// let deadline = Instant::now() + Duration::from_millis(100);
+/// assert!(event::wait_deadline(listener, deadline).await);
+/// #
+/// # })
+/// ```
+pub fn wait_deadline(listener: EventListener, deadline: Instant) -> WaitTimeout {
+    WaitTimeout {
+        inner: Timeout::at(listener, deadline),
+    }
+}
+
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                                     struct WaitTimeout                                     │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+/// A future waiting for an [`EventListener`] to be notified with a timeout (see
+/// [`wait_timeout`] and [`wait_deadline`]).
+#[derive(Debug)]
+#[must_use = "futures do nothing unless you `.await` or poll them"]
+pub struct WaitTimeout {
+    inner: Timeout<EventListener>,
+}
+
+impl Future for WaitTimeout {
+    type Output = bool;
+
+    fn poll(mut self: Pin<&mut Self>, ctx: &mut Context) -> Poll<Self::Output> {
+        Pin::new(&mut self.inner)
+            .poll(ctx)
+            .map(|output| output.is_some())
+    }
+}
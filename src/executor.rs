@@ -0,0 +1,147 @@
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                                                                                            │ *
+ * │ This Source Code Form is subject to the terms of the Mozilla Public                        │ *
+ * │ License, v. 2.0. If a copy of the MPL was not distributed with this                        │ *
+ * │ file, You can obtain one at http://mozilla.org/MPL/2.0/.                                   │ *
+ * │                                                                                            │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                                       Documentation                                        │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+//! An [`Executor`] wrapper enforcing a default timeout on every spawned task.
+//!
+//! ## Example
+//!
+//! ```rust
+//! use async_io::Timer;
+//! # use futures_lite::future;
+//! use smol_timeout::executor::TimedExecutor;
+//! use std::time::Duration;
+//!
+//! let ex = TimedExecutor::new(Duration::from_millis(100));
+//!
+//! # future::block_on(ex.run(async {
+//! #
+//! let foo = ex.spawn(async {
+//!     Timer::after(Duration::from_millis(250)).await;
+//!     24
+//! });
+//!
+//! assert_eq!(foo.await, None);
+//!
+//! let bar = ex.spawn_with_timeout(Duration::from_millis(500), async {
+//!     Timer::after(Duration::from_millis(250)).await;
+//!     42
+//! });
+//!
+//! assert_eq!(bar.await, Some(42));
+//! assert_eq!(ex.reaped(), 1);
+//! #
+//! # }));
+//! ```
+
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                                          Imports                                           │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+use crate::TimeoutExt;
+use async_executor::{Executor, Task};
+use core::future::Future;
+use core::sync::atomic::{AtomicUsize, Ordering};
+use core::time::Duration;
+use std::sync::Arc;
+
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                                  struct TimedExecutor<'a>                                  │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+/// An [`Executor`] wrapper where every spawned task is cancelled once it runs for longer than a
+/// configurable default timeout, which can be overridden per spawn using
+/// [`spawn_with_timeout`](TimedExecutor::spawn_with_timeout).
+///
+/// Tasks spawned on a [`TimedExecutor`] output [`None`] if they were cancelled because of their
+/// timeout, and the executor counts how many tasks were reaped that way.
+#[derive(Debug)]
+pub struct TimedExecutor<'a> {
+    executor: Executor<'a>,
+    timeout: Duration,
+    reaped: Arc<AtomicUsize>,
+}
+
+impl<'a> TimedExecutor<'a> {
+    /// Creates a new [`TimedExecutor`] whose tasks will be cancelled after `timeout` by default.
+    pub fn new(timeout: Duration) -> Self {
+        TimedExecutor {
+            executor: Executor::new(),
+            timeout,
+            reaped: Arc::new(AtomicUsize::new(0)),
+        }
+    }
+
+    /// Returns the default timeout of the tasks spawned on this executor.
+    pub fn timeout(&self) -> Duration {
+        self.timeout
+    }
+
+    /// Returns the number of tasks that were cancelled because of their timeout.
+    pub fn reaped(&self) -> usize {
+        self.reaped.load(Ordering::Relaxed)
+    }
+
+    /// Returns the underlying [`Executor`], e.g. to spawn tasks without any timeout.
+    pub fn executor(&self) -> &Executor<'a> {
+        &self.executor
+    }
+
+    /// Spawns a task that will be cancelled if it doesn't complete before the executor's default
+    /// timeout, in which case its output is [`None`].
+    pub fn spawn<T, Fut>(&self, future: Fut) -> Task<Option<T>>
+    where
+        T: Send + 'a,
+        Fut: Future<Output = T> + Send + 'a,
+    {
+        self.spawn_with_timeout(self.timeout, future)
+    }
+
+    /// Spawns a task that will be cancelled if it doesn't complete before `timeout`, in which
+    /// case its output is [`None`].
+    pub fn spawn_with_timeout<T, Fut>(&self, timeout: Duration, future: Fut) -> Task<Option<T>>
+    where
+        T: Send + 'a,
+        Fut: Future<Output = T> + Send + 'a,
+    {
+        let reaped = self.reaped.clone();
+
+        self.executor.spawn(async move {
+            let output = future.timeout(timeout).await;
+            if output.is_none() {
+                reaped.fetch_add(1, Ordering::Relaxed);
+            }
+
+            output
+        })
+    }
+
+    /// Runs the executor until `future` completes (see [`Executor::run`]).
+    pub async fn run<T>(&self, future: impl Future<Output = T>) -> T {
+        self.executor.run(future).await
+    }
+
+    /// Runs a single task if one is scheduled, returning whether one was (see
+    /// [`Executor::try_tick`]).
+    pub fn try_tick(&self) -> bool {
+        self.executor.try_tick()
+    }
+
+    /// Runs a single task, waiting for one to be scheduled (see [`Executor::tick`]).
+    pub async fn tick(&self) {
+        self.executor.tick().await
+    }
+
+    /// Returns `true` if there are no unfinished tasks.
+    pub fn is_empty(&self) -> bool {
+        self.executor.is_empty()
+    }
+}
@@ -0,0 +1,270 @@
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                                                                                            │ *
+ * │ This Source Code Form is subject to the terms of the Mozilla Public                        │ *
+ * │ License, v. 2.0. If a copy of the MPL was not distributed with this                        │ *
+ * │ file, You can obtain one at http://mozilla.org/MPL/2.0/.                                   │ *
+ * │                                                                                            │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                                       Documentation                                        │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+//! A channel (built on top of [`async_channel`]) whose messages expire if they aren't received
+//! before their deadline.
+//!
+//! Expired messages are silently dropped by the receiver, or passed to a callback registered
+//! with [`Receiver::on_expired`]. This is useful for work queues where stale requests are
+//! worthless.
+//!
+//! ## Example
+//!
+//! ```rust
+//! use async_io::Timer;
+//! # use futures_lite::future;
+//! use smol_timeout::expiring;
+//! use std::time::Duration;
+//!
+//! # future::block_on(async {
+//! #
+//! let (sender, receiver) = expiring::unbounded();
+//!
+//! sender.send(24, Duration::from_millis(100)).await.unwrap();
+//! sender.send(42, Duration::from_millis(500)).await.unwrap();
+//!
+//! Timer::after(Duration::from_millis(250)).await;
+//! assert_eq!(receiver.recv().await, Ok(42));
+//! #
+//! # });
+//! ```
+
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                                          Imports                                           │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+use crate::{clock, deadline};
+use async_channel::{RecvError, SendError, TryRecvError, TrySendError};
+use core::fmt;
+use core::time::Duration;
+use std::sync::Arc;
+use std::time::Instant;
+
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                                         Functions                                          │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+/// Creates a bounded expiring channel (see [`async_channel::bounded`]).
+///
+/// Expired messages keep using the channel's capacity until a receiver drops them.
+pub fn bounded<T>(cap: usize) -> (Sender<T>, Receiver<T>) {
+    let (sender, receiver) = async_channel::bounded(cap);
+    from_channel(sender, receiver)
+}
+
+/// Creates an unbounded expiring channel (see [`async_channel::unbounded`]).
+pub fn unbounded<T>() -> (Sender<T>, Receiver<T>) {
+    let (sender, receiver) = async_channel::unbounded();
+    from_channel(sender, receiver)
+}
+
+fn from_channel<T>(
+    sender: async_channel::Sender<(Instant, T)>,
+    receiver: async_channel::Receiver<(Instant, T)>,
+) -> (Sender<T>, Receiver<T>) {
+    let receiver = Receiver {
+        inner: receiver,
+        on_expired: None,
+    };
+
+    (Sender { inner: sender }, receiver)
+}
+
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                                      struct Sender<T>                                      │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+/// The sending side of an expiring channel.
+///
+/// Every message is sent with a deadline, after which receivers drop it instead of returning it.
+pub struct Sender<T> {
+    inner: async_channel::Sender<(Instant, T)>,
+}
+
+impl<T> Sender<T> {
+    /// Sends a message that will expire after `ttl`, waiting for the channel to have enough
+    /// capacity.
+    pub async fn send(&self, msg: T, ttl: Duration) -> Result<(), SendError<T>> {
+        self.send_until(msg, deadline::deadline_after(clock::now(), ttl))
+            .await
+    }
+
+    /// Sends a message that will expire at `deadline`, waiting for the channel to have enough
+    /// capacity.
+    pub async fn send_until(&self, msg: T, deadline: Instant) -> Result<(), SendError<T>> {
+        self.inner
+            .send((deadline, msg))
+            .await
+            .map_err(|SendError((_, msg))| SendError(msg))
+    }
+
+    /// Attempts to send a message that will expire after `ttl`, without waiting.
+    pub fn try_send(&self, msg: T, ttl: Duration) -> Result<(), TrySendError<T>> {
+        self.inner
+            .try_send((deadline::deadline_after(clock::now(), ttl), msg))
+            .map_err(|err| match err {
+                TrySendError::Full((_, msg)) => TrySendError::Full(msg),
+                TrySendError::Closed((_, msg)) => TrySendError::Closed(msg),
+            })
+    }
+
+    /// Closes the channel, returning `true` if it wasn't already closed.
+    pub fn close(&self) -> bool {
+        self.inner.close()
+    }
+
+    /// Returns the number of messages in the channel, including the expired ones that weren't
+    /// dropped yet.
+    pub fn len(&self) -> usize {
+        self.inner.len()
+    }
+
+    /// Returns `true` if the channel is empty.
+    pub fn is_empty(&self) -> bool {
+        self.inner.is_empty()
+    }
+}
+
+impl<T> Clone for Sender<T> {
+    fn clone(&self) -> Self {
+        Sender {
+            inner: self.inner.clone(),
+        }
+    }
+}
+
+impl<T> fmt::Debug for Sender<T> {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        fmt.debug_struct("Sender").finish()
+    }
+}
+
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                                     struct Receiver<T>                                     │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+/// The receiving side of an expiring channel.
+///
+/// Receiving skips over the messages whose deadline passed, dropping them or passing them to the
+/// callback registered with [`on_expired`](Receiver::on_expired).
+pub struct Receiver<T> {
+    inner: async_channel::Receiver<(Instant, T)>,
+    #[allow(clippy::type_complexity)]
+    on_expired: Option<Arc<dyn Fn(T) + Send + Sync>>,
+}
+
+impl<T> Receiver<T> {
+    /// Sets a callback that will be called with every expired message this receiver (or any of
+    /// its later clones) drops.
+    ///
+    /// ## Example
+    ///
+    /// ```rust
+    /// use async_io::Timer;
+    /// # use futures_lite::future;
+    /// use smol_timeout::expiring;
+    /// use std::sync::atomic::{AtomicUsize, Ordering};
+    /// use std::sync::Arc;
+    /// use std::time::Duration;
+    ///
+    /// # future::block_on(async {
+    /// #
+    /// let expired = Arc::new(AtomicUsize::new(0));
+    ///
+    /// let (sender, receiver) = expiring::unbounded();
+    /// let receiver = {
+    ///     let expired = expired.clone();
+    ///     receiver.on_expired(move |_| {
+    ///         expired.fetch_add(1, Ordering::Relaxed);
+    ///     })
+    /// };
+    ///
+    /// sender.send(24, Duration::from_millis(100)).await.unwrap();
+    /// sender.send(42, Duration::from_secs(1)).await.unwrap();
+    ///
+    /// Timer::after(Duration::from_millis(250)).await;
+    /// assert_eq!(receiver.recv().await, Ok(42));
+    /// assert_eq!(expired.load(Ordering::Relaxed), 1);
+    /// #
+    /// # })
+    /// ```
+    pub fn on_expired<F>(mut self, on_expired: F) -> Self
+    where
+        F: Fn(T) + Send + Sync + 'static,
+    {
+        self.on_expired = Some(Arc::new(on_expired));
+        self
+    }
+
+    /// Receives the next message that hasn't expired, waiting for one to be sent.
+    pub async fn recv(&self) -> Result<T, RecvError> {
+        loop {
+            let (deadline, msg) = self.inner.recv().await?;
+            if let Some(msg) = self.unexpired(deadline, msg) {
+                return Ok(msg);
+            }
+        }
+    }
+
+    /// Attempts to receive the next message that hasn't expired, without waiting.
+    pub fn try_recv(&self) -> Result<T, TryRecvError> {
+        loop {
+            let (deadline, msg) = self.inner.try_recv()?;
+            if let Some(msg) = self.unexpired(deadline, msg) {
+                return Ok(msg);
+            }
+        }
+    }
+
+    /// Closes the channel, returning `true` if it wasn't already closed.
+    pub fn close(&self) -> bool {
+        self.inner.close()
+    }
+
+    /// Returns the number of messages in the channel, including the expired ones that weren't
+    /// dropped yet.
+    pub fn len(&self) -> usize {
+        self.inner.len()
+    }
+
+    /// Returns `true` if the channel is empty.
+    pub fn is_empty(&self) -> bool {
+        self.inner.is_empty()
+    }
+
+    fn unexpired(&self, deadline: Instant, msg: T) -> Option<T> {
+        if clock::now() < deadline {
+            return Some(msg);
+        }
+
+        if let Some(on_expired) = &self.on_expired {
+            on_expired(msg);
+        }
+
+        None
+    }
+}
+
+impl<T> Clone for Receiver<T> {
+    fn clone(&self) -> Self {
+        Receiver {
+            inner: self.inner.clone(),
+            on_expired: self.on_expired.clone(),
+        }
+    }
+}
+
+impl<T> fmt::Debug for Receiver<T> {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        fmt.debug_struct("Receiver").finish()
+    }
+}
@@ -0,0 +1,179 @@
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                                                                                            │ *
+ * │ This Source Code Form is subject to the terms of the Mozilla Public                        │ *
+ * │ License, v. 2.0. If a copy of the MPL was not distributed with this                        │ *
+ * │ file, You can obtain one at http://mozilla.org/MPL/2.0/.                                   │ *
+ * │                                                                                            │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                                       Documentation                                        │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+//! A driver polling multiple futures round-robin within a time slice.
+//!
+//! Combinators polling multiple futures usually poll all of them, in the same order, every time
+//! they are polled, which lets a future whose polls take long (e.g. because it processes
+//! everything that is ready every time it is polled) delay its siblings, and the other tasks of
+//! the executor, by as much. A [`FairJoin`] instead stops polling its futures once a time slice
+//! is spent, yields to the executor, and resumes with the next future the next time it is
+//! polled.
+//!
+//! A single poll can't be interrupted, so a slice can still be overrun by the poll which
+//! exhausts it.
+//!
+//! ## Example
+//!
+//! ```rust
+//! use async_io::Timer;
+//! # use futures_lite::future;
+//! use smol_timeout::fair;
+//! use std::time::Duration;
+//!
+//! # future::block_on(async {
+//! #
+//! let futures = [100, 50].iter().map(|&millis| async move {
+//!     Timer::after(Duration::from_millis(millis)).await;
+//!     millis
+//! });
+//!
+//! let outputs = fair::fair_join(futures, Duration::from_millis(1)).await;
+//! assert_eq!(outputs, [100, 50]);
+//! #
+//! # });
+//! ```
+
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                                          Imports                                           │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+use crate::clock::Stamp;
+use core::fmt;
+use core::future::Future;
+use core::pin::Pin;
+use core::task::{Context, Poll};
+use core::time::Duration;
+use std::boxed::Box;
+use std::vec::Vec;
+
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                                    struct FairJoin<Fut>                                    │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+/// A future polling a collection of futures round-robin, yielding to the executor whenever
+/// polling them takes longer than a time slice, and returning all their outputs.
+///
+/// Created by [`fair_join`].
+#[must_use = "futures do nothing unless you `.await` or poll them"]
+pub struct FairJoin<Fut: Future> {
+    futures: Vec<Option<Pin<Box<Fut>>>>,
+    outputs: Vec<Option<Fut::Output>>,
+    pending: usize,
+    /// The index of the future to poll first the next time this future is polled.
+    cursor: usize,
+    slice: Duration,
+}
+
+// The futures are boxed and their outputs are never pinned.
+impl<Fut: Future> Unpin for FairJoin<Fut> {}
+
+impl<Fut: Future> fmt::Debug for FairJoin<Fut> {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        fmt.debug_struct("FairJoin")
+            .field("pending", &self.pending)
+            .field("cursor", &self.cursor)
+            .field("slice", &self.slice)
+            .finish()
+    }
+}
+
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                                       fn fair_join()                                       │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+/// Given a collection of futures and a [`Duration`], creates and returns a new [`FairJoin`]
+/// that will drive all the futures concurrently until they all complete, polling them
+/// round-robin and yielding to the executor whenever polling them takes longer than the provided
+/// time slice.
+///
+/// The returned outputs are in the same order as the futures.
+///
+/// ## Example
+///
+/// ```rust
+/// # use futures_lite::future;
+/// use smol_timeout::fair;
+/// use std::time::Duration;
+///
+/// # future::block_on(async {
+/// #
+/// let futures = vec![future::ready(1), future::ready(2)];
+///
+/// let outputs = fair::fair_join(futures, Duration::from_millis(1)).await;
+/// assert_eq!(outputs, [1, 2]);
+/// #
+/// # })
+/// ```
+pub fn fair_join<I>(futures: I, slice: Duration) -> FairJoin<I::Item>
+where
+    I: IntoIterator,
+    I::Item: Future,
+{
+    let futures = futures
+        .into_iter()
+        .map(|future| Some(Box::pin(future)))
+        .collect::<Vec<_>>();
+
+    FairJoin {
+        outputs: futures.iter().map(|_| None).collect(),
+        pending: futures.len(),
+        futures,
+        cursor: 0,
+        slice,
+    }
+}
+
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                               impl Future for FairJoin<Fut>                                │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+impl<Fut: Future> Future for FairJoin<Fut> {
+    type Output = Vec<Fut::Output>;
+
+    fn poll(self: Pin<&mut Self>, ctx: &mut Context) -> Poll<Self::Output> {
+        let this = self.get_mut();
+        let start = Stamp::now();
+        let len = this.futures.len();
+
+        for step in 0..len {
+            if this.pending == 0 {
+                break;
+            }
+
+            let index = (this.cursor + step) % len;
+            if let Some(future) = &mut this.futures[index] {
+                if let Poll::Ready(output) = future.as_mut().poll(ctx) {
+                    this.outputs[index] = Some(output);
+                    this.futures[index] = None;
+                    this.pending -= 1;
+                }
+            }
+
+            if this.pending > 0 && step + 1 < len && start.elapsed() >= this.slice {
+                // The slice is spent: yielding, and resuming with the next future.
+                this.cursor = (index + 1) % len;
+                ctx.waker().wake_by_ref();
+                return Poll::Pending;
+            }
+        }
+
+        if this.pending > 0 {
+            // Rotating the first future to poll, so that none is always polled first.
+            this.cursor = (this.cursor + 1) % len;
+            return Poll::Pending;
+        }
+
+        this.futures.clear();
+        Poll::Ready(this.outputs.drain(..).flatten().collect())
+    }
+}
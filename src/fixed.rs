@@ -0,0 +1,158 @@
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                                                                                            │ *
+ * │ This Source Code Form is subject to the terms of the Mozilla Public                        │ *
+ * │ License, v. 2.0. If a copy of the MPL was not distributed with this                        │ *
+ * │ file, You can obtain one at http://mozilla.org/MPL/2.0/.                                   │ *
+ * │                                                                                            │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                                       Documentation                                        │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+//! Timeouts whose duration is fixed at compile time, as part of their type.
+//!
+//! A [`FixedTimeout<Fut, MS>`](FixedTimeout) always times out after `MS` milliseconds: its
+//! duration can't be misconfigured at runtime, appears in the signatures of the functions
+//! returning it, and is checked to be non-zero at compile time. [`Millis<MS>`](Millis) is a
+//! zero-sized policy type creating such timeouts, which can be stored in configuration structs
+//! or used as a generic parameter at no cost.
+//!
+//! ## Example
+//!
+//! ```rust
+//! use async_io::Timer;
+//! # use futures_lite::future;
+//! use smol_timeout::fixed::{FixedTimeout, FixedTimeoutExt, Millis};
+//! use std::future::Future;
+//! use std::time::Duration;
+//!
+//! fn get_user() -> FixedTimeout<impl Future<Output = u32>, 100> {
+//!     let get_user = async {
+//!         Timer::after(Duration::from_millis(250)).await;
+//!         24
+//!     };
+//!
+//!     get_user.timeout_ms::<100>()
+//! }
+//!
+//! # future::block_on(async {
+//! #
+//! assert_eq!(get_user().await, None);
+//!
+//! type GetPost = Millis<250>;
+//! assert_eq!(GetPost::DURATION, Duration::from_millis(250));
+//!
+//! let get_post = async {
+//!     Timer::after(Duration::from_millis(100)).await;
+//!     42
+//! };
+//!
+//! assert_eq!(GetPost::timeout(get_post).await, Some(42));
+//! #
+//! # });
+//! ```
+
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                                          Imports                                           │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+use crate::{Timeout, Timer};
+use core::fmt;
+use core::future::Future;
+use core::pin::Pin;
+use core::task::{Context, Poll};
+use core::time::Duration;
+use std::time::Instant;
+
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                                     struct Millis<MS>                                      │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+/// A zero-sized policy creating timeouts of `MS` milliseconds.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Millis<const MS: u64>;
+
+impl<const MS: u64> Millis<MS> {
+    /// The duration of the timeouts created by this policy.
+    pub const DURATION: Duration = Duration::from_millis(MS);
+
+    /// Fails to compile if `MS` is zero.
+    const NON_ZERO: () = assert!(MS > 0, "a fixed timeout can't be zero");
+
+    /// Given a future, creates and returns a new [`FixedTimeout`] that will poll both the
+    /// future and a [`Timer`] that will complete after `MS` milliseconds, and return the
+    /// future's output or [`None`] if the timer completes first.
+    ///
+    /// Fails to compile if `MS` is zero.
+    pub fn timeout<Fut: Future>(future: Fut) -> FixedTimeout<Fut, MS> {
+        #[allow(clippy::let_unit_value)]
+        let () = Self::NON_ZERO;
+
+        Timeout::new(
+            future,
+            FixedTimer {
+                timer: Timer::after(Self::DURATION),
+            },
+        )
+    }
+}
+
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                                   struct FixedTimer<MS>                                    │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+/// A [`Timer`] completing `MS` milliseconds after it was created, used as the deadline of a
+/// [`FixedTimeout`].
+#[must_use = "futures do nothing unless you `.await` or poll them"]
+pub struct FixedTimer<const MS: u64> {
+    timer: Timer,
+}
+
+impl<const MS: u64> Future for FixedTimer<MS> {
+    type Output = Instant;
+
+    fn poll(mut self: Pin<&mut Self>, ctx: &mut Context) -> Poll<Instant> {
+        Pin::new(&mut self.timer).poll(ctx)
+    }
+}
+
+impl<const MS: u64> fmt::Debug for FixedTimer<MS> {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        fmt.debug_struct("FixedTimer")
+            .field("after", &Millis::<MS>::DURATION)
+            .finish()
+    }
+}
+
+/// A [`Timeout`] whose timer completes after `MS` milliseconds.
+pub type FixedTimeout<Fut, const MS: u64> = Timeout<Fut, FixedTimer<MS>>;
+
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                               trait FixedTimeoutExt: Future                                │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+/// An extension trait for [`Future`]s that provides a way to create [`FixedTimeout`]s.
+pub trait FixedTimeoutExt: Future {
+    /// Creates and returns a new [`FixedTimeout`] that will poll both the future and a [`Timer`]
+    /// that will complete after `MS` milliseconds, and return the future's output or [`None`]
+    /// if the timer completes first.
+    ///
+    /// Fails to compile if `MS` is zero.
+    ///
+    /// ## Example
+    ///
+    /// ```rust,compile_fail
+    /// use smol_timeout::fixed::FixedTimeoutExt;
+    ///
+    /// let foo = async { 42 }.timeout_ms::<0>();
+    /// ```
+    fn timeout_ms<const MS: u64>(self) -> FixedTimeout<Self, MS>
+    where
+        Self: Sized,
+    {
+        Millis::<MS>::timeout(self)
+    }
+}
+
+impl<Fut: Future> FixedTimeoutExt for Fut {}
@@ -0,0 +1,264 @@
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                                                                                            │ *
+ * │ This Source Code Form is subject to the terms of the Mozilla Public                        │ *
+ * │ License, v. 2.0. If a copy of the MPL was not distributed with this                        │ *
+ * │ file, You can obtain one at http://mozilla.org/MPL/2.0/.                                   │ *
+ * │                                                                                            │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                                       Documentation                                        │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+//! Length-delimited frames read with an idle timeout between them and a deadline for each.
+//!
+//! With the `futures-io` feature, a [`FramedReader`] is a [`Stream`] of the frames read from
+//! an [`AsyncRead`], each one prefixed with its length as a big-endian `u32`. Two timeouts
+//! bound the reads, with a single timer:
+//!
+//! - the idle timeout, between the end of a frame (or the creation of the reader) and the first
+//!   byte of the next one, which fails with [`FrameError::Idle`];
+//! - the frame timeout, between the first and last bytes of a frame, which fails with
+//!   [`FrameError::Incomplete`] and evicts peers trickling data to keep a connection busy.
+//!
+//! The stream ends after its first error, as the reader can't be resynchronized with the frames.
+//!
+//! ## Example
+//!
+//! ```rust
+//! use async_io::Async;
+//! # use futures_lite::future;
+//! use futures_lite::{AsyncWriteExt, StreamExt};
+//! use smol_timeout::framed::{FrameError, FramedReader};
+//! use std::io;
+//! use std::net::{TcpListener, TcpStream};
+//! use std::time::Duration;
+//!
+//! # future::block_on(async {
+//! #
+//! let listener = Async::<TcpListener>::bind(([127, 0, 0, 1], 0))?;
+//! let mut client = Async::<TcpStream>::connect(listener.get_ref().local_addr()?).await?;
+//! let (server, _) = listener.accept().await?;
+//!
+//! let mut frames = FramedReader::new(
+//!     server,
+//!     Duration::from_millis(100),
+//!     Duration::from_millis(250),
+//! );
+//!
+//! client.write_all(b"\0\0\0\x05hello\0\0\0\x05wor").await?;
+//!
+//! assert_eq!(frames.next().await.unwrap().unwrap(), b"hello");
+//! assert!(matches!(frames.next().await, Some(Err(FrameError::Incomplete))));
+//! assert!(frames.next().await.is_none());
+//! #
+//! # io::Result::Ok(())
+//! # }).unwrap();
+//! ```
+
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                                          Imports                                           │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+use crate::Timer;
+use core::fmt;
+use core::future::Future;
+use core::pin::Pin;
+use core::task::{Context, Poll};
+use core::time::Duration;
+use futures_core::stream::Stream;
+use futures_io::AsyncRead;
+use std::io;
+use std::vec;
+use std::vec::Vec;
+
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                                      enum FrameError                                       │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+/// The error yielded by a [`FramedReader`], after which its stream ends.
+#[derive(Debug)]
+pub enum FrameError {
+    /// No frame started within the idle timeout.
+    Idle,
+    /// A frame started but didn't complete within the frame timeout.
+    Incomplete,
+    /// A frame was announced with this length, longer than the maximum one.
+    TooLong(usize),
+    /// Reading failed, or the reader ended in the middle of a frame.
+    Io(io::Error),
+}
+
+impl fmt::Display for FrameError {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            FrameError::Idle => fmt.write_str("no frame started within the idle timeout"),
+            FrameError::Incomplete => fmt.write_str("frame not completed within its timeout"),
+            FrameError::TooLong(len) => {
+                write!(fmt, "frame of {} bytes exceeds the maximum length", len)
+            }
+            FrameError::Io(err) => fmt::Display::fmt(err, fmt),
+        }
+    }
+}
+
+impl std::error::Error for FrameError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            FrameError::Io(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                                   struct FramedReader<R>                                   │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+/// A [`Stream`] of the length-delimited frames read from an [`AsyncRead`], with an idle timeout
+/// between frames and a timeout for each frame.
+pub struct FramedReader<R> {
+    reader: R,
+    frame_timeout: Duration,
+    idle_timeout: Duration,
+    max_frame_len: usize,
+    timer: Timer,
+    header: [u8; 4],
+    /// The frame being read, once its length is known.
+    frame: Option<Vec<u8>>,
+    /// The number of bytes of the header or frame read so far.
+    filled: usize,
+    /// Whether the first byte of the current frame was read (and the frame timeout armed).
+    started: bool,
+    done: bool,
+}
+
+impl<R: AsyncRead + Unpin> FramedReader<R> {
+    /// The default maximum length of a frame, of 8 MiB.
+    pub const DEFAULT_MAX_FRAME_LEN: usize = 8 * 1024 * 1024;
+
+    /// Creates a new [`FramedReader`] reading frames from the reader, each within
+    /// `frame_timeout`, and with at most `idle_timeout` between them.
+    pub fn new(reader: R, frame_timeout: Duration, idle_timeout: Duration) -> Self {
+        FramedReader {
+            reader,
+            frame_timeout,
+            idle_timeout,
+            max_frame_len: Self::DEFAULT_MAX_FRAME_LEN,
+            timer: Timer::after(idle_timeout),
+            header: [0; 4],
+            frame: None,
+            filled: 0,
+            started: false,
+            done: false,
+        }
+    }
+
+    /// Sets the maximum length of a frame (of [`DEFAULT_MAX_FRAME_LEN`] by default), longer
+    /// frames failing with [`FrameError::TooLong`] without being read.
+    ///
+    /// [`DEFAULT_MAX_FRAME_LEN`]: FramedReader::DEFAULT_MAX_FRAME_LEN
+    pub fn max_frame_len(mut self, max_frame_len: usize) -> Self {
+        self.max_frame_len = max_frame_len;
+        self
+    }
+
+    /// Returns a reference to the reader.
+    pub fn get_ref(&self) -> &R {
+        &self.reader
+    }
+
+    /// Returns a mutable reference to the reader.
+    pub fn get_mut(&mut self) -> &mut R {
+        &mut self.reader
+    }
+
+    /// Consumes the [`FramedReader`], returning the reader.
+    pub fn into_inner(self) -> R {
+        self.reader
+    }
+
+    /// Ends the stream with the provided error.
+    fn fail(&mut self, err: FrameError) -> Poll<Option<Result<Vec<u8>, FrameError>>> {
+        self.done = true;
+        Poll::Ready(Some(Err(err)))
+    }
+}
+
+impl<R> fmt::Debug for FramedReader<R> {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        fmt.debug_struct("FramedReader")
+            .field("frame_timeout", &self.frame_timeout)
+            .field("idle_timeout", &self.idle_timeout)
+            .field("max_frame_len", &self.max_frame_len)
+            .finish()
+    }
+}
+
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                              impl Stream for FramedReader<R>                               │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+impl<R: AsyncRead + Unpin> Stream for FramedReader<R> {
+    type Item = Result<Vec<u8>, FrameError>;
+
+    fn poll_next(self: Pin<&mut Self>, ctx: &mut Context) -> Poll<Option<Self::Item>> {
+        let this = self.get_mut();
+
+        if this.done {
+            return Poll::Ready(None);
+        }
+
+        loop {
+            let target = match &mut this.frame {
+                Some(frame) if this.filled == frame.len() => {
+                    let frame = this.frame.take().unwrap();
+                    this.filled = 0;
+                    this.started = false;
+                    this.timer.set_after(this.idle_timeout);
+
+                    return Poll::Ready(Some(Ok(frame)));
+                }
+                Some(frame) => &mut frame[this.filled..],
+                None => &mut this.header[this.filled..],
+            };
+
+            let read = match Pin::new(&mut this.reader).poll_read(ctx, target) {
+                Poll::Ready(Ok(0)) if !this.started => {
+                    this.done = true;
+                    return Poll::Ready(None);
+                }
+                Poll::Ready(Ok(0)) => {
+                    return this.fail(FrameError::Io(io::ErrorKind::UnexpectedEof.into()));
+                }
+                Poll::Ready(Ok(read)) => read,
+                Poll::Ready(Err(err)) if err.kind() == io::ErrorKind::Interrupted => continue,
+                Poll::Ready(Err(err)) => return this.fail(FrameError::Io(err)),
+                Poll::Pending if Pin::new(&mut this.timer).poll(ctx).is_ready() => {
+                    return this.fail(if this.started {
+                        FrameError::Incomplete
+                    } else {
+                        FrameError::Idle
+                    });
+                }
+                Poll::Pending => return Poll::Pending,
+            };
+
+            if !this.started {
+                this.started = true;
+                this.timer.set_after(this.frame_timeout);
+            }
+
+            this.filled += read;
+            if this.frame.is_none() && this.filled == this.header.len() {
+                let len = u32::from_be_bytes(this.header) as usize;
+                if len > this.max_frame_len {
+                    return this.fail(FrameError::TooLong(len));
+                }
+
+                this.frame = Some(vec![0; len]);
+                this.filled = 0;
+            }
+        }
+    }
+}
@@ -0,0 +1,187 @@
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                                                                                            │ *
+ * │ This Source Code Form is subject to the terms of the Mozilla Public                        │ *
+ * │ License, v. 2.0. If a copy of the MPL was not distributed with this                        │ *
+ * │ file, You can obtain one at http://mozilla.org/MPL/2.0/.                                   │ *
+ * │                                                                                            │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                                       Documentation                                        │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+//! Streams dropping the items which are too old by the time they would be yielded.
+//!
+//! A [`Fresh`] stream reads the timestamp of every item of its stream (e.g. the instant it was
+//! received at), and drops it instead of yielding it if it is older than a freshness window,
+//! counting the dropped items. This is meant for pipelines where stale data (e.g. market data
+//! or sensor readings) is worse than no data at all.
+//!
+//! Items can carry their timestamp by implementing [`Timestamped`] (as `(Instant, T)` does),
+//! or have it read by a closure, with [`fresh_by`](FreshExt::fresh_by).
+//!
+//! ## Example
+//!
+//! ```rust
+//! # use futures_lite::future;
+//! use futures_lite::{stream, StreamExt};
+//! use smol_timeout::fresh::FreshExt;
+//! use std::time::{Duration, Instant};
+//!
+//! # future::block_on(async {
+//! #
+//! let now = Instant::now();
+//! let readings = vec![
+//!     (now - Duration::from_secs(5), 1),
+//!     (now, 2),
+//!     (now - Duration::from_secs(10), 3),
+//!     (now, 4),
+//! ];
+//!
+//! let mut fresh = stream::iter(readings).fresh(Duration::from_secs(1));
+//!
+//! assert_eq!(fresh.next().await.map(|(_, reading)| reading), Some(2));
+//! assert_eq!(fresh.next().await.map(|(_, reading)| reading), Some(4));
+//! assert_eq!(fresh.next().await, None);
+//! assert_eq!(fresh.dropped(), 2);
+//! #
+//! # });
+//! ```
+
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                                          Imports                                           │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+use crate::deadline;
+use core::fmt;
+use core::pin::Pin;
+use core::task::{Context, Poll};
+use core::time::Duration;
+use futures_core::stream::Stream;
+use pin_project_lite::pin_project;
+use std::time::Instant;
+
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                                     trait Timestamped                                      │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+/// A trait for items carrying the instant they were produced or received at.
+pub trait Timestamped {
+    /// Returns the instant the item was produced or received at.
+    fn timestamp(&self) -> Instant;
+}
+
+impl<T> Timestamped for (Instant, T) {
+    fn timestamp(&self) -> Instant {
+        self.0
+    }
+}
+
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                                     struct Fresh<S, F>                                     │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+pin_project! {
+    /// A stream yielding the items of another stream, unless they are older than a freshness
+    /// window, in which case they are dropped.
+    ///
+    /// Created by [`FreshExt::fresh`] and [`FreshExt::fresh_by`].
+    #[must_use = "streams do nothing unless polled"]
+    pub struct Fresh<S, F> {
+        #[pin]
+        stream: S,
+        window: Duration,
+        timestamp: F,
+        dropped: u64,
+    }
+}
+
+impl<S, F> Fresh<S, F> {
+    /// Returns the freshness window of the stream.
+    pub fn window(&self) -> Duration {
+        self.window
+    }
+
+    /// Returns the number of items dropped so far because they were stale.
+    pub fn dropped(&self) -> u64 {
+        self.dropped
+    }
+}
+
+impl<S, F> fmt::Debug for Fresh<S, F> {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        fmt.debug_struct("Fresh")
+            .field("window", &self.window)
+            .field("dropped", &self.dropped)
+            .finish()
+    }
+}
+
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                                   trait FreshExt: Stream                                   │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+/// An extension trait for [`Stream`]s that provides a way to create [`Fresh`] streams.
+pub trait FreshExt: Stream {
+    /// Given a freshness window, creates and returns a new [`Fresh`] stream that will yield the
+    /// items of the stream, unless their [timestamp](Timestamped::timestamp) is older than the
+    /// window by the time they are ready.
+    fn fresh(self, window: Duration) -> Fresh<Self, fn(&Self::Item) -> Instant>
+    where
+        Self: Sized,
+        Self::Item: Timestamped,
+    {
+        self.fresh_by(window, Timestamped::timestamp)
+    }
+
+    /// Given a freshness window and a closure returning the timestamp of an item, creates and
+    /// returns a new [`Fresh`] stream that will yield the items of the stream, unless their
+    /// timestamp is older than the window by the time they are ready.
+    fn fresh_by<F>(self, window: Duration, timestamp: F) -> Fresh<Self, F>
+    where
+        Self: Sized,
+        F: FnMut(&Self::Item) -> Instant,
+    {
+        Fresh {
+            stream: self,
+            window,
+            timestamp,
+            dropped: 0,
+        }
+    }
+}
+
+impl<S: Stream> FreshExt for S {}
+
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                                impl Stream for Fresh<S, F>                                 │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+impl<S, F> Stream for Fresh<S, F>
+where
+    S: Stream,
+    F: FnMut(&S::Item) -> Instant,
+{
+    type Item = S::Item;
+
+    fn poll_next(self: Pin<&mut Self>, ctx: &mut Context) -> Poll<Option<Self::Item>> {
+        let mut this = self.project();
+
+        loop {
+            let item = match this.stream.as_mut().poll_next(ctx) {
+                Poll::Ready(Some(item)) => item,
+                poll => return poll,
+            };
+
+            if deadline::exceeded_by((this.timestamp)(&item)) <= *this.window {
+                return Poll::Ready(Some(item));
+            }
+
+            *this.dropped += 1;
+        }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (0, self.stream.size_hint().1)
+    }
+}
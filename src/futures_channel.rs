@@ -0,0 +1,383 @@
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                                                                                            │ *
+ * │ This Source Code Form is subject to the terms of the Mozilla Public                        │ *
+ * │ License, v. 2.0. If a copy of the MPL was not distributed with this                        │ *
+ * │ file, You can obtain one at http://mozilla.org/MPL/2.0/.                                   │ *
+ * │                                                                                            │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                                       Documentation                                        │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+//! Timeouts for sending and receiving messages over [`futures_channel`]'s [`oneshot`] and
+//! [`mpsc`] channels.
+//!
+//! All the futures returned by this module borrow the channel instead of consuming it, so that
+//! it can still be used after a timeout.
+//!
+//! Like a [`Timeout`](crate::Timeout), they poll the channel before their timer, so that a
+//! message which is already available is received (or sent) even if the timeout elapsed.
+//!
+//! ## Example
+//!
+//! ```rust
+//! use futures_channel::{mpsc, oneshot};
+//! # use futures_lite::future;
+//! use smol_timeout::futures_channel::{MpscReceiverExt, OneshotReceiverExt, RecvTimeoutError};
+//! use std::time::Duration;
+//!
+//! # future::block_on(async {
+//! #
+//! let (sender, mut receiver) = oneshot::channel::<i32>();
+//!
+//! assert_eq!(
+//!     receiver.recv_timeout(Duration::from_millis(100)).await,
+//!     Err(RecvTimeoutError::Timeout),
+//! );
+//!
+//! drop(sender);
+//! assert_eq!(
+//!     receiver.recv_timeout(Duration::from_millis(100)).await,
+//!     Err(RecvTimeoutError::Canceled),
+//! );
+//!
+//! let (sender, mut receiver) = mpsc::unbounded();
+//! sender.unbounded_send(42).unwrap();
+//!
+//! assert_eq!(receiver.next_timeout(Duration::from_millis(100)).await, Ok(42));
+//!
+//! sender.unbounded_send(24).unwrap();
+//! assert_eq!(receiver.next_timeout(Duration::from_secs(0)).await, Ok(24));
+//! #
+//! # });
+//! ```
+
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                                          Imports                                           │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+use crate::Timer;
+use ::futures_channel::{mpsc, oneshot};
+use core::fmt;
+use core::future::Future;
+use core::pin::Pin;
+use core::task::{Context, Poll};
+use core::time::Duration;
+use futures_core::Stream;
+
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                                   enum RecvTimeoutError                                    │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+/// The error returned when receiving from a channel with a timeout.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum RecvTimeoutError {
+    /// No message was received before the timeout.
+    Timeout,
+    /// The channel was canceled: the [`oneshot::Sender`] was dropped, or all the [`mpsc`] senders
+    /// were dropped and the channel is empty.
+    Canceled,
+}
+
+impl fmt::Display for RecvTimeoutError {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            RecvTimeoutError::Timeout => fmt.write_str("timed out receiving from the channel"),
+            RecvTimeoutError::Canceled => fmt.write_str("receiving from a canceled channel"),
+        }
+    }
+}
+
+impl std::error::Error for RecvTimeoutError {}
+
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                                  enum SendTimeoutError<T>                                  │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+/// The error returned when sending into an [`mpsc`] channel with a timeout. Contains the message
+/// that couldn't be sent.
+#[derive(Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum SendTimeoutError<T> {
+    /// The channel didn't have enough capacity before the timeout.
+    Timeout(T),
+    /// The receiver was dropped.
+    Disconnected(T),
+}
+
+impl<T> SendTimeoutError<T> {
+    /// Returns the message that couldn't be sent.
+    pub fn into_inner(self) -> T {
+        match self {
+            SendTimeoutError::Timeout(msg) | SendTimeoutError::Disconnected(msg) => msg,
+        }
+    }
+}
+
+impl<T> fmt::Debug for SendTimeoutError<T> {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SendTimeoutError::Timeout(_) => fmt.write_str("Timeout(..)"),
+            SendTimeoutError::Disconnected(_) => fmt.write_str("Disconnected(..)"),
+        }
+    }
+}
+
+impl<T> fmt::Display for SendTimeoutError<T> {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SendTimeoutError::Timeout(_) => fmt.write_str("timed out sending into the channel"),
+            SendTimeoutError::Disconnected(_) => {
+                fmt.write_str("sending into a disconnected channel")
+            }
+        }
+    }
+}
+
+impl<T> std::error::Error for SendTimeoutError<T> {}
+
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                                trait OneshotReceiverExt<T>                                 │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+/// An extension trait for [`oneshot::Receiver`]s that provides a way to receive their message
+/// with a timeout.
+pub trait OneshotReceiverExt<T> {
+    /// Receives the message sent into the channel, waiting for at most the provided duration for
+    /// it to be sent.
+    ///
+    /// ## Example
+    ///
+    /// ```rust
+    /// use futures_channel::oneshot;
+    /// # use futures_lite::future;
+    /// use smol_timeout::futures_channel::OneshotReceiverExt;
+    /// use std::time::Duration;
+    ///
+    /// # future::block_on(async {
+    /// #
+    /// let (sender, mut receiver) = oneshot::channel();
+    /// sender.send(42).unwrap();
+    ///
+    /// assert_eq!(receiver.recv_timeout(Duration::from_millis(100)).await, Ok(42));
+    /// #
+    /// # })
+    /// ```
+    fn recv_timeout(&mut self, after: Duration) -> RecvTimeout<'_, T>;
+}
+
+impl<T> OneshotReceiverExt<T> for oneshot::Receiver<T> {
+    fn recv_timeout(&mut self, after: Duration) -> RecvTimeout<'_, T> {
+        RecvTimeout {
+            receiver: self,
+            timer: Timer::after(after),
+        }
+    }
+}
+
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                                   trait MpscReceiverExt                                    │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+/// An extension trait for [`mpsc::Receiver`]s and [`mpsc::UnboundedReceiver`]s that provides a
+/// way to receive messages with a timeout.
+pub trait MpscReceiverExt: Stream + Unpin + Sized {
+    /// Receives the next message from the channel, waiting for at most the provided duration for
+    /// one to be sent.
+    ///
+    /// ## Example
+    ///
+    /// ```rust
+    /// use futures_channel::mpsc;
+    /// # use futures_lite::future;
+    /// use smol_timeout::futures_channel::{MpscReceiverExt, RecvTimeoutError};
+    /// use std::time::Duration;
+    ///
+    /// # future::block_on(async {
+    /// #
+    /// let (sender, mut receiver) = mpsc::channel::<i32>(1);
+    ///
+    /// assert_eq!(
+    ///     receiver.next_timeout(Duration::from_millis(100)).await,
+    ///     Err(RecvTimeoutError::Timeout),
+    /// );
+    ///
+    /// drop(sender);
+    /// assert_eq!(
+    ///     receiver.next_timeout(Duration::from_millis(100)).await,
+    ///     Err(RecvTimeoutError::Canceled),
+    /// );
+    /// #
+    /// # })
+    /// ```
+    fn next_timeout(&mut self, after: Duration) -> NextTimeout<'_, Self> {
+        NextTimeout {
+            receiver: self,
+            timer: Timer::after(after),
+        }
+    }
+}
+
+impl<T> MpscReceiverExt for mpsc::Receiver<T> {}
+impl<T> MpscReceiverExt for mpsc::UnboundedReceiver<T> {}
+
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                                   trait MpscSenderExt<T>                                   │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+/// An extension trait for [`mpsc::Sender`]s that provides a way to send messages with a timeout.
+pub trait MpscSenderExt<T> {
+    /// Sends a message into the channel, waiting for at most the provided duration for the
+    /// channel to have enough capacity. If the message couldn't be sent, it is returned in the
+    /// error.
+    ///
+    /// ## Example
+    ///
+    /// ```rust
+    /// use futures_channel::mpsc;
+    /// # use futures_lite::future;
+    /// use smol_timeout::futures_channel::{MpscSenderExt, SendTimeoutError};
+    /// use std::time::Duration;
+    ///
+    /// # future::block_on(async {
+    /// #
+    /// let (mut sender, receiver) = mpsc::channel(0);
+    ///
+    /// assert_eq!(sender.send_timeout(24, Duration::from_millis(100)).await, Ok(()));
+    /// assert_eq!(
+    ///     sender.send_timeout(42, Duration::from_millis(100)).await,
+    ///     Err(SendTimeoutError::Timeout(42)),
+    /// );
+    ///
+    /// drop(receiver);
+    /// assert_eq!(
+    ///     sender.send_timeout(42, Duration::from_millis(100)).await,
+    ///     Err(SendTimeoutError::Disconnected(42)),
+    /// );
+    /// #
+    /// # })
+    /// ```
+    fn send_timeout(&mut self, msg: T, after: Duration) -> SendTimeout<'_, T>;
+}
+
+impl<T> MpscSenderExt<T> for mpsc::Sender<T> {
+    fn send_timeout(&mut self, msg: T, after: Duration) -> SendTimeout<'_, T> {
+        SendTimeout {
+            sender: self,
+            msg: Some(msg),
+            timer: Timer::after(after),
+        }
+    }
+}
+
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                                 struct RecvTimeout<'a, T>                                  │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+/// A future receiving the message of a [`oneshot`] channel with a timeout (see
+/// [`OneshotReceiverExt::recv_timeout`]).
+#[derive(Debug)]
+#[must_use = "futures do nothing unless you `.await` or poll them"]
+pub struct RecvTimeout<'a, T> {
+    receiver: &'a mut oneshot::Receiver<T>,
+    timer: Timer,
+}
+
+impl<T> Future for RecvTimeout<'_, T> {
+    type Output = Result<T, RecvTimeoutError>;
+
+    fn poll(mut self: Pin<&mut Self>, ctx: &mut Context) -> Poll<Self::Output> {
+        if let Poll::Ready(output) = Pin::new(&mut *self.receiver).poll(ctx) {
+            return Poll::Ready(output.map_err(|oneshot::Canceled| RecvTimeoutError::Canceled));
+        }
+
+        Pin::new(&mut self.timer)
+            .poll(ctx)
+            .map(|_| Err(RecvTimeoutError::Timeout))
+    }
+}
+
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                                 struct NextTimeout<'a, R>                                  │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+/// A future receiving the next message of an [`mpsc`] channel with a timeout (see
+/// [`MpscReceiverExt::next_timeout`]).
+#[derive(Debug)]
+#[must_use = "futures do nothing unless you `.await` or poll them"]
+pub struct NextTimeout<'a, R> {
+    receiver: &'a mut R,
+    timer: Timer,
+}
+
+impl<R: Stream + Unpin> Future for NextTimeout<'_, R> {
+    type Output = Result<R::Item, RecvTimeoutError>;
+
+    fn poll(mut self: Pin<&mut Self>, ctx: &mut Context) -> Poll<Self::Output> {
+        if let Poll::Ready(msg) = Pin::new(&mut *self.receiver).poll_next(ctx) {
+            return Poll::Ready(msg.ok_or(RecvTimeoutError::Canceled));
+        }
+
+        Pin::new(&mut self.timer)
+            .poll(ctx)
+            .map(|_| Err(RecvTimeoutError::Timeout))
+    }
+}
+
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                                 struct SendTimeout<'a, T>                                  │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+/// A future sending a message into an [`mpsc`] channel with a timeout (see
+/// [`MpscSenderExt::send_timeout`]).
+#[derive(Debug)]
+#[must_use = "futures do nothing unless you `.await` or poll them"]
+pub struct SendTimeout<'a, T> {
+    sender: &'a mut mpsc::Sender<T>,
+    msg: Option<T>,
+    timer: Timer,
+}
+
+impl<T> Unpin for SendTimeout<'_, T> {}
+
+impl<T> Future for SendTimeout<'_, T> {
+    type Output = Result<(), SendTimeoutError<T>>;
+
+    fn poll(mut self: Pin<&mut Self>, ctx: &mut Context) -> Poll<Self::Output> {
+        let this = &mut *self;
+        let mut msg = match this.msg.take() {
+            Some(msg) => msg,
+            None => panic!("`SendTimeout` polled after completion"),
+        };
+
+        loop {
+            match this.sender.poll_ready(ctx) {
+                Poll::Ready(Ok(())) => {}
+                Poll::Ready(Err(_)) => {
+                    return Poll::Ready(Err(SendTimeoutError::Disconnected(msg)))
+                }
+                // The timer is only checked once the channel has no capacity, so that a message
+                // which can be sent is sent even if the deadline passed.
+                Poll::Pending => {
+                    if Pin::new(&mut this.timer).poll(ctx).is_ready() {
+                        return Poll::Ready(Err(SendTimeoutError::Timeout(msg)));
+                    }
+
+                    this.msg = Some(msg);
+                    return Poll::Pending;
+                }
+            }
+
+            match this.sender.try_send(msg) {
+                Ok(()) => return Poll::Ready(Ok(())),
+                Err(err) if err.is_disconnected() => {
+                    return Poll::Ready(Err(SendTimeoutError::Disconnected(err.into_inner())));
+                }
+                // Another sender took the slot we were given, so we need to wait again.
+                Err(err) => msg = err.into_inner(),
+            }
+        }
+    }
+}
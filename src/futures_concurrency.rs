@@ -0,0 +1,177 @@
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                                                                                            │ *
+ * │ This Source Code Form is subject to the terms of the Mozilla Public                        │ *
+ * │ License, v. 2.0. If a copy of the MPL was not distributed with this                        │ *
+ * │ file, You can obtain one at http://mozilla.org/MPL/2.0/.                                   │ *
+ * │                                                                                            │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                                       Documentation                                        │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+//! Timeouts for [`futures_concurrency`]'s [`Race`], [`RaceOk`], [`Join`] and [`TryJoin`]
+//! combinators.
+//!
+//! The futures returned by these combinators can already be bounded using
+//! [`TimeoutExt::timeout`], e.g. `(a, b).race().timeout(after)`. The extension traits of this
+//! module do the same in a single call, and name the returned type as a [`Timeout`] of the
+//! combinator's future, so that it can be stored without boxing.
+//!
+//! ## Example
+//!
+//! ```rust
+//! use async_io::Timer;
+//! # use futures_lite::future;
+//! use futures_concurrency::future::Race;
+//! use smol_timeout::futures_concurrency::{JoinTimeoutExt, RaceTimeoutExt};
+//! use smol_timeout::TimeoutExt;
+//! use std::time::Duration;
+//!
+//! # future::block_on(async {
+//! #
+//! let sleep = |millis| async move {
+//!     Timer::after(Duration::from_millis(millis)).await;
+//!     millis
+//! };
+//!
+//! assert_eq!((sleep(250), sleep(50)).race().timeout(Duration::from_millis(100)).await, Some(50));
+//! assert_eq!((sleep(250), sleep(150)).race_timeout(Duration::from_millis(100)).await, None);
+//!
+//! assert_eq!([sleep(50), sleep(100)].join_timeout(Duration::from_millis(250)).await, Some([50, 100]));
+//! #
+//! # });
+//! ```
+
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                                          Imports                                           │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+use crate::{Timeout, TimeoutExt};
+use ::futures_concurrency::future::{Join, Race, RaceOk, TryJoin};
+use core::time::Duration;
+
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                                    trait RaceTimeoutExt                                    │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+/// An extension trait for collections implementing [`Race`] that provides a way to race them
+/// with a timeout.
+pub trait RaceTimeoutExt: Race + Sized {
+    /// Races the futures, returning the output of the first one to complete, or [`None`] if
+    /// none completes before the provided duration.
+    ///
+    /// ## Example
+    ///
+    /// ```rust
+    /// # use futures_lite::future;
+    /// use smol_timeout::futures_concurrency::RaceTimeoutExt;
+    /// use std::time::Duration;
+    ///
+    /// # future::block_on(async {
+    /// #
+    /// let raced = (future::pending(), future::ready(42)).race_timeout(Duration::from_millis(100));
+    /// assert_eq!(raced.await, Some(42));
+    /// #
+    /// # })
+    /// ```
+    fn race_timeout(self, after: Duration) -> Timeout<Self::Future> {
+        self.race().timeout(after)
+    }
+}
+
+impl<T: Race> RaceTimeoutExt for T {}
+
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                                   trait RaceOkTimeoutExt                                   │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+/// An extension trait for collections implementing [`RaceOk`] that provides a way to race them
+/// with a timeout.
+pub trait RaceOkTimeoutExt: RaceOk + Sized {
+    /// Races the futures, returning the output of the first one to succeed or all the errors if
+    /// they all fail, or [`None`] if neither happens before the provided duration.
+    ///
+    /// ## Example
+    ///
+    /// ```rust
+    /// # use futures_lite::future;
+    /// use smol_timeout::futures_concurrency::RaceOkTimeoutExt;
+    /// use std::time::Duration;
+    ///
+    /// # future::block_on(async {
+    /// #
+    /// let raced = [future::ready(Err(())), future::ready(Ok(42))];
+    /// let raced = raced.race_ok_timeout(Duration::from_millis(100));
+    /// assert_eq!(raced.await.map(Result::ok), Some(Some(42)));
+    /// #
+    /// # })
+    /// ```
+    fn race_ok_timeout(self, after: Duration) -> Timeout<Self::Future> {
+        self.race_ok().timeout(after)
+    }
+}
+
+impl<T: RaceOk> RaceOkTimeoutExt for T {}
+
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                                    trait JoinTimeoutExt                                    │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+/// An extension trait for collections implementing [`Join`] that provides a way to join them
+/// with a timeout.
+pub trait JoinTimeoutExt: Join + Sized {
+    /// Joins the futures, returning all their outputs, or [`None`] if they don't all complete
+    /// before the provided duration.
+    ///
+    /// ## Example
+    ///
+    /// ```rust
+    /// # use futures_lite::future;
+    /// use smol_timeout::futures_concurrency::JoinTimeoutExt;
+    /// use std::time::Duration;
+    ///
+    /// # future::block_on(async {
+    /// #
+    /// let joined = (future::ready(24), future::ready(42));
+    /// assert_eq!(joined.join_timeout(Duration::from_millis(100)).await, Some((24, 42)));
+    /// #
+    /// # })
+    /// ```
+    fn join_timeout(self, after: Duration) -> Timeout<Self::Future> {
+        self.join().timeout(after)
+    }
+}
+
+impl<T: Join> JoinTimeoutExt for T {}
+
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                                  trait TryJoinTimeoutExt                                   │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+/// An extension trait for collections implementing [`TryJoin`] that provides a way to join them
+/// with a timeout.
+pub trait TryJoinTimeoutExt: TryJoin + Sized {
+    /// Joins the futures, returning all their outputs or the first error, or [`None`] if neither
+    /// happens before the provided duration.
+    ///
+    /// ## Example
+    ///
+    /// ```rust
+    /// # use futures_lite::future;
+    /// use smol_timeout::futures_concurrency::TryJoinTimeoutExt;
+    /// use std::time::Duration;
+    ///
+    /// # future::block_on(async {
+    /// #
+    /// let joined = (future::ready(Ok::<_, ()>(24)), future::pending::<Result<i32, ()>>());
+    /// assert_eq!(joined.try_join_timeout(Duration::from_millis(100)).await, None);
+    /// #
+    /// # })
+    /// ```
+    fn try_join_timeout(self, after: Duration) -> Timeout<Self::Future> {
+        self.try_join().timeout(after)
+    }
+}
+
+impl<T: TryJoin> TryJoinTimeoutExt for T {}
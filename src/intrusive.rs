@@ -0,0 +1,431 @@
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                                                                                            │ *
+ * │ This Source Code Form is subject to the terms of the Mozilla Public                        │ *
+ * │ License, v. 2.0. If a copy of the MPL was not distributed with this                        │ *
+ * │ file, You can obtain one at http://mozilla.org/MPL/2.0/.                                   │ *
+ * │                                                                                            │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                                       Documentation                                        │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+//! Timeouts linking themselves into an intrusive, per-thread list serviced by a single
+//! [`Timer`], for proxy-style workloads creating and cancelling timeouts at a high rate.
+//!
+//! An [`IntrusiveTimeout`] embeds the node it links into the sorted list of the thread it was
+//! created on the first time its future returns [`Poll::Pending`], so that creating a timeout
+//! never allocates, and unlinking it when it completes or is dropped is a constant-time
+//! operation. Each thread only keeps a single [`Timer`] registered with the reactor, armed at
+//! the deadline at the head of its list.
+//!
+//! Nodes are inserted by walking the list from its tail, which is a constant-time operation when
+//! timeouts are created with similar durations, as is typical for a proxy, but is linear in the
+//! number of pending timeouts in the worst case. See [`wheel`](crate::wheel) when durations vary
+//! widely.
+//!
+//! The timeouts stay [`Send`], and can be polled and dropped from any thread.
+//!
+//! ## Example
+//!
+//! ```rust
+//! use async_io::Timer;
+//! # use futures_lite::future;
+//! use smol_timeout::intrusive;
+//! use std::time::Duration;
+//!
+//! # future::block_on(async {
+//! #
+//! let foo = async {
+//!     Timer::after(Duration::from_millis(250)).await;
+//!     24
+//! };
+//!
+//! assert_eq!(intrusive::timeout(foo, Duration::from_millis(100)).await, None);
+//!
+//! let bar = async {
+//!     Timer::after(Duration::from_millis(100)).await;
+//!     42
+//! };
+//!
+//! assert_eq!(intrusive::timeout(bar, Duration::from_millis(250)).await, Some(42));
+//! #
+//! # });
+//! ```
+
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                                          Imports                                           │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+use crate::Timer;
+use crate::{clock, deadline};
+use core::cell::UnsafeCell;
+use core::fmt;
+use core::future::Future;
+use core::marker::PhantomPinned;
+use core::pin::Pin;
+use core::ptr;
+use core::task::{Context, Poll, Waker};
+use core::time::Duration;
+use pin_project_lite::pin_project;
+use std::sync::{Arc, Mutex};
+use std::task::Wake;
+use std::thread_local;
+use std::time::Instant;
+use std::vec::Vec;
+
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                                       struct Driver                                        │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+/// The sorted list of the nodes of a thread, and the timer armed at the deadline of its head.
+struct List {
+    head: *const Node,
+    tail: *const Node,
+    timer: Timer,
+    armed: Option<Instant>,
+}
+
+// SAFETY: the nodes a list points to are only accessed while its driver's lock is held.
+unsafe impl Send for List {}
+
+struct Driver {
+    list: Mutex<List>,
+}
+
+thread_local! {
+    static DRIVER: Arc<Driver> = Arc::new(Driver {
+        list: Mutex::new(List {
+            head: ptr::null(),
+            tail: ptr::null(),
+            timer: Timer::never(),
+            armed: None,
+        }),
+    });
+}
+
+impl Driver {
+    /// Unlinks the nodes whose deadline passed, pushing their wakers, and arms the timer at the
+    /// deadline of the remaining head.
+    fn rearm(self: &Arc<Self>, list: &mut List, wakers: &mut Vec<Waker>) {
+        loop {
+            let now = clock::now();
+
+            // SAFETY: the lock is held, and linked nodes are valid.
+            unsafe {
+                while !list.head.is_null() && (*list.head).deadline <= now {
+                    let head = list.head;
+                    list.unlink(head);
+
+                    let links = &mut *(*head).links.get();
+                    links.fired = true;
+                    wakers.extend(links.waker.take());
+                }
+            }
+
+            if list.head.is_null() {
+                list.armed = None;
+                return;
+            }
+
+            // SAFETY: the lock is held, and linked nodes are valid.
+            let earliest = unsafe { (*list.head).deadline };
+            if list.armed != Some(earliest) {
+                list.timer.set_at(earliest);
+                list.armed = Some(earliest);
+            }
+
+            // The timer is polled with a waker processing the whole list.
+            let waker = Waker::from(self.clone());
+            if Pin::new(&mut list.timer)
+                .poll(&mut Context::from_waker(&waker))
+                .is_pending()
+            {
+                return;
+            }
+
+            list.armed = None;
+        }
+    }
+}
+
+impl Wake for Driver {
+    fn wake(self: Arc<Self>) {
+        self.wake_by_ref();
+    }
+
+    fn wake_by_ref(self: &Arc<Self>) {
+        let mut wakers = Vec::new();
+        {
+            let mut list = self.list.lock().unwrap();
+            list.armed = None;
+            self.rearm(&mut list, &mut wakers);
+        }
+
+        for waker in wakers {
+            waker.wake();
+        }
+    }
+}
+
+impl List {
+    /// Links a node into the list, keeping it sorted by deadline, and returns `true` if it
+    /// became its head.
+    ///
+    /// ## Safety
+    ///
+    /// The driver's lock must be held, the node must be pinned and unlinked, and it must unlink
+    /// itself before being dropped.
+    unsafe fn link(&mut self, node: *const Node) -> bool {
+        let deadline = (*node).deadline;
+
+        let mut prev = self.tail;
+        while !prev.is_null() && (*prev).deadline > deadline {
+            prev = (*(*prev).links.get()).prev;
+        }
+
+        let next = if prev.is_null() {
+            self.head
+        } else {
+            (*(*prev).links.get()).next
+        };
+
+        let links = (*node).links.get();
+        (*links).prev = prev;
+        (*links).next = next;
+        (*links).linked = true;
+
+        if prev.is_null() {
+            self.head = node;
+        } else {
+            (*(*prev).links.get()).next = node;
+        }
+
+        if next.is_null() {
+            self.tail = node;
+        } else {
+            (*(*next).links.get()).prev = node;
+        }
+
+        prev.is_null()
+    }
+
+    /// Unlinks a node from the list.
+    ///
+    /// ## Safety
+    ///
+    /// The driver's lock must be held, and the node must be linked into this list.
+    unsafe fn unlink(&mut self, node: *const Node) {
+        let links = (*node).links.get();
+        let (prev, next) = ((*links).prev, (*links).next);
+
+        if prev.is_null() {
+            self.head = next;
+        } else {
+            (*(*prev).links.get()).next = next;
+        }
+
+        if next.is_null() {
+            self.tail = prev;
+        } else {
+            (*(*next).links.get()).prev = prev;
+        }
+
+        (*links).prev = ptr::null();
+        (*links).next = ptr::null();
+        (*links).linked = false;
+    }
+}
+
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                                        struct Node                                         │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+/// The part of a node that is only accessed while its driver's lock is held.
+struct Links {
+    prev: *const Node,
+    next: *const Node,
+    waker: Option<Waker>,
+    linked: bool,
+    fired: bool,
+}
+
+/// A node of the list of a [`Driver`], embedded in an [`IntrusiveTimeout`].
+struct Node {
+    driver: Arc<Driver>,
+    deadline: Instant,
+    links: UnsafeCell<Links>,
+    _pin: PhantomPinned,
+}
+
+// SAFETY: a node's links are only accessed while its driver's lock is held.
+unsafe impl Send for Node {}
+unsafe impl Sync for Node {}
+
+impl Node {
+    fn new(deadline: Instant) -> Self {
+        Node {
+            driver: DRIVER.with(|driver| driver.clone()),
+            deadline,
+            links: UnsafeCell::new(Links {
+                prev: ptr::null(),
+                next: ptr::null(),
+                waker: None,
+                linked: false,
+                fired: false,
+            }),
+            _pin: PhantomPinned,
+        }
+    }
+
+    fn poll(self: Pin<&Self>, ctx: &mut Context) -> Poll<()> {
+        let now = clock::now();
+        let mut wakers = Vec::new();
+
+        let ready = {
+            let mut list = self.driver.list.lock().unwrap();
+            let links = self.links.get();
+
+            // SAFETY: the lock is held, the node is pinned, and it unlinks itself when dropped.
+            // No reference to its links is held across the calls linking or unlinking it.
+            unsafe {
+                if (*links).fired {
+                    true
+                } else if self.deadline <= now {
+                    if (*links).linked {
+                        list.unlink(&*self);
+                    }
+
+                    (*links).fired = true;
+                    true
+                } else {
+                    match &(*links).waker {
+                        Some(waker) if waker.will_wake(ctx.waker()) => (),
+                        _ => (*links).waker = Some(ctx.waker().clone()),
+                    }
+
+                    if !(*links).linked && list.link(&*self) {
+                        self.driver.rearm(&mut list, &mut wakers);
+                    }
+
+                    false
+                }
+            }
+        };
+
+        for waker in wakers {
+            waker.wake();
+        }
+
+        if ready {
+            Poll::Ready(())
+        } else {
+            Poll::Pending
+        }
+    }
+}
+
+impl Drop for Node {
+    fn drop(&mut self) {
+        let mut list = self.driver.list.lock().unwrap();
+
+        // SAFETY: the lock is held, and a node is only linked once pinned, so that it is still
+        // at the same address.
+        unsafe {
+            if (*self.links.get()).linked {
+                list.unlink(self);
+            }
+        }
+    }
+}
+
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                                struct IntrusiveTimeout<Fut>                                │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+pin_project! {
+    /// A future polling both another future and a node linked into the list of the thread it
+    /// was created on, and returning the future's output or [`None`] if the node's deadline
+    /// passes first.
+    ///
+    /// Created by [`timeout`] and [`timeout_at`].
+    #[must_use = "futures do nothing unless you `.await` or poll them"]
+    pub struct IntrusiveTimeout<Fut> {
+        #[pin]
+        future: Fut,
+        #[pin]
+        node: Node,
+        armed: bool,
+    }
+}
+
+/// Given a future and a [`Duration`], creates and returns a new [`IntrusiveTimeout`] that will
+/// poll the future until the provided duration elapses, and return its output or [`None`] if it
+/// didn't complete in time.
+pub fn timeout<Fut: Future>(future: Fut, after: Duration) -> IntrusiveTimeout<Fut> {
+    timeout_at(future, deadline::deadline_after(clock::now(), after))
+}
+
+/// Given a future and an [`Instant`], creates and returns a new [`IntrusiveTimeout`] that will
+/// poll the future until the provided deadline, and return its output or [`None`] if it didn't
+/// complete in time.
+///
+/// ## Example
+///
+/// ```rust
+/// # use futures_lite::future;
+/// use smol_timeout::intrusive;
+/// use std::time::{Duration, Instant};
+///
+/// # future::block_on(async {
+/// #
+/// let deadline = Instant::now() + Duration::from_millis(100);
+///
+/// assert_eq!(intrusive::timeout_at(future::pending::<()>(), deadline).await, None);
+/// assert!(Instant::now() >= deadline);
+/// #
+/// # })
+/// ```
+pub fn timeout_at<Fut: Future>(future: Fut, deadline: Instant) -> IntrusiveTimeout<Fut> {
+    IntrusiveTimeout {
+        future,
+        node: Node::new(deadline),
+        armed: false,
+    }
+}
+
+impl<Fut: Future> Future for IntrusiveTimeout<Fut> {
+    type Output = Option<Fut::Output>;
+
+    fn poll(self: Pin<&mut Self>, ctx: &mut Context) -> Poll<Self::Output> {
+        let this = self.project();
+        let node = this.node.into_ref();
+
+        // Like a `Timeout`, the node is only linked once the future returned `Poll::Pending`.
+        if *this.armed && node.poll(ctx).is_ready() {
+            return Poll::Ready(None);
+        }
+
+        if let Poll::Ready(output) = this.future.poll(ctx) {
+            return Poll::Ready(Some(output));
+        }
+
+        if !*this.armed {
+            *this.armed = true;
+            if node.poll(ctx).is_ready() {
+                return Poll::Ready(None);
+            }
+        }
+
+        Poll::Pending
+    }
+}
+
+impl<Fut: fmt::Debug> fmt::Debug for IntrusiveTimeout<Fut> {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        fmt.debug_struct("IntrusiveTimeout")
+            .field("future", &self.future)
+            .field("deadline", &self.node.deadline)
+            .finish()
+    }
+}
@@ -0,0 +1,219 @@
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                                                                                            │ *
+ * │ This Source Code Form is subject to the terms of the Mozilla Public                        │ *
+ * │ License, v. 2.0. If a copy of the MPL was not distributed with this                        │ *
+ * │ file, You can obtain one at http://mozilla.org/MPL/2.0/.                                   │ *
+ * │                                                                                            │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                                       Documentation                                        │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+//! Intervals whose ticks are randomly perturbed, so that clients polling on the same schedule
+//! spread their load instead of hitting a server all at once.
+//!
+//! A [`JitteredInterval`] is a [`Stream`] ticking once per period, each tick being moved
+//! randomly by up to its [`Jitter`] (an absolute duration or a percentage of the period) before
+//! or after its nominal instant. The ticks are jittered around a fixed schedule rather than from
+//! one another, so that the interval doesn't drift over time and still ticks once per period on
+//! average.
+//!
+//! With the `sim` feature, the jitter is drawn from the seeded random number generator of the
+//! simulation (see [`sim::random`]), so that it is reproducible.
+//!
+//! [`sim::random`]: crate::sim::random
+//!
+//! ## Example
+//!
+//! ```rust
+//! # use futures_lite::future;
+//! use futures_lite::StreamExt;
+//! use smol_timeout::jitter::{self, Jitter};
+//! use std::time::{Duration, Instant};
+//!
+//! # future::block_on(async {
+//! #
+//! let start = Instant::now();
+//! let mut interval = jitter::interval(Duration::from_millis(100), Jitter::Percent(10));
+//!
+//! interval.next().await;
+//! interval.next().await;
+//!
+//! assert!(start.elapsed() >= Duration::from_millis(190));
+//! #
+//! # });
+//! ```
+
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                                          Imports                                           │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+use crate::{clock, deadline, Timer};
+use core::convert::TryFrom;
+use core::future::Future;
+use core::pin::Pin;
+use core::task::{Context, Poll};
+use core::time::Duration;
+use futures_core::stream::Stream;
+use std::time::Instant;
+
+#[cfg(not(all(feature = "sim", not(target_os = "wasi"))))]
+use std::collections::hash_map::RandomState;
+#[cfg(not(all(feature = "sim", not(target_os = "wasi"))))]
+use std::hash::{BuildHasher, Hasher};
+
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                                        enum Jitter                                         │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+/// The maximum amount a tick of a [`JitteredInterval`] can be moved by, before or after its
+/// nominal instant.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Jitter {
+    /// A fixed duration.
+    Absolute(Duration),
+    /// A percentage of the interval's period, between `0` and `100`.
+    Percent(u8),
+}
+
+impl Jitter {
+    /// Returns the maximum amount a tick can be moved by, given the period of the interval.
+    fn max(&self, period: Duration) -> Duration {
+        match *self {
+            Jitter::Absolute(jitter) => jitter,
+            Jitter::Percent(percent) => {
+                let nanos = period.as_nanos() * u128::from(percent) / 100;
+                Duration::from_nanos(u64::try_from(nanos).unwrap_or(u64::MAX))
+            }
+        }
+    }
+}
+
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                                  struct JitteredInterval                                   │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+/// A stream ticking once per period on average, with every tick randomly moved by up to its
+/// [`Jitter`], and yielding the instant it ticked at.
+///
+/// Created by [`interval`] and [`interval_at`].
+#[derive(Debug)]
+#[must_use = "streams do nothing unless polled"]
+pub struct JitteredInterval {
+    timer: Timer,
+    period: Duration,
+    jitter: Duration,
+    /// The nominal instant of the next tick.
+    next: Instant,
+}
+
+impl JitteredInterval {
+    /// Returns the period of the interval.
+    pub fn period(&self) -> Duration {
+        self.period
+    }
+
+    /// Returns the maximum amount a tick can be moved by.
+    pub fn jitter(&self) -> Duration {
+        self.jitter
+    }
+
+    /// Sets the timer to the next nominal instant, moved by a random amount of up to the jitter.
+    fn schedule(&mut self) {
+        let jitter = u64::try_from(self.jitter.as_nanos()).unwrap_or(u64::MAX / 2);
+        let offset = random() % (jitter.saturating_mul(2).saturating_add(1));
+
+        let deadline = if offset >= jitter {
+            self.next + Duration::from_nanos(offset - jitter)
+        } else {
+            let early = Duration::from_nanos(jitter - offset);
+            self.next.checked_sub(early).unwrap_or(self.next)
+        };
+
+        self.timer.set_at(deadline);
+    }
+}
+
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                                       fn interval()                                        │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+/// Creates and returns a new [`JitteredInterval`] ticking once per `period` on average, starting
+/// one period from now, with every tick moved by up to `jitter`.
+///
+/// ## Panics
+///
+/// Panics if `period` is zero, or if `jitter` is a percentage above `100`.
+pub fn interval(period: Duration, jitter: Jitter) -> JitteredInterval {
+    interval_at(
+        deadline::deadline_after(clock::now(), period),
+        period,
+        jitter,
+    )
+}
+
+/// Creates and returns a new [`JitteredInterval`] ticking once per `period` on average, starting
+/// at `start`, with every tick moved by up to `jitter`.
+///
+/// ## Panics
+///
+/// Panics if `period` is zero, or if `jitter` is a percentage above `100`.
+pub fn interval_at(start: Instant, period: Duration, jitter: Jitter) -> JitteredInterval {
+    assert!(
+        period > Duration::from_secs(0),
+        "an interval's period can't be zero"
+    );
+
+    if let Jitter::Percent(percent) = jitter {
+        assert!(percent <= 100, "a jitter can't be above 100% of the period");
+    }
+
+    let mut interval = JitteredInterval {
+        timer: Timer::never(),
+        period,
+        jitter: jitter.max(period),
+        next: start,
+    };
+
+    interval.schedule();
+    interval
+}
+
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                              impl Stream for JitteredInterval                              │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+impl Stream for JitteredInterval {
+    type Item = Instant;
+
+    fn poll_next(self: Pin<&mut Self>, ctx: &mut Context) -> Poll<Option<Instant>> {
+        let this = self.get_mut();
+
+        let instant = match Pin::new(&mut this.timer).poll(ctx) {
+            Poll::Ready(instant) => instant,
+            Poll::Pending => return Poll::Pending,
+        };
+
+        this.next += this.period;
+        this.schedule();
+
+        Poll::Ready(Some(instant))
+    }
+}
+
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                                        fn random()                                         │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+/// Returns a random number, from the simulation's seeded random number generator.
+#[cfg(all(feature = "sim", not(target_os = "wasi")))]
+fn random() -> u64 {
+    crate::sim::random()
+}
+
+/// Returns a random number, from a randomly seeded hasher.
+#[cfg(not(all(feature = "sim", not(target_os = "wasi"))))]
+fn random() -> u64 {
+    RandomState::new().build_hasher().finish()
+}
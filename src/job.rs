@@ -0,0 +1,301 @@
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                                                                                            │ *
+ * │ This Source Code Form is subject to the terms of the Mozilla Public                        │ *
+ * │ License, v. 2.0. If a copy of the MPL was not distributed with this                        │ *
+ * │ file, You can obtain one at http://mozilla.org/MPL/2.0/.                                   │ *
+ * │                                                                                            │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                                       Documentation                                        │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+//! A runner driving a recurring job forever, with a timeout for every run.
+//!
+//! A [`JobRunner`] starts a run of its job (a future created by a factory) on every tick of a
+//! schedule, which is either a fixed interval (see [`JobRunner::every`]) or any [`Stream`] of
+//! ticks (e.g. a [`JitteredInterval`]). Every run is bounded by a timeout, and an [`Overlap`]
+//! policy decides what happens when a tick arrives while the previous run is still going. The
+//! [`RunOutcome`] of every tick is passed to a hook, e.g. to log or count them.
+//!
+//! [`JitteredInterval`]: crate::jitter::JitteredInterval
+//!
+//! ## Example
+//!
+//! ```rust
+//! use async_io::Timer;
+//! # use futures_lite::future;
+//! use smol_timeout::job::{JobRunner, Overlap, RunOutcome};
+//! use std::sync::{Arc, Mutex};
+//! use std::time::Duration;
+//!
+//! # future::block_on(async {
+//! #
+//! let outcomes = Arc::new(Mutex::new(Vec::new()));
+//!
+//! let runner = JobRunner::every(Duration::from_millis(200), || async {
+//!     Timer::after(Duration::from_millis(300)).await;
+//!     "synced"
+//! })
+//! .timeout(Duration::from_millis(500))
+//! .overlap(Overlap::Skip)
+//! .on_outcome({
+//!     let outcomes = outcomes.clone();
+//!     move |outcome| outcomes.lock().unwrap().push(outcome)
+//! });
+//!
+//! // Runs start at 0ms and 400ms, and the ticks at 200ms and 600ms are skipped.
+//! let deadline = Timer::after(Duration::from_millis(750));
+//! future::or(runner, async { deadline.await; }).await;
+//!
+//! let outcomes = outcomes.lock().unwrap();
+//! assert_eq!(
+//!     *outcomes,
+//!     [
+//!         RunOutcome::Skipped,
+//!         RunOutcome::Completed("synced"),
+//!         RunOutcome::Skipped,
+//!         RunOutcome::Completed("synced"),
+//!     ],
+//! );
+//! #
+//! # });
+//! ```
+
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                                          Imports                                           │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+use crate::jitter::{self, Jitter, JitteredInterval};
+use crate::{clock, Timer};
+use core::fmt;
+use core::future::Future;
+use core::pin::Pin;
+use core::task::{Context, Poll};
+use core::time::Duration;
+use futures_core::stream::Stream;
+use pin_project_lite::pin_project;
+use std::boxed::Box;
+
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                                        enum Overlap                                        │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+/// What a [`JobRunner`] does when its schedule ticks while the previous run is still going.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Overlap {
+    /// The tick is skipped, and the previous run keeps going.
+    #[default]
+    Skip,
+    /// The tick is queued, and a new run starts as soon as the previous one ends.
+    Queue,
+    /// The previous run is cancelled, and a new run starts right away.
+    CancelPrevious,
+}
+
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                                     enum RunOutcome<T>                                     │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+/// The outcome of a tick of a [`JobRunner`], passed to its hook (see [`JobRunner::on_outcome`]).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum RunOutcome<T> {
+    /// The run completed with the provided output.
+    Completed(T),
+    /// The run didn't complete before the runner's timeout, and was dropped.
+    TimedOut,
+    /// The run was dropped because of a new tick (see [`Overlap::CancelPrevious`]).
+    Cancelled,
+    /// The tick was skipped because the previous run was still going (see [`Overlap::Skip`]).
+    Skipped,
+}
+
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                                struct JobRunner<S, F, Fut>                                 │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+pin_project! {
+    /// A future running the futures created by a factory on every tick of a schedule, one at a
+    /// time, with a timeout for every run.
+    ///
+    /// The runner completes once its schedule ends and its last run ends, and never completes
+    /// with a fixed interval (see [`JobRunner::every`]).
+    #[must_use = "futures do nothing unless you `.await` or poll them"]
+    pub struct JobRunner<S, F, Fut: Future> {
+        ticks: S,
+        factory: F,
+        #[pin]
+        run: Option<Fut>,
+        timer: Timer,
+        timeout: Option<Duration>,
+        overlap: Overlap,
+        // The number of ticks queued while a run was going.
+        queued: usize,
+        // Whether the schedule ended.
+        ended: bool,
+        hook: Option<Box<dyn FnMut(RunOutcome<Fut::Output>) + Send>>,
+    }
+}
+
+impl<F, Fut> JobRunner<JitteredInterval, F, Fut>
+where
+    F: FnMut() -> Fut,
+    Fut: Future,
+{
+    /// Creates a new [`JobRunner`] running the futures returned by `factory` every `period`
+    /// starting now, without any timeout and skipping the ticks of overlapping runs.
+    ///
+    /// ## Panics
+    ///
+    /// Panics if `period` is zero.
+    pub fn every(period: Duration, factory: F) -> Self {
+        let ticks =
+            jitter::interval_at(clock::now(), period, Jitter::Absolute(Duration::default()));
+        JobRunner::new(ticks, factory)
+    }
+}
+
+impl<S, F, Fut> JobRunner<S, F, Fut>
+where
+    S: Stream + Unpin,
+    F: FnMut() -> Fut,
+    Fut: Future,
+{
+    /// Creates a new [`JobRunner`] running the futures returned by `factory` on every item of
+    /// `ticks`, without any timeout and skipping the ticks of overlapping runs.
+    pub fn new(ticks: S, factory: F) -> Self {
+        JobRunner {
+            ticks,
+            factory,
+            run: None,
+            timer: Timer::never(),
+            timeout: None,
+            overlap: Overlap::default(),
+            queued: 0,
+            ended: false,
+            hook: None,
+        }
+    }
+
+    /// Sets the duration after which a run is considered to have timed out and is dropped.
+    pub fn timeout(mut self, after: Duration) -> Self {
+        self.timeout = Some(after);
+        self
+    }
+
+    /// Sets the [`Overlap`] policy used when the schedule ticks while a run is still going.
+    pub fn overlap(mut self, overlap: Overlap) -> Self {
+        self.overlap = overlap;
+        self
+    }
+
+    /// Sets a hook called with the [`RunOutcome`] of every tick, e.g. to log them.
+    pub fn on_outcome<H>(mut self, hook: H) -> Self
+    where
+        H: FnMut(RunOutcome<Fut::Output>) + Send + 'static,
+    {
+        self.hook = Some(Box::new(hook));
+        self
+    }
+}
+
+impl<S, F, Fut: Future> fmt::Debug for JobRunner<S, F, Fut> {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        fmt.debug_struct("JobRunner")
+            .field("timeout", &self.timeout)
+            .field("overlap", &self.overlap)
+            .field("running", &self.run.is_some())
+            .field("queued", &self.queued)
+            .finish()
+    }
+}
+
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                            impl Future for JobRunner<S, F, Fut>                            │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+impl<S, F, Fut> Future for JobRunner<S, F, Fut>
+where
+    S: Stream + Unpin,
+    F: FnMut() -> Fut,
+    Fut: Future,
+{
+    type Output = ();
+
+    fn poll(self: Pin<&mut Self>, ctx: &mut Context) -> Poll<()> {
+        let mut this = self.project();
+
+        loop {
+            if let Some(run) = this.run.as_mut().as_pin_mut() {
+                if let Poll::Ready(output) = run.poll(ctx) {
+                    this.run.set(None);
+                    emit(this.hook, RunOutcome::Completed(output));
+                } else if this.timeout.is_some() && Pin::new(&mut *this.timer).poll(ctx).is_ready()
+                {
+                    this.run.set(None);
+                    emit(this.hook, RunOutcome::TimedOut);
+                }
+            }
+
+            let mut ticked = false;
+            let start = if this.run.is_none() && *this.queued > 0 {
+                *this.queued -= 1;
+                true
+            } else if *this.ended {
+                false
+            } else {
+                let tick = Pin::new(&mut *this.ticks).poll_next(ctx);
+                ticked = matches!(tick, Poll::Ready(Some(_)));
+
+                match tick {
+                    Poll::Ready(Some(_)) if this.run.is_none() => true,
+                    Poll::Ready(Some(_)) => match this.overlap {
+                        Overlap::Skip => {
+                            emit(this.hook, RunOutcome::Skipped);
+                            false
+                        }
+                        Overlap::Queue => {
+                            *this.queued += 1;
+                            false
+                        }
+                        Overlap::CancelPrevious => {
+                            this.run.set(None);
+                            emit(this.hook, RunOutcome::Cancelled);
+                            true
+                        }
+                    },
+                    Poll::Ready(None) => {
+                        *this.ended = true;
+                        false
+                    }
+                    Poll::Pending => false,
+                }
+            };
+
+            if start {
+                this.run.set(Some((this.factory)()));
+                if let Some(after) = *this.timeout {
+                    this.timer.set_after(after);
+                }
+
+                continue;
+            }
+
+            if ticked {
+                continue;
+            }
+
+            if this.run.is_none() && *this.ended && *this.queued == 0 {
+                return Poll::Ready(());
+            }
+
+            return Poll::Pending;
+        }
+    }
+}
+
+fn emit<T>(hook: &mut Option<Box<dyn FnMut(RunOutcome<T>) + Send>>, outcome: RunOutcome<T>) {
+    if let Some(hook) = hook {
+        hook(outcome);
+    }
+}
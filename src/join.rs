@@ -0,0 +1,761 @@
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                                                                                            │ *
+ * │ This Source Code Form is subject to the terms of the Mozilla Public                        │ *
+ * │ License, v. 2.0. If a copy of the MPL was not distributed with this                        │ *
+ * │ file, You can obtain one at http://mozilla.org/MPL/2.0/.                                   │ *
+ * │                                                                                            │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                                       Documentation                                        │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+//! Ways to drive collections of futures concurrently under a deadline.
+//!
+//! ## Example
+//!
+//! ```rust
+//! use async_io::Timer;
+//! # use futures_lite::future;
+//! use smol_timeout::join;
+//! use std::time::Duration;
+//!
+//! # future::block_on(async {
+//! #
+//! let futures = [100, 250, 50].iter().map(|&millis| async move {
+//!     Timer::after(Duration::from_millis(millis)).await;
+//!     millis
+//! });
+//!
+//! let outputs = join::join_all_with_deadline(futures, Duration::from_millis(200)).await;
+//! assert_eq!(outputs, [Some(100), None, Some(50)]);
+//! #
+//! # });
+//! ```
+
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                                          Imports                                           │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+use crate::clock::Stamp;
+use crate::ready_queue::{ReadyEntry, ReadyQueue};
+use crate::{Elapsed, Timeout, TimeoutExt, Timer};
+use core::fmt;
+use core::future::Future;
+use core::pin::Pin;
+use core::task::{Context, Poll, Waker};
+use core::time::Duration;
+use futures_core::future::TryFuture;
+use futures_core::stream::Stream;
+use std::boxed::Box;
+use std::sync::Arc;
+use std::vec::Vec;
+
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                                    enum TryJoinError<E>                                    │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+/// The error returned by [`TryJoinWithDeadline`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum TryJoinError<E> {
+    /// Some futures didn't complete before the deadline.
+    Elapsed,
+    /// A future failed. Contains the first error.
+    Failed(E),
+}
+
+impl<E: fmt::Display> fmt::Display for TryJoinError<E> {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            TryJoinError::Elapsed => fmt::Display::fmt(&Elapsed::new(), fmt),
+            TryJoinError::Failed(err) => fmt::Display::fmt(err, fmt),
+        }
+    }
+}
+
+impl<E: fmt::Debug + fmt::Display> std::error::Error for TryJoinError<E> {}
+
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                              struct JoinAllWithDeadline<Fut>                               │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+/// A future polling a collection of futures concurrently and a single [`Timer`] that will
+/// complete after a specified timeout, and returning the futures' outputs, with [`None`] in
+/// place of those that weren't ready when the timer completed.
+///
+/// Created by [`join_all_with_deadline`].
+#[must_use = "futures do nothing unless you `.await` or poll them"]
+pub struct JoinAllWithDeadline<Fut: Future> {
+    futures: Vec<Option<Pin<Box<Fut>>>>,
+    outputs: Vec<Option<Fut::Output>>,
+    pending: usize,
+    timer: Timer,
+}
+
+// The futures are boxed and their outputs are never pinned.
+impl<Fut: Future> Unpin for JoinAllWithDeadline<Fut> {}
+
+impl<Fut: Future> fmt::Debug for JoinAllWithDeadline<Fut> {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        fmt.debug_struct("JoinAllWithDeadline")
+            .field("pending", &self.pending)
+            .field("timer", &self.timer)
+            .finish()
+    }
+}
+
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                                fn join_all_with_deadline()                                 │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+/// Given a collection of futures and a [`Duration`], creates and returns a new
+/// [`JoinAllWithDeadline`] that will drive all the futures concurrently until they all complete
+/// or the provided duration elapses, sharing a single [`Timer`] between them.
+///
+/// The returned outputs are in the same order as the futures, with [`None`] in place of the
+/// outputs of the futures that didn't complete in time.
+///
+/// ## Example
+///
+/// ```rust
+/// # use futures_lite::future;
+/// use smol_timeout::join;
+/// use std::time::Duration;
+///
+/// # future::block_on(async {
+/// #
+/// let futures = vec![future::ready(1), future::ready(2)];
+///
+/// let outputs = join::join_all_with_deadline(futures, Duration::from_millis(100)).await;
+/// assert_eq!(outputs, [Some(1), Some(2)]);
+/// #
+/// # })
+/// ```
+pub fn join_all_with_deadline<I>(futures: I, after: Duration) -> JoinAllWithDeadline<I::Item>
+where
+    I: IntoIterator,
+    I::Item: Future,
+{
+    let futures = futures
+        .into_iter()
+        .map(|future| Some(Box::pin(future)))
+        .collect::<Vec<_>>();
+
+    JoinAllWithDeadline {
+        outputs: futures.iter().map(|_| None).collect(),
+        pending: futures.len(),
+        futures,
+        timer: Timer::after(after),
+    }
+}
+
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                          impl Future for JoinAllWithDeadline<Fut>                          │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+impl<Fut: Future> Future for JoinAllWithDeadline<Fut> {
+    type Output = Vec<Option<Fut::Output>>;
+
+    fn poll(mut self: Pin<&mut Self>, ctx: &mut Context) -> Poll<Self::Output> {
+        let this = &mut *self;
+
+        // The futures are polled before the timer, so that the outputs that are ready when it
+        // completes are still returned.
+        for (future, output) in this.futures.iter_mut().zip(this.outputs.iter_mut()) {
+            if let Some(fut) = future {
+                if let Poll::Ready(out) = fut.as_mut().poll(ctx) {
+                    *output = Some(out);
+                    *future = None;
+                    this.pending -= 1;
+                }
+            }
+        }
+
+        if this.pending > 0 && Pin::new(&mut this.timer).poll(ctx).is_pending() {
+            return Poll::Pending;
+        }
+
+        // Dropping the futures that didn't complete in time.
+        this.futures.clear();
+        Poll::Ready(this.outputs.drain(..).collect())
+    }
+}
+
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                              struct TryJoinWithDeadline<Fut>                               │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+/// A future polling a collection of fallible futures concurrently and a single [`Timer`] that
+/// will complete after a specified timeout, and returning either all the futures' outputs, the
+/// first error, or [`TryJoinError::Elapsed`] if the timer completed first.
+///
+/// Created by [`try_join_with_deadline`].
+#[must_use = "futures do nothing unless you `.await` or poll them"]
+pub struct TryJoinWithDeadline<Fut: TryFuture> {
+    futures: Vec<Option<Pin<Box<Fut>>>>,
+    outputs: Vec<Option<Fut::Ok>>,
+    pending: usize,
+    timer: Timer,
+}
+
+// The futures are boxed and their outputs are never pinned.
+impl<Fut: TryFuture> Unpin for TryJoinWithDeadline<Fut> {}
+
+impl<Fut: TryFuture> fmt::Debug for TryJoinWithDeadline<Fut> {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        fmt.debug_struct("TryJoinWithDeadline")
+            .field("pending", &self.pending)
+            .field("timer", &self.timer)
+            .finish()
+    }
+}
+
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                                fn try_join_with_deadline()                                 │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+/// Given a collection of fallible futures and a [`Duration`], creates and returns a new
+/// [`TryJoinWithDeadline`] that will drive all the futures concurrently until they all succeed,
+/// one of them fails, or the provided duration elapses, sharing a single [`Timer`] between them.
+///
+/// The remaining futures are dropped as soon as one of them fails or the duration elapses.
+///
+/// ## Example
+///
+/// ```rust
+/// use async_io::Timer;
+/// # use futures_lite::future;
+/// use smol_timeout::join::{self, TryJoinError};
+/// use std::time::Duration;
+///
+/// # future::block_on(async {
+/// #
+/// let fetch = |millis| async move {
+///     Timer::after(Duration::from_millis(millis)).await;
+///     if millis > 200 {
+///         Err("too slow")
+///     } else {
+///         Ok(millis)
+///     }
+/// };
+///
+/// let outputs = join::try_join_with_deadline(vec![fetch(50), fetch(100)], Duration::from_secs(1));
+/// assert_eq!(outputs.await, Ok(vec![50, 100]));
+///
+/// let outputs = join::try_join_with_deadline(vec![fetch(50), fetch(250)], Duration::from_secs(1));
+/// assert_eq!(outputs.await, Err(TryJoinError::Failed("too slow")));
+///
+/// let outputs = join::try_join_with_deadline(vec![fetch(50), fetch(150)], Duration::from_millis(100));
+/// assert_eq!(outputs.await, Err(TryJoinError::Elapsed));
+/// #
+/// # })
+/// ```
+pub fn try_join_with_deadline<I>(futures: I, after: Duration) -> TryJoinWithDeadline<I::Item>
+where
+    I: IntoIterator,
+    I::Item: TryFuture,
+{
+    let futures = futures
+        .into_iter()
+        .map(|future| Some(Box::pin(future)))
+        .collect::<Vec<_>>();
+
+    TryJoinWithDeadline {
+        outputs: futures.iter().map(|_| None).collect(),
+        pending: futures.len(),
+        futures,
+        timer: Timer::after(after),
+    }
+}
+
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                          impl Future for TryJoinWithDeadline<Fut>                          │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+impl<Fut: TryFuture> Future for TryJoinWithDeadline<Fut> {
+    type Output = Result<Vec<Fut::Ok>, TryJoinError<Fut::Error>>;
+
+    fn poll(self: Pin<&mut Self>, ctx: &mut Context) -> Poll<Self::Output> {
+        let this = self.get_mut();
+
+        let mut failed = None;
+        for (future, output) in this.futures.iter_mut().zip(this.outputs.iter_mut()) {
+            if let Some(fut) = future {
+                if let Poll::Ready(out) = fut.as_mut().try_poll(ctx) {
+                    *future = None;
+                    this.pending -= 1;
+
+                    match out {
+                        Ok(out) => *output = Some(out),
+                        Err(err) => {
+                            failed = Some(err);
+                            break;
+                        }
+                    }
+                }
+            }
+        }
+
+        if let Some(err) = failed {
+            this.futures.clear();
+            return Poll::Ready(Err(TryJoinError::Failed(err)));
+        }
+
+        if this.pending == 0 {
+            return Poll::Ready(Ok(this.outputs.drain(..).flatten().collect()));
+        }
+
+        if Pin::new(&mut this.timer).poll(ctx).is_pending() {
+            return Poll::Pending;
+        }
+
+        this.futures.clear();
+        Poll::Ready(Err(TryJoinError::Elapsed))
+    }
+}
+
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                                   struct TimeoutAll<Fut>                                   │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+struct Entry<Fut: Future> {
+    future: Pin<Box<Timeout<Fut>>>,
+    ready: Arc<ReadyEntry>,
+    /// The waker the future is polled with, queueing the entry when woken up.
+    waker: Waker,
+}
+
+/// A stream polling a collection of [`Timeout`]s concurrently, and yielding each one's index
+/// and output as soon as it completes.
+///
+/// Every future is polled with its own waker, so that only the futures that were woken up are
+/// polled again.
+///
+/// Created by [`timeout_all`].
+#[must_use = "streams do nothing unless polled"]
+pub struct TimeoutAll<Fut: Future> {
+    futures: Vec<Option<Entry<Fut>>>,
+    pending: usize,
+    queue: Arc<ReadyQueue>,
+}
+
+// The futures are boxed.
+impl<Fut: Future> Unpin for TimeoutAll<Fut> {}
+
+impl<Fut: Future> fmt::Debug for TimeoutAll<Fut> {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        fmt.debug_struct("TimeoutAll")
+            .field("pending", &self.pending)
+            .finish()
+    }
+}
+
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                                      fn timeout_all()                                      │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+/// Given a collection of futures and a closure returning the [`Duration`] to allow each of them
+/// given its index, creates and returns a new [`TimeoutAll`] stream that will drive all the
+/// futures concurrently, each with its own timeout.
+///
+/// The stream yields `(index, output)` pairs in the order the futures complete or time out,
+/// where `output` is [`None`] if the future at `index` timed out.
+///
+/// ## Example
+///
+/// ```rust
+/// use async_io::Timer;
+/// # use futures_lite::future;
+/// use futures_lite::StreamExt;
+/// use smol_timeout::join;
+/// use std::time::Duration;
+///
+/// # future::block_on(async {
+/// #
+/// let futures = [150, 50].iter().map(|&millis| async move {
+///     Timer::after(Duration::from_millis(millis)).await;
+///     millis
+/// });
+///
+/// let budgets = [Duration::from_millis(100), Duration::from_millis(250)];
+/// let results = join::timeout_all(futures, |index| budgets[index]);
+///
+/// assert_eq!(results.collect::<Vec<_>>().await, [(1, Some(50)), (0, None)]);
+/// #
+/// # })
+/// ```
+pub fn timeout_all<I, F>(futures: I, mut budget: F) -> TimeoutAll<I::Item>
+where
+    I: IntoIterator,
+    I::Item: Future,
+    F: FnMut(usize) -> Duration,
+{
+    let queue = ReadyQueue::new();
+    let futures = futures
+        .into_iter()
+        .enumerate()
+        .map(|(index, future)| {
+            let ready = queue.entry(index);
+            Some(Entry {
+                future: Box::pin(future.timeout(budget(index))),
+                waker: Waker::from(ready.clone()),
+                ready,
+            })
+        })
+        .collect::<Vec<_>>();
+
+    TimeoutAll {
+        pending: futures.len(),
+        futures,
+        queue,
+    }
+}
+
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                              impl Stream for TimeoutAll<Fut>                               │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+impl<Fut: Future> Stream for TimeoutAll<Fut> {
+    type Item = (usize, Option<Fut::Output>);
+
+    fn poll_next(self: Pin<&mut Self>, ctx: &mut Context) -> Poll<Option<Self::Item>> {
+        let this = self.get_mut();
+
+        if this.pending == 0 {
+            return Poll::Ready(None);
+        }
+
+        this.queue.register(ctx.waker());
+
+        // Polling at most as many futures as are pending before yielding, so that futures
+        // waking themselves up can't starve the task.
+        for _ in 0..this.pending {
+            let index = match this.queue.pop() {
+                Some(index) => index,
+                None => return Poll::Pending,
+            };
+
+            // The future may have completed since it was woken up.
+            let entry = match &mut this.futures[index] {
+                Some(entry) => entry,
+                None => continue,
+            };
+
+            entry.ready.dequeued();
+            let poll = entry
+                .future
+                .as_mut()
+                .poll(&mut Context::from_waker(&entry.waker));
+
+            if let Poll::Ready(output) = poll {
+                this.futures[index] = None;
+                this.pending -= 1;
+                return Poll::Ready(Some((index, output)));
+            }
+        }
+
+        ctx.waker().wake_by_ref();
+        Poll::Pending
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.pending, Some(self.pending))
+    }
+}
+
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                                      struct Winner<T>                                      │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+/// The first future to complete in a [`RaceWithDeadline`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct Winner<T> {
+    /// The index of the future that completed first.
+    pub index: usize,
+    /// The output of the future that completed first.
+    pub output: T,
+    /// The duration between the creation of the race and the completion of the future.
+    pub elapsed: Duration,
+}
+
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                                struct RaceWithDeadline<Fut>                                │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+/// A future polling a collection of futures concurrently and a single [`Timer`] that will
+/// complete after a specified timeout, and returning the first future to complete as a
+/// [`Winner`], or [`Elapsed`] if the timer completes first.
+///
+/// Created by [`race_with_deadline`].
+#[must_use = "futures do nothing unless you `.await` or poll them"]
+pub struct RaceWithDeadline<Fut: Future> {
+    futures: Vec<Pin<Box<Fut>>>,
+    start: Stamp,
+    timer: Timer,
+}
+
+// The futures are boxed.
+impl<Fut: Future> Unpin for RaceWithDeadline<Fut> {}
+
+impl<Fut: Future> fmt::Debug for RaceWithDeadline<Fut> {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        fmt.debug_struct("RaceWithDeadline")
+            .field("futures", &self.futures.len())
+            .field("start", &self.start)
+            .field("timer", &self.timer)
+            .finish()
+    }
+}
+
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                                  fn race_with_deadline()                                   │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+/// Given a collection of futures and a [`Duration`], creates and returns a new
+/// [`RaceWithDeadline`] that will drive all the futures concurrently until one of them completes
+/// or the provided duration elapses.
+///
+/// All the other futures are dropped as soon as one completes or the duration elapses, and a
+/// race without any future always returns [`Elapsed`] once the duration elapses.
+///
+/// ## Example
+///
+/// ```rust
+/// use async_io::Timer;
+/// # use futures_lite::future;
+/// use smol_timeout::join;
+/// use smol_timeout::Elapsed;
+/// use std::time::Duration;
+///
+/// # future::block_on(async {
+/// #
+/// let mirror = |millis| async move {
+///     Timer::after(Duration::from_millis(millis)).await;
+///     millis
+/// };
+///
+/// let winner = join::race_with_deadline([mirror(250), mirror(50)], Duration::from_millis(100));
+/// let winner = winner.await.unwrap();
+/// assert_eq!((winner.index, winner.output), (1, 50));
+/// assert!(winner.elapsed >= Duration::from_millis(50));
+///
+/// let winner = join::race_with_deadline([mirror(250), mirror(150)], Duration::from_millis(100));
+/// assert_eq!(winner.await, Err(Elapsed::new()));
+/// #
+/// # })
+/// ```
+pub fn race_with_deadline<I>(futures: I, after: Duration) -> RaceWithDeadline<I::Item>
+where
+    I: IntoIterator,
+    I::Item: Future,
+{
+    RaceWithDeadline {
+        futures: futures.into_iter().map(Box::pin).collect(),
+        start: Stamp::now(),
+        timer: Timer::after(after),
+    }
+}
+
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                           impl Future for RaceWithDeadline<Fut>                            │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+impl<Fut: Future> Future for RaceWithDeadline<Fut> {
+    type Output = Result<Winner<Fut::Output>, Elapsed>;
+
+    fn poll(self: Pin<&mut Self>, ctx: &mut Context) -> Poll<Self::Output> {
+        let this = self.get_mut();
+
+        let mut winner = None;
+        for (index, future) in this.futures.iter_mut().enumerate() {
+            if let Poll::Ready(output) = future.as_mut().poll(ctx) {
+                winner = Some(Winner {
+                    index,
+                    output,
+                    elapsed: this.start.elapsed(),
+                });
+
+                break;
+            }
+        }
+
+        if winner.is_none() && Pin::new(&mut this.timer).poll(ctx).is_pending() {
+            return Poll::Pending;
+        }
+
+        // Dropping the losers.
+        this.futures.clear();
+        Poll::Ready(winner.ok_or(Elapsed::new()))
+    }
+}
+
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                                    enum FirstOkError<E>                                    │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+/// The error returned by [`FirstOkWithin`]. Contains the errors of the futures that failed, in
+/// the order they failed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum FirstOkError<E> {
+    /// All the futures failed.
+    AllFailed(Vec<E>),
+    /// No future succeeded before the deadline.
+    Elapsed(Vec<E>),
+}
+
+impl<E> FirstOkError<E> {
+    /// Returns the errors of the futures that failed.
+    pub fn into_errors(self) -> Vec<E> {
+        match self {
+            FirstOkError::AllFailed(errors) | FirstOkError::Elapsed(errors) => errors,
+        }
+    }
+}
+
+impl<E> fmt::Display for FirstOkError<E> {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            FirstOkError::AllFailed(errors) => write!(fmt, "all {} futures failed", errors.len()),
+            FirstOkError::Elapsed(errors) => write!(
+                fmt,
+                "deadline has elapsed ({} futures failed)",
+                errors.len()
+            ),
+        }
+    }
+}
+
+impl<E: fmt::Debug> std::error::Error for FirstOkError<E> {}
+
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                                 struct FirstOkWithin<Fut>                                  │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+/// A future polling a collection of fallible futures concurrently and a single [`Timer`] that
+/// will complete after a specified timeout, and returning the output of the first future to
+/// succeed, or a [`FirstOkError`] if they all fail or the timer completes first.
+///
+/// Created by [`first_ok_within`].
+#[must_use = "futures do nothing unless you `.await` or poll them"]
+pub struct FirstOkWithin<Fut: TryFuture> {
+    futures: Vec<Option<Pin<Box<Fut>>>>,
+    errors: Vec<Fut::Error>,
+    timer: Timer,
+}
+
+// The futures are boxed and their errors are never pinned.
+impl<Fut: TryFuture> Unpin for FirstOkWithin<Fut> {}
+
+impl<Fut: TryFuture> fmt::Debug for FirstOkWithin<Fut> {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        fmt.debug_struct("FirstOkWithin")
+            .field("failed", &self.errors.len())
+            .field("timer", &self.timer)
+            .finish()
+    }
+}
+
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                                    fn first_ok_within()                                    │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+/// Given a collection of fallible futures and a [`Duration`], creates and returns a new
+/// [`FirstOkWithin`] that will drive all the futures concurrently until one of them succeeds,
+/// they all fail, or the provided duration elapses.
+///
+/// All the other futures are dropped as soon as one succeeds or the duration elapses, and an
+/// empty collection immediately fails with [`FirstOkError::AllFailed`].
+///
+/// ## Example
+///
+/// ```rust
+/// use async_io::Timer;
+/// # use futures_lite::future;
+/// use smol_timeout::join::{self, FirstOkError};
+/// use std::time::Duration;
+///
+/// # future::block_on(async {
+/// #
+/// let mirror = |millis, ok| async move {
+///     Timer::after(Duration::from_millis(millis)).await;
+///     if ok {
+///         Ok(millis)
+///     } else {
+///         Err(millis)
+///     }
+/// };
+///
+/// let mirrors = vec![mirror(50, false), mirror(100, true), mirror(150, true)];
+/// let fetched = join::first_ok_within(mirrors, Duration::from_millis(250));
+/// assert_eq!(fetched.await, Ok(100));
+///
+/// let mirrors = vec![mirror(50, false), mirror(250, true)];
+/// let fetched = join::first_ok_within(mirrors, Duration::from_millis(100));
+/// assert_eq!(fetched.await, Err(FirstOkError::Elapsed(vec![50])));
+/// #
+/// # })
+/// ```
+pub fn first_ok_within<I>(futures: I, after: Duration) -> FirstOkWithin<I::Item>
+where
+    I: IntoIterator,
+    I::Item: TryFuture,
+{
+    FirstOkWithin {
+        futures: futures
+            .into_iter()
+            .map(|future| Some(Box::pin(future)))
+            .collect(),
+        errors: Vec::new(),
+        timer: Timer::after(after),
+    }
+}
+
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                             impl Future for FirstOkWithin<Fut>                             │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+impl<Fut: TryFuture> Future for FirstOkWithin<Fut> {
+    type Output = Result<Fut::Ok, FirstOkError<Fut::Error>>;
+
+    fn poll(self: Pin<&mut Self>, ctx: &mut Context) -> Poll<Self::Output> {
+        let this = self.get_mut();
+
+        let mut succeeded = None;
+        for future in this.futures.iter_mut() {
+            if let Some(fut) = future {
+                if let Poll::Ready(out) = fut.as_mut().try_poll(ctx) {
+                    *future = None;
+
+                    match out {
+                        Ok(out) => {
+                            succeeded = Some(out);
+                            break;
+                        }
+                        Err(err) => this.errors.push(err),
+                    }
+                }
+            }
+        }
+
+        if let Some(out) = succeeded {
+            this.futures.clear();
+            return Poll::Ready(Ok(out));
+        }
+
+        if this.errors.len() == this.futures.len() {
+            let errors = this.errors.drain(..).collect();
+            this.futures.clear();
+            return Poll::Ready(Err(FirstOkError::AllFailed(errors)));
+        }
+
+        if Pin::new(&mut this.timer).poll(ctx).is_pending() {
+            return Poll::Pending;
+        }
+
+        this.futures.clear();
+        Poll::Ready(Err(FirstOkError::Elapsed(this.errors.drain(..).collect())))
+    }
+}
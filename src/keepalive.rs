@@ -0,0 +1,548 @@
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                                                                                            │ *
+ * │ This Source Code Form is subject to the terms of the Mozilla Public                        │ *
+ * │ License, v. 2.0. If a copy of the MPL was not distributed with this                        │ *
+ * │ file, You can obtain one at http://mozilla.org/MPL/2.0/.                                   │ *
+ * │                                                                                            │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                                       Documentation                                        │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+//! Keepalives sending pings periodically and failing if they aren't answered in time.
+//!
+//! A [`Keepalive`] calls its closure to send a ping every interval, then waits for the paired
+//! [`Pong`] handle to be signalled (e.g. by the task reading the connection, when it receives
+//! the peer's pong) within a response deadline. It only completes when the keepalive fails,
+//! either because a pong wasn't received in time or because sending a ping failed, so that it
+//! can be raced against the rest of a connection's work.
+//!
+//! With the `futures-sink` feature, [`monitor`] manages the whole keepalive of a message
+//! transport (e.g. a WebSocket) which is both a `Stream` and a `Sink`, without depending on any
+//! particular implementation.
+//!
+//! ## Example
+//!
+//! ```rust
+//! # use futures_lite::future;
+//! use smol_timeout::keepalive::{self, KeepaliveError};
+//! use std::time::Duration;
+//!
+//! # future::block_on(async {
+//! #
+//! let mut pings = 0;
+//! let (keepalive, _pong) = keepalive::keepalive(
+//!     Duration::from_millis(50),
+//!     Duration::from_millis(100),
+//!     |pong| {
+//!         pings += 1;
+//!         // The peer answers the first two pings only.
+//!         if pings <= 2 {
+//!             pong.received();
+//!         }
+//!
+//!         async { Ok::<_, std::io::Error>(()) }
+//!     },
+//! );
+//!
+//! assert!(matches!(keepalive.await, KeepaliveError::Timeout));
+//! assert_eq!(pings, 3);
+//! #
+//! # });
+//! ```
+
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                                          Imports                                           │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+use crate::{Elapsed, Timer};
+use core::fmt;
+use core::future::Future;
+use core::pin::Pin;
+use core::task::{Context, Poll, Waker};
+use core::time::Duration;
+use pin_project_lite::pin_project;
+use std::sync::{Arc, Mutex};
+
+#[cfg(feature = "futures-sink")]
+use futures_core::stream::Stream;
+#[cfg(feature = "futures-sink")]
+use futures_sink::Sink;
+
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                                    enum KeepaliveError                                     │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+/// The error returned by a [`Keepalive`] once it failed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum KeepaliveError<E> {
+    /// A pong wasn't received within the response deadline.
+    Timeout,
+    /// Sending a ping failed.
+    Ping(E),
+}
+
+impl<E: fmt::Display> fmt::Display for KeepaliveError<E> {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            KeepaliveError::Timeout => fmt::Display::fmt(&Elapsed::new(), fmt),
+            KeepaliveError::Ping(err) => fmt::Display::fmt(err, fmt),
+        }
+    }
+}
+
+impl<E: fmt::Debug + fmt::Display> std::error::Error for KeepaliveError<E> {}
+
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                                        struct Pong                                         │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+#[derive(Debug, Default)]
+struct Received {
+    /// The number of pongs received so far.
+    pongs: u64,
+    waker: Option<Waker>,
+}
+
+/// A handle to signal a [`Keepalive`] that a pong was received.
+///
+/// Cloning a [`Pong`] returns a new handle to the same keepalive.
+#[derive(Debug, Clone)]
+pub struct Pong {
+    received: Arc<Mutex<Received>>,
+}
+
+impl Pong {
+    /// Signals the keepalive that a pong was received.
+    pub fn received(&self) {
+        let mut received = self.received.lock().unwrap();
+        received.pongs += 1;
+        if let Some(waker) = received.waker.take() {
+            waker.wake();
+        }
+    }
+
+    fn pongs(&self, waker: &Waker) -> u64 {
+        let mut received = self.received.lock().unwrap();
+        match &received.waker {
+            Some(registered) if registered.will_wake(waker) => (),
+            _ => received.waker = Some(waker.clone()),
+        }
+
+        received.pongs
+    }
+}
+
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                                  struct Keepalive<F, Fut>                                  │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+pin_project! {
+    /// A future sending pings periodically and returning a [`KeepaliveError`] once a pong isn't
+    /// received in time or sending a ping fails.
+    ///
+    /// Created by [`keepalive`].
+    #[must_use = "futures do nothing unless you `.await` or poll them"]
+    pub struct Keepalive<F, Fut> {
+        ping: F,
+        #[pin]
+        sending: Option<Fut>,
+        pong: Pong,
+        interval: Duration,
+        deadline: Duration,
+        timer: Timer,
+        // The number of pongs received before the last ping was sent, if it is awaiting one.
+        awaiting: Option<u64>,
+    }
+}
+
+impl<F, Fut> fmt::Debug for Keepalive<F, Fut> {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        fmt.debug_struct("Keepalive")
+            .field("interval", &self.interval)
+            .field("deadline", &self.deadline)
+            .field("sending", &self.sending.is_some())
+            .field("awaiting", &self.awaiting.is_some())
+            .finish()
+    }
+}
+
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                                       fn keepalive()                                       │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+/// Creates and returns a new [`Keepalive`] that will call `ping` to send a ping every
+/// `interval`, and fail if a pong isn't signalled with the returned [`Pong`] within `deadline`
+/// of a ping being sent.
+///
+/// The closure is passed the [`Pong`] handle, e.g. for in-process peers. The next ping is sent
+/// `interval` after the previous pong was received.
+pub fn keepalive<F, Fut, E>(
+    interval: Duration,
+    deadline: Duration,
+    ping: F,
+) -> (Keepalive<F, Fut>, Pong)
+where
+    F: FnMut(&Pong) -> Fut,
+    Fut: Future<Output = Result<(), E>>,
+{
+    let pong = Pong {
+        received: Arc::new(Mutex::new(Received::default())),
+    };
+
+    let keepalive = Keepalive {
+        ping,
+        sending: None,
+        pong: pong.clone(),
+        interval,
+        deadline,
+        timer: Timer::after(interval),
+        awaiting: None,
+    };
+
+    (keepalive, pong)
+}
+
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                             impl Future for Keepalive<F, Fut>                              │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+impl<F, Fut, E> Future for Keepalive<F, Fut>
+where
+    F: FnMut(&Pong) -> Fut,
+    Fut: Future<Output = Result<(), E>>,
+{
+    type Output = KeepaliveError<E>;
+
+    fn poll(self: Pin<&mut Self>, ctx: &mut Context) -> Poll<Self::Output> {
+        let mut this = self.project();
+
+        loop {
+            if let Some(sending) = this.sending.as_mut().as_pin_mut() {
+                match sending.poll(ctx) {
+                    Poll::Ready(Ok(())) => {
+                        this.sending.set(None);
+                        this.timer.set_after(*this.deadline);
+                    }
+                    Poll::Ready(Err(err)) => {
+                        this.sending.set(None);
+                        return Poll::Ready(KeepaliveError::Ping(err));
+                    }
+                    Poll::Pending => return Poll::Pending,
+                }
+            }
+
+            if let Some(before) = *this.awaiting {
+                if this.pong.pongs(ctx.waker()) > before {
+                    *this.awaiting = None;
+                    this.timer.set_after(*this.interval);
+                    continue;
+                }
+
+                if Pin::new(&mut *this.timer).poll(ctx).is_ready() {
+                    return Poll::Ready(KeepaliveError::Timeout);
+                }
+
+                return Poll::Pending;
+            }
+
+            if Pin::new(&mut *this.timer).poll(ctx).is_pending() {
+                return Poll::Pending;
+            }
+
+            // Pongs received while sending the ping count as answers to it.
+            *this.awaiting = Some(this.pong.pongs(ctx.waker()));
+            this.sending.set(Some((this.ping)(this.pong)));
+        }
+    }
+}
+
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                                   enum TransportEvent<M>                                   │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+/// An item yielded by a [`Monitor`].
+///
+/// Only available with the `futures-sink` feature.
+#[cfg(feature = "futures-sink")]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum TransportEvent<M> {
+    /// A message was received from the peer (including pongs).
+    Message(M),
+    /// The peer didn't answer a ping within the response deadline.
+    Unresponsive,
+}
+
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                                 struct Monitor<T, M, P, F>                                 │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+/// A message transport (e.g. a WebSocket) sending pings periodically and reporting peers which
+/// don't answer them in time.
+///
+/// Created by [`monitor`]. Only available with the `futures-sink` feature.
+#[cfg(feature = "futures-sink")]
+#[must_use = "streams do nothing unless polled"]
+pub struct Monitor<T, M, P, F> {
+    transport: T,
+    make_ping: P,
+    is_pong: F,
+    interval: Duration,
+    deadline: Duration,
+    timer: Timer,
+    /// The ping waiting for the transport to be ready, if any.
+    ping: Option<M>,
+    /// Whether the last ping was sent but isn't flushed yet.
+    flushing: bool,
+    /// Whether a pong is awaited.
+    awaiting: bool,
+}
+
+#[cfg(feature = "futures-sink")]
+impl<T, M, P, F> Monitor<T, M, P, F> {
+    /// Returns a reference to the transport.
+    pub fn get_ref(&self) -> &T {
+        &self.transport
+    }
+
+    /// Returns a mutable reference to the transport.
+    pub fn get_mut(&mut self) -> &mut T {
+        &mut self.transport
+    }
+
+    /// Consumes the [`Monitor`], returning the transport.
+    pub fn into_inner(self) -> T {
+        self.transport
+    }
+}
+
+// The messages are never pinned.
+#[cfg(feature = "futures-sink")]
+impl<T: Unpin, M, P, F> Unpin for Monitor<T, M, P, F> {}
+
+#[cfg(feature = "futures-sink")]
+impl<T, M, P, F> fmt::Debug for Monitor<T, M, P, F> {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        fmt.debug_struct("Monitor")
+            .field("interval", &self.interval)
+            .field("deadline", &self.deadline)
+            .field("awaiting", &self.awaiting)
+            .finish()
+    }
+}
+
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                                        fn monitor()                                        │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+/// Wraps a [`Stream`] and [`Sink`] message transport (e.g. from `async-tungstenite` or
+/// `soketto`) into a [`Monitor`] sending a ping built by `make_ping` every `interval`, and
+/// yielding a [`TransportEvent::Unresponsive`] if no message for which `is_pong` returns `true`
+/// is received within `deadline` of a ping being sent.
+///
+/// The monitor yields all the messages received (including pongs) as
+/// [`TransportEvent::Message`]s, and is a [`Sink`] sending messages into the transport. It
+/// keeps sending pings after a peer was reported unresponsive, leaving it up to the caller to
+/// close the transport. The next ping is sent `interval` after the previous pong was received.
+///
+/// Only available with the `futures-sink` feature.
+///
+/// [`Stream`]: futures_core::Stream
+///
+/// ## Example
+///
+/// ```rust
+/// # use futures_lite::future;
+/// use futures_lite::StreamExt;
+/// use smol_timeout::keepalive::{self, TransportEvent};
+/// use std::time::Duration;
+/// # use futures_sink::Sink;
+/// # use futures_lite::Stream;
+/// # use std::collections::VecDeque;
+/// # use std::pin::Pin;
+/// # use std::task::{Context, Poll};
+///
+/// #[derive(Debug, PartialEq)]
+/// enum Message {
+///     Ping,
+///     Pong,
+/// }
+///
+/// # // A peer answering the first ping only.
+/// # struct Peer(VecDeque<Message>, usize);
+/// #
+/// # impl Stream for Peer {
+/// #     type Item = Result<Message, ()>;
+/// #
+/// #     fn poll_next(mut self: Pin<&mut Self>, _: &mut Context) -> Poll<Option<Self::Item>> {
+/// #         match self.0.pop_front() {
+/// #             Some(msg) => Poll::Ready(Some(Ok(msg))),
+/// #             None => Poll::Pending,
+/// #         }
+/// #     }
+/// # }
+/// #
+/// # impl Sink<Message> for Peer {
+/// #     type Error = ();
+/// #
+/// #     fn poll_ready(self: Pin<&mut Self>, _: &mut Context) -> Poll<Result<(), ()>> {
+/// #         Poll::Ready(Ok(()))
+/// #     }
+/// #
+/// #     fn start_send(mut self: Pin<&mut Self>, _: Message) -> Result<(), ()> {
+/// #         self.1 += 1;
+/// #         if self.1 == 1 {
+/// #             self.0.push_back(Message::Pong);
+/// #         }
+/// #
+/// #         Ok(())
+/// #     }
+/// #
+/// #     fn poll_flush(self: Pin<&mut Self>, _: &mut Context) -> Poll<Result<(), ()>> {
+/// #         Poll::Ready(Ok(()))
+/// #     }
+/// #
+/// #     fn poll_close(self: Pin<&mut Self>, _: &mut Context) -> Poll<Result<(), ()>> {
+/// #         Poll::Ready(Ok(()))
+/// #     }
+/// # }
+/// #
+/// # future::block_on(async {
+/// #
+/// # let transport = Peer(VecDeque::new(), 0);
+/// let mut monitor = keepalive::monitor(
+///     transport,
+///     Duration::from_millis(50),
+///     Duration::from_millis(100),
+///     || Message::Ping,
+///     |msg| *msg == Message::Pong,
+/// );
+///
+/// assert_eq!(monitor.next().await, Some(Ok(TransportEvent::Message(Message::Pong))));
+/// assert_eq!(monitor.next().await, Some(Ok(TransportEvent::Unresponsive)));
+/// #
+/// # });
+/// ```
+#[cfg(feature = "futures-sink")]
+pub fn monitor<T, M, E, P, F>(
+    transport: T,
+    interval: Duration,
+    deadline: Duration,
+    make_ping: P,
+    is_pong: F,
+) -> Monitor<T, M, P, F>
+where
+    T: Stream<Item = Result<M, E>> + Sink<M, Error = E> + Unpin,
+    P: FnMut() -> M,
+    F: FnMut(&M) -> bool,
+{
+    Monitor {
+        transport,
+        make_ping,
+        is_pong,
+        interval,
+        deadline,
+        timer: Timer::after(interval),
+        ping: None,
+        flushing: false,
+        awaiting: false,
+    }
+}
+
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                         impl Stream + Sink for Monitor<T, M, P, F>                         │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+#[cfg(feature = "futures-sink")]
+impl<T, M, E, P, F> Stream for Monitor<T, M, P, F>
+where
+    T: Stream<Item = Result<M, E>> + Sink<M, Error = E> + Unpin,
+    P: FnMut() -> M,
+    F: FnMut(&M) -> bool,
+{
+    type Item = Result<TransportEvent<M>, E>;
+
+    fn poll_next(self: Pin<&mut Self>, ctx: &mut Context) -> Poll<Option<Self::Item>> {
+        let this = self.get_mut();
+
+        loop {
+            if let Some(ping) = this.ping.take() {
+                match Pin::new(&mut this.transport).poll_ready(ctx) {
+                    Poll::Ready(Ok(())) => {
+                        if let Err(err) = Pin::new(&mut this.transport).start_send(ping) {
+                            return Poll::Ready(Some(Err(err)));
+                        }
+
+                        this.flushing = true;
+                    }
+                    Poll::Ready(Err(err)) => return Poll::Ready(Some(Err(err))),
+                    Poll::Pending => this.ping = Some(ping),
+                }
+            }
+
+            if this.flushing {
+                match Pin::new(&mut this.transport).poll_flush(ctx) {
+                    Poll::Ready(Ok(())) => this.flushing = false,
+                    Poll::Ready(Err(err)) => return Poll::Ready(Some(Err(err))),
+                    Poll::Pending => (),
+                }
+            }
+
+            match Pin::new(&mut this.transport).poll_next(ctx) {
+                Poll::Ready(Some(Ok(msg))) => {
+                    if this.awaiting && (this.is_pong)(&msg) {
+                        this.awaiting = false;
+                        this.timer.set_after(this.interval);
+                    }
+
+                    return Poll::Ready(Some(Ok(TransportEvent::Message(msg))));
+                }
+                Poll::Ready(Some(Err(err))) => return Poll::Ready(Some(Err(err))),
+                Poll::Ready(None) => return Poll::Ready(None),
+                Poll::Pending => (),
+            }
+
+            if Pin::new(&mut this.timer).poll(ctx).is_pending() {
+                return Poll::Pending;
+            }
+
+            if this.awaiting {
+                this.awaiting = false;
+                this.ping = None;
+                this.timer.set_after(this.interval);
+
+                return Poll::Ready(Some(Ok(TransportEvent::Unresponsive)));
+            }
+
+            this.ping = Some((this.make_ping)());
+            this.awaiting = true;
+            this.timer.set_after(this.deadline);
+        }
+    }
+}
+
+#[cfg(feature = "futures-sink")]
+impl<T, M, P, F> Sink<M> for Monitor<T, M, P, F>
+where
+    T: Sink<M> + Unpin,
+{
+    type Error = T::Error;
+
+    fn poll_ready(self: Pin<&mut Self>, ctx: &mut Context) -> Poll<Result<(), T::Error>> {
+        Pin::new(&mut self.get_mut().transport).poll_ready(ctx)
+    }
+
+    fn start_send(self: Pin<&mut Self>, msg: M) -> Result<(), T::Error> {
+        Pin::new(&mut self.get_mut().transport).start_send(msg)
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, ctx: &mut Context) -> Poll<Result<(), T::Error>> {
+        Pin::new(&mut self.get_mut().transport).poll_flush(ctx)
+    }
+
+    fn poll_close(self: Pin<&mut Self>, ctx: &mut Context) -> Poll<Result<(), T::Error>> {
+        Pin::new(&mut self.get_mut().transport).poll_close(ctx)
+    }
+}
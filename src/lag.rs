@@ -0,0 +1,220 @@
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                                                                                            │ *
+ * │ This Source Code Form is subject to the terms of the Mozilla Public                        │ *
+ * │ License, v. 2.0. If a copy of the MPL was not distributed with this                        │ *
+ * │ file, You can obtain one at http://mozilla.org/MPL/2.0/.                                   │ *
+ * │                                                                                            │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                                       Documentation                                        │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+//! Timeouts measuring how late their timer fired, to detect an overloaded reactor or a starved
+//! executor.
+//!
+//! The lag of a timeout is the duration between its deadline and the instant its task observed
+//! the timer firing, which includes both the delay of the reactor and the time the task spent
+//! waiting to be polled. A [`LagTimeout`] returns it as part of its [`Lagged`] error, and
+//! reports it to the hook set with [`set_hook`], if any, e.g. to feed a histogram.
+//!
+//! ## Example
+//!
+//! ```rust
+//! # use futures_lite::future;
+//! use smol_timeout::lag::{self, LagTimeoutExt};
+//! use std::sync::atomic::{AtomicUsize, Ordering};
+//! use std::time::Duration;
+//!
+//! static FIRED: AtomicUsize = AtomicUsize::new(0);
+//!
+//! lag::set_hook(|_lag: Duration| {
+//!     FIRED.fetch_add(1, Ordering::Relaxed);
+//! });
+//!
+//! # future::block_on(async {
+//! #
+//! let foo = future::pending::<()>().timeout_with_lag(Duration::from_millis(100));
+//! let lagged = foo.await.unwrap_err();
+//! assert!(lagged.lag() < Duration::from_secs(1));
+//! assert_eq!(FIRED.load(Ordering::Relaxed), 1);
+//!
+//! let bar = future::ready(42).timeout_with_lag(Duration::from_millis(100));
+//! assert_eq!(bar.await, Ok(42));
+//! #
+//! # });
+//! ```
+
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                                          Imports                                           │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+use crate::{clock, deadline, Elapsed, Timer};
+use core::fmt;
+use core::future::Future;
+use core::pin::Pin;
+use core::task::{Context, Poll};
+use core::time::Duration;
+use pin_project_lite::pin_project;
+use std::boxed::Box;
+use std::sync::RwLock;
+use std::time::Instant;
+
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                                       fn set_hook()                                        │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+type Hook = Box<dyn Fn(Duration) + Send + Sync>;
+
+static HOOK: RwLock<Option<Hook>> = RwLock::new(None);
+
+/// Sets the hook called with the lag of every [`LagTimeout`] that fires, replacing the
+/// previous one (if any).
+///
+/// The hook is called from the tasks polling the timeouts, so it should be cheap.
+pub fn set_hook<H>(hook: H)
+where
+    H: Fn(Duration) + Send + Sync + 'static,
+{
+    *HOOK.write().unwrap() = Some(Box::new(hook));
+}
+
+/// Removes the hook set with [`set_hook`], if any.
+pub fn clear_hook() {
+    *HOOK.write().unwrap() = None;
+}
+
+fn report(lag: Duration) {
+    if let Some(hook) = &*HOOK.read().unwrap() {
+        hook(lag);
+    }
+}
+
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                                       struct Lagged                                        │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+/// The error returned by a [`LagTimeout`] whose timer completed first, recording how late it
+/// fired.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Lagged {
+    deadline: Instant,
+    lag: Duration,
+}
+
+impl Lagged {
+    /// Returns the deadline of the timeout.
+    pub fn deadline(&self) -> Instant {
+        self.deadline
+    }
+
+    /// Returns the duration between the deadline of the timeout and the instant its timer was
+    /// observed firing.
+    pub fn lag(&self) -> Duration {
+        self.lag
+    }
+}
+
+impl From<Lagged> for Elapsed {
+    fn from(_: Lagged) -> Self {
+        Elapsed::new()
+    }
+}
+
+impl fmt::Display for Lagged {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            fmt,
+            "deadline has elapsed (timer fired {:?} late)",
+            self.lag
+        )
+    }
+}
+
+impl std::error::Error for Lagged {}
+
+#[cfg(feature = "defmt")]
+impl defmt::Format for Lagged {
+    fn format(&self, fmt: defmt::Formatter) {
+        defmt::write!(fmt, "deadline has elapsed (timer fired {} late)", self.lag);
+    }
+}
+
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                                   struct LagTimeout<Fut>                                   │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+pin_project! {
+    /// A future polling both another future and a [`Timer`] that will complete at a specified
+    /// deadline, and returning the future's output or a [`Lagged`] error if the timer completes
+    /// first.
+    #[derive(Debug)]
+    #[must_use = "futures do nothing unless you `.await` or poll them"]
+    pub struct LagTimeout<Fut> {
+        #[pin]
+        future: Fut,
+        deadline: Instant,
+        timer: Timer,
+    }
+}
+
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                                trait LagTimeoutExt: Future                                 │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+/// An extension trait for [`Future`]s that provides a way to create [`LagTimeout`]s.
+pub trait LagTimeoutExt: Future {
+    /// Given a [`Duration`], creates and returns a new [`LagTimeout`] that will poll both the
+    /// future and a [`Timer`] that will complete after the provided duration, and return the
+    /// future's output or a [`Lagged`] error if the timer completes first.
+    fn timeout_with_lag(self, after: Duration) -> LagTimeout<Self>
+    where
+        Self: Sized,
+    {
+        self.timeout_at_with_lag(deadline::deadline_after(clock::now(), after))
+    }
+
+    /// Given an [`Instant`], creates and returns a new [`LagTimeout`] that will poll both the
+    /// future and a [`Timer`] that will complete at the provided deadline, and return the
+    /// future's output or a [`Lagged`] error if the timer completes first.
+    fn timeout_at_with_lag(self, deadline: Instant) -> LagTimeout<Self>
+    where
+        Self: Sized,
+    {
+        LagTimeout {
+            future: self,
+            deadline,
+            timer: Timer::at(deadline),
+        }
+    }
+}
+
+impl<Fut: Future> LagTimeoutExt for Fut {}
+
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                              impl Future for LagTimeout<Fut>                               │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+impl<Fut: Future> Future for LagTimeout<Fut> {
+    type Output = Result<Fut::Output, Lagged>;
+
+    fn poll(self: Pin<&mut Self>, ctx: &mut Context) -> Poll<Self::Output> {
+        let this = self.project();
+
+        if let Poll::Ready(output) = this.future.poll(ctx) {
+            return Poll::Ready(Ok(output));
+        }
+
+        if Pin::new(this.timer).poll(ctx).is_ready() {
+            let lag = deadline::exceeded_by(*this.deadline);
+            report(lag);
+
+            return Poll::Ready(Err(Lagged {
+                deadline: *this.deadline,
+                lag,
+            }));
+        }
+
+        Poll::Pending
+    }
+}
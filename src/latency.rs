@@ -0,0 +1,383 @@
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                                                                                            │ *
+ * │ This Source Code Form is subject to the terms of the Mozilla Public                        │ *
+ * │ License, v. 2.0. If a copy of the MPL was not distributed with this                        │ *
+ * │ file, You can obtain one at http://mozilla.org/MPL/2.0/.                                   │ *
+ * │                                                                                            │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                                       Documentation                                        │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+//! A recorder of completion times, keeping a histogram per name.
+//!
+//! A [`LatencyRecorder`] records durations into log-linear histograms (in the style of HDR
+//! histograms): every power of two is divided into 32 buckets, so that percentiles are
+//! accurate to about 3% whatever their magnitude. It can be fed directly, or by the futures
+//! returned by [`LatencyRecorder::timed`] and [`LatencyRecorder::timeout`], and queried with
+//! [`LatencyRecorder::snapshot`].
+//!
+//! ## Example
+//!
+//! ```rust
+//! use async_io::Timer;
+//! # use futures_lite::future;
+//! use smol_timeout::latency::LatencyRecorder;
+//! use std::time::Duration;
+//!
+//! # future::block_on(async {
+//! #
+//! let recorder = LatencyRecorder::new();
+//!
+//! for millis in 1..=100 {
+//!     recorder.record("db.get_user", Duration::from_millis(millis));
+//! }
+//!
+//! let foo = async {
+//!     Timer::after(Duration::from_millis(250)).await;
+//!     24
+//! };
+//!
+//! let foo = recorder.timeout("db.get_user", foo, Duration::from_millis(100));
+//! assert_eq!(foo.await, None);
+//!
+//! let snapshot = recorder.snapshot("db.get_user").unwrap();
+//! assert_eq!(snapshot.count(), 100);
+//! assert_eq!(snapshot.expired(), 1);
+//!
+//! let p50 = snapshot.percentile(50.);
+//! assert!(p50 >= Duration::from_millis(49) && p50 <= Duration::from_millis(52));
+//! assert_eq!(snapshot.max(), Duration::from_millis(100));
+//! #
+//! # });
+//! ```
+
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                                          Imports                                           │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+use crate::timed::{Timed, TimedExt};
+use crate::{Timeout, TimeoutExt};
+use core::convert::TryFrom;
+use core::fmt;
+use core::future::Future;
+use core::pin::Pin;
+use core::task::{Context, Poll};
+use core::time::Duration;
+use pin_project_lite::pin_project;
+use std::collections::BTreeMap;
+use std::sync::{Arc, Mutex};
+use std::vec;
+use std::vec::Vec;
+
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                                      struct Histogram                                      │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+/// The number of bits of the buckets of every power of two.
+const SUB_BITS: u32 = 5;
+const SUB: u64 = 1 << SUB_BITS;
+/// The number of buckets needed to hold any `u64`.
+const BUCKETS: usize = ((64 - SUB_BITS + 1) as usize) << SUB_BITS;
+
+/// Returns the index of the bucket holding the provided value.
+fn index(value: u64) -> usize {
+    if value < SUB {
+        return value as usize;
+    }
+
+    let exp = 63 - value.leading_zeros();
+    let shift = exp - SUB_BITS;
+    let sub = (value >> shift) - SUB;
+
+    (((shift + 1) as u64) << SUB_BITS | sub) as usize
+}
+
+/// Returns the highest value held by the bucket with the provided index.
+fn highest(index: usize) -> u64 {
+    let index = index as u64;
+    if index < SUB {
+        return index;
+    }
+
+    let shift = (index >> SUB_BITS) - 1;
+    let sub = index & (SUB - 1);
+
+    ((SUB + sub) << shift) + ((1 << shift) - 1)
+}
+
+#[derive(Clone)]
+struct Histogram {
+    counts: Vec<u64>,
+    count: u64,
+    expired: u64,
+    min: u64,
+    max: u64,
+    sum: u128,
+}
+
+impl Histogram {
+    fn new() -> Self {
+        Histogram {
+            counts: vec![0; BUCKETS],
+            count: 0,
+            expired: 0,
+            min: u64::MAX,
+            max: 0,
+            sum: 0,
+        }
+    }
+
+    fn record(&mut self, duration: Duration) {
+        let nanos = u64::try_from(duration.as_nanos()).unwrap_or(u64::MAX);
+
+        self.counts[index(nanos)] += 1;
+        self.count += 1;
+        self.min = self.min.min(nanos);
+        self.max = self.max.max(nanos);
+        self.sum += u128::from(nanos);
+    }
+}
+
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                                   struct LatencyRecorder                                   │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+/// A recorder of completion times, keeping a histogram per name.
+///
+/// Cloning a [`LatencyRecorder`] returns a new handle to the same histograms.
+#[derive(Clone, Default)]
+pub struct LatencyRecorder {
+    histograms: Arc<Mutex<BTreeMap<&'static str, Histogram>>>,
+}
+
+impl LatencyRecorder {
+    /// Creates a new [`LatencyRecorder`] without any histogram.
+    pub fn new() -> Self {
+        LatencyRecorder::default()
+    }
+
+    /// Records a completion time under the provided name.
+    pub fn record(&self, name: &'static str, duration: Duration) {
+        self.histograms
+            .lock()
+            .unwrap()
+            .entry(name)
+            .or_insert_with(Histogram::new)
+            .record(duration);
+    }
+
+    /// Records an expiry (which has no completion time) under the provided name.
+    pub fn record_expired(&self, name: &'static str) {
+        self.histograms
+            .lock()
+            .unwrap()
+            .entry(name)
+            .or_insert_with(Histogram::new)
+            .expired += 1;
+    }
+
+    /// Returns a snapshot of the histogram with the provided name, if anything was recorded
+    /// under it.
+    pub fn snapshot(&self, name: &str) -> Option<LatencySnapshot> {
+        let histogram = self.histograms.lock().unwrap().get(name)?.clone();
+        Some(LatencySnapshot { histogram })
+    }
+
+    /// Returns the names under which something was recorded.
+    pub fn names(&self) -> Vec<&'static str> {
+        self.histograms.lock().unwrap().keys().copied().collect()
+    }
+
+    /// Given a name and a future, creates and returns a new [`Recorded`] future that will poll
+    /// the future and record its completion time under the provided name.
+    pub fn timed<Fut: Future>(&self, name: &'static str, future: Fut) -> Recorded<Fut> {
+        Recorded {
+            future: future.timed(),
+            recorder: self.clone(),
+            name,
+        }
+    }
+
+    /// Given a name, a future and a [`Duration`], creates and returns a new [`RecordedTimeout`]
+    /// that will poll both the future and a timer that will complete after the provided
+    /// duration, return the future's output or [`None`] if the timer completes first, and
+    /// record either the future's completion time or the expiry under the provided name.
+    pub fn timeout<Fut: Future>(
+        &self,
+        name: &'static str,
+        future: Fut,
+        after: Duration,
+    ) -> RecordedTimeout<Fut> {
+        RecordedTimeout {
+            future: future.timed().timeout(after),
+            recorder: self.clone(),
+            name,
+        }
+    }
+}
+
+impl fmt::Debug for LatencyRecorder {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        fmt.debug_struct("LatencyRecorder")
+            .field("names", &self.names())
+            .finish()
+    }
+}
+
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                                   struct LatencySnapshot                                   │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+/// A snapshot of a histogram of a [`LatencyRecorder`].
+#[derive(Clone)]
+pub struct LatencySnapshot {
+    histogram: Histogram,
+}
+
+impl LatencySnapshot {
+    /// Returns the number of completion times recorded.
+    pub fn count(&self) -> u64 {
+        self.histogram.count
+    }
+
+    /// Returns the number of expiries recorded.
+    pub fn expired(&self) -> u64 {
+        self.histogram.expired
+    }
+
+    /// Returns the lowest completion time recorded, or zero if none was.
+    pub fn min(&self) -> Duration {
+        if self.histogram.count == 0 {
+            return Duration::from_secs(0);
+        }
+
+        Duration::from_nanos(self.histogram.min)
+    }
+
+    /// Returns the highest completion time recorded, or zero if none was.
+    pub fn max(&self) -> Duration {
+        Duration::from_nanos(self.histogram.max)
+    }
+
+    /// Returns the mean of the completion times recorded, or zero if none was.
+    pub fn mean(&self) -> Duration {
+        if self.histogram.count == 0 {
+            return Duration::from_secs(0);
+        }
+
+        let mean = self.histogram.sum / u128::from(self.histogram.count);
+        Duration::from_nanos(mean as u64)
+    }
+
+    /// Returns the completion time below which the provided percentage (between `0.` and
+    /// `100.`) of the completion times recorded fall, or zero if none was.
+    pub fn percentile(&self, percentile: f64) -> Duration {
+        let histogram = &self.histogram;
+        if histogram.count == 0 {
+            return Duration::from_secs(0);
+        }
+
+        let rank = ((percentile.clamp(0., 100.) / 100.) * histogram.count as f64).ceil() as u64;
+        let rank = rank.max(1);
+
+        let mut seen = 0;
+        for (index, count) in histogram.counts.iter().enumerate() {
+            seen += count;
+            if seen >= rank {
+                let value = highest(index).clamp(histogram.min, histogram.max);
+                return Duration::from_nanos(value);
+            }
+        }
+
+        Duration::from_nanos(histogram.max)
+    }
+}
+
+impl fmt::Debug for LatencySnapshot {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        fmt.debug_struct("LatencySnapshot")
+            .field("count", &self.count())
+            .field("expired", &self.expired())
+            .field("min", &self.min())
+            .field("max", &self.max())
+            .field("mean", &self.mean())
+            .finish()
+    }
+}
+
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                                    struct Recorded<Fut>                                    │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+pin_project! {
+    /// A future polling another future and recording its completion time in a
+    /// [`LatencyRecorder`].
+    ///
+    /// Created by [`LatencyRecorder::timed`].
+    #[derive(Debug)]
+    #[must_use = "futures do nothing unless you `.await` or poll them"]
+    pub struct Recorded<Fut> {
+        #[pin]
+        future: Timed<Fut>,
+        recorder: LatencyRecorder,
+        name: &'static str,
+    }
+}
+
+impl<Fut: Future> Future for Recorded<Fut> {
+    type Output = Fut::Output;
+
+    fn poll(self: Pin<&mut Self>, ctx: &mut Context) -> Poll<Self::Output> {
+        let this = self.project();
+
+        let (output, elapsed) = match this.future.poll(ctx) {
+            Poll::Ready(ready) => ready,
+            Poll::Pending => return Poll::Pending,
+        };
+
+        this.recorder.record(this.name, elapsed);
+        Poll::Ready(output)
+    }
+}
+
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                                struct RecordedTimeout<Fut>                                 │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+pin_project! {
+    /// A future polling both another future and a timer, returning the future's output or
+    /// [`None`] if the timer completes first, and recording the outcome in a
+    /// [`LatencyRecorder`].
+    ///
+    /// Created by [`LatencyRecorder::timeout`].
+    #[derive(Debug)]
+    #[must_use = "futures do nothing unless you `.await` or poll them"]
+    pub struct RecordedTimeout<Fut: Future> {
+        #[pin]
+        future: Timeout<Timed<Fut>>,
+        recorder: LatencyRecorder,
+        name: &'static str,
+    }
+}
+
+impl<Fut: Future> Future for RecordedTimeout<Fut> {
+    type Output = Option<Fut::Output>;
+
+    fn poll(self: Pin<&mut Self>, ctx: &mut Context) -> Poll<Self::Output> {
+        let this = self.project();
+
+        match this.future.poll(ctx) {
+            Poll::Ready(Some((output, elapsed))) => {
+                this.recorder.record(this.name, elapsed);
+                Poll::Ready(Some(output))
+            }
+            Poll::Ready(None) => {
+                this.recorder.record_expired(this.name);
+                Poll::Ready(None)
+            }
+            Poll::Pending => Poll::Pending,
+        }
+    }
+}
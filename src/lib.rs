@@ -47,25 +47,323 @@
 //! # });
 //! ```
 
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                                          Modules                                           │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+pub mod active;
+
+#[cfg(feature = "event-listener")]
+pub mod barrier;
+
+#[cfg(feature = "boottime")]
+pub mod boottime;
+
+#[cfg(feature = "budget")]
+pub mod budget;
+
+#[cfg(feature = "futures-io")]
+pub mod buf_read;
+
+#[cfg(feature = "async-channel")]
+pub mod channel;
+
+pub mod clock;
+
+pub mod coalesce;
+
+#[cfg(feature = "async-lock")]
+pub mod condvar;
+
+#[cfg(all(feature = "cputime", unix))]
+pub mod cputime;
+
+pub mod deadline;
+
+#[cfg(feature = "blocking")]
+pub mod dns;
+
+pub mod duration;
+
+#[cfg(feature = "event-listener")]
+pub mod event;
+
+#[cfg(feature = "async-executor")]
+pub mod executor;
+
+#[cfg(feature = "async-channel")]
+pub mod expiring;
+
+pub mod fair;
+
+pub mod fixed;
+
+#[cfg(feature = "futures-io")]
+pub mod framed;
+
+pub mod fresh;
+
+#[cfg(feature = "futures-channel")]
+pub mod futures_channel;
+
+#[cfg(feature = "futures-concurrency")]
+pub mod futures_concurrency;
+
+pub mod intrusive;
+
+pub mod jitter;
+
+pub mod job;
+
+pub mod join;
+
+pub mod keepalive;
+
+pub mod lag;
+
+pub mod latency;
+
+pub mod local;
+
+#[cfg(feature = "chrono-tz")]
+pub mod local_time;
+
+#[cfg(feature = "async-lock")]
+pub mod lock;
+
+#[cfg(feature = "log")]
+pub mod log;
+
+mod macros;
+
+pub mod map;
+
+#[cfg(feature = "metrics")]
+pub mod metrics;
+
+pub mod named;
+
+pub mod once_cell;
+
+pub mod overdue;
+
+pub mod panic;
+
+pub mod pending;
+
+pub mod phase;
+
+pub mod pipeline;
+
+pub mod poll_fn;
+
+pub mod precise;
+
+pub mod precision;
+
+pub mod preempt;
+
+pub mod progress;
+
+#[cfg(feature = "prometheus")]
+pub mod prometheus;
+
+mod ready_queue;
+
+pub mod refresh;
+
+#[cfg(feature = "registry")]
+pub mod registry;
+
+pub mod set;
+
+#[cfg(all(feature = "sim", not(target_os = "wasi")))]
+pub mod sim;
+
+#[cfg(feature = "futures-sink")]
+pub mod sink;
+
+pub mod slim;
+
+pub mod slow;
+
+#[cfg(feature = "futures-io")]
+pub mod socket;
+
+pub mod stats;
+
+pub mod supervisor;
+
+#[cfg(feature = "async-task")]
+pub mod task;
+
+#[cfg(all(feature = "test-util", not(target_os = "wasi")))]
+pub mod test_util;
+
+pub mod throttle;
+
+pub mod timed;
+
+#[cfg(feature = "futures-rustls")]
+pub mod tls;
+
+pub mod traced;
+
+#[cfg(feature = "blocking")]
+pub mod unblock;
+
+#[cfg(all(feature = "io-uring", target_os = "linux"))]
+pub mod uring;
+
+mod waker_set;
+
+pub mod wall;
+
+#[cfg(all(feature = "wasi", target_os = "wasi"))]
+pub mod wasi;
+
+pub mod watchdog;
+
+pub mod wheel;
+
 /* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
  * │                                          Imports                                           │ *
 \* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
 
-use async_io::Timer;
+extern crate std;
+
+#[cfg(all(target_os = "wasi", not(feature = "wasi")))]
+compile_error!("the `wasi` feature is required to use smol-timeout on WASI targets");
+
+#[cfg(all(feature = "sim", not(target_os = "wasi")))]
+pub(crate) use crate::sim::Timer;
+#[cfg(all(feature = "wasi", target_os = "wasi"))]
+pub(crate) use crate::wasi::{block_on, Timer};
+#[cfg(not(target_os = "wasi"))]
+pub(crate) use async_io::block_on;
+#[cfg(all(not(feature = "sim"), not(target_os = "wasi")))]
+pub(crate) use async_io::Timer;
+
+#[doc(hidden)]
+pub use crate::macros::{__block_on, __stuck};
+
+/// Wraps the body of an `async fn` (typically a test) in a
+/// [`PanicTimeout`](crate::panic::PanicTimeout), named after the function, so that it panics
+/// with diagnostics instead of hanging once the provided duration elapsed.
+///
+/// The duration is either an expression evaluating to a [`Duration`], or a string literal made
+/// of an integer and a unit (`ns`, `us`, `ms`, `s`, `m` or `h`), e.g. `"5s"`.
+///
+/// When applied above a plain `#[test]`, the function is turned into a synchronous one running
+/// its body with [`async_io::block_on`]. Otherwise, it stays `async` and can be placed
+/// above another test attribute running it (e.g. `#[smol_potat::test]` or
+/// `#[async_std::test]`).
+///
+/// ## Example
+///
+/// ```rust
+/// use async_io::Timer;
+/// # use futures_lite::future;
+/// use std::time::Duration;
+///
+/// #[smol_timeout::timeout("250ms")]
+/// async fn foo() -> u32 {
+///     Timer::after(Duration::from_millis(100)).await;
+///     42
+/// }
+///
+/// #[smol_timeout::timeout(Duration::from_millis(100))]
+/// async fn bar() {
+///     Timer::after(Duration::from_millis(250)).await;
+/// }
+///
+/// assert_eq!(future::block_on(foo()), 42);
+///
+/// let panicked = std::panic::catch_unwind(|| future::block_on(bar()));
+/// assert!(panicked.is_err());
+/// ```
+#[cfg(feature = "macros")]
+pub use smol_timeout_macros::timeout;
+
+/// Wraps the body of an `async fn` in a [`Timeout`], changing its return type from `T` to
+/// `Result<T, Elapsed>` and returning an [`Elapsed`] error once the provided duration elapsed.
+///
+/// The duration is either an expression evaluating to a [`Duration`], which is evaluated before
+/// the body starts and can thus use the function's arguments or constants, or a string literal
+/// made of an integer and a unit (`ns`, `us`, `ms`, `s`, `m` or `h`), e.g. `"5s"`.
+///
+/// ## Example
+///
+/// ```rust
+/// use async_io::Timer;
+/// # use futures_lite::future;
+/// use smol_timeout::{with_timeout, Elapsed};
+/// use std::time::Duration;
+///
+/// const GET_USER: Duration = Duration::from_millis(250);
+///
+/// #[with_timeout(GET_USER)]
+/// async fn get_user(delay: u64) -> u32 {
+///     Timer::after(Duration::from_millis(delay)).await;
+///     42
+/// }
+///
+/// #[with_timeout(Duration::from_millis(deadline))]
+/// async fn get_post(delay: u64, deadline: u64) -> u32 {
+///     Timer::after(Duration::from_millis(delay)).await;
+///     24
+/// }
+///
+/// # future::block_on(async {
+/// #
+/// assert_eq!(get_user(100).await, Ok(42));
+/// assert_eq!(get_user(500).await, Err(Elapsed::new()));
+/// assert_eq!(get_post(250, 100).await, Err(Elapsed::new()));
+/// #
+/// # });
+/// ```
+#[cfg(feature = "macros")]
+pub use smol_timeout_macros::with_timeout;
+
+use core::fmt;
 use core::future::Future;
+use core::hash::{Hash, Hasher};
+use core::panic::Location;
 use core::pin::Pin;
 use core::task::{Context, Poll};
 use core::time::Duration;
+use futures_core::future::FusedFuture;
 use pin_project_lite::pin_project;
+use std::boxed::Box;
+use std::time::Instant;
+
+#[cfg(feature = "backtrace")]
+use std::backtrace::Backtrace;
+#[cfg(feature = "backtrace")]
+use std::sync::Arc;
 
 /* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
- * │                                    struct Timeout<Fut>                                     │ *
+ * │                                   struct Timeout<Fut, D>                                   │ *
 \* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
 
 pin_project! {
     #[derive(Debug)]
-    /// A future polling both another future and a [`Timer`] that will complete after a specified
-    /// timeout, and returning the future's output or [`None`] if the timer completes first.
+    /// A future polling both another future and a [`Sleep`] that will complete after a specified
+    /// timeout, and returning the future's output or [`None`] if the sleep completes first.
+    ///
+    /// Most timeouts never fire, so the sleep is only polled, and thus registered with the
+    /// reactor, once the future returned [`Poll::Pending`]: a future completing on its first poll
+    /// never touches the reactor, even if its timeout already elapsed.
+    ///
+    /// The sleep can be replaced by any [`DeadlineSource`] (e.g. a sleep of a
+    /// [`TimerWheel`](crate::wheel::TimerWheel)), or by any future acting as a deadline (e.g. a
+    /// shutdown signal), whose output is ignored, using [`Timeout::new`] or
+    /// [`TimeoutExt::timeout_with`]. Only timeouts whose deadline is a [`DeadlineSource`] can be
+    /// shortened, restarted and cloned.
+    ///
+    /// Once it completed, a [`Timeout`] neither polls its future nor its deadline anymore, and
+    /// returns [`Poll::Pending`] if polled again (as reported by its [`FusedFuture`]
+    /// implementation), until it is [restarted](Timeout::restart).
     ///
     /// ## Example
     ///
@@ -92,17 +390,336 @@ pin_project! {
     ///
     /// let bar = bar.timeout(Duration::from_millis(250));
     /// assert_eq!(bar.await, Some(42));
+    ///
+    /// let baz = future::ready(42).timeout(Duration::from_millis(0));
+    /// assert_eq!(baz.await, Some(42));
     /// #
     /// # })
     /// ```
-    pub struct Timeout<Fut: Future> {
+    pub struct Timeout<Fut: Future, D = Sleep> {
         #[pin]
         future: Fut,
         #[pin]
-        timer: Timer,
+        timer: D,
+        armed: bool,
+        // The duration the deadline was armed for, if it was armed by the timeout.
+        after: Option<Duration>,
+        // The instant the deadline will complete at, if it was armed by the timeout.
+        at: Option<Instant>,
+        // Whether the timeout completed (and wasn't restarted since).
+        done: bool,
+    }
+}
+
+impl<Fut: Future, D: Future> Timeout<Fut, D> {
+    /// Creates and returns a new [`Timeout`] that will poll both the future and the provided
+    /// deadline, and return the future's output or [`None`] if the deadline completes first.
+    ///
+    /// This allows the deadline to be created by the caller, e.g. as a [`Sleep`]
+    /// [`at`](Sleep::at) an instant shared by several operations, or as any other future (whose
+    /// output is ignored).
+    ///
+    /// The duration of the deadline is unknown to the timeout, so that it can only be
+    /// [restarted](Timeout::restart_after) with a new one, and its clones complete at the same
+    /// deadline.
+    ///
+    /// ## Example
+    ///
+    /// ```rust
+    /// use async_io::Timer;
+    /// # use futures_lite::future;
+    /// use smol_timeout::{Sleep, Timeout};
+    /// use std::time::{Duration, Instant};
+    ///
+    /// # future::block_on(async {
+    /// #
+    /// let deadline = Instant::now() + Duration::from_millis(100);
+    ///
+    /// let foo = async {
+    ///     Timer::after(Duration::from_millis(250)).await;
+    ///     24
+    /// };
+    ///
+    /// let mut foo = Box::pin(Timeout::new(foo, Sleep::at(deadline)));
+    /// assert_eq!(foo.as_mut().await, None);
+    ///
+    /// // The duration of the deadline is unknown, so it can only be restarted with a new one.
+    /// assert_eq!(foo.as_mut().restart(), None);
+    /// foo.as_mut().restart_after(Duration::from_millis(500));
+    /// assert_eq!(foo.await, Some(24));
+    /// #
+    /// # })
+    /// ```
+    pub fn new(future: Fut, deadline: D) -> Self {
+        Timeout {
+            future,
+            timer: deadline,
+            armed: false,
+            after: None,
+            at: None,
+            done: false,
+        }
+    }
+
+    /// Returns a reference to the future.
+    pub fn get_ref(&self) -> &Fut {
+        &self.future
+    }
+
+    /// Returns a mutable reference to the future.
+    pub fn get_mut(&mut self) -> &mut Fut {
+        &mut self.future
+    }
+
+    /// Returns a pinned mutable reference to the future.
+    pub fn get_pin_mut(self: Pin<&mut Self>) -> Pin<&mut Fut> {
+        self.project().future
+    }
+
+    /// Returns a reference to the deadline.
+    pub fn deadline(&self) -> &D {
+        &self.timer
+    }
+
+    /// Consumes the [`Timeout`], returning the future.
+    pub fn into_inner(self) -> Fut {
+        self.future
+    }
+}
+
+impl<Fut: Future> Timeout<Fut> {
+    /// Creates and returns a new [`Timeout`] whose sleep will complete after the provided
+    /// duration.
+    #[track_caller]
+    pub(crate) fn after(future: Fut, after: Duration) -> Self {
+        Timeout::armed(future, Sleep::after(after), after)
+    }
+
+    /// Creates and returns a new [`Timeout`] whose sleep will complete at the provided deadline,
+    /// and whose duration is the time left before the deadline.
+    #[track_caller]
+    pub(crate) fn at(future: Fut, at: Instant) -> Self {
+        Timeout::armed(future, Sleep::at(at), deadline::remaining_until(at))
+    }
+}
+
+impl<Fut: Future, D: DeadlineSource> Timeout<Fut, D> {
+    /// Creates and returns a new [`Timeout`] whose deadline was armed for the provided duration,
+    /// and which can thus be restarted and cloned.
+    #[track_caller]
+    pub(crate) fn armed(future: Fut, deadline: D, after: Duration) -> Self {
+        let at = deadline.deadline();
+
+        #[cfg(all(feature = "log", debug_assertions))]
+        if let Some(at) = at {
+            deadline::check(at, after);
+        }
+
+        Timeout {
+            future,
+            timer: deadline,
+            armed: false,
+            after: Some(after),
+            at,
+            done: false,
+        }
+    }
+
+    /// Shortens the timeout so that its deadline completes after the provided duration, unless
+    /// it was already going to complete earlier, i.e. returns a timeout completing at the earlier
+    /// of both deadlines.
+    ///
+    /// This is meant to be used instead of wrapping a timeout in another one, which would
+    /// register two timers with the reactor and poll both of them, for a single one to matter.
+    ///
+    /// ## Example
+    ///
+    /// ```rust
+    /// use async_io::Timer;
+    /// # use futures_lite::future;
+    /// use smol_timeout::TimeoutExt;
+    /// use std::time::Duration;
+    ///
+    /// # future::block_on(async {
+    /// #
+    /// let foo = async {
+    ///     Timer::after(Duration::from_millis(250)).await;
+    ///     24
+    /// };
+    ///
+    /// let foo = foo
+    ///     .timeout(Duration::from_secs(60))
+    ///     .min_with(Duration::from_millis(100));
+    /// assert_eq!(foo.await, None);
+    ///
+    /// let bar = async {
+    ///     Timer::after(Duration::from_millis(100)).await;
+    ///     42
+    /// };
+    ///
+    /// let bar = bar
+    ///     .timeout(Duration::from_millis(250))
+    ///     .min_with(Duration::from_secs(60));
+    /// assert_eq!(bar.await, Some(42));
+    /// #
+    /// # })
+    /// ```
+    pub fn min_with(mut self, after: Duration) -> Self {
+        // A duration too long to be represented never shortens the deadline.
+        let at = match self.timer.now().checked_add(after) {
+            Some(at) => at,
+            None => return self,
+        };
+
+        if self.timer.deadline().is_none_or(|current| at < current) {
+            self.timer.set_at(at);
+            self.armed = false;
+            self.after = Some(after);
+            self.at = Some(at);
+        }
+
+        self
+    }
+
+    /// Rearms the deadline to complete after the duration the timeout was created for, so that
+    /// the timeout can be polled again once it completed, with the same future, and returns
+    /// that duration.
+    ///
+    /// This is meant for timeouts whose deadline completed first and whose future can thus still
+    /// be polled, or whose future can be polled again after completing (e.g. a mutable reference
+    /// to a stream's `next` future created anew on every poll).
+    ///
+    /// Returns [`None`] without rearming anything if the timeout was created with
+    /// [`Timeout::new`], as the duration of its deadline is unknown. Use
+    /// [`restart_after`](Timeout::restart_after) instead.
+    ///
+    /// ## Example
+    ///
+    /// ```rust
+    /// use async_io::Timer;
+    /// # use futures_lite::future;
+    /// use smol_timeout::TimeoutExt;
+    /// use std::pin::pin;
+    /// use std::time::Duration;
+    ///
+    /// # future::block_on(async {
+    /// #
+    /// let foo = async {
+    ///     Timer::after(Duration::from_millis(250)).await;
+    ///     42
+    /// };
+    ///
+    /// let mut foo = pin!(foo.timeout(Duration::from_millis(100)));
+    ///
+    /// let mut budgets = 1;
+    /// let output = loop {
+    ///     match foo.as_mut().await {
+    ///         Some(output) => break output,
+    ///         None => {
+    ///             budgets += 1;
+    ///             foo.as_mut().restart();
+    ///         }
+    ///     }
+    /// };
+    ///
+    /// assert_eq!(output, 42);
+    /// assert_eq!(budgets, 3);
+    /// #
+    /// # })
+    /// ```
+    pub fn restart(self: Pin<&mut Self>) -> Option<Duration> {
+        let after = self.after?;
+        self.restart_after(after);
+        Some(after)
+    }
+
+    /// Rearms the deadline to complete after the provided duration, so that the timeout can be
+    /// polled again once it completed, with the same future.
+    ///
+    /// The provided duration is also the one used by later calls to
+    /// [`restart`](Timeout::restart) and by clones.
+    pub fn restart_after(self: Pin<&mut Self>, after: Duration) {
+        let this = self.project();
+
+        *this.at = rearm_after(this.timer.get_mut(), after);
+        *this.armed = false;
+        *this.after = Some(after);
+        *this.done = false;
+    }
+}
+
+/// Rearms a deadline to complete after the provided duration, or to never complete if the
+/// duration is too long to be represented, and returns the instant it completes at.
+fn rearm_after<D: DeadlineSource>(timer: &mut D, after: Duration) -> Option<Instant> {
+    match timer.now().checked_add(after) {
+        Some(at) => {
+            timer.set_at(at);
+            Some(at)
+        }
+        None => {
+            timer.set_never();
+            None
+        }
+    }
+}
+
+/// Clones the future, with the clone getting a fresh deadline (from the same source) that will
+/// complete after the duration the timeout was created for, i.e. as if the clone was created
+/// now.
+///
+/// This allows a template of a request, built with its timeout, to be cloned for each attempt.
+///
+/// The clones of a timeout created with [`Timeout::new`], whose duration is unknown, complete at
+/// the same deadline as the original.
+///
+/// ## Example
+///
+/// ```rust
+/// use async_io::Timer;
+/// # use futures_lite::future;
+/// use smol_timeout::TimeoutExt;
+/// use std::time::Duration;
+///
+/// # future::block_on(async {
+/// #
+/// let template = std::future::ready(42).timeout(Duration::from_millis(100));
+///
+/// Timer::after(Duration::from_millis(250)).await;
+/// for _ in 0..3 {
+///     assert_eq!(template.clone().await, Some(42));
+/// }
+/// #
+/// # })
+/// ```
+impl<Fut: Future + Clone, D: DeadlineSource> Clone for Timeout<Fut, D> {
+    fn clone(&self) -> Self {
+        let mut timer = self.timer.fork();
+
+        match self.after {
+            Some(after) => {
+                rearm_after(&mut timer, after);
+                Timeout::armed(self.future.clone(), timer, after)
+            }
+            None => Timeout::new(self.future.clone(), timer),
+        }
     }
 }
 
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                                   type BoxTimeout<'a, T>                                   │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+/// A [`Timeout`] polling a boxed, type-erased, [`Send`] future, e.g. to store timeouts around
+/// different futures in the same collection.
+///
+/// Created by [`TimeoutExt::timeout_boxed`].
+pub type BoxTimeout<'a, T> = Timeout<Pin<Box<dyn Future<Output = T> + Send + 'a>>>;
+
+/// A [`Timeout`] polling a boxed, type-erased future which isn't necessarily [`Send`].
+///
+/// Created by [`TimeoutExt::timeout_boxed_local`].
+pub type LocalBoxTimeout<'a, T> = Timeout<Pin<Box<dyn Future<Output = T> + 'a>>>;
+
 /* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
  * │                                  trait TimeoutExt: Future                                  │ *
 \* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
@@ -141,37 +758,540 @@ pub trait TimeoutExt: Future {
     /// #
     /// # })
     /// ```
+    #[track_caller]
     fn timeout(self, after: Duration) -> Timeout<Self>
     where
         Self: Sized,
     {
-        Timeout {
-            future: self,
-            timer: Timer::after(after),
-        }
+        Timeout::after(self, after)
+    }
+
+    /// Creates and returns a new [`Timeout`] that will poll both the future and a [`Timer`] that
+    /// will complete after the provided number of seconds, and return the future's output or
+    /// [`None`] if the timer completes first.
+    ///
+    /// This is a shorthand for `timeout(Duration::from_secs(secs))`.
+    #[track_caller]
+    fn timeout_secs(self, secs: u64) -> Timeout<Self>
+    where
+        Self: Sized,
+    {
+        self.timeout(Duration::from_secs(secs))
+    }
+
+    /// Creates and returns a new [`Timeout`] that will poll both the future and a [`Timer`] that
+    /// will complete after the provided number of milliseconds, and return the future's output
+    /// or [`None`] if the timer completes first.
+    ///
+    /// This is a shorthand for `timeout(Duration::from_millis(millis))`.
+    ///
+    /// ## Example
+    ///
+    /// ```rust
+    /// use async_io::Timer;
+    /// # use futures_lite::future;
+    /// use smol_timeout::TimeoutExt;
+    /// use std::time::Duration;
+    ///
+    /// # future::block_on(async {
+    /// #
+    /// let foo = async {
+    ///     Timer::after(Duration::from_millis(250)).await;
+    ///     24
+    /// };
+    ///
+    /// assert_eq!(foo.timeout_millis(100).await, None);
+    /// assert_eq!(async { 42 }.timeout_secs(1).await, Some(42));
+    /// #
+    /// # })
+    /// ```
+    #[track_caller]
+    fn timeout_millis(self, millis: u64) -> Timeout<Self>
+    where
+        Self: Sized,
+    {
+        self.timeout(Duration::from_millis(millis))
+    }
+
+    /// Given a [`Duration`], creates and returns a new [`Timeout`] that will poll both a mutable
+    /// reference to the future and a [`Timer`] that will complete after the provided duration,
+    /// and return the future's output or [`None`] if the timer completes first.
+    ///
+    /// The future stays owned by the caller, so that it can be awaited again (e.g. under a new
+    /// deadline) if the timer completes first.
+    ///
+    /// ## Example
+    ///
+    /// ```rust
+    /// use async_io::Timer;
+    /// # use futures_lite::future;
+    /// use smol_timeout::TimeoutExt;
+    /// use std::time::Duration;
+    ///
+    /// # future::block_on(async {
+    /// #
+    /// let mut foo = Box::pin(async {
+    ///     Timer::after(Duration::from_millis(250)).await;
+    ///     42
+    /// });
+    ///
+    /// let mut timeouts = 0;
+    /// let output = loop {
+    ///     match foo.timeout_ref(Duration::from_millis(100)).await {
+    ///         Some(output) => break output,
+    ///         None => timeouts += 1,
+    ///     }
+    /// };
+    ///
+    /// assert_eq!(output, 42);
+    /// assert_eq!(timeouts, 2);
+    /// #
+    /// # })
+    /// ```
+    #[track_caller]
+    fn timeout_ref(&mut self, after: Duration) -> Timeout<&mut Self>
+    where
+        Self: Unpin,
+    {
+        Timeout::after(self, after)
+    }
+
+    /// Given a [`Duration`], creates and returns a new [`Timeout`] that will poll both the
+    /// pinned future and a [`Timer`] that will complete after the provided duration, and return
+    /// the future's output or [`None`] if the timer completes first.
+    ///
+    /// This is [`timeout_ref`](TimeoutExt::timeout_ref) for futures which aren't [`Unpin`],
+    /// e.g. ones pinned on the stack.
+    ///
+    /// ## Example
+    ///
+    /// ```rust
+    /// use async_io::Timer;
+    /// # use futures_lite::future;
+    /// use smol_timeout::TimeoutExt;
+    /// use std::pin::pin;
+    /// use std::time::Duration;
+    ///
+    /// # future::block_on(async {
+    /// #
+    /// let mut foo = pin!(async {
+    ///     Timer::after(Duration::from_millis(250)).await;
+    ///     42
+    /// });
+    ///
+    /// assert_eq!(foo.as_mut().timeout_pinned(Duration::from_millis(100)).await, None);
+    /// assert_eq!(foo.timeout_pinned(Duration::from_millis(250)).await, Some(42));
+    /// #
+    /// # })
+    /// ```
+    #[track_caller]
+    fn timeout_pinned(self: Pin<&mut Self>, after: Duration) -> Timeout<Pin<&mut Self>> {
+        Timeout::after(self, after)
+    }
+
+    /// Given a future acting as a deadline, creates and returns a new [`Timeout`] that will
+    /// poll both the future and the deadline, and return the future's output or [`None`] if the
+    /// deadline completes first.
+    ///
+    /// ## Example
+    ///
+    /// ```rust
+    /// use async_io::Timer;
+    /// # use futures_lite::future;
+    /// use smol_timeout::TimeoutExt;
+    /// use std::time::Duration;
+    ///
+    /// # future::block_on(async {
+    /// #
+    /// let shutdown = async {
+    ///     Timer::after(Duration::from_millis(100)).await;
+    ///     println!("shutting down");
+    /// };
+    ///
+    /// let foo = async {
+    ///     Timer::after(Duration::from_millis(250)).await;
+    ///     24
+    /// };
+    ///
+    /// assert_eq!(foo.timeout_with(shutdown).await, None);
+    /// #
+    /// # })
+    /// ```
+    fn timeout_with<D: Future>(self, deadline: D) -> Timeout<Self, D>
+    where
+        Self: Sized,
+    {
+        Timeout::new(self, deadline)
+    }
+
+    /// Given a [`Duration`], boxes the future and creates and returns a new [`BoxTimeout`] that
+    /// will poll both it and a [`Timer`] that will complete after the provided duration, and
+    /// return the future's output or [`None`] if the timer completes first.
+    ///
+    /// ## Example
+    ///
+    /// ```rust
+    /// use async_io::Timer;
+    /// # use futures_lite::future;
+    /// use smol_timeout::{BoxTimeout, TimeoutExt};
+    /// use std::time::Duration;
+    ///
+    /// # future::block_on(async {
+    /// #
+    /// let foo = async {
+    ///     Timer::after(Duration::from_millis(250)).await;
+    ///     24
+    /// };
+    ///
+    /// let bar = async { 42 };
+    ///
+    /// let timeouts: Vec<BoxTimeout<'_, u32>> = vec![
+    ///     foo.timeout_boxed(Duration::from_millis(100)),
+    ///     bar.timeout_boxed(Duration::from_millis(100)),
+    /// ];
+    ///
+    /// let mut outputs = Vec::new();
+    /// for timeout in timeouts {
+    ///     outputs.push(timeout.await);
+    /// }
+    ///
+    /// assert_eq!(outputs, [None, Some(42)]);
+    /// #
+    /// # })
+    /// ```
+    #[track_caller]
+    fn timeout_boxed<'a>(self, after: Duration) -> BoxTimeout<'a, Self::Output>
+    where
+        Self: Sized + Send + 'a,
+    {
+        Timeout::after(Box::pin(self), after)
+    }
+
+    /// Given a [`Duration`], boxes the future and creates and returns a new [`LocalBoxTimeout`]
+    /// that will poll both it and a [`Timer`] that will complete after the provided duration,
+    /// and return the future's output or [`None`] if the timer completes first.
+    #[track_caller]
+    fn timeout_boxed_local<'a>(self, after: Duration) -> LocalBoxTimeout<'a, Self::Output>
+    where
+        Self: Sized + 'a,
+    {
+        Timeout::after(Box::pin(self), after)
     }
 }
 
 impl<Fut: Future> TimeoutExt for Fut {}
 
 /* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
- * │                                impl Future for Timeout<Fut>                                │ *
+ * │                              impl Future for Timeout<Fut, D>                               │ *
 \* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
 
-impl<Fut: Future> Future for Timeout<Fut> {
+impl<Fut: Future, D: Future> Future for Timeout<Fut, D> {
     type Output = Option<Fut::Output>;
 
     fn poll(self: Pin<&mut Self>, ctx: &mut Context) -> Poll<Self::Output> {
-        let this = self.project();
+        let mut this = self.project();
 
-        if this.timer.poll(ctx).is_ready() {
-            return Poll::Ready(None);
+        if *this.done {
+            return Poll::Pending;
         }
 
-        if let Poll::Ready(output) = this.future.poll(ctx) {
-            return Poll::Ready(Some(output));
+        let output = if *this.armed && this.timer.as_mut().poll(ctx).is_ready() {
+            None
+        } else if let Poll::Ready(output) = {
+            #[cfg(all(feature = "log", debug_assertions))]
+            let _ambient = deadline::enter(*this.at);
+
+            this.future.poll(ctx)
+        } {
+            Some(output)
+        } else if !*this.armed {
+            *this.armed = true;
+            if this.timer.poll(ctx).is_pending() {
+                return Poll::Pending;
+            }
+
+            None
+        } else {
+            return Poll::Pending;
+        };
+
+        *this.done = true;
+        Poll::Ready(output)
+    }
+}
+
+impl<Fut: Future, D: Future> FusedFuture for Timeout<Fut, D> {
+    fn is_terminated(&self) -> bool {
+        self.done
+    }
+}
+
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                                trait DeadlineSource: Future                                │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+/// A future completing at a known deadline, which can be rearmed, and which a [`Timeout`] can
+/// thus shorten, restart and clone.
+///
+/// Besides [`Sleep`], the default deadline of a [`Timeout`], the sleeps of a
+/// [`TimerWheel`](crate::wheel::TimerWheel), of a [`Coalescer`](crate::coalesce::Coalescer) or
+/// of the thread-local timers of [`local`] are deadline sources, as are the simulated timers of
+/// [`sim`] (with the `sim` feature).
+pub trait DeadlineSource: Future + Unpin {
+    /// Returns the current instant on the clock the deadline follows, from which instants to
+    /// [`set_at`](DeadlineSource::set_at) are computed.
+    fn now(&self) -> Instant;
+
+    /// Returns the instant the deadline completes at, or [`None`] if it never does.
+    fn deadline(&self) -> Option<Instant>;
+
+    /// Rearms the deadline to complete at the provided instant.
+    fn set_at(&mut self, at: Instant);
+
+    /// Rearms the deadline to never complete, e.g. when it is computed from a duration too long
+    /// to be represented.
+    ///
+    /// By default, the deadline is rearmed about 30 years from now, for sources which can't be
+    /// disarmed.
+    fn set_never(&mut self) {
+        let at = deadline::deadline_after(self.now(), Duration::MAX);
+        self.set_at(at);
+    }
+
+    /// Returns a new deadline from the same source (e.g. registered with the same wheel),
+    /// completing at the same instant.
+    fn fork(&self) -> Self
+    where
+        Self: Sized;
+}
+
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                                        struct Sleep                                        │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+/// A [`Timer`] remembering the instant it completes at, which is the default deadline of a
+/// [`Timeout`], and returning that instant once it completes.
+///
+/// ## Example
+///
+/// ```rust
+/// # use futures_lite::future;
+/// use smol_timeout::{DeadlineSource, Sleep};
+/// use std::time::{Duration, Instant};
+///
+/// # future::block_on(async {
+/// #
+/// let deadline = Instant::now() + Duration::from_millis(100);
+///
+/// let sleep = Sleep::at(deadline);
+/// assert_eq!(sleep.deadline(), Some(deadline));
+/// assert!(sleep.await >= deadline);
+/// #
+/// # })
+/// ```
+#[derive(Debug)]
+#[must_use = "futures do nothing unless you `.await` or poll them"]
+pub struct Sleep {
+    timer: Timer,
+    at: Option<Instant>,
+}
+
+impl Sleep {
+    /// Creates and returns a new [`Sleep`] that will complete after the provided duration, or
+    /// that will never complete if the duration is too long to be represented (e.g.
+    /// [`Duration::MAX`]).
+    ///
+    /// ## Example
+    ///
+    /// ```rust
+    /// # use futures_lite::future;
+    /// use smol_timeout::{DeadlineSource, Sleep, TimeoutExt};
+    /// use std::time::Duration;
+    ///
+    /// # future::block_on(async {
+    /// #
+    /// assert_eq!(Sleep::after(Duration::MAX).deadline(), None);
+    ///
+    /// let mut foo = Box::pin(future::pending::<()>().timeout(Duration::MAX));
+    /// assert_eq!(future::poll_once(&mut foo).await, None);
+    ///
+    /// foo.as_mut().restart_after(Duration::MAX);
+    /// assert_eq!(foo.deadline().deadline(), None);
+    ///
+    /// let bar = async { 42 }.timeout(Duration::MAX).min_with(Duration::MAX);
+    /// assert_eq!(bar.await, Some(42));
+    /// #
+    /// # })
+    /// ```
+    pub fn after(after: Duration) -> Self {
+        match clock::now().checked_add(after) {
+            Some(deadline) => Sleep::at(deadline),
+            None => Sleep::never(),
         }
+    }
+
+    /// Creates and returns a new [`Sleep`] that will complete at the provided deadline.
+    pub fn at(deadline: Instant) -> Self {
+        Sleep {
+            timer: Timer::at(deadline),
+            at: Some(deadline),
+        }
+    }
+
+    /// Creates and returns a new [`Sleep`] that will never complete.
+    pub fn never() -> Self {
+        Sleep {
+            timer: Timer::never(),
+            at: None,
+        }
+    }
+}
+
+impl Future for Sleep {
+    type Output = Instant;
+
+    fn poll(mut self: Pin<&mut Self>, ctx: &mut Context) -> Poll<Instant> {
+        Pin::new(&mut self.timer).poll(ctx)
+    }
+}
+
+impl DeadlineSource for Sleep {
+    fn now(&self) -> Instant {
+        clock::now()
+    }
+
+    fn deadline(&self) -> Option<Instant> {
+        self.at
+    }
+
+    fn set_at(&mut self, at: Instant) {
+        self.timer.set_at(at);
+        self.at = Some(at);
+    }
+
+    fn set_never(&mut self) {
+        self.timer = Timer::never();
+        self.at = None;
+    }
+
+    fn fork(&self) -> Self {
+        match self.at {
+            Some(at) => Sleep::at(at),
+            None => Sleep::never(),
+        }
+    }
+}
+
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                                       struct Elapsed                                       │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+/// The error returned when a timer completed before the operation it was bounding.
+///
+/// An [`Elapsed`] error created with [`Elapsed::traced`] (e.g. by a
+/// [`TracedTimeout`](crate::traced::TracedTimeout)) records the location the timeout was created
+/// at and, with the `backtrace` feature, a [backtrace](std::backtrace::Backtrace) captured
+/// there (only when enabled by the `RUST_BACKTRACE` or `RUST_LIB_BACKTRACE` environment
+/// variables). Two [`Elapsed`] errors compare equal regardless of where they were created.
+///
+/// ## Example
+///
+/// ```rust
+/// use smol_timeout::Elapsed;
+///
+/// let err = Elapsed::traced();
+/// assert_eq!(err.location().unwrap().line(), line!() - 1);
+///
+/// assert_eq!(err, Elapsed::new());
+/// assert_eq!(Elapsed::new().location(), None);
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct Elapsed {
+    location: Option<&'static Location<'static>>,
+    #[cfg(feature = "backtrace")]
+    backtrace: Option<Arc<Backtrace>>,
+}
+
+impl Elapsed {
+    /// Creates and returns a new [`Elapsed`] error, which doesn't record where it was created.
+    pub const fn new() -> Self {
+        Elapsed {
+            location: None,
+            #[cfg(feature = "backtrace")]
+            backtrace: None,
+        }
+    }
+
+    /// Creates and returns a new [`Elapsed`] error recording the location of the caller and,
+    /// with the `backtrace` feature, a backtrace captured there.
+    #[track_caller]
+    pub fn traced() -> Self {
+        Elapsed {
+            location: Some(Location::caller()),
+            #[cfg(feature = "backtrace")]
+            backtrace: Some(Arc::new(Backtrace::capture())),
+        }
+    }
+
+    /// Returns the location the error was created at, if it was created with
+    /// [`Elapsed::traced`].
+    pub fn location(&self) -> Option<&'static Location<'static>> {
+        self.location
+    }
+
+    /// Returns the backtrace captured where the error was created, if it was created with
+    /// [`Elapsed::traced`].
+    ///
+    /// Only available with the `backtrace` feature.
+    #[cfg(feature = "backtrace")]
+    pub fn backtrace(&self) -> Option<&Backtrace> {
+        self.backtrace.as_deref()
+    }
+}
+
+impl PartialEq for Elapsed {
+    fn eq(&self, _: &Elapsed) -> bool {
+        true
+    }
+}
+
+impl Eq for Elapsed {}
+
+impl Hash for Elapsed {
+    fn hash<H: Hasher>(&self, _: &mut H) {}
+}
+
+impl fmt::Display for Elapsed {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        match self.location {
+            Some(location) => write!(fmt, "deadline set at {} has elapsed", location),
+            None => fmt.write_str("deadline has elapsed"),
+        }
+    }
+}
+
+#[cfg(feature = "defmt")]
+impl defmt::Format for Elapsed {
+    fn format(&self, fmt: defmt::Formatter) {
+        match self.location {
+            Some(location) => defmt::write!(
+                fmt,
+                "deadline set at {=str}:{=u32}:{=u32} has elapsed",
+                location.file(),
+                location.line(),
+                location.column()
+            ),
+            None => defmt::write!(fmt, "deadline has elapsed"),
+        }
+    }
+}
+
+impl std::error::Error for Elapsed {}
 
-        Poll::Pending
+/// Converts into an [`io::Error`](std::io::Error) of kind
+/// [`TimedOut`](std::io::ErrorKind::TimedOut), for timeouts bounding I/O operations.
+impl From<Elapsed> for std::io::Error {
+    fn from(elapsed: Elapsed) -> Self {
+        std::io::Error::new(std::io::ErrorKind::TimedOut, elapsed)
     }
 }
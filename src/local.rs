@@ -0,0 +1,326 @@
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                                                                                            │ *
+ * │ This Source Code Form is subject to the terms of the Mozilla Public                        │ *
+ * │ License, v. 2.0. If a copy of the MPL was not distributed with this                        │ *
+ * │ file, You can obtain one at http://mozilla.org/MPL/2.0/.                                   │ *
+ * │                                                                                            │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                                       Documentation                                        │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+//! Timeouts sharing a single thread-local [`Timer`], for hot paths creating large numbers of
+//! short-lived timeouts.
+//!
+//! Creating a [`Timer`] for every timeout registers and deregisters it with the reactor every
+//! time. The timeouts of this module instead record their deadline in a list owned by the
+//! thread they are created on, and every thread only keeps a single [`Timer`] registered with
+//! the reactor, armed at the earliest deadline of its list. Creating a timeout, or dropping one
+//! which didn't fire (the common case), never touches the reactor unless its deadline is the
+//! earliest one of its thread.
+//!
+//! The timeouts stay [`Send`], and can be polled and dropped from any thread.
+//!
+//! ## Example
+//!
+//! ```rust
+//! use async_io::Timer;
+//! # use futures_lite::future;
+//! use smol_timeout::local;
+//! use std::time::Duration;
+//!
+//! # future::block_on(async {
+//! #
+//! let foo = async {
+//!     Timer::after(Duration::from_millis(250)).await;
+//!     24
+//! };
+//!
+//! assert_eq!(local::timeout(foo, Duration::from_millis(100)).await, None);
+//!
+//! let bar = async {
+//!     Timer::after(Duration::from_millis(100)).await;
+//!     42
+//! };
+//!
+//! assert_eq!(local::timeout(bar, Duration::from_millis(250)).await, Some(42));
+//! #
+//! # });
+//! ```
+
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                                          Imports                                           │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+use crate::{clock, deadline};
+use crate::{DeadlineSource, Timeout, Timer};
+use core::fmt;
+use core::future::Future;
+use core::mem;
+use core::pin::Pin;
+use core::task::{Context, Poll, Waker};
+use core::time::Duration;
+use std::collections::BTreeMap;
+use std::sync::{Arc, Mutex};
+use std::task::Wake;
+use std::thread_local;
+use std::time::Instant;
+use std::vec::Vec;
+
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                                       struct Driver                                        │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+struct State {
+    /// The timer registered with the reactor, armed at the earliest deadline of `sleeps`.
+    timer: Timer,
+    armed: Option<Instant>,
+    /// The wakers of the sleeps that didn't complete yet, sorted by deadline.
+    sleeps: BTreeMap<(Instant, u64), Option<Waker>>,
+    next_id: u64,
+}
+
+/// The list of deadlines of a thread, and the timer armed at the earliest one.
+struct Driver {
+    state: Mutex<State>,
+}
+
+thread_local! {
+    static DRIVER: Arc<Driver> = Arc::new(Driver {
+        state: Mutex::new(State {
+            timer: Timer::never(),
+            armed: None,
+            sleeps: BTreeMap::new(),
+            next_id: 0,
+        }),
+    });
+}
+
+impl Driver {
+    /// Removes the sleeps whose deadline passed, pushing their wakers, and arms the timer at the
+    /// earliest remaining deadline.
+    fn rearm(self: &Arc<Self>, state: &mut State, wakers: &mut Vec<Waker>) {
+        loop {
+            let now = clock::now();
+            while let Some(entry) = state.sleeps.first_entry() {
+                if entry.key().0 > now {
+                    break;
+                }
+
+                wakers.extend(entry.remove());
+            }
+
+            let earliest = match state.sleeps.keys().next() {
+                Some((deadline, _)) => *deadline,
+                None => {
+                    // Leaving the timer registered until it fires, as it is likely to be rearmed
+                    // soon.
+                    state.armed = None;
+                    return;
+                }
+            };
+
+            if state.armed != Some(earliest) {
+                state.timer.set_at(earliest);
+                state.armed = Some(earliest);
+            }
+
+            // The timer is polled with a waker processing the whole list.
+            let waker = Waker::from(self.clone());
+            if Pin::new(&mut state.timer)
+                .poll(&mut Context::from_waker(&waker))
+                .is_pending()
+            {
+                return;
+            }
+
+            state.armed = None;
+        }
+    }
+}
+
+impl Wake for Driver {
+    fn wake(self: Arc<Self>) {
+        self.wake_by_ref();
+    }
+
+    fn wake_by_ref(self: &Arc<Self>) {
+        let mut wakers = Vec::new();
+        {
+            let mut state = self.state.lock().unwrap();
+            state.armed = None;
+            self.rearm(&mut state, &mut wakers);
+        }
+
+        for waker in wakers {
+            waker.wake();
+        }
+    }
+}
+
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                                     struct LocalSleep                                      │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+/// A future completing after a deadline, sharing the thread-local [`Timer`] of the thread it was
+/// created on.
+///
+/// A [`LocalSleep`] is a [`DeadlineSource`], which keeps sharing the timer of the thread it was
+/// created on when it is rearmed or forked.
+///
+/// Created by [`sleep`] and [`sleep_until`].
+#[must_use = "futures do nothing unless you `.await` or poll them"]
+pub struct LocalSleep {
+    driver: Arc<Driver>,
+    deadline: Instant,
+    id: u64,
+}
+
+/// Creates and returns a new [`LocalSleep`] future that will complete after the provided
+/// duration.
+pub fn sleep(after: Duration) -> LocalSleep {
+    sleep_until(deadline::deadline_after(clock::now(), after))
+}
+
+/// Creates and returns a new [`LocalSleep`] future that will complete at the provided deadline.
+///
+/// ## Example
+///
+/// ```rust
+/// # use futures_lite::future;
+/// use smol_timeout::local;
+/// use std::time::{Duration, Instant};
+///
+/// # future::block_on(async {
+/// #
+/// let deadline = Instant::now() + Duration::from_millis(100);
+///
+/// local::sleep_until(deadline).await;
+/// assert!(Instant::now() >= deadline);
+/// #
+/// # })
+/// ```
+pub fn sleep_until(deadline: Instant) -> LocalSleep {
+    LocalSleep::on(DRIVER.with(|driver| driver.clone()), deadline)
+}
+
+impl LocalSleep {
+    fn on(driver: Arc<Driver>, deadline: Instant) -> Self {
+        let id = {
+            let mut state = driver.state.lock().unwrap();
+            state.next_id += 1;
+            state.next_id
+        };
+
+        LocalSleep {
+            driver,
+            deadline,
+            id,
+        }
+    }
+
+    /// Removes the sleep from the list of its thread.
+    fn deregister(&mut self) {
+        let waker = {
+            let mut state = self.driver.state.lock().unwrap();
+            state.sleeps.remove(&(self.deadline, self.id))
+        };
+
+        // Dropping the waker outside of the lock.
+        mem::drop(waker);
+    }
+}
+
+impl Future for LocalSleep {
+    type Output = ();
+
+    fn poll(self: Pin<&mut Self>, ctx: &mut Context) -> Poll<()> {
+        if clock::now() >= self.deadline {
+            return Poll::Ready(());
+        }
+
+        let mut wakers = Vec::new();
+        {
+            let mut state = self.driver.state.lock().unwrap();
+            let waker = state.sleeps.entry((self.deadline, self.id)).or_insert(None);
+
+            match waker {
+                Some(waker) if waker.will_wake(ctx.waker()) => return Poll::Pending,
+                _ => *waker = Some(ctx.waker().clone()),
+            }
+
+            if state.armed.is_none_or(|armed| self.deadline < armed) {
+                self.driver.rearm(&mut state, &mut wakers);
+            }
+        }
+
+        // Waking the sleeps whose deadline passed while rearming, which might include this one.
+        let mut ready = false;
+        for waker in wakers {
+            if waker.will_wake(ctx.waker()) {
+                ready = true;
+            } else {
+                waker.wake();
+            }
+        }
+
+        if ready {
+            return Poll::Ready(());
+        }
+
+        Poll::Pending
+    }
+}
+
+impl DeadlineSource for LocalSleep {
+    fn now(&self) -> Instant {
+        clock::now()
+    }
+
+    fn deadline(&self) -> Option<Instant> {
+        Some(self.deadline)
+    }
+
+    fn set_at(&mut self, at: Instant) {
+        // The sleep is listed again at its new deadline once it is polled.
+        self.deregister();
+        self.deadline = at;
+    }
+
+    fn fork(&self) -> Self {
+        LocalSleep::on(self.driver.clone(), self.deadline)
+    }
+}
+
+impl Drop for LocalSleep {
+    fn drop(&mut self) {
+        self.deregister();
+    }
+}
+
+impl fmt::Debug for LocalSleep {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        fmt.debug_struct("LocalSleep")
+            .field("deadline", &self.deadline)
+            .finish()
+    }
+}
+
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                                   type LocalTimeout<Fut>                                   │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+/// A [`Timeout`] whose deadline is a [`LocalSleep`], and which can thus be shortened, restarted
+/// and cloned like any other.
+///
+/// Created by [`timeout`].
+pub type LocalTimeout<Fut> = Timeout<Fut, LocalSleep>;
+
+/// Given a future and a [`Duration`], creates and returns a new [`LocalTimeout`] that will poll
+/// both the future and a [`LocalSleep`] that will complete after the provided duration, and
+/// return the future's output or [`None`] if the sleep completes first.
+#[track_caller]
+pub fn timeout<Fut: Future>(future: Fut, after: Duration) -> LocalTimeout<Fut> {
+    Timeout::armed(future, sleep(after), after)
+}
@@ -0,0 +1,291 @@
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                                                                                            │ *
+ * │ This Source Code Form is subject to the terms of the Mozilla Public                        │ *
+ * │ License, v. 2.0. If a copy of the MPL was not distributed with this                        │ *
+ * │ file, You can obtain one at http://mozilla.org/MPL/2.0/.                                   │ *
+ * │                                                                                            │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                                       Documentation                                        │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+//! Schedules firing at a local time of day in a timezone, e.g. at 03:30 in Europe/Helsinki.
+//!
+//! With the `chrono-tz` feature, a [`Daily`] schedule is a [`Stream`] ticking every day at a
+//! local time in a [`chrono_tz::Tz`] timezone. Like a [`WallInterval`], it computes every
+//! occurrence on the wall clock and sleeps until it with a [`SleepUntil`], re-checking the wall
+//! clock when it wakes up and regularly during long sleeps.
+//!
+//! Around daylight saving time transitions, a local time may be skipped (when clocks move
+//! forward) or repeated (when they move back). What happens then is decided by an [`OnGap`]
+//! and an [`OnFold`] policy.
+//!
+//! [`WallInterval`]: crate::wall::WallInterval
+//! [`SleepUntil`]: crate::wall::SleepUntil
+//!
+//! ## Example
+//!
+//! ```rust
+//! use chrono::{NaiveDate, NaiveTime};
+//! use chrono_tz::Europe::Helsinki;
+//! use smol_timeout::local_time::{self, OnFold, OnGap};
+//!
+//! let time = NaiveTime::from_hms_opt(3, 30, 0).unwrap();
+//! let schedule = local_time::daily(time, Helsinki);
+//!
+//! // Clocks move from 03:00 to 04:00 on March 31st, 2024, so 03:30 is shifted to 04:30...
+//! let spring = NaiveDate::from_ymd_opt(2024, 3, 31).unwrap();
+//! let occurrences = schedule.occurrences_on(spring);
+//! assert_eq!(occurrences[0].time(), NaiveTime::from_hms_opt(4, 30, 0).unwrap());
+//!
+//! // ...and clocks move from 04:00 back to 03:00 on October 27th, so 03:30 happens twice
+//! // (first at 00:30 UTC, then at 01:30 UTC).
+//! let autumn = NaiveDate::from_ymd_opt(2024, 10, 27).unwrap();
+//! let occurrences = schedule.occurrences_on(autumn);
+//! assert_eq!(occurrences.len(), 1);
+//! assert_eq!(occurrences[0].naive_utc(), autumn.and_hms_opt(0, 30, 0).unwrap());
+//!
+//! let schedule = schedule.on_gap(OnGap::Skip).on_fold(OnFold::Both);
+//! assert!(schedule.occurrences_on(spring).is_empty());
+//! assert_eq!(schedule.occurrences_on(autumn).len(), 2);
+//! ```
+
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                                          Imports                                           │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+use crate::wall::{self, SleepUntil};
+use chrono::{DateTime, MappedLocalTime, NaiveDate, NaiveTime, Offset, TimeDelta, TimeZone};
+use chrono_tz::Tz;
+use core::future::Future;
+use core::pin::Pin;
+use core::task::{Context, Poll};
+use core::time::Duration;
+use futures_core::stream::Stream;
+use std::time::{SystemTime, UNIX_EPOCH};
+use std::vec;
+use std::vec::Vec;
+
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                                         enum OnGap                                         │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+/// What a [`Daily`] schedule does on days its local time is skipped, because clocks move
+/// forward over it.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum OnGap {
+    /// The occurrence is shifted forward by the length of the gap (e.g. 02:30 becomes 03:30
+    /// when clocks move from 02:00 to 03:00).
+    #[default]
+    Shift,
+    /// There is no occurrence on that day.
+    Skip,
+}
+
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                                        enum OnFold                                         │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+/// What a [`Daily`] schedule does on days its local time happens twice, because clocks move
+/// back over it.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum OnFold {
+    /// The schedule only fires the first time.
+    #[default]
+    First,
+    /// The schedule only fires the second time.
+    Second,
+    /// The schedule fires both times.
+    Both,
+}
+
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                                        struct Daily                                        │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+/// A stream ticking every day at a local time in a timezone, and yielding the occurrence every
+/// tick was due at.
+///
+/// An occurrence missed by more than a tick (e.g. while the system was suspended) is yielded
+/// late, and the occurrences missed meanwhile are skipped.
+///
+/// Created by [`daily`]. Only available with the `chrono-tz` feature.
+#[derive(Debug)]
+#[must_use = "streams do nothing unless polled"]
+pub struct Daily {
+    /// A sleep until the next occurrence.
+    sleep: SleepUntil,
+    time: NaiveTime,
+    tz: Tz,
+    gap: OnGap,
+    fold: OnFold,
+    /// The next occurrence.
+    next: DateTime<Tz>,
+}
+
+impl Daily {
+    /// The default longest duration slept before re-checking the wall clock.
+    pub const DEFAULT_RESYNC: Duration = SleepUntil::DEFAULT_RESYNC;
+
+    /// Sets the [`OnGap`] policy used on days the local time is skipped.
+    pub fn on_gap(mut self, gap: OnGap) -> Self {
+        self.gap = gap;
+        self.reschedule();
+        self
+    }
+
+    /// Sets the [`OnFold`] policy used on days the local time happens twice.
+    pub fn on_fold(mut self, fold: OnFold) -> Self {
+        self.fold = fold;
+        self.reschedule();
+        self
+    }
+
+    /// Sets the longest duration slept on the monotonic clock before re-checking the wall clock,
+    /// which defaults to [`Daily::DEFAULT_RESYNC`].
+    ///
+    /// ## Panics
+    ///
+    /// Panics if `resync` is zero.
+    pub fn resync(mut self, resync: Duration) -> Self {
+        self.sleep = self.sleep.resync(resync);
+        self
+    }
+
+    /// Returns the next occurrence of the schedule.
+    pub fn next_occurrence(&self) -> DateTime<Tz> {
+        self.next
+    }
+
+    /// Returns the occurrences of the schedule on the provided local date, according to its
+    /// [`OnGap`] and [`OnFold`] policies.
+    pub fn occurrences_on(&self, date: NaiveDate) -> Vec<DateTime<Tz>> {
+        let local = date.and_time(self.time);
+
+        match self.tz.from_local_datetime(&local) {
+            MappedLocalTime::Single(occurrence) => vec![occurrence],
+            MappedLocalTime::Ambiguous(first, second) => match self.fold {
+                OnFold::First => vec![first],
+                OnFold::Second => vec![second],
+                OnFold::Both => vec![first, second],
+            },
+            MappedLocalTime::None => match self.gap {
+                // Interpreting the local time with the offset from before the gap shifts it
+                // forward by the gap's length.
+                OnGap::Shift => {
+                    let before = self
+                        .tz
+                        .offset_from_utc_datetime(&(local - TimeDelta::days(1)))
+                        .fix();
+
+                    let utc = local - TimeDelta::seconds(i64::from(before.local_minus_utc()));
+                    vec![self.tz.from_utc_datetime(&utc)]
+                }
+                OnGap::Skip => Vec::new(),
+            },
+        }
+    }
+
+    /// Returns the first occurrence strictly after `after`.
+    fn occurrence_after(&self, after: DateTime<Tz>) -> DateTime<Tz> {
+        let mut date = after.date_naive();
+
+        loop {
+            let occurrence = self
+                .occurrences_on(date)
+                .into_iter()
+                .find(|occurrence| *occurrence > after);
+
+            if let Some(occurrence) = occurrence {
+                return occurrence;
+            }
+
+            date = date
+                .succ_opt()
+                .expect("a schedule can't go past the end of time");
+        }
+    }
+
+    /// Recomputes the next occurrence from now, e.g. after a policy changed.
+    fn reschedule(&mut self) {
+        let now = local(SystemTime::now(), &self.tz);
+        self.schedule(self.occurrence_after(now));
+    }
+
+    fn schedule(&mut self, next: DateTime<Tz>) {
+        self.next = next;
+        self.sleep.set(system_time(&next));
+    }
+}
+
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                                         fn daily()                                         │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+/// Creates and returns a new [`Daily`] schedule ticking every day at `time` in the timezone
+/// `tz`, shifting skipped local times forward and only firing the first time for repeated ones.
+///
+/// Only available with the `chrono-tz` feature.
+pub fn daily(time: NaiveTime, tz: Tz) -> Daily {
+    let now = SystemTime::now();
+    let mut daily = Daily {
+        sleep: wall::sleep_until(now),
+        time,
+        tz,
+        gap: OnGap::default(),
+        fold: OnFold::default(),
+        next: local(now, &tz),
+    };
+
+    daily.reschedule();
+    daily
+}
+
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                                   impl Stream for Daily                                    │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+impl Stream for Daily {
+    type Item = DateTime<Tz>;
+
+    fn poll_next(self: Pin<&mut Self>, ctx: &mut Context) -> Poll<Option<DateTime<Tz>>> {
+        let this = self.get_mut();
+
+        let now = match Pin::new(&mut this.sleep).poll(ctx) {
+            Poll::Ready(now) => local(now, &this.tz),
+            Poll::Pending => return Poll::Pending,
+        };
+
+        let due = this.next;
+        this.schedule(this.occurrence_after(now.max(due)));
+
+        Poll::Ready(Some(due))
+    }
+}
+
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                                        Conversions                                         │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+/// Returns the provided wall-clock instant in the provided timezone.
+fn local(time: SystemTime, tz: &Tz) -> DateTime<Tz> {
+    let since = time.duration_since(UNIX_EPOCH).unwrap_or_default();
+
+    let time = DateTime::from_timestamp(since.as_secs() as i64, since.subsec_nanos())
+        .expect("the wall clock is within chrono's range");
+
+    time.with_timezone(tz)
+}
+
+/// Returns the [`SystemTime`] corresponding to the provided date and time.
+fn system_time(time: &DateTime<Tz>) -> SystemTime {
+    let secs = time.timestamp();
+    let nanos = Duration::from_nanos(u64::from(time.timestamp_subsec_nanos()));
+
+    if secs >= 0 {
+        UNIX_EPOCH + Duration::from_secs(secs as u64) + nanos
+    } else {
+        UNIX_EPOCH - Duration::from_secs(secs.unsigned_abs()) + nanos
+    }
+}
@@ -0,0 +1,205 @@
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                                                                                            │ *
+ * │ This Source Code Form is subject to the terms of the Mozilla Public                        │ *
+ * │ License, v. 2.0. If a copy of the MPL was not distributed with this                        │ *
+ * │ file, You can obtain one at http://mozilla.org/MPL/2.0/.                                   │ *
+ * │                                                                                            │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                                       Documentation                                        │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+//! Timeouts for acquiring [`async_lock`] locks and semaphore permits.
+//!
+//! ## Example
+//!
+//! ```rust
+//! use async_lock::Mutex;
+//! # use futures_lite::future;
+//! use smol_timeout::lock::MutexExt;
+//! use std::time::Duration;
+//!
+//! # future::block_on(async {
+//! #
+//! let mutex = Mutex::new(42);
+//!
+//! let guard = mutex.lock_timeout(Duration::from_millis(100)).await;
+//! assert_eq!(guard.as_deref(), Some(&42));
+//!
+//! assert!(mutex.lock_timeout(Duration::from_millis(100)).await.is_none());
+//! #
+//! # });
+//! ```
+
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                                          Imports                                           │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+use crate::{Timeout, TimeoutExt};
+use async_lock::futures::{Acquire, AcquireArc, Lock, Read, Write};
+use async_lock::{Mutex, RwLock, Semaphore};
+use core::time::Duration;
+use std::sync::Arc;
+
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                                     trait MutexExt<T>                                      │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+/// An extension trait for [`Mutex`]es that provides a way to lock them with a timeout.
+pub trait MutexExt<T: ?Sized> {
+    /// Acquires the mutex, waiting for at most the provided duration, and returns a guard or
+    /// [`None`] if the mutex couldn't be acquired in time.
+    ///
+    /// ## Example
+    ///
+    /// ```rust
+    /// use async_lock::Mutex;
+    /// # use futures_lite::future;
+    /// use smol_timeout::lock::MutexExt;
+    /// use std::time::Duration;
+    ///
+    /// # future::block_on(async {
+    /// #
+    /// let mutex = Mutex::new(42);
+    /// let guard = mutex.lock().await;
+    ///
+    /// assert!(mutex.lock_timeout(Duration::from_millis(100)).await.is_none());
+    /// #
+    /// # })
+    /// ```
+    fn lock_timeout(&self, after: Duration) -> Timeout<Lock<'_, T>>;
+}
+
+impl<T: ?Sized> MutexExt<T> for Mutex<T> {
+    fn lock_timeout(&self, after: Duration) -> Timeout<Lock<'_, T>> {
+        self.lock().timeout(after)
+    }
+}
+
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                                     trait RwLockExt<T>                                     │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+/// An extension trait for [`RwLock`]s that provides a way to lock them with a timeout.
+pub trait RwLockExt<T: ?Sized> {
+    /// Acquires a read lock, waiting for at most the provided duration, and returns a guard or
+    /// [`None`] if the lock couldn't be acquired in time.
+    ///
+    /// ## Example
+    ///
+    /// ```rust
+    /// use async_lock::RwLock;
+    /// # use futures_lite::future;
+    /// use smol_timeout::lock::RwLockExt;
+    /// use std::time::Duration;
+    ///
+    /// # future::block_on(async {
+    /// #
+    /// let lock = RwLock::new(42);
+    /// let reader = lock.read().await;
+    ///
+    /// assert!(lock.read_timeout(Duration::from_millis(100)).await.is_some());
+    /// #
+    /// # })
+    /// ```
+    fn read_timeout(&self, after: Duration) -> Timeout<Read<'_, T>>;
+
+    /// Acquires the write lock, waiting for at most the provided duration, and returns a guard
+    /// or [`None`] if the lock couldn't be acquired in time.
+    ///
+    /// ## Example
+    ///
+    /// ```rust
+    /// use async_lock::RwLock;
+    /// # use futures_lite::future;
+    /// use smol_timeout::lock::RwLockExt;
+    /// use std::time::Duration;
+    ///
+    /// # future::block_on(async {
+    /// #
+    /// let lock = RwLock::new(42);
+    /// let reader = lock.read().await;
+    ///
+    /// assert!(lock.write_timeout(Duration::from_millis(100)).await.is_none());
+    /// #
+    /// # })
+    /// ```
+    fn write_timeout(&self, after: Duration) -> Timeout<Write<'_, T>>;
+}
+
+impl<T: ?Sized> RwLockExt<T> for RwLock<T> {
+    fn read_timeout(&self, after: Duration) -> Timeout<Read<'_, T>> {
+        self.read().timeout(after)
+    }
+
+    fn write_timeout(&self, after: Duration) -> Timeout<Write<'_, T>> {
+        self.write().timeout(after)
+    }
+}
+
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                                     trait SemaphoreExt                                     │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+/// An extension trait for [`Semaphore`]s that provides a way to acquire permits with a timeout.
+pub trait SemaphoreExt {
+    /// Acquires a permit, waiting for at most the provided duration, and returns a guard or
+    /// [`None`] if no permit could be acquired in time.
+    ///
+    /// ## Example
+    ///
+    /// ```rust
+    /// use async_lock::Semaphore;
+    /// # use futures_lite::future;
+    /// use smol_timeout::lock::SemaphoreExt;
+    /// use std::time::Duration;
+    ///
+    /// # future::block_on(async {
+    /// #
+    /// let semaphore = Semaphore::new(1);
+    ///
+    /// let permit = semaphore.acquire_timeout(Duration::from_millis(100)).await;
+    /// assert!(permit.is_some());
+    ///
+    /// assert!(semaphore.acquire_timeout(Duration::from_millis(100)).await.is_none());
+    /// #
+    /// # })
+    /// ```
+    fn acquire_timeout(&self, after: Duration) -> Timeout<Acquire<'_>>;
+
+    /// Acquires a permit from a semaphore held in an [`Arc`], waiting for at most the provided
+    /// duration, and returns an owned guard or [`None`] if no permit could be acquired in time.
+    ///
+    /// ## Example
+    ///
+    /// ```rust
+    /// use async_lock::Semaphore;
+    /// # use futures_lite::future;
+    /// use smol_timeout::lock::SemaphoreExt;
+    /// use std::sync::Arc;
+    /// use std::time::Duration;
+    ///
+    /// # future::block_on(async {
+    /// #
+    /// let semaphore = Arc::new(Semaphore::new(1));
+    ///
+    /// let permit = semaphore.acquire_arc_timeout(Duration::from_millis(100)).await;
+    /// assert!(permit.is_some());
+    ///
+    /// assert!(semaphore.acquire_arc_timeout(Duration::from_millis(100)).await.is_none());
+    /// #
+    /// # })
+    /// ```
+    fn acquire_arc_timeout(self: &Arc<Self>, after: Duration) -> Timeout<AcquireArc>;
+}
+
+impl SemaphoreExt for Semaphore {
+    fn acquire_timeout(&self, after: Duration) -> Timeout<Acquire<'_>> {
+        self.acquire().timeout(after)
+    }
+
+    fn acquire_arc_timeout(self: &Arc<Self>, after: Duration) -> Timeout<AcquireArc> {
+        self.acquire_arc().timeout(after)
+    }
+}
@@ -0,0 +1,153 @@
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                                                                                            │ *
+ * │ This Source Code Form is subject to the terms of the Mozilla Public                        │ *
+ * │ License, v. 2.0. If a copy of the MPL was not distributed with this                        │ *
+ * │ file, You can obtain one at http://mozilla.org/MPL/2.0/.                                   │ *
+ * │                                                                                            │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                                       Documentation                                        │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+//! Timeouts logging a warning with the [`log`] crate when they expire.
+//!
+//! When its timer completes first, a [`LoggedTimeout`] emits a warn-level record (with the
+//! `smol_timeout` target) including its name, its configured duration and the time elapsed
+//! since it was created, for applications not using [`tracing`](https://docs.rs/tracing).
+//!
+//! ## Example
+//!
+//! ```rust
+//! use async_io::Timer;
+//! # use futures_lite::future;
+//! use smol_timeout::log;
+//! use std::time::Duration;
+//!
+//! # future::block_on(async {
+//! #
+//! let foo = async {
+//!     Timer::after(Duration::from_millis(250)).await;
+//!     24
+//! };
+//!
+//! assert_eq!(log::timeout("foo", foo, Duration::from_millis(100)).await, None);
+//!
+//! let bar = async {
+//!     Timer::after(Duration::from_millis(100)).await;
+//!     42
+//! };
+//!
+//! assert_eq!(log::timeout("bar", bar, Duration::from_millis(250)).await, Some(42));
+//! #
+//! # });
+//! ```
+
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                                          Imports                                           │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+use crate::clock::Stamp;
+use crate::Timer;
+use ::log::warn;
+use core::future::Future;
+#[cfg(debug_assertions)]
+use core::panic::Location;
+use core::pin::Pin;
+use core::task::{Context, Poll};
+use core::time::Duration;
+use pin_project_lite::pin_project;
+
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                                        fn expired()                                        │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+/// Logs the expiry of the timeout with the provided name.
+pub(crate) fn expired(name: &'static str, after: Duration, elapsed: Duration) {
+    warn!(
+        target: "smol_timeout",
+        "timeout `{}` expired after {:?} (configured for {:?})", name, elapsed, after
+    );
+}
+
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                                       fn redundant()                                       │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+/// Logs the creation of a timeout which can never fire first, as an enclosing deadline elapses
+/// before it.
+#[cfg(debug_assertions)]
+pub(crate) fn redundant(location: &Location, after: Duration, remaining: Duration) {
+    warn!(
+        target: "smol_timeout",
+        "timeout created at {} for {:?} can never fire, as an enclosing deadline elapses in {:?}",
+        location,
+        after,
+        remaining
+    );
+}
+
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                                 struct LoggedTimeout<Fut>                                  │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+pin_project! {
+    /// A future polling both another future and a [`Timer`], returning the future's output or
+    /// [`None`] if the timer completes first, and logging a warning if it does.
+    ///
+    /// Created by [`timeout`].
+    #[derive(Debug)]
+    #[must_use = "futures do nothing unless you `.await` or poll them"]
+    pub struct LoggedTimeout<Fut> {
+        #[pin]
+        future: Fut,
+        timer: Timer,
+        name: &'static str,
+        after: Duration,
+        start: Stamp,
+    }
+}
+
+/// Given a name, a future and a [`Duration`], creates and returns a new [`LoggedTimeout`] that
+/// will poll both the future and a [`Timer`] that will complete after the provided duration,
+/// and return the future's output or [`None`] if the timer completes first, logging a warning
+/// including the provided name if it does.
+pub fn timeout<Fut: Future>(
+    name: &'static str,
+    future: Fut,
+    after: Duration,
+) -> LoggedTimeout<Fut> {
+    LoggedTimeout {
+        future,
+        timer: Timer::after(after),
+        name,
+        after,
+        start: Stamp::now(),
+    }
+}
+
+impl<Fut> LoggedTimeout<Fut> {
+    /// Returns the name included in the warning logged if the timeout expires.
+    pub fn name(&self) -> &'static str {
+        self.name
+    }
+}
+
+impl<Fut: Future> Future for LoggedTimeout<Fut> {
+    type Output = Option<Fut::Output>;
+
+    fn poll(self: Pin<&mut Self>, ctx: &mut Context) -> Poll<Self::Output> {
+        let this = self.project();
+
+        if let Poll::Ready(output) = this.future.poll(ctx) {
+            return Poll::Ready(Some(output));
+        }
+
+        if Pin::new(this.timer).poll(ctx).is_ready() {
+            expired(this.name, *this.after, this.start.elapsed());
+            return Poll::Ready(None);
+        }
+
+        Poll::Pending
+    }
+}
@@ -0,0 +1,248 @@
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                                                                                            │ *
+ * │ This Source Code Form is subject to the terms of the Mozilla Public                        │ *
+ * │ License, v. 2.0. If a copy of the MPL was not distributed with this                        │ *
+ * │ file, You can obtain one at http://mozilla.org/MPL/2.0/.                                   │ *
+ * │                                                                                            │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                                   macro select_timeout!                                    │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+/// Waits on multiple futures concurrently until one of them completes or a timeout elapses,
+/// and evaluates the branch of the first one to complete, or the `timeout` branch.
+///
+/// Each branch is written `name = future => body`, where `name` is bound to the future's output
+/// inside `body`. The last branch is written `timeout => body`. The futures are polled in order
+/// within a [`Timeout`](crate::Timeout), and the ones which didn't complete are dropped before
+/// the selected branch is evaluated, so that branches can use `return`, `?` or `break` like any
+/// other expression.
+///
+/// ## Example
+///
+/// ```rust
+/// use async_io::Timer;
+/// # use futures_lite::future;
+/// use smol_timeout::select_timeout;
+/// use std::time::Duration;
+///
+/// # future::block_on(async {
+/// #
+/// let sleep = |millis| async move {
+///     Timer::after(Duration::from_millis(millis)).await;
+///     millis
+/// };
+///
+/// let selected = select_timeout!(Duration::from_millis(100),
+///     foo = sleep(250) => format!("foo: {}", foo),
+///     bar = sleep(50) => format!("bar: {}", bar),
+///     timeout => String::from("timeout"),
+/// );
+///
+/// assert_eq!(selected, "bar: 50");
+///
+/// let selected = select_timeout!(Duration::from_millis(100),
+///     foo = sleep(250) => Some(foo),
+///     timeout => None,
+/// );
+///
+/// assert_eq!(selected, None);
+/// #
+/// # });
+/// ```
+#[macro_export]
+macro_rules! select_timeout {
+    (@munch $after:expr; [$($name:ident = $fut:expr => $body:expr;)+]; timeout => $timeout:expr $(,)?) => {{
+        #[allow(non_camel_case_types)]
+        enum __Selected<$($name,)+> {
+            $($name($name),)+
+        }
+
+        let __selected = {
+            $(let mut $name = ::core::pin::pin!($fut);)+
+
+            let __selected = ::core::future::poll_fn(|__ctx| {
+                $(
+                    if let ::core::task::Poll::Ready(__output) =
+                        ::core::future::Future::poll($name.as_mut(), __ctx)
+                    {
+                        return ::core::task::Poll::Ready(__Selected::$name(__output));
+                    }
+                )+
+
+                ::core::task::Poll::Pending
+            });
+
+            $crate::TimeoutExt::timeout(__selected, $after).await
+        };
+
+        match __selected {
+            $(::core::option::Option::Some(__Selected::$name($name)) => $body,)+
+            ::core::option::Option::None => $timeout,
+        }
+    }};
+
+    (@munch $after:expr; [$($acc:tt)*]; $name:ident = $fut:expr => $body:expr, $($rest:tt)+) => {
+        $crate::select_timeout!(@munch $after; [$($acc)* $name = $fut => $body;]; $($rest)+)
+    };
+
+    ($after:expr, $($rest:tt)+) => {
+        $crate::select_timeout!(@munch $after; []; $($rest)+)
+    };
+}
+
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                               macro assert_completes_within!                               │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+/// Awaits a future, panicking if it doesn't complete within a duration, and evaluates to its
+/// output otherwise.
+///
+/// The panic message includes the future's expression, or the provided message. With the
+/// `registry` feature, it also lists the named timeouts that were pending (see
+/// [`crate::registry`]), to tell where the future was stuck.
+///
+/// ## Example
+///
+/// ```rust
+/// use async_io::Timer;
+/// # use futures_lite::future;
+/// use smol_timeout::assert_completes_within;
+/// use std::time::Duration;
+///
+/// # future::block_on(async {
+/// #
+/// let foo = async {
+///     Timer::after(Duration::from_millis(100)).await;
+///     42
+/// };
+///
+/// let output = assert_completes_within!(foo, Duration::from_millis(250));
+/// assert_eq!(output, 42);
+/// #
+/// # });
+/// ```
+#[macro_export]
+macro_rules! assert_completes_within {
+    ($fut:expr, $after:expr $(,)?) => {
+        $crate::assert_completes_within!(
+            $fut,
+            $after,
+            "`{}` didn't complete",
+            ::core::stringify!($fut)
+        )
+    };
+
+    ($fut:expr, $after:expr, $($arg:tt)+) => {{
+        let __after = $after;
+
+        // The future is kept alive while reporting where it was stuck.
+        let mut __fut = ::core::pin::pin!($fut);
+        match $crate::TimeoutExt::timeout(__fut.as_mut(), __after).await {
+            ::core::option::Option::Some(__output) => __output,
+            ::core::option::Option::None => ::core::panic!(
+                "assertion failed: {} within {:?}{}",
+                ::core::format_args!($($arg)+),
+                __after,
+                $crate::__stuck(),
+            ),
+        }
+    }};
+}
+
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                                 macro assert_pending_for!                                  │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+/// Polls a future for a duration, panicking if it completes before the duration elapsed.
+///
+/// The future is dropped afterwards, unless a mutable reference to it was provided, so that it
+/// can still be awaited once its precondition is met.
+///
+/// ## Example
+///
+/// ```rust
+/// use async_io::Timer;
+/// # use futures_lite::future;
+/// use smol_timeout::assert_pending_for;
+/// use std::time::Duration;
+///
+/// # future::block_on(async {
+/// #
+/// let mut foo = Box::pin(async {
+///     Timer::after(Duration::from_millis(250)).await;
+///     42
+/// });
+///
+/// assert_pending_for!(&mut foo, Duration::from_millis(100));
+/// assert_eq!(foo.await, 42);
+/// #
+/// # });
+/// ```
+#[macro_export]
+macro_rules! assert_pending_for {
+    ($fut:expr, $after:expr $(,)?) => {
+        $crate::assert_pending_for!(
+            $fut,
+            $after,
+            "`{}` completed",
+            ::core::stringify!($fut)
+        )
+    };
+
+    ($fut:expr, $after:expr, $($arg:tt)+) => {{
+        let __after = $after;
+        if $crate::TimeoutExt::timeout($fut, __after).await.is_some() {
+            ::core::panic!(
+                "assertion failed: {} before {:?} elapsed",
+                ::core::format_args!($($arg)+),
+                __after,
+            );
+        }
+    }};
+}
+
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                                        fn __stuck()                                        │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+/// Returns the list of the pending named timeouts appended to the message of a failed
+/// [`assert_completes_within!`] or an expired [`PanicTimeout`](crate::panic::PanicTimeout), or
+/// an empty string without the `registry` feature.
+#[doc(hidden)]
+pub fn __stuck() -> std::string::String {
+    #[cfg(feature = "registry")]
+    {
+        use core::fmt::Write;
+
+        let mut stuck = std::string::String::new();
+        for pending in crate::registry::snapshot() {
+            let _ = write!(
+                stuck,
+                "\n  `{}` pending for {:?}",
+                pending.name(),
+                pending.waited()
+            );
+        }
+
+        if !stuck.is_empty() {
+            stuck.insert_str(0, "\npending named timeouts:");
+        }
+
+        stuck
+    }
+
+    #[cfg(not(feature = "registry"))]
+    std::string::String::new()
+}
+
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                                      fn __block_on()                                       │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+/// Runs the body of a `#[test]` wrapped by the `#[timeout]` attribute.
+#[doc(hidden)]
+pub fn __block_on<Fut: core::future::Future>(future: Fut) -> Fut::Output {
+    crate::block_on(future)
+}
@@ -0,0 +1,251 @@
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                                                                                            │ *
+ * │ This Source Code Form is subject to the terms of the Mozilla Public                        │ *
+ * │ License, v. 2.0. If a copy of the MPL was not distributed with this                        │ *
+ * │ file, You can obtain one at http://mozilla.org/MPL/2.0/.                                   │ *
+ * │                                                                                            │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                                       Documentation                                        │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+//! A set of keys expiring once they have been idle for too long.
+//!
+//! Inserting a key into a [`TimeoutMap`] arms an idle timeout for it, and
+//! [touching](TimeoutMap::touch) it rearms it; the map is also a [`Stream`] yielding the keys as
+//! they expire, using a single [`Sleep`] armed for the earliest deadline. This is the building
+//! block of session reaping, NAT-table style expiry or cache invalidation.
+//!
+//! The sleep can be replaced by any [`DeadlineSource`] (e.g. a sleep of a
+//! [`TimerWheel`](crate::wheel::TimerWheel)) using [`TimeoutMap::with_source`], whose clock the
+//! deadlines of the keys then follow.
+//!
+//! ## Example
+//!
+//! ```rust
+//! use async_io::Timer;
+//! # use futures_lite::future;
+//! use futures_lite::StreamExt;
+//! use smol_timeout::map::TimeoutMap;
+//! use std::time::Duration;
+//!
+//! # future::block_on(async {
+//! #
+//! let mut sessions = TimeoutMap::new();
+//! sessions.insert("alice", Duration::from_millis(100));
+//! sessions.insert("bob", Duration::from_millis(200));
+//!
+//! Timer::after(Duration::from_millis(50)).await;
+//! assert!(sessions.touch(&"alice"));
+//!
+//! assert_eq!(sessions.next().await, Some("alice"));
+//! assert_eq!(sessions.next().await, Some("bob"));
+//! assert!(sessions.is_empty());
+//! #
+//! # });
+//! ```
+
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                                          Imports                                           │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+use crate::{clock, deadline, DeadlineSource, Sleep};
+use core::fmt;
+use core::hash::Hash;
+use core::pin::Pin;
+use core::task::{Context, Poll};
+use core::time::Duration;
+use futures_core::stream::Stream;
+use std::collections::{BTreeMap, HashMap};
+use std::time::Instant;
+
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                                    struct TimeoutMap<K>                                    │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+struct Entry {
+    deadline: Instant,
+    /// Tells apart the entries of [`TimeoutMap::deadlines`] sharing the same deadline.
+    seq: u64,
+    idle: Duration,
+}
+
+/// A set of keys, each inserted with its own idle timeout, that is also a [`Stream`] yielding
+/// every key once it has been idle for longer than its timeout.
+///
+/// Expired keys are removed from the map as they are yielded. Unlike a
+/// [`TimeoutSet`](crate::set::TimeoutSet), the stream doesn't end when the map is empty, so that
+/// it can be polled alongside the events inserting keys into it.
+pub struct TimeoutMap<K, D = Sleep> {
+    keys: HashMap<K, Entry>,
+    deadlines: BTreeMap<(Instant, u64), K>,
+    next_seq: u64,
+    timer: D,
+    /// The deadline the timer is armed for, if any.
+    armed: Option<Instant>,
+}
+
+impl<K: Eq + Hash + Clone> TimeoutMap<K> {
+    /// Creates a new empty [`TimeoutMap`].
+    pub fn new() -> Self {
+        TimeoutMap::with_source(Sleep::at(clock::now()))
+    }
+}
+
+impl<K: Eq + Hash + Clone, D: DeadlineSource> TimeoutMap<K, D> {
+    /// Creates a new empty [`TimeoutMap`], whose expiries are waited for by rearming the
+    /// provided [`DeadlineSource`] (whose current deadline is ignored).
+    ///
+    /// ## Example
+    ///
+    /// ```rust
+    /// # use futures_lite::future;
+    /// use futures_lite::StreamExt;
+    /// use smol_timeout::map::TimeoutMap;
+    /// use smol_timeout::wheel::TimerWheel;
+    /// use std::time::Duration;
+    ///
+    /// # future::block_on(async {
+    /// #
+    /// let wheel = TimerWheel::new(Duration::from_millis(10));
+    ///
+    /// let mut sessions = TimeoutMap::with_source(wheel.sleep(Duration::from_secs(0)));
+    /// sessions.insert("alice", Duration::from_millis(100));
+    ///
+    /// assert_eq!(sessions.next().await, Some("alice"));
+    /// assert!(sessions.is_empty());
+    /// #
+    /// # });
+    /// ```
+    pub fn with_source(source: D) -> Self {
+        TimeoutMap {
+            keys: HashMap::new(),
+            deadlines: BTreeMap::new(),
+            next_seq: 0,
+            timer: source,
+            armed: None,
+        }
+    }
+
+    /// Inserts the key into the map, expiring once it has been idle for the provided duration,
+    /// and returns whether it was already in it (in which case its idle timeout is replaced).
+    pub fn insert(&mut self, key: K, idle: Duration) -> bool {
+        let present = self.remove(&key);
+
+        let deadline = deadline::deadline_after(self.timer.now(), idle);
+        let seq = self.next_seq;
+        self.next_seq += 1;
+
+        self.deadlines.insert((deadline, seq), key.clone());
+        self.keys.insert(
+            key,
+            Entry {
+                deadline,
+                seq,
+                idle,
+            },
+        );
+
+        present
+    }
+
+    /// Rearms the idle timeout of the key, and returns whether it was in the map.
+    pub fn touch(&mut self, key: &K) -> bool {
+        let idle = match self.keys.get(key) {
+            Some(entry) => entry.idle,
+            None => return false,
+        };
+
+        self.insert(key.clone(), idle)
+    }
+
+    /// Removes the key from the map, and returns whether it was in it.
+    pub fn remove(&mut self, key: &K) -> bool {
+        match self.keys.remove(key) {
+            Some(entry) => {
+                self.deadlines.remove(&(entry.deadline, entry.seq));
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Returns whether the key is in the map.
+    pub fn contains_key(&self, key: &K) -> bool {
+        self.keys.contains_key(key)
+    }
+
+    /// Returns the time left before the key expires, if it is in the map.
+    pub fn expires_in(&self, key: &K) -> Option<Duration> {
+        let now = self.timer.now();
+        self.keys
+            .get(key)
+            .map(|entry| entry.deadline.saturating_duration_since(now))
+    }
+
+    /// Returns the number of keys in the map.
+    pub fn len(&self) -> usize {
+        self.keys.len()
+    }
+
+    /// Returns `true` if the map contains no keys.
+    pub fn is_empty(&self) -> bool {
+        self.keys.is_empty()
+    }
+}
+
+impl<K: Eq + Hash + Clone> Default for TimeoutMap<K> {
+    fn default() -> Self {
+        TimeoutMap::new()
+    }
+}
+
+// The keys are never pinned.
+impl<K, D: Unpin> Unpin for TimeoutMap<K, D> {}
+
+impl<K: fmt::Debug, D> fmt::Debug for TimeoutMap<K, D> {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        fmt.debug_list().entries(self.deadlines.values()).finish()
+    }
+}
+
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                               impl Stream for TimeoutMap<K>                                │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+impl<K: Eq + Hash + Clone, D: DeadlineSource> Stream for TimeoutMap<K, D> {
+    type Item = K;
+
+    fn poll_next(self: Pin<&mut Self>, ctx: &mut Context) -> Poll<Option<Self::Item>> {
+        let this = self.get_mut();
+
+        loop {
+            let (deadline, seq) = match this.deadlines.keys().next() {
+                Some(&first) => first,
+                None => return Poll::Pending,
+            };
+
+            if deadline <= this.timer.now() {
+                let key = this
+                    .deadlines
+                    .remove(&(deadline, seq))
+                    .expect("the earliest deadline was just found");
+
+                this.keys.remove(&key);
+                return Poll::Ready(Some(key));
+            }
+
+            if this.armed != Some(deadline) {
+                this.timer.set_at(deadline);
+                this.armed = Some(deadline);
+            }
+
+            if Pin::new(&mut this.timer).poll(ctx).is_pending() {
+                return Poll::Pending;
+            }
+
+            this.armed = None;
+        }
+    }
+}
@@ -0,0 +1,142 @@
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                                                                                            │ *
+ * │ This Source Code Form is subject to the terms of the Mozilla Public                        │ *
+ * │ License, v. 2.0. If a copy of the MPL was not distributed with this                        │ *
+ * │ file, You can obtain one at http://mozilla.org/MPL/2.0/.                                   │ *
+ * │                                                                                            │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                                       Documentation                                        │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+//! Timeouts recording their outcome with the [`metrics`] crate.
+//!
+//! Every [`MeteredTimeout`] records, labelled with its name (as `name`):
+//!
+//! - a `timeout.completed` counter, incremented when the future completes in time,
+//! - a `timeout.expired` counter, incremented when the timer completes first,
+//! - a `timeout.elapsed_ms` histogram, recording the milliseconds elapsed between the creation
+//!   of the timeout and its completion, in both cases.
+//!
+//! The metrics go to the recorder installed by the application (e.g. a Prometheus exporter),
+//! if any.
+//!
+//! ## Example
+//!
+//! ```rust
+//! use async_io::Timer;
+//! # use futures_lite::future;
+//! use smol_timeout::metrics;
+//! use std::time::Duration;
+//!
+//! # future::block_on(async {
+//! #
+//! let foo = async {
+//!     Timer::after(Duration::from_millis(250)).await;
+//!     24
+//! };
+//!
+//! assert_eq!(metrics::timeout("foo", foo, Duration::from_millis(100)).await, None);
+//!
+//! let bar = async {
+//!     Timer::after(Duration::from_millis(100)).await;
+//!     42
+//! };
+//!
+//! assert_eq!(metrics::timeout("bar", bar, Duration::from_millis(250)).await, Some(42));
+//! #
+//! # });
+//! ```
+
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                                          Imports                                           │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+use crate::clock::Stamp;
+use crate::Timer;
+use ::metrics::{counter, histogram};
+use core::future::Future;
+use core::pin::Pin;
+use core::task::{Context, Poll};
+use core::time::Duration;
+use pin_project_lite::pin_project;
+
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                                        fn record()                                         │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+/// Records the outcome of the timeout with the provided name.
+pub(crate) fn record(name: &'static str, elapsed: Duration, expired: bool) {
+    if expired {
+        counter!("timeout.expired", "name" => name).increment(1);
+    } else {
+        counter!("timeout.completed", "name" => name).increment(1);
+    }
+
+    histogram!("timeout.elapsed_ms", "name" => name).record(elapsed.as_secs_f64() * 1000.);
+}
+
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                                 struct MeteredTimeout<Fut>                                 │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+pin_project! {
+    /// A future polling both another future and a [`Timer`], returning the future's output or
+    /// [`None`] if the timer completes first, and recording which one did.
+    ///
+    /// Created by [`timeout`].
+    #[derive(Debug)]
+    #[must_use = "futures do nothing unless you `.await` or poll them"]
+    pub struct MeteredTimeout<Fut> {
+        #[pin]
+        future: Fut,
+        timer: Timer,
+        name: &'static str,
+        start: Stamp,
+    }
+}
+
+/// Given a name, a future and a [`Duration`], creates and returns a new [`MeteredTimeout`] that
+/// will poll both the future and a [`Timer`] that will complete after the provided duration,
+/// return the future's output or [`None`] if the timer completes first, and record the outcome
+/// under the provided name.
+pub fn timeout<Fut: Future>(
+    name: &'static str,
+    future: Fut,
+    after: Duration,
+) -> MeteredTimeout<Fut> {
+    MeteredTimeout {
+        future,
+        timer: Timer::after(after),
+        name,
+        start: Stamp::now(),
+    }
+}
+
+impl<Fut> MeteredTimeout<Fut> {
+    /// Returns the name the outcome of the timeout is recorded under.
+    pub fn name(&self) -> &'static str {
+        self.name
+    }
+}
+
+impl<Fut: Future> Future for MeteredTimeout<Fut> {
+    type Output = Option<Fut::Output>;
+
+    fn poll(self: Pin<&mut Self>, ctx: &mut Context) -> Poll<Self::Output> {
+        let this = self.project();
+
+        if let Poll::Ready(output) = this.future.poll(ctx) {
+            record(this.name, this.start.elapsed(), false);
+            return Poll::Ready(Some(output));
+        }
+
+        if Pin::new(this.timer).poll(ctx).is_ready() {
+            record(this.name, this.start.elapsed(), true);
+            return Poll::Ready(None);
+        }
+
+        Poll::Pending
+    }
+}
@@ -0,0 +1,241 @@
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                                                                                            │ *
+ * │ This Source Code Form is subject to the terms of the Mozilla Public                        │ *
+ * │ License, v. 2.0. If a copy of the MPL was not distributed with this                        │ *
+ * │ file, You can obtain one at http://mozilla.org/MPL/2.0/.                                   │ *
+ * │                                                                                            │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                                       Documentation                                        │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+//! Timeouts carrying a name, for telemetry telling which operation timed out.
+//!
+//! A [`NamedTimeout`] returns a [`NamedElapsed`] error including its name and configured duration
+//! if its timer completes first. Its outcome is also recorded with the [`metrics`] crate (with the
+//! `metrics` feature, see [`crate::metrics`]) and logged with the [`log`] crate (with the `log`
+//! feature, see [`crate::log`]) or [`defmt`] (with the `defmt` feature) under its name. With the
+//! `registry` feature, pending named timeouts can be listed (see [`crate::registry`]), and with
+//! the `prometheus` feature, their statistics can be exported (see [`crate::prometheus`]). With
+//! the `budget` feature, alerts can be raised when too many of them expire (see
+//! [`crate::budget`]).
+//!
+//! [`metrics`]: https://docs.rs/metrics
+//! [`log`]: https://docs.rs/log
+//! [`defmt`]: https://docs.rs/defmt
+//!
+//! ## Example
+//!
+//! ```rust
+//! use async_io::Timer;
+//! # use futures_lite::future;
+//! use smol_timeout::named::NamedTimeoutExt;
+//! use std::time::Duration;
+//!
+//! # future::block_on(async {
+//! #
+//! let foo = async {
+//!     Timer::after(Duration::from_millis(250)).await;
+//!     24
+//! };
+//!
+//! let err = foo
+//!     .timeout_named(Duration::from_millis(100), "db.get_user")
+//!     .await
+//!     .unwrap_err();
+//!
+//! assert_eq!(err.name(), "db.get_user");
+//! assert_eq!(err.to_string(), "`db.get_user` timed out after 100ms");
+//!
+//! let bar = async {
+//!     Timer::after(Duration::from_millis(100)).await;
+//!     42
+//! };
+//!
+//! let bar = bar.timeout_named(Duration::from_millis(250), "db.get_post");
+//! assert_eq!(bar.await, Ok(42));
+//! #
+//! # });
+//! ```
+
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                                          Imports                                           │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+use crate::{clock, deadline, Elapsed, Timer};
+use core::fmt;
+use core::future::Future;
+use core::pin::Pin;
+use core::task::{Context, Poll};
+use core::time::Duration;
+use pin_project_lite::pin_project;
+use std::time::Instant;
+
+#[cfg(feature = "registry")]
+use crate::registry::Registration;
+
+/// Without the `registry` feature, timeouts aren't registered anywhere.
+#[cfg(not(feature = "registry"))]
+#[derive(Debug)]
+struct Registration;
+
+#[cfg(not(feature = "registry"))]
+impl Registration {
+    fn new(_: &'static str, _: Instant, _: Instant) -> Self {
+        Registration
+    }
+}
+
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                                    struct NamedElapsed                                     │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+/// The error returned by a [`NamedTimeout`] whose timer completed first.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct NamedElapsed {
+    name: &'static str,
+    after: Duration,
+}
+
+impl NamedElapsed {
+    /// Returns the name of the timeout.
+    pub fn name(&self) -> &'static str {
+        self.name
+    }
+
+    /// Returns the duration the timeout was configured for.
+    pub fn after(&self) -> Duration {
+        self.after
+    }
+}
+
+impl From<NamedElapsed> for Elapsed {
+    fn from(_: NamedElapsed) -> Self {
+        Elapsed::new()
+    }
+}
+
+impl fmt::Display for NamedElapsed {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        write!(fmt, "`{}` timed out after {:?}", self.name, self.after)
+    }
+}
+
+impl std::error::Error for NamedElapsed {}
+
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                                  struct NamedTimeout<Fut>                                  │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+pin_project! {
+    /// A future polling both another future and a [`Timer`] that will complete after a specified
+    /// timeout, and returning the future's output or a [`NamedElapsed`] error if the timer
+    /// completes first.
+    #[derive(Debug)]
+    #[must_use = "futures do nothing unless you `.await` or poll them"]
+    pub struct NamedTimeout<Fut> {
+        #[pin]
+        future: Fut,
+        timer: Timer,
+        name: &'static str,
+        after: Duration,
+        start: Instant,
+        registration: Registration,
+    }
+}
+
+impl<Fut> NamedTimeout<Fut> {
+    /// Returns the name of the timeout.
+    pub fn name(&self) -> &'static str {
+        self.name
+    }
+}
+
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                               trait NamedTimeoutExt: Future                                │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+/// An extension trait for [`Future`]s that provides a way to create [`NamedTimeout`]s.
+pub trait NamedTimeoutExt: Future {
+    /// Given a [`Duration`] and a name, creates and returns a new [`NamedTimeout`] that will
+    /// poll both the future and a [`Timer`] that will complete after the provided duration, and
+    /// return the future's output or a [`NamedElapsed`] error including the provided name if
+    /// the timer completes first.
+    fn timeout_named(self, after: Duration, name: &'static str) -> NamedTimeout<Self>
+    where
+        Self: Sized,
+    {
+        let start = clock::now();
+
+        NamedTimeout {
+            future: self,
+            timer: Timer::after(after),
+            name,
+            after,
+            start,
+            registration: Registration::new(name, start, deadline::deadline_after(start, after)),
+        }
+    }
+}
+
+impl<Fut: Future> NamedTimeoutExt for Fut {}
+
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                             impl Future for NamedTimeout<Fut>                              │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+impl<Fut: Future> Future for NamedTimeout<Fut> {
+    type Output = Result<Fut::Output, NamedElapsed>;
+
+    fn poll(self: Pin<&mut Self>, ctx: &mut Context) -> Poll<Self::Output> {
+        let this = self.project();
+
+        if let Poll::Ready(output) = this.future.poll(ctx) {
+            let _elapsed = clock::now().saturating_duration_since(*this.start);
+
+            #[cfg(feature = "metrics")]
+            crate::metrics::record(this.name, _elapsed, false);
+
+            #[cfg(feature = "prometheus")]
+            crate::prometheus::record(this.name, _elapsed, false);
+
+            #[cfg(feature = "budget")]
+            crate::budget::record(this.name, false);
+
+            return Poll::Ready(Ok(output));
+        }
+
+        if Pin::new(this.timer).poll(ctx).is_ready() {
+            let _elapsed = clock::now().saturating_duration_since(*this.start);
+
+            #[cfg(feature = "metrics")]
+            crate::metrics::record(this.name, _elapsed, true);
+
+            #[cfg(feature = "prometheus")]
+            crate::prometheus::record(this.name, _elapsed, true);
+
+            #[cfg(feature = "budget")]
+            crate::budget::record(this.name, true);
+
+            #[cfg(feature = "log")]
+            crate::log::expired(this.name, *this.after, _elapsed);
+
+            #[cfg(feature = "defmt")]
+            defmt::warn!(
+                "timeout `{=str}` expired after {} (configured for {})",
+                this.name,
+                _elapsed,
+                *this.after
+            );
+
+            return Poll::Ready(Err(NamedElapsed {
+                name: this.name,
+                after: *this.after,
+            }));
+        }
+
+        Poll::Pending
+    }
+}
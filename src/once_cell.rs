@@ -0,0 +1,427 @@
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                                                                                            │ *
+ * │ This Source Code Form is subject to the terms of the Mozilla Public                        │ *
+ * │ License, v. 2.0. If a copy of the MPL was not distributed with this                        │ *
+ * │ file, You can obtain one at http://mozilla.org/MPL/2.0/.                                   │ *
+ * │                                                                                            │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                                       Documentation                                        │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+//! An asynchronous once-cell whose initialization can be awaited with a timeout.
+//!
+//! The initializer of a [`OnceCell`] isn't owned by the task that started it: it is stored in
+//! the cell and driven by whichever tasks are waiting on the cell. A task whose wait times out
+//! simply stops driving it, and the initializer keeps making progress as long as other tasks are
+//! waiting (or resumes when the next one starts waiting), so that its late completion still
+//! populates the cell.
+//!
+//! Nothing drives the initializer in the background while no task is waiting on the cell.
+//! Instead, [`OnceCell::get`] polls it once, so that a completion that happened meanwhile (e.g.
+//! because its timer elapsed or its response arrived) is picked up. If polling the initializer
+//! panics, the cell goes back to having no initializer, and the next wait starts its own.
+//!
+//! ## Example
+//!
+//! ```rust
+//! use async_io::Timer;
+//! # use futures_lite::future;
+//! use smol_timeout::once_cell::OnceCell;
+//! use smol_timeout::Elapsed;
+//! use std::time::Duration;
+//!
+//! # future::block_on(async {
+//! #
+//! let cell = OnceCell::new();
+//!
+//! let init = || async {
+//!     Timer::after(Duration::from_millis(250)).await;
+//!     42
+//! };
+//!
+//! // The first wait times out, but the initializer keeps running...
+//! let value = cell.get_or_init_timeout(init, Duration::from_millis(100)).await;
+//! assert_eq!(value, Err(Elapsed::new()));
+//!
+//! // ...and the next wait picks it up where it left instead of starting a new one.
+//! let value = cell.get_or_init_timeout(|| async { 24 }, Duration::from_millis(500)).await;
+//! assert_eq!(value, Ok(&42));
+//! #
+//! # });
+//! ```
+
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                                          Imports                                           │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+use crate::waker_set::WakerSet;
+use crate::{Elapsed, Timer};
+use core::convert::Infallible;
+use core::fmt;
+use core::future::{self, Future};
+use core::mem;
+use core::pin::Pin;
+use core::task::{Context, Poll};
+use core::time::Duration;
+use std::boxed::Box;
+use std::sync::{Arc, Mutex, OnceLock};
+use std::thread;
+
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                                  enum InitTimeoutError<E>                                  │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+/// The error returned by [`OnceCell::get_or_try_init_timeout`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum InitTimeoutError<E> {
+    /// The cell wasn't initialized before the timeout.
+    Elapsed,
+    /// The initializer started by this call failed.
+    Failed(E),
+}
+
+impl<E: fmt::Display> fmt::Display for InitTimeoutError<E> {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            InitTimeoutError::Elapsed => fmt::Display::fmt(&Elapsed::new(), fmt),
+            InitTimeoutError::Failed(err) => write!(fmt, "initialization failed: {}", err),
+        }
+    }
+}
+
+impl<E: fmt::Debug + fmt::Display> std::error::Error for InitTimeoutError<E> {}
+
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                                     struct OnceCell<T>                                     │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+type Init<T> = Pin<Box<dyn Future<Output = Option<T>> + Send>>;
+
+enum State<T> {
+    /// No initializer is running.
+    Idle,
+    /// An initializer is running and no task is currently polling it.
+    Initializing(Init<T>),
+    /// A task is currently polling the initializer.
+    Polling,
+}
+
+/// An asynchronous cell that can be initialized at most once, and whose initialization can be
+/// awaited with a timeout.
+pub struct OnceCell<T> {
+    value: OnceLock<T>,
+    state: Mutex<State<T>>,
+    wakers: Arc<WakerSet>,
+}
+
+impl<T: Send + 'static> OnceCell<T> {
+    /// Creates a new uninitialized [`OnceCell`].
+    pub fn new() -> Self {
+        OnceCell {
+            value: OnceLock::new(),
+            state: Mutex::new(State::Idle),
+            wakers: WakerSet::new(),
+        }
+    }
+
+    /// Returns the cell's value, or [`None`] if it isn't initialized yet.
+    ///
+    /// If an initializer is running and no task is polling it, it is polled once, so that its
+    /// late completion populates the cell.
+    ///
+    /// ## Example
+    ///
+    /// ```rust
+    /// use async_io::Timer;
+    /// # use futures_lite::future;
+    /// use smol_timeout::once_cell::OnceCell;
+    /// use smol_timeout::Elapsed;
+    /// use std::time::Duration;
+    ///
+    /// # future::block_on(async {
+    /// #
+    /// let cell = OnceCell::new();
+    ///
+    /// let init = || async {
+    ///     Timer::after(Duration::from_millis(100)).await;
+    ///     42
+    /// };
+    ///
+    /// let value = cell.get_or_init_timeout(init, Duration::from_millis(50)).await;
+    /// assert_eq!(value, Err(Elapsed::new()));
+    /// assert_eq!(cell.get(), None);
+    ///
+    /// // No task is waiting on the cell anymore, but the initializer's timer elapsed.
+    /// Timer::after(Duration::from_millis(100)).await;
+    /// assert_eq!(cell.get(), Some(&42));
+    /// #
+    /// # });
+    /// ```
+    pub fn get(&self) -> Option<&T> {
+        if let Some(value) = self.value.get() {
+            return Some(value);
+        }
+
+        let running = {
+            let mut state = self.state.lock().unwrap();
+            match mem::replace(&mut *state, State::Polling) {
+                State::Initializing(running) => running,
+                other => {
+                    *state = other;
+                    return None;
+                }
+            }
+        };
+
+        self.drive(running);
+        self.value.get()
+    }
+
+    /// Returns `true` if the cell is initialized, polling its initializer like
+    /// [`OnceCell::get`].
+    pub fn is_initialized(&self) -> bool {
+        self.get().is_some()
+    }
+
+    /// Returns the cell's value, initializing it using `init` if no initializer is running yet,
+    /// or returns [`Elapsed`] if the cell isn't initialized before the provided duration.
+    ///
+    /// See the [module-level documentation](self) for what happens to the initializer when the
+    /// wait times out.
+    ///
+    /// ## Example
+    ///
+    /// ```rust
+    /// # use futures_lite::future;
+    /// use smol_timeout::once_cell::OnceCell;
+    /// use std::panic::{self, AssertUnwindSafe};
+    /// use std::time::Duration;
+    ///
+    /// let cell = OnceCell::new();
+    ///
+    /// let init = || async {
+    ///     if true {
+    ///         panic!("oops");
+    ///     }
+    ///
+    ///     24
+    /// };
+    ///
+    /// let wait = cell.get_or_init_timeout(init, Duration::from_millis(100));
+    /// let panicked = panic::catch_unwind(AssertUnwindSafe(|| future::block_on(wait)));
+    /// assert!(panicked.is_err());
+    ///
+    /// // The panicking initializer was discarded.
+    /// let wait = cell.get_or_init_timeout(|| async { 42 }, Duration::from_millis(100));
+    /// assert_eq!(future::block_on(wait), Ok(&42));
+    /// ```
+    pub async fn get_or_init_timeout<F, Fut>(&self, init: F, after: Duration) -> Result<&T, Elapsed>
+    where
+        F: FnOnce() -> Fut,
+        Fut: Future<Output = T> + Send + 'static,
+    {
+        let init = || {
+            let init = init();
+            async move { Ok::<_, Infallible>(init.await) }
+        };
+
+        self.get_or_try_init_timeout(init, after)
+            .await
+            .map_err(|_| Elapsed::new())
+    }
+
+    /// Returns the cell's value, initializing it using `init` if no initializer is running yet,
+    /// or returns an error if the cell isn't initialized before the provided duration or the
+    /// initializer started by this call fails.
+    ///
+    /// When an initializer fails, the cell stays uninitialized and the next task waiting on it
+    /// starts its own initializer.
+    ///
+    /// ## Example
+    ///
+    /// ```rust
+    /// # use futures_lite::future;
+    /// use smol_timeout::once_cell::{InitTimeoutError, OnceCell};
+    /// use std::time::Duration;
+    ///
+    /// # future::block_on(async {
+    /// #
+    /// let cell = OnceCell::new();
+    ///
+    /// let value = cell
+    ///     .get_or_try_init_timeout(|| async { Err("oops") }, Duration::from_millis(100))
+    ///     .await;
+    /// assert_eq!(value, Err(InitTimeoutError::Failed("oops")));
+    ///
+    /// let value = cell
+    ///     .get_or_try_init_timeout(|| async { Ok::<_, ()>(42) }, Duration::from_millis(100))
+    ///     .await;
+    /// assert_eq!(value, Ok(&42));
+    /// #
+    /// # })
+    /// ```
+    pub async fn get_or_try_init_timeout<F, Fut, E>(
+        &self,
+        init: F,
+        after: Duration,
+    ) -> Result<&T, InitTimeoutError<E>>
+    where
+        F: FnOnce() -> Fut,
+        Fut: Future<Output = Result<T, E>> + Send + 'static,
+        E: Send + 'static,
+    {
+        if let Some(value) = self.value.get() {
+            return Ok(value);
+        }
+
+        let mut init = Some(init);
+        let error = Arc::new(Mutex::new(None));
+
+        let registration = Registration {
+            wakers: &self.wakers,
+            key: self.wakers.key(),
+        };
+
+        let mut timer = Timer::after(after);
+
+        future::poll_fn(|ctx| {
+            loop {
+                if let Some(value) = self.value.get() {
+                    return Poll::Ready(Ok(value));
+                }
+
+                if let Some(err) = error.lock().unwrap().take() {
+                    return Poll::Ready(Err(InitTimeoutError::Failed(err)));
+                }
+
+                self.wakers.register(registration.key, ctx.waker());
+
+                let mut state = self.state.lock().unwrap();
+                let running = match mem::replace(&mut *state, State::Polling) {
+                    State::Initializing(running) => running,
+                    State::Idle => match init.take() {
+                        Some(init) => {
+                            let error = error.clone();
+                            let init = init();
+
+                            Box::pin(async move {
+                                match init.await {
+                                    Ok(value) => Some(value),
+                                    Err(err) => {
+                                        *error.lock().unwrap() = Some(err);
+                                        None
+                                    }
+                                }
+                            }) as Init<T>
+                        }
+                        None => {
+                            // Our initializer already completed, so the loop will return.
+                            *state = State::Idle;
+                            continue;
+                        }
+                    },
+                    State::Polling => break,
+                };
+
+                drop(state);
+
+                if !self.drive(running) {
+                    break;
+                }
+            }
+
+            if Pin::new(&mut timer).poll(ctx).is_ready() {
+                return Poll::Ready(Err(InitTimeoutError::Elapsed));
+            }
+
+            Poll::Pending
+        })
+        .await
+    }
+}
+
+impl<T> OnceCell<T> {
+    /// Polls the initializer taken out of the cell's state (which was left as
+    /// [`State::Polling`]), and either stores it back or populates the cell, returning whether
+    /// it completed.
+    fn drive(&self, mut running: Init<T>) -> bool {
+        let guard = ResetOnUnwind { cell: self };
+
+        // The initializer is polled with a waker waking all the waiting tasks, so that it keeps
+        // being driven even if the task polling it stops waiting.
+        let waker = self.wakers.waker();
+        let polled = running.as_mut().poll(&mut Context::from_waker(&waker));
+        drop(guard);
+
+        let mut state = self.state.lock().unwrap();
+        match polled {
+            Poll::Pending => {
+                *state = State::Initializing(running);
+                false
+            }
+            Poll::Ready(value) => {
+                if let Some(value) = value {
+                    let _ = self.value.set(value);
+                }
+
+                *state = State::Idle;
+                drop(state);
+                self.wakers.wake_all();
+                true
+            }
+        }
+    }
+}
+
+impl<T: Send + 'static> Default for OnceCell<T> {
+    fn default() -> Self {
+        OnceCell::new()
+    }
+}
+
+impl<T: fmt::Debug> fmt::Debug for OnceCell<T> {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        fmt.debug_struct("OnceCell")
+            .field("value", &self.value.get())
+            .finish()
+    }
+}
+
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                                  struct Registration<'a>                                   │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+/// Removes a waiting task's waker from its cell's [`WakerSet`] when the task stops waiting.
+struct Registration<'a> {
+    wakers: &'a WakerSet,
+    key: u64,
+}
+
+impl Drop for Registration<'_> {
+    fn drop(&mut self) {
+        self.wakers.remove(self.key);
+    }
+}
+
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                                struct ResetOnUnwind<'a, T>                                 │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+/// Resets the state of a cell if polling its initializer panics, so that the cell isn't left as
+/// [`State::Polling`] forever, and wakes the waiting tasks up so that one of them starts its own
+/// initializer.
+struct ResetOnUnwind<'a, T> {
+    cell: &'a OnceCell<T>,
+}
+
+impl<T> Drop for ResetOnUnwind<'_, T> {
+    fn drop(&mut self) {
+        if thread::panicking() {
+            *self.cell.state.lock().unwrap() = State::Idle;
+            self.cell.wakers.wake_all();
+        }
+    }
+}
@@ -0,0 +1,226 @@
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                                                                                            │ *
+ * │ This Source Code Form is subject to the terms of the Mozilla Public                        │ *
+ * │ License, v. 2.0. If a copy of the MPL was not distributed with this                        │ *
+ * │ file, You can obtain one at http://mozilla.org/MPL/2.0/.                                   │ *
+ * │                                                                                            │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                                       Documentation                                        │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+//! A way to find slow futures without cancelling them.
+//!
+//! Cancelling a future isn't always safe (e.g. in the middle of a transaction). An [`Overdue`]
+//! future never cancels its future, but calls a callback (or logs a warning, with the `log`
+//! feature) with an [`OverdueEvent::Exceeded`] the first time it runs for longer than a
+//! threshold, then with an [`OverdueEvent::Completed`] including the total time elapsed once it
+//! completes, to find the slow paths of a program in production.
+//!
+//! ## Example
+//!
+//! ```rust
+//! use async_io::Timer;
+//! # use futures_lite::future;
+//! use smol_timeout::overdue::{OverdueEvent, OverdueExt};
+//! use std::time::Duration;
+//!
+//! # future::block_on(async {
+//! #
+//! let mut events = Vec::new();
+//!
+//! let foo = async {
+//!     Timer::after(Duration::from_millis(250)).await;
+//!     42
+//! };
+//!
+//! let foo = foo.on_overdue(Duration::from_millis(100), |event| events.push(event));
+//! assert_eq!(foo.await, 42);
+//!
+//! assert!(matches!(events[0], OverdueEvent::Exceeded(_)));
+//! match events[1] {
+//!     OverdueEvent::Completed(elapsed) => assert!(elapsed >= Duration::from_millis(250)),
+//!     _ => unreachable!(),
+//! }
+//! #
+//! # });
+//! ```
+
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                                          Imports                                           │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+use crate::{clock, Timer};
+use core::fmt;
+use core::future::Future;
+use core::pin::Pin;
+use core::task::{Context, Poll};
+use core::time::Duration;
+use pin_project_lite::pin_project;
+use std::time::Instant;
+
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                                     enum OverdueEvent                                      │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+/// An event reported by an [`Overdue`] future to its callback.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum OverdueEvent {
+    /// The future ran for longer than the threshold, after this much time.
+    Exceeded(Duration),
+    /// The future completed after exceeding the threshold, after this much time in total.
+    Completed(Duration),
+}
+
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                                   struct Overdue<Fut, F>                                   │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+pin_project! {
+    /// A future polling another future to completion, and calling a callback once it runs for
+    /// longer than a threshold, then once it completes.
+    #[must_use = "futures do nothing unless you `.await` or poll them"]
+    pub struct Overdue<Fut, F> {
+        #[pin]
+        future: Fut,
+        timer: Timer,
+        threshold: Duration,
+        start: Instant,
+        exceeded: bool,
+        callback: F,
+    }
+}
+
+impl<Fut, F> fmt::Debug for Overdue<Fut, F> {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        fmt.debug_struct("Overdue")
+            .field("threshold", &self.threshold)
+            .field("exceeded", &self.exceeded)
+            .finish()
+    }
+}
+
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                                  trait OverdueExt: Future                                  │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+/// An extension trait for [`Future`]s that provides a way to create [`Overdue`]s.
+pub trait OverdueExt: Future {
+    /// Given a threshold and a callback, creates and returns a new [`Overdue`] that will poll
+    /// the future to completion, and call the callback once the future runs for longer than
+    /// the threshold, then once it completes.
+    fn on_overdue<F>(self, threshold: Duration, callback: F) -> Overdue<Self, F>
+    where
+        Self: Sized,
+        F: FnMut(OverdueEvent),
+    {
+        let start = clock::now();
+
+        Overdue {
+            future: self,
+            timer: Timer::at(start + threshold),
+            threshold,
+            start,
+            exceeded: false,
+            callback,
+        }
+    }
+
+    /// Given a threshold and a name, creates and returns a new [`Overdue`] that will poll the
+    /// future to completion, and log a warning including the name once the future runs for
+    /// longer than the threshold, then once it completes.
+    ///
+    /// Only available with the `log` feature.
+    #[cfg(feature = "log")]
+    fn log_overdue(self, threshold: Duration, name: &'static str) -> Overdue<Self, OverdueLogger>
+    where
+        Self: Sized,
+    {
+        let start = clock::now();
+
+        Overdue {
+            future: self,
+            timer: Timer::at(start + threshold),
+            threshold,
+            start,
+            exceeded: false,
+            callback: OverdueLogger { name },
+        }
+    }
+}
+
+impl<Fut: Future> OverdueExt for Fut {}
+
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                              impl Future for Overdue<Fut, F>                               │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+impl<Fut: Future, F: OverdueCallback> Future for Overdue<Fut, F> {
+    type Output = Fut::Output;
+
+    fn poll(self: Pin<&mut Self>, ctx: &mut Context) -> Poll<Self::Output> {
+        let this = self.project();
+
+        if let Poll::Ready(output) = this.future.poll(ctx) {
+            if *this.exceeded {
+                let elapsed = clock::now().saturating_duration_since(*this.start);
+                this.callback.call(OverdueEvent::Completed(elapsed));
+            }
+
+            return Poll::Ready(output);
+        }
+
+        if !*this.exceeded && Pin::new(&mut *this.timer).poll(ctx).is_ready() {
+            *this.exceeded = true;
+
+            let elapsed = clock::now().saturating_duration_since(*this.start);
+            this.callback.call(OverdueEvent::Exceeded(elapsed));
+        }
+
+        Poll::Pending
+    }
+}
+
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                                   trait OverdueCallback                                    │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+/// The callbacks [`Overdue`]s can call, implemented by closures and [`OverdueLogger`].
+pub trait OverdueCallback {
+    /// Called once the future ran for longer than the threshold, then once it completes.
+    fn call(&mut self, event: OverdueEvent);
+}
+
+impl<F: FnMut(OverdueEvent)> OverdueCallback for F {
+    fn call(&mut self, event: OverdueEvent) {
+        self(event)
+    }
+}
+
+/// An [`OverdueCallback`] logging a warning once the future ran for longer than the threshold,
+/// then once it completes (see [`OverdueExt::log_overdue`]).
+///
+/// Only available with the `log` feature.
+#[cfg(feature = "log")]
+#[derive(Debug, Clone, Copy)]
+pub struct OverdueLogger {
+    name: &'static str,
+}
+
+#[cfg(feature = "log")]
+impl OverdueCallback for OverdueLogger {
+    fn call(&mut self, event: OverdueEvent) {
+        match event {
+            OverdueEvent::Exceeded(elapsed) => ::log::warn!(
+                target: "smol_timeout",
+                "`{}` is still running after {:?}", self.name, elapsed
+            ),
+            OverdueEvent::Completed(elapsed) => ::log::warn!(
+                target: "smol_timeout",
+                "`{}` completed after {:?}", self.name, elapsed
+            ),
+        }
+    }
+}
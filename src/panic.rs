@@ -0,0 +1,155 @@
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                                                                                            │ *
+ * │ This Source Code Form is subject to the terms of the Mozilla Public                        │ *
+ * │ License, v. 2.0. If a copy of the MPL was not distributed with this                        │ *
+ * │ file, You can obtain one at http://mozilla.org/MPL/2.0/.                                   │ *
+ * │                                                                                            │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                                       Documentation                                        │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+//! Timeouts panicking when they expire, for tests.
+//!
+//! An async test hanging on an `.await` runs until the test harness (or CI) kills it, with no
+//! indication of what it was waiting on. A [`PanicTimeout`] instead panics once its timer
+//! completes, with a message including its name, its configured duration, the time that
+//! actually elapsed and where it was created, so that the test fails loudly at the offending
+//! `.await`. With the `registry` feature, the message also lists the named timeouts that were
+//! pending (see [`crate::registry`]).
+//!
+//! With the `macros` feature, the [`timeout`](crate::timeout) attribute wraps the body of an
+//! async test in a [`PanicTimeout`].
+//!
+//! ## Example
+//!
+//! ```rust
+//! use async_io::Timer;
+//! # use futures_lite::future;
+//! use smol_timeout::panic::PanicTimeoutExt;
+//! use std::time::Duration;
+//!
+//! # future::block_on(async {
+//! #
+//! let foo = async {
+//!     Timer::after(Duration::from_millis(100)).await;
+//!     42
+//! };
+//!
+//! let foo = foo.timeout_or_panic(Duration::from_millis(250), "foo");
+//! assert_eq!(foo.await, 42);
+//! #
+//! # });
+//! ```
+
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                                          Imports                                           │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+use crate::clock::Stamp;
+use crate::Timer;
+use core::future::Future;
+use core::panic::Location;
+use core::pin::Pin;
+use core::task::{Context, Poll};
+use core::time::Duration;
+use pin_project_lite::pin_project;
+
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                                  struct PanicTimeout<Fut>                                  │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+pin_project! {
+    /// A future polling both another future and a [`Timer`] that will complete after a specified
+    /// timeout, and returning the future's output or panicking if the timer completes first.
+    #[derive(Debug)]
+    #[must_use = "futures do nothing unless you `.await` or poll them"]
+    pub struct PanicTimeout<Fut> {
+        #[pin]
+        future: Fut,
+        timer: Timer,
+        name: &'static str,
+        after: Duration,
+        start: Stamp,
+        location: &'static Location<'static>,
+    }
+}
+
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                               trait PanicTimeoutExt: Future                                │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+/// An extension trait for [`Future`]s that provides a way to create [`PanicTimeout`]s.
+pub trait PanicTimeoutExt: Future {
+    /// Given a [`Duration`] and a name, creates and returns a new [`PanicTimeout`] that will
+    /// poll both the future and a [`Timer`] that will complete after the provided duration, and
+    /// return the future's output or panic with a message including the provided name if the
+    /// timer completes first.
+    ///
+    /// ## Example
+    ///
+    /// ```rust,should_panic
+    /// use async_io::Timer;
+    /// # use futures_lite::future;
+    /// use smol_timeout::panic::PanicTimeoutExt;
+    /// use std::time::Duration;
+    ///
+    /// # future::block_on(async {
+    /// #
+    /// let foo = async {
+    ///     Timer::after(Duration::from_millis(250)).await;
+    ///     24
+    /// };
+    ///
+    /// // Panics with "`foo` timed out after 100.2ms (configured for 100ms), created at ...".
+    /// foo.timeout_or_panic(Duration::from_millis(100), "foo").await;
+    /// #
+    /// # });
+    /// ```
+    #[track_caller]
+    fn timeout_or_panic(self, after: Duration, name: &'static str) -> PanicTimeout<Self>
+    where
+        Self: Sized,
+    {
+        PanicTimeout {
+            future: self,
+            timer: Timer::after(after),
+            name,
+            after,
+            start: Stamp::now(),
+            location: Location::caller(),
+        }
+    }
+}
+
+impl<Fut: Future> PanicTimeoutExt for Fut {}
+
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                             impl Future for PanicTimeout<Fut>                              │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+impl<Fut: Future> Future for PanicTimeout<Fut> {
+    type Output = Fut::Output;
+
+    fn poll(self: Pin<&mut Self>, ctx: &mut Context) -> Poll<Self::Output> {
+        let this = self.project();
+
+        if let Poll::Ready(output) = this.future.poll(ctx) {
+            return Poll::Ready(output);
+        }
+
+        if Pin::new(this.timer).poll(ctx).is_ready() {
+            panic!(
+                "`{}` timed out after {:?} (configured for {:?}), created at {}{}",
+                this.name,
+                this.start.elapsed(),
+                this.after,
+                this.location,
+                crate::__stuck(),
+            );
+        }
+
+        Poll::Pending
+    }
+}
@@ -0,0 +1,234 @@
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                                                                                            │ *
+ * │ This Source Code Form is subject to the terms of the Mozilla Public                        │ *
+ * │ License, v. 2.0. If a copy of the MPL was not distributed with this                        │ *
+ * │ file, You can obtain one at http://mozilla.org/MPL/2.0/.                                   │ *
+ * │                                                                                            │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                                       Documentation                                        │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+//! A map correlating requests with their responses, where each pending request has its own
+//! timeout.
+//!
+//! This is the building block of multiplexed protocol clients: a request is registered with its
+//! id before being sent, the task receiving responses completes the request with the matching
+//! id, and the task waiting for the response gets it or [`Elapsed`] if it doesn't arrive in time.
+//!
+//! ## Example
+//!
+//! ```rust
+//! # use futures_lite::future;
+//! use smol_timeout::pending::PendingMap;
+//! use smol_timeout::Elapsed;
+//! use std::time::Duration;
+//!
+//! # future::block_on(async {
+//! #
+//! let map = PendingMap::new();
+//!
+//! let foo = map.register(1, Duration::from_millis(100)).unwrap();
+//! assert_eq!(foo.await, Err(Elapsed::new()));
+//!
+//! let bar = map.register(2, Duration::from_millis(100)).unwrap();
+//! assert_eq!(map.complete(&2, "bar"), Ok(()));
+//! assert_eq!(bar.await, Ok("bar"));
+//!
+//! assert_eq!(map.complete(&1, "foo"), Err("foo"));
+//! assert!(map.is_empty());
+//! #
+//! # });
+//! ```
+
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                                          Imports                                           │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+use crate::{Elapsed, Timer};
+use core::fmt;
+use core::future::Future;
+use core::hash::Hash;
+use core::pin::Pin;
+use core::task::{Context, Poll, Waker};
+use core::time::Duration;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                                       struct Slot<T>                                       │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+struct Slot<T> {
+    response: Option<T>,
+    waker: Option<Waker>,
+}
+
+type Slots<Id, T> = Arc<Mutex<HashMap<Id, Slot<T>>>>;
+
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                                  struct PendingMap<Id, T>                                  │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+/// A map of pending requests, identified by ids of type `Id` and waiting for responses of type
+/// `T`.
+///
+/// Cloning a [`PendingMap`] returns a new handle to the same map.
+pub struct PendingMap<Id, T> {
+    slots: Slots<Id, T>,
+}
+
+impl<Id: Eq + Hash + Clone, T> PendingMap<Id, T> {
+    /// Creates a new empty [`PendingMap`].
+    pub fn new() -> Self {
+        PendingMap {
+            slots: Arc::new(Mutex::new(HashMap::new())),
+        }
+    }
+
+    /// Registers a new pending request, returning a [`Pending`] future that will complete with
+    /// its response or with [`Elapsed`] if it isn't completed before the provided duration, or
+    /// [`None`] if a request with the same id is already pending.
+    ///
+    /// The request is removed from the map once its [`Pending`] future completes or is dropped.
+    pub fn register(&self, id: Id, after: Duration) -> Option<Pending<Id, T>> {
+        let mut slots = self.slots.lock().unwrap();
+        if slots.contains_key(&id) {
+            return None;
+        }
+
+        slots.insert(
+            id.clone(),
+            Slot {
+                response: None,
+                waker: None,
+            },
+        );
+
+        Some(Pending {
+            slots: self.slots.clone(),
+            id: Some(id),
+            timer: Timer::after(after),
+        })
+    }
+
+    /// Completes the pending request with the provided id, waking its [`Pending`] future, or
+    /// returns the response if no such request is pending (e.g. because it timed out).
+    pub fn complete(&self, id: &Id, response: T) -> Result<(), T> {
+        let mut slots = self.slots.lock().unwrap();
+        let slot = match slots.get_mut(id) {
+            Some(slot) if slot.response.is_none() => slot,
+            _ => return Err(response),
+        };
+
+        slot.response = Some(response);
+        if let Some(waker) = slot.waker.take() {
+            waker.wake();
+        }
+
+        Ok(())
+    }
+
+    /// Returns `true` if a request with the provided id is pending.
+    pub fn contains(&self, id: &Id) -> bool {
+        self.slots.lock().unwrap().contains_key(id)
+    }
+
+    /// Returns the number of pending requests.
+    pub fn len(&self) -> usize {
+        self.slots.lock().unwrap().len()
+    }
+
+    /// Returns `true` if there are no pending requests.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+impl<Id: Eq + Hash + Clone, T> Default for PendingMap<Id, T> {
+    fn default() -> Self {
+        PendingMap::new()
+    }
+}
+
+impl<Id, T> Clone for PendingMap<Id, T> {
+    fn clone(&self) -> Self {
+        PendingMap {
+            slots: self.slots.clone(),
+        }
+    }
+}
+
+impl<Id, T> fmt::Debug for PendingMap<Id, T> {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        fmt.debug_struct("PendingMap").finish()
+    }
+}
+
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                                   struct Pending<Id, T>                                    │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+/// A future waiting for the response of a request registered in a [`PendingMap`], or for its
+/// timeout (see [`PendingMap::register`]).
+#[must_use = "futures do nothing unless you `.await` or poll them"]
+pub struct Pending<Id: Eq + Hash, T> {
+    slots: Slots<Id, T>,
+    /// The id of the request, or [`None`] once it was removed from the map.
+    id: Option<Id>,
+    timer: Timer,
+}
+
+impl<Id: Eq + Hash, T> Pending<Id, T> {
+    /// Returns the id of the request, or [`None`] if the future already completed.
+    pub fn id(&self) -> Option<&Id> {
+        self.id.as_ref()
+    }
+}
+
+impl<Id: Eq + Hash, T> Unpin for Pending<Id, T> {}
+
+impl<Id: Eq + Hash, T> Future for Pending<Id, T> {
+    type Output = Result<T, Elapsed>;
+
+    fn poll(mut self: Pin<&mut Self>, ctx: &mut Context) -> Poll<Self::Output> {
+        let this = &mut *self;
+        let id = match &this.id {
+            Some(id) => id,
+            None => panic!("`Pending` polled after completion"),
+        };
+
+        let mut slots = this.slots.lock().unwrap();
+        let slot = slots
+            .get_mut(id)
+            .expect("pending request missing from its map");
+
+        if slot.response.is_none() && Pin::new(&mut this.timer).poll(ctx).is_pending() {
+            slot.waker = Some(ctx.waker().clone());
+            return Poll::Pending;
+        }
+
+        let slot = slots.remove(id).unwrap();
+        drop(slots);
+        this.id = None;
+
+        Poll::Ready(slot.response.ok_or(Elapsed::new()))
+    }
+}
+
+impl<Id: Eq + Hash, T> Drop for Pending<Id, T> {
+    fn drop(&mut self) {
+        if let Some(id) = &self.id {
+            if let Ok(mut slots) = self.slots.lock() {
+                slots.remove(id);
+            }
+        }
+    }
+}
+
+impl<Id: Eq + Hash + fmt::Debug, T> fmt::Debug for Pending<Id, T> {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        fmt.debug_struct("Pending").field("id", &self.id).finish()
+    }
+}
@@ -0,0 +1,396 @@
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                                                                                            │ *
+ * │ This Source Code Form is subject to the terms of the Mozilla Public                        │ *
+ * │ License, v. 2.0. If a copy of the MPL was not distributed with this                        │ *
+ * │ file, You can obtain one at http://mozilla.org/MPL/2.0/.                                   │ *
+ * │                                                                                            │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                                       Documentation                                        │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+//! Layered timeouts for the phases of a request, as used by HTTP and RPC clients.
+//!
+//! A [`PhaseTimeouts`] configures a timeout for each phase of a request:
+//!
+//! - connecting to the server ([`Phases::connect`]);
+//! - waiting for the first byte or the headers of the response ([`Phases::first_byte`]);
+//! - waiting between two chunks of the response's body ([`Phases::chunks`]);
+//! - the whole request, from [`PhaseTimeouts::start`] to the end of the body.
+//!
+//! Every phase is also bounded by the total deadline, and fails with a [`PhaseElapsed`] error
+//! telling which [`Phase`] expired, so that a slow server can be told from a stalled download.
+//!
+//! ## Example
+//!
+//! ```rust
+//! use async_io::Timer;
+//! # use futures_lite::future;
+//! use futures_lite::{stream, StreamExt};
+//! use smol_timeout::phase::{Phase, PhaseTimeouts};
+//! use std::time::Duration;
+//!
+//! # future::block_on(async {
+//! #
+//! let timeouts = PhaseTimeouts::new()
+//!     .connect(Duration::from_millis(100))
+//!     .first_byte(Duration::from_millis(250))
+//!     .between_chunks(Duration::from_millis(100))
+//!     .total(Duration::from_secs(1));
+//!
+//! let phases = timeouts.start();
+//!
+//! let conn = phases.connect(async { "conn" }).await.unwrap();
+//! assert_eq!(conn, "conn");
+//!
+//! let headers = phases.first_byte(async {
+//!     Timer::after(Duration::from_millis(50)).await;
+//!     "200 OK"
+//! });
+//!
+//! assert_eq!(headers.await.unwrap(), "200 OK");
+//!
+//! let body = stream::iter(vec![1, 2]).chain(stream::pending());
+//! let mut chunks = phases.chunks(body);
+//!
+//! assert_eq!(chunks.next().await, Some(Ok(1)));
+//! assert_eq!(chunks.next().await, Some(Ok(2)));
+//!
+//! let err = chunks.next().await.unwrap().unwrap_err();
+//! assert_eq!(err.phase(), Phase::BetweenChunks);
+//! assert_eq!(chunks.next().await, None);
+//! #
+//! # });
+//! ```
+
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                                          Imports                                           │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+use crate::{clock, deadline, Elapsed, Timer};
+use core::fmt;
+use core::future::Future;
+use core::pin::Pin;
+use core::task::{Context, Poll};
+use core::time::Duration;
+use futures_core::stream::Stream;
+use pin_project_lite::pin_project;
+use std::io;
+use std::time::Instant;
+
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                                         enum Phase                                         │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+/// A phase of a request whose timeout expired.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Phase {
+    /// Connecting to the server.
+    Connect,
+    /// Waiting for the first byte (or the headers) of the response.
+    FirstByte,
+    /// Waiting between two chunks of the response's body.
+    BetweenChunks,
+    /// The whole request.
+    Total,
+}
+
+impl fmt::Display for Phase {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        fmt.write_str(match self {
+            Phase::Connect => "connect",
+            Phase::FirstByte => "first byte",
+            Phase::BetweenChunks => "between chunks",
+            Phase::Total => "total",
+        })
+    }
+}
+
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                                    struct PhaseElapsed                                     │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+/// The error returned when the timeout of a [`Phase`] expired.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct PhaseElapsed {
+    phase: Phase,
+    after: Duration,
+}
+
+impl PhaseElapsed {
+    /// Returns the phase whose timeout expired.
+    pub fn phase(&self) -> Phase {
+        self.phase
+    }
+
+    /// Returns the duration the phase's timeout was configured for.
+    pub fn after(&self) -> Duration {
+        self.after
+    }
+}
+
+impl From<PhaseElapsed> for Elapsed {
+    fn from(_: PhaseElapsed) -> Self {
+        Elapsed::new()
+    }
+}
+
+impl From<PhaseElapsed> for io::Error {
+    fn from(elapsed: PhaseElapsed) -> Self {
+        io::Error::new(io::ErrorKind::TimedOut, elapsed)
+    }
+}
+
+impl fmt::Display for PhaseElapsed {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        write!(fmt, "{} timeout expired after {:?}", self.phase, self.after)
+    }
+}
+
+impl std::error::Error for PhaseElapsed {}
+
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                                    struct PhaseTimeouts                                    │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+/// The timeouts of the phases of a request.
+///
+/// Phases without a timeout are only bounded by the total timeout, if any.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct PhaseTimeouts {
+    connect: Option<Duration>,
+    first_byte: Option<Duration>,
+    between_chunks: Option<Duration>,
+    total: Option<Duration>,
+}
+
+impl PhaseTimeouts {
+    /// Creates a new [`PhaseTimeouts`] without any timeout.
+    pub fn new() -> Self {
+        PhaseTimeouts::default()
+    }
+
+    /// Sets the timeout for connecting to the server.
+    pub fn connect(mut self, after: Duration) -> Self {
+        self.connect = Some(after);
+        self
+    }
+
+    /// Sets the timeout for receiving the first byte (or the headers) of the response.
+    pub fn first_byte(mut self, after: Duration) -> Self {
+        self.first_byte = Some(after);
+        self
+    }
+
+    /// Sets the timeout between two chunks of the response's body.
+    pub fn between_chunks(mut self, after: Duration) -> Self {
+        self.between_chunks = Some(after);
+        self
+    }
+
+    /// Sets the timeout for the whole request.
+    pub fn total(mut self, after: Duration) -> Self {
+        self.total = Some(after);
+        self
+    }
+
+    /// Starts a request, returning the [`Phases`] applying these timeouts to its futures and
+    /// streams, with the total deadline starting now.
+    pub fn start(&self) -> Phases {
+        Phases {
+            timeouts: *self,
+            deadline: self
+                .total
+                .map(|total| deadline::deadline_after(clock::now(), total)),
+        }
+    }
+}
+
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                                       struct Phases                                        │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+/// The timeouts of a started request, applied to the futures and streams of its phases.
+///
+/// Created by [`PhaseTimeouts::start`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Phases {
+    timeouts: PhaseTimeouts,
+    deadline: Option<Instant>,
+}
+
+impl Phases {
+    /// Returns the time left before the total deadline, if any.
+    pub fn remaining(&self) -> Option<Duration> {
+        self.deadline.map(crate::deadline::remaining_until)
+    }
+
+    /// Bounds a future connecting to the server by the connect timeout and the total deadline.
+    pub fn connect<Fut: Future>(&self, future: Fut) -> PhaseTimeout<Fut> {
+        self.timeout(future, Phase::Connect, self.timeouts.connect)
+    }
+
+    /// Bounds a future waiting for the first byte (or the headers) of the response by the first
+    /// byte timeout and the total deadline.
+    pub fn first_byte<Fut: Future>(&self, future: Fut) -> PhaseTimeout<Fut> {
+        self.timeout(future, Phase::FirstByte, self.timeouts.first_byte)
+    }
+
+    /// Bounds a future by the total deadline only, e.g. while sending the request's body.
+    pub fn total<Fut: Future>(&self, future: Fut) -> PhaseTimeout<Fut> {
+        self.timeout(future, Phase::Total, None)
+    }
+
+    /// Bounds every item of a stream of the response's chunks by the timeout between chunks
+    /// (starting now for the first one) and the total deadline.
+    ///
+    /// The returned stream ends after yielding a [`PhaseElapsed`] error.
+    pub fn chunks<S: Stream>(&self, stream: S) -> PhaseStream<S> {
+        let (timer, elapsed) = self.bound(Phase::BetweenChunks, self.timeouts.between_chunks);
+
+        PhaseStream {
+            stream,
+            phases: *self,
+            timer,
+            elapsed,
+            done: false,
+        }
+    }
+
+    fn timeout<Fut: Future>(
+        &self,
+        future: Fut,
+        phase: Phase,
+        after: Option<Duration>,
+    ) -> PhaseTimeout<Fut> {
+        let (timer, elapsed) = self.bound(phase, after);
+
+        PhaseTimeout {
+            future,
+            timer,
+            elapsed,
+        }
+    }
+
+    /// Returns a timer completing after the provided phase's timeout or at the total deadline,
+    /// whichever comes first, and the error to return once it completes.
+    fn bound(&self, phase: Phase, after: Option<Duration>) -> (Timer, PhaseElapsed) {
+        let total = PhaseElapsed {
+            phase: Phase::Total,
+            after: self.timeouts.total.unwrap_or_default(),
+        };
+
+        match (
+            after.map(|after| deadline::deadline_after(clock::now(), after)),
+            self.deadline,
+        ) {
+            (Some(at), Some(deadline)) if deadline <= at => (Timer::at(deadline), total),
+            (Some(at), _) => (
+                Timer::at(at),
+                PhaseElapsed {
+                    phase,
+                    after: after.unwrap_or_default(),
+                },
+            ),
+            (None, Some(deadline)) => (Timer::at(deadline), total),
+            (None, None) => (Timer::never(), total),
+        }
+    }
+}
+
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                                  struct PhaseTimeout<Fut>                                  │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+pin_project! {
+    /// A future polling both another future and a [`Timer`] completing at the end of a phase's
+    /// timeout or at the total deadline, and returning the future's output or a [`PhaseElapsed`]
+    /// error if the timer completes first.
+    ///
+    /// Created by [`Phases::connect`], [`Phases::first_byte`] and [`Phases::total`].
+    #[derive(Debug)]
+    #[must_use = "futures do nothing unless you `.await` or poll them"]
+    pub struct PhaseTimeout<Fut> {
+        #[pin]
+        future: Fut,
+        timer: Timer,
+        elapsed: PhaseElapsed,
+    }
+}
+
+impl<Fut: Future> Future for PhaseTimeout<Fut> {
+    type Output = Result<Fut::Output, PhaseElapsed>;
+
+    fn poll(self: Pin<&mut Self>, ctx: &mut Context) -> Poll<Self::Output> {
+        let this = self.project();
+
+        if let Poll::Ready(output) = this.future.poll(ctx) {
+            return Poll::Ready(Ok(output));
+        }
+
+        if Pin::new(this.timer).poll(ctx).is_ready() {
+            return Poll::Ready(Err(*this.elapsed));
+        }
+
+        Poll::Pending
+    }
+}
+
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                                   struct PhaseStream<S>                                    │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+pin_project! {
+    /// A stream bounding each of the items of another stream by the timeout between chunks and
+    /// the total deadline, and ending after yielding a [`PhaseElapsed`] error.
+    ///
+    /// Created by [`Phases::chunks`].
+    #[derive(Debug)]
+    #[must_use = "streams do nothing unless polled"]
+    pub struct PhaseStream<S> {
+        #[pin]
+        stream: S,
+        phases: Phases,
+        timer: Timer,
+        elapsed: PhaseElapsed,
+        done: bool,
+    }
+}
+
+impl<S: Stream> Stream for PhaseStream<S> {
+    type Item = Result<S::Item, PhaseElapsed>;
+
+    fn poll_next(self: Pin<&mut Self>, ctx: &mut Context) -> Poll<Option<Self::Item>> {
+        let this = self.project();
+
+        if *this.done {
+            return Poll::Ready(None);
+        }
+
+        match this.stream.poll_next(ctx) {
+            Poll::Ready(Some(item)) => {
+                let (timer, elapsed) = this
+                    .phases
+                    .bound(Phase::BetweenChunks, this.phases.timeouts.between_chunks);
+
+                *this.timer = timer;
+                *this.elapsed = elapsed;
+
+                return Poll::Ready(Some(Ok(item)));
+            }
+            Poll::Ready(None) => {
+                *this.done = true;
+                return Poll::Ready(None);
+            }
+            Poll::Pending => (),
+        }
+
+        if Pin::new(this.timer).poll(ctx).is_ready() {
+            *this.done = true;
+            return Poll::Ready(Some(Err(*this.elapsed)));
+        }
+
+        Poll::Pending
+    }
+}
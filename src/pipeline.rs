@@ -0,0 +1,182 @@
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                                                                                            │ *
+ * │ This Source Code Form is subject to the terms of the Mozilla Public                        │ *
+ * │ License, v. 2.0. If a copy of the MPL was not distributed with this                        │ *
+ * │ file, You can obtain one at http://mozilla.org/MPL/2.0/.                                   │ *
+ * │                                                                                            │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                                       Documentation                                        │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+//! Chains of futures sharing a single deadline.
+//!
+//! A [`Pipeline`] runs its stages one after the other, each one created by a closure from the
+//! output of the previous one, and [`deadline`](Pipeline::deadline) bounds the whole chain with
+//! a single [`Timer`](crate::Timer): every stage thus runs within whatever the previous ones
+//! left of the total, without any bookkeeping of instants between them.
+//!
+//! ## Example
+//!
+//! ```rust
+//! use async_io::Timer;
+//! # use futures_lite::future;
+//! use smol_timeout::pipeline::PipelineExt;
+//! use std::time::Duration;
+//!
+//! # future::block_on(async {
+//! #
+//! let connect = async {
+//!     Timer::after(Duration::from_millis(50)).await;
+//!     1
+//! };
+//!
+//! let request = connect
+//!     .then_within(|conn| async move {
+//!         Timer::after(Duration::from_millis(50)).await;
+//!         conn + 1
+//!     })
+//!     .then_within(|session| async move {
+//!         Timer::after(Duration::from_millis(50)).await;
+//!         session * 2
+//!     })
+//!     .deadline(Duration::from_millis(500));
+//!
+//! assert_eq!(request.await, Some(4));
+//!
+//! let slow = future::ready(1)
+//!     .then_within(|_| Timer::after(Duration::from_millis(100)))
+//!     .then_within(|_| Timer::after(Duration::from_millis(100)))
+//!     .deadline(Duration::from_millis(150));
+//!
+//! assert_eq!(slow.await, None);
+//! #
+//! # });
+//! ```
+
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                                          Imports                                           │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+use crate::Timeout;
+use core::fmt;
+use core::future::Future;
+use core::pin::Pin;
+use core::task::{Context, Poll};
+use core::time::Duration;
+use pin_project_lite::pin_project;
+
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                               struct Pipeline<Fut, F, Next>                                │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+pin_project! {
+    /// A future polling a first future until it completes, then a second one created by a
+    /// closure from the output of the first, and returning the output of the second.
+    ///
+    /// Created by [`PipelineExt::then_within`], and bounded by a single deadline with
+    /// [`Pipeline::deadline`].
+    #[must_use = "futures do nothing unless you `.await` or poll them"]
+    pub struct Pipeline<Fut, F, Next> {
+        #[pin]
+        first: Option<Fut>,
+        then: Option<F>,
+        #[pin]
+        next: Option<Next>,
+    }
+}
+
+impl<Fut, F, Next> Pipeline<Fut, F, Next>
+where
+    Fut: Future,
+    F: FnOnce(Fut::Output) -> Next,
+    Next: Future,
+{
+    /// Given a [`Duration`], creates and returns a new [`Timeout`] that will poll both the
+    /// whole pipeline and a [`Timer`](crate::Timer) that will complete after the provided
+    /// duration, and return the output of the last stage or [`None`] if the timer completes
+    /// first.
+    pub fn deadline(self, total: Duration) -> Timeout<Self> {
+        Timeout::after(self, total)
+    }
+}
+
+impl<Fut, F, Next> fmt::Debug for Pipeline<Fut, F, Next> {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        fmt.debug_struct("Pipeline")
+            .field(
+                "stage",
+                &if self.first.is_some() {
+                    "first"
+                } else {
+                    "next"
+                },
+            )
+            .finish()
+    }
+}
+
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                                 trait PipelineExt: Future                                  │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+/// An extension trait for [`Future`]s that provides a way to create [`Pipeline`]s.
+pub trait PipelineExt: Future {
+    /// Given a closure creating the next stage from the future's output, creates and returns a
+    /// new [`Pipeline`] that will poll the future, then the next stage, and return the output
+    /// of the next stage.
+    ///
+    /// The next stage is only created once the future completed, so that it runs within what
+    /// is left of the pipeline's [`deadline`](Pipeline::deadline).
+    fn then_within<F, Next>(self, then: F) -> Pipeline<Self, F, Next>
+    where
+        Self: Sized,
+        F: FnOnce(Self::Output) -> Next,
+        Next: Future,
+    {
+        Pipeline {
+            first: Some(self),
+            then: Some(then),
+            next: None,
+        }
+    }
+}
+
+impl<Fut: Future> PipelineExt for Fut {}
+
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                           impl Future for Pipeline<Fut, F, Next>                           │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+impl<Fut, F, Next> Future for Pipeline<Fut, F, Next>
+where
+    Fut: Future,
+    F: FnOnce(Fut::Output) -> Next,
+    Next: Future,
+{
+    type Output = Next::Output;
+
+    fn poll(self: Pin<&mut Self>, ctx: &mut Context) -> Poll<Self::Output> {
+        let mut this = self.project();
+
+        if let Some(first) = this.first.as_mut().as_pin_mut() {
+            let output = match first.poll(ctx) {
+                Poll::Ready(output) => output,
+                Poll::Pending => return Poll::Pending,
+            };
+
+            this.first.set(None);
+            let then = this
+                .then
+                .take()
+                .expect("a pipeline's closure is only called once");
+            this.next.set(Some(then(output)));
+        }
+
+        this.next
+            .as_pin_mut()
+            .expect("`Pipeline` polled after completion")
+            .poll(ctx)
+    }
+}
@@ -0,0 +1,75 @@
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                                                                                            │ *
+ * │ This Source Code Form is subject to the terms of the Mozilla Public                        │ *
+ * │ License, v. 2.0. If a copy of the MPL was not distributed with this                        │ *
+ * │ file, You can obtain one at http://mozilla.org/MPL/2.0/.                                   │ *
+ * │                                                                                            │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                                       Documentation                                        │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+//! Timeouts around futures written as a closure polled until it returns [`Poll::Ready`].
+//!
+//! Wrapping a [`poll_fn`](core::future::poll_fn) in a [`Timeout`] by hand often needs type
+//! annotations for the closure's argument, as its signature can't be inferred from the
+//! [`TimeoutExt::timeout`](crate::TimeoutExt::timeout) call. [`poll_fn_timeout`] takes the
+//! closure directly, and returns a [`PollFnTimeout`] which can be named in struct fields.
+//!
+//! ## Example
+//!
+//! ```rust
+//! # use futures_lite::future;
+//! use smol_timeout::poll_fn::poll_fn_timeout;
+//! use std::task::Poll;
+//! use std::time::Duration;
+//!
+//! # future::block_on(async {
+//! #
+//! let mut polls = 0;
+//! let foo = poll_fn_timeout(Duration::from_millis(100), |ctx| {
+//!     polls += 1;
+//!     if polls < 3 {
+//!         ctx.waker().wake_by_ref();
+//!         return Poll::Pending;
+//!     }
+//!
+//!     Poll::Ready(42)
+//! });
+//!
+//! assert_eq!(foo.await, Some(42));
+//!
+//! let bar = poll_fn_timeout(Duration::from_millis(100), |_| Poll::<()>::Pending);
+//! assert_eq!(bar.await, None);
+//! #
+//! # });
+//! ```
+
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                                          Imports                                           │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+use crate::Timeout;
+use core::future::{self, PollFn};
+use core::task::{Context, Poll};
+use core::time::Duration;
+
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                                   type PollFnTimeout<F>                                    │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+/// A [`Timeout`] polling a closure until it returns [`Poll::Ready`].
+///
+/// Created by [`poll_fn_timeout`].
+pub type PollFnTimeout<F> = Timeout<PollFn<F>>;
+
+/// Given a [`Duration`] and a closure, creates and returns a new [`PollFnTimeout`] that will
+/// poll both the closure and a [`Timer`](crate::Timer) that will complete after the provided duration, and
+/// return the closure's output or [`None`] if the timer completes first.
+pub fn poll_fn_timeout<T, F>(after: Duration, f: F) -> PollFnTimeout<F>
+where
+    F: FnMut(&mut Context<'_>) -> Poll<T>,
+{
+    Timeout::after(future::poll_fn(f), after)
+}
@@ -0,0 +1,202 @@
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                                                                                            │ *
+ * │ This Source Code Form is subject to the terms of the Mozilla Public                        │ *
+ * │ License, v. 2.0. If a copy of the MPL was not distributed with this                        │ *
+ * │ file, You can obtain one at http://mozilla.org/MPL/2.0/.                                   │ *
+ * │                                                                                            │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                                       Documentation                                        │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+//! High-precision timeouts, for latency-sensitive code needing sub-millisecond deadlines.
+//!
+//! The timers of the OS routinely fire 1 to 15 milliseconds late. The sleeps of this module
+//! instead arm a [`Timer`] a margin (see [`DEFAULT_MARGIN`]) before their deadline, and then
+//! spin for the final stretch: every time they are polled, they wake their task right away and
+//! return [`Poll::Pending`], yielding to the executor (and so to its other tasks) until the
+//! deadline elapsed.
+//!
+//! This trades CPU time for precision: a thread of the executor stays busy for the whole
+//! margin of every sleep.
+//!
+//! ## Example
+//!
+//! ```rust
+//! # use futures_lite::future;
+//! use smol_timeout::precise;
+//! use std::time::{Duration, Instant};
+//!
+//! # future::block_on(async {
+//! #
+//! let start = Instant::now();
+//! precise::sleep(Duration::from_micros(500)).await;
+//! assert!(start.elapsed() >= Duration::from_micros(500));
+//!
+//! let foo = future::pending::<()>();
+//! assert_eq!(precise::timeout(foo, Duration::from_micros(250)).await, None);
+//! #
+//! # });
+//! ```
+
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                                          Imports                                           │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+use crate::{clock, deadline, Timer};
+use core::future::Future;
+use core::pin::Pin;
+use core::task::{Context, Poll};
+use core::time::Duration;
+use pin_project_lite::pin_project;
+use std::time::Instant;
+
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                                    struct PreciseSleep                                     │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+/// The default duration before their deadline for which precise sleeps spin instead of
+/// waiting for their [`Timer`].
+pub const DEFAULT_MARGIN: Duration = Duration::from_millis(2);
+
+/// A future completing at a deadline, spinning for the final stretch before it instead of
+/// relying on the precision of the reactor's timers.
+///
+/// Created by [`sleep`] and [`sleep_until`].
+#[derive(Debug)]
+#[must_use = "futures do nothing unless you `.await` or poll them"]
+pub struct PreciseSleep {
+    deadline: Instant,
+    margin: Duration,
+    timer: Timer,
+    armed: bool,
+}
+
+/// Creates and returns a new [`PreciseSleep`] future that will complete after the provided
+/// duration.
+pub fn sleep(after: Duration) -> PreciseSleep {
+    sleep_until(deadline::deadline_after(clock::now(), after))
+}
+
+/// Creates and returns a new [`PreciseSleep`] future that will complete at the provided
+/// deadline.
+pub fn sleep_until(deadline: Instant) -> PreciseSleep {
+    PreciseSleep {
+        deadline,
+        margin: DEFAULT_MARGIN,
+        timer: Timer::never(),
+        armed: false,
+    }
+}
+
+impl PreciseSleep {
+    /// Sets the duration before the deadline for which the sleep spins, instead of the
+    /// [`DEFAULT_MARGIN`].
+    ///
+    /// Has no effect once the sleep was polled.
+    pub fn with_margin(mut self, margin: Duration) -> Self {
+        self.margin = margin;
+        self
+    }
+
+    /// Returns the deadline of the sleep.
+    pub fn deadline(&self) -> Instant {
+        self.deadline
+    }
+}
+
+impl Future for PreciseSleep {
+    type Output = ();
+
+    fn poll(mut self: Pin<&mut Self>, ctx: &mut Context) -> Poll<()> {
+        let now = clock::now();
+        if now >= self.deadline {
+            return Poll::Ready(());
+        }
+
+        if self.deadline - now > self.margin {
+            if !self.armed {
+                let wakeup = self.deadline - self.margin;
+                self.timer.set_at(wakeup);
+                self.armed = true;
+            }
+
+            if Pin::new(&mut self.timer).poll(ctx).is_pending() {
+                return Poll::Pending;
+            }
+        }
+
+        // Spinning through the executor, so that its other tasks can still run.
+        core::hint::spin_loop();
+        ctx.waker().wake_by_ref();
+        Poll::Pending
+    }
+}
+
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                                 struct PreciseTimeout<Fut>                                 │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+pin_project! {
+    /// A future polling both another future and a [`PreciseSleep`], and returning the future's
+    /// output or [`None`] if the sleep completes first.
+    ///
+    /// Created by [`timeout`] and [`timeout_at`].
+    #[derive(Debug)]
+    #[must_use = "futures do nothing unless you `.await` or poll them"]
+    pub struct PreciseTimeout<Fut> {
+        #[pin]
+        future: Fut,
+        sleep: PreciseSleep,
+    }
+}
+
+/// Given a future and a [`Duration`], creates and returns a new [`PreciseTimeout`] that will
+/// poll both the future and a [`PreciseSleep`] that will complete after the provided duration,
+/// and return the future's output or [`None`] if the sleep completes first.
+pub fn timeout<Fut: Future>(future: Fut, after: Duration) -> PreciseTimeout<Fut> {
+    PreciseTimeout {
+        future,
+        sleep: sleep(after),
+    }
+}
+
+/// Given a future and an [`Instant`], creates and returns a new [`PreciseTimeout`] that will
+/// poll both the future and a [`PreciseSleep`] that will complete at the provided deadline, and
+/// return the future's output or [`None`] if the sleep completes first.
+pub fn timeout_at<Fut: Future>(future: Fut, deadline: Instant) -> PreciseTimeout<Fut> {
+    PreciseTimeout {
+        future,
+        sleep: sleep_until(deadline),
+    }
+}
+
+impl<Fut> PreciseTimeout<Fut> {
+    /// Sets the duration before the deadline for which the timeout's sleep spins, instead of
+    /// the [`DEFAULT_MARGIN`].
+    ///
+    /// Has no effect once the timeout was polled.
+    pub fn with_margin(mut self, margin: Duration) -> Self {
+        self.sleep = self.sleep.with_margin(margin);
+        self
+    }
+}
+
+impl<Fut: Future> Future for PreciseTimeout<Fut> {
+    type Output = Option<Fut::Output>;
+
+    fn poll(self: Pin<&mut Self>, ctx: &mut Context) -> Poll<Self::Output> {
+        let this = self.project();
+
+        if let Poll::Ready(output) = this.future.poll(ctx) {
+            return Poll::Ready(Some(output));
+        }
+
+        if Pin::new(this.sleep).poll(ctx).is_ready() {
+            return Poll::Ready(None);
+        }
+
+        Poll::Pending
+    }
+}
@@ -0,0 +1,260 @@
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                                                                                            │ *
+ * │ This Source Code Form is subject to the terms of the Mozilla Public                        │ *
+ * │ License, v. 2.0. If a copy of the MPL was not distributed with this                        │ *
+ * │ file, You can obtain one at http://mozilla.org/MPL/2.0/.                                   │ *
+ * │                                                                                            │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                                       Documentation                                        │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+//! A way to declare how precise timeouts need to be, so that they can be batched together.
+//!
+//! The tolerance of a timeout is how late it is allowed to fire: a tolerance of 50ms declares
+//! that firing up to 50ms after the deadline is fine. A [`TolerantTimeout`] with a non-zero
+//! tolerance shares its timer with the other timeouts firing in the same window, using a
+//! process-wide [`Coalescer`] per tolerance (rounded down to a power of two nanoseconds), instead
+//! of registering its own timer with the reactor.
+//!
+//! The default tolerance, used by [`TolerantTimeoutExt::timeout_tolerant`], can be changed
+//! globally with [`set_tolerance`]. The [`Tolerance`] trait lets wrappers query the tolerance
+//! of the timers they wrap, e.g. to create their own with the same one.
+//!
+//! ## Example
+//!
+//! ```rust
+//! use async_io::Timer;
+//! # use futures_lite::future;
+//! use smol_timeout::precision::{Tolerance, TolerantTimeoutExt};
+//! use std::time::Duration;
+//!
+//! # future::block_on(async {
+//! #
+//! let foo = async {
+//!     Timer::after(Duration::from_millis(250)).await;
+//!     24
+//! };
+//!
+//! let foo = foo.timeout_within(Duration::from_millis(100), Duration::from_millis(50));
+//! assert_eq!(foo.tolerance(), Duration::from_millis(50));
+//! assert_eq!(foo.await, None);
+//!
+//! let bar = async {
+//!     Timer::after(Duration::from_millis(100)).await;
+//!     42
+//! };
+//!
+//! let bar = bar.timeout_within(Duration::from_millis(250), Duration::from_millis(50));
+//! assert_eq!(bar.await, Some(42));
+//! #
+//! # });
+//! ```
+
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                                          Imports                                           │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+use crate::coalesce::{CoalescedSleep, Coalescer};
+use crate::wheel::TimerWheel;
+use crate::Timer;
+use crate::{clock, deadline};
+use core::convert::TryFrom;
+use core::future::Future;
+use core::pin::Pin;
+use core::sync::atomic::{AtomicU64, Ordering};
+use core::task::{Context, Poll};
+use core::time::Duration;
+use pin_project_lite::pin_project;
+use std::collections::BTreeMap;
+use std::sync::Mutex;
+use std::time::Instant;
+
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                                     fn set_tolerance()                                     │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+/// The default tolerance, in nanoseconds.
+static TOLERANCE: AtomicU64 = AtomicU64::new(0);
+
+/// The coalescers shared by tolerant timeouts, keyed by the base-2 logarithm of their quantum.
+static COALESCERS: Mutex<BTreeMap<u32, Coalescer>> = Mutex::new(BTreeMap::new());
+
+/// Sets the default tolerance of [`TolerantTimeout`]s (see
+/// [`TolerantTimeoutExt::timeout_tolerant`]), which is zero unless set.
+pub fn set_tolerance(tolerance: Duration) {
+    TOLERANCE.store(nanos(tolerance), Ordering::Relaxed);
+}
+
+/// Returns the default tolerance of [`TolerantTimeout`]s.
+pub fn tolerance() -> Duration {
+    Duration::from_nanos(TOLERANCE.load(Ordering::Relaxed))
+}
+
+fn nanos(duration: Duration) -> u64 {
+    u64::try_from(duration.as_nanos()).unwrap_or(u64::MAX)
+}
+
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                                      trait Tolerance                                       │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+/// A trait for timers that can fire late, returning by how much at most.
+pub trait Tolerance {
+    /// Returns the duration after their deadline up to which the timers may fire.
+    fn tolerance(&self) -> Duration;
+}
+
+impl Tolerance for Coalescer {
+    fn tolerance(&self) -> Duration {
+        self.quantum()
+    }
+}
+
+impl Tolerance for TimerWheel {
+    fn tolerance(&self) -> Duration {
+        self.tick()
+    }
+}
+
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                                struct TolerantTimeout<Fut>                                 │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+#[derive(Debug)]
+enum Sleep {
+    Exact(Timer),
+    Coalesced(CoalescedSleep),
+}
+
+impl Sleep {
+    fn new(deadline: Instant, tolerance: Duration) -> Self {
+        let nanos = nanos(tolerance);
+        if nanos == 0 {
+            return Sleep::Exact(Timer::at(deadline));
+        }
+
+        // Rounding the tolerance down keeps the number of coalescers bounded.
+        let log2 = 63 - nanos.leading_zeros();
+        let coalescer = COALESCERS
+            .lock()
+            .unwrap()
+            .entry(log2)
+            .or_insert_with(|| Coalescer::new(Duration::from_nanos(1 << log2)))
+            .clone();
+
+        Sleep::Coalesced(coalescer.sleep_until(deadline))
+    }
+
+    fn poll(&mut self, ctx: &mut Context) -> Poll<()> {
+        match self {
+            Sleep::Exact(timer) => Pin::new(timer).poll(ctx).map(|_| ()),
+            Sleep::Coalesced(sleep) => Pin::new(sleep).poll(ctx),
+        }
+    }
+}
+
+pin_project! {
+    /// A future polling both another future and a timer that will complete at a specified
+    /// deadline or shortly after it, within a tolerance, and returning the future's output or
+    /// [`None`] if the timer completes first.
+    #[derive(Debug)]
+    #[must_use = "futures do nothing unless you `.await` or poll them"]
+    pub struct TolerantTimeout<Fut> {
+        #[pin]
+        future: Fut,
+        tolerance: Duration,
+        sleep: Sleep,
+    }
+}
+
+impl<Fut> Tolerance for TolerantTimeout<Fut> {
+    fn tolerance(&self) -> Duration {
+        self.tolerance
+    }
+}
+
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                              trait TolerantTimeoutExt: Future                              │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+/// An extension trait for [`Future`]s that provides a way to create [`TolerantTimeout`]s.
+pub trait TolerantTimeoutExt: Future {
+    /// Given a [`Duration`] and a tolerance, creates and returns a new [`TolerantTimeout`] that
+    /// will poll both the future and a timer that will complete after the provided duration (and
+    /// at most `tolerance` later), and return the future's output or [`None`] if the timer
+    /// completes first.
+    fn timeout_within(self, after: Duration, tolerance: Duration) -> TolerantTimeout<Self>
+    where
+        Self: Sized,
+    {
+        self.timeout_at_within(deadline::deadline_after(clock::now(), after), tolerance)
+    }
+
+    /// Given an [`Instant`] and a tolerance, creates and returns a new [`TolerantTimeout`] that
+    /// will poll both the future and a timer that will complete at the provided deadline (or at
+    /// most `tolerance` later), and return the future's output or [`None`] if the timer
+    /// completes first.
+    fn timeout_at_within(self, deadline: Instant, tolerance: Duration) -> TolerantTimeout<Self>
+    where
+        Self: Sized,
+    {
+        TolerantTimeout {
+            future: self,
+            tolerance,
+            sleep: Sleep::new(deadline, tolerance),
+        }
+    }
+
+    /// Given a [`Duration`], creates and returns a new [`TolerantTimeout`] with the default
+    /// tolerance (see [`set_tolerance`]).
+    ///
+    /// ## Example
+    ///
+    /// ```rust
+    /// # use futures_lite::future;
+    /// use smol_timeout::precision::{self, Tolerance, TolerantTimeoutExt};
+    /// use std::time::Duration;
+    ///
+    /// # future::block_on(async {
+    /// #
+    /// precision::set_tolerance(Duration::from_millis(10));
+    ///
+    /// let foo = future::pending::<()>().timeout_tolerant(Duration::from_millis(100));
+    /// assert_eq!(foo.tolerance(), Duration::from_millis(10));
+    /// assert_eq!(foo.await, None);
+    /// #
+    /// # })
+    /// ```
+    fn timeout_tolerant(self, after: Duration) -> TolerantTimeout<Self>
+    where
+        Self: Sized,
+    {
+        self.timeout_within(after, tolerance())
+    }
+}
+
+impl<Fut: Future> TolerantTimeoutExt for Fut {}
+
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                            impl Future for TolerantTimeout<Fut>                            │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+impl<Fut: Future> Future for TolerantTimeout<Fut> {
+    type Output = Option<Fut::Output>;
+
+    fn poll(self: Pin<&mut Self>, ctx: &mut Context) -> Poll<Self::Output> {
+        let this = self.project();
+
+        if this.sleep.poll(ctx).is_ready() {
+            return Poll::Ready(None);
+        }
+
+        if let Poll::Ready(output) = this.future.poll(ctx) {
+            return Poll::Ready(Some(output));
+        }
+
+        Poll::Pending
+    }
+}
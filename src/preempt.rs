@@ -0,0 +1,148 @@
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                                                                                            │ *
+ * │ This Source Code Form is subject to the terms of the Mozilla Public                        │ *
+ * │ License, v. 2.0. If a copy of the MPL was not distributed with this                        │ *
+ * │ file, You can obtain one at http://mozilla.org/MPL/2.0/.                                   │ *
+ * │                                                                                            │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                                       Documentation                                        │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+//! Cooperative preemption of futures hogging their executor.
+//!
+//! A future which is always ready to make progress (e.g. because it keeps waking itself up, or
+//! its inputs are always ready) can keep its task busy indefinitely, starving the other tasks of
+//! single-threaded executors. A [`YieldAfter`] tracks for how long its future has been running
+//! since it last waited on something, and once that exceeds its time slice, returns
+//! [`Poll::Pending`] instead of polling it, after waking its task up, so that the executor can
+//! run its other tasks first.
+//!
+//! The future is never interrupted in the middle of a `poll`: a single `poll` running for longer
+//! than the time slice only makes the following one yield.
+//!
+//! ## Example
+//!
+//! ```rust
+//! # use futures_lite::future;
+//! use smol_timeout::preempt::YieldAfterExt;
+//! use std::time::{Duration, Instant};
+//!
+//! # future::block_on(async {
+//! #
+//! let busy = async {
+//!     let start = Instant::now();
+//!     while start.elapsed() < Duration::from_millis(50) {
+//!         future::yield_now().await;
+//!     }
+//!
+//!     42
+//! };
+//!
+//! assert_eq!(busy.yield_after(Duration::from_millis(5)).await, 42);
+//! #
+//! # });
+//! ```
+
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                                          Imports                                           │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+use crate::clock::Stamp;
+use crate::stats::Counter;
+use core::fmt;
+use core::future::Future;
+use core::pin::Pin;
+use core::task::{Context, Poll, Waker};
+use core::time::Duration;
+use pin_project_lite::pin_project;
+use std::sync::Arc;
+
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                                   struct YieldAfter<Fut>                                   │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+pin_project! {
+    /// A future polling another future, and yielding to the executor once the future has been
+    /// running for longer than a time slice since it last waited on something.
+    #[must_use = "futures do nothing unless you `.await` or poll them"]
+    pub struct YieldAfter<Fut> {
+        #[pin]
+        future: Fut,
+        slice: Duration,
+        // The instant the future started running since it last waited on something.
+        streak: Option<Stamp>,
+        counter: Arc<Counter>,
+        waker: Waker,
+    }
+}
+
+impl<Fut> fmt::Debug for YieldAfter<Fut> {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        fmt.debug_struct("YieldAfter")
+            .field("slice", &self.slice)
+            .finish()
+    }
+}
+
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                                trait YieldAfterExt: Future                                 │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+/// An extension trait for [`Future`]s that provides a way to create [`YieldAfter`]s.
+pub trait YieldAfterExt: Future {
+    /// Given a time slice, creates and returns a new [`YieldAfter`] that will poll the future,
+    /// yielding to the executor whenever it has been running for longer than the time slice
+    /// since it last waited on something.
+    fn yield_after(self, slice: Duration) -> YieldAfter<Self>
+    where
+        Self: Sized,
+    {
+        let counter = Arc::new(Counter::default());
+
+        YieldAfter {
+            future: self,
+            slice,
+            streak: None,
+            waker: Waker::from(counter.clone()),
+            counter,
+        }
+    }
+}
+
+impl<Fut: Future> YieldAfterExt for Fut {}
+
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                              impl Future for YieldAfter<Fut>                               │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+impl<Fut: Future> Future for YieldAfter<Fut> {
+    type Output = Fut::Output;
+
+    fn poll(self: Pin<&mut Self>, ctx: &mut Context) -> Poll<Self::Output> {
+        let this = self.project();
+
+        if let Some(streak) = *this.streak {
+            if streak.elapsed() >= *this.slice {
+                *this.streak = None;
+                ctx.waker().wake_by_ref();
+                return Poll::Pending;
+            }
+        }
+
+        this.counter.register(ctx.waker());
+        let wakeups = this.counter.wakeups();
+        this.streak.get_or_insert_with(Stamp::now);
+
+        let mut ctx = Context::from_waker(this.waker);
+        let poll = this.future.poll(&mut ctx);
+
+        // The future waits on something unless it woke itself up while being polled.
+        if poll.is_pending() && this.counter.wakeups() == wakeups {
+            *this.streak = None;
+        }
+
+        poll
+    }
+}
@@ -0,0 +1,176 @@
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                                                                                            │ *
+ * │ This Source Code Form is subject to the terms of the Mozilla Public                        │ *
+ * │ License, v. 2.0. If a copy of the MPL was not distributed with this                        │ *
+ * │ file, You can obtain one at http://mozilla.org/MPL/2.0/.                                   │ *
+ * │                                                                                            │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                                       Documentation                                        │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+//! Streams ticking periodically while a future is pending, and yielding its output at the end.
+//!
+//! A [`WithProgress`] stream yields a [`Progress::Pending`] tick with the time elapsed every
+//! interval until its future completes, then a final [`Progress::Done`] with the future's
+//! output, so that spinners or elapsed counters can be rendered around a long await without
+//! spawning a separate task.
+//!
+//! ## Example
+//!
+//! ```rust
+//! use async_io::Timer;
+//! # use futures_lite::future;
+//! use futures_lite::StreamExt;
+//! use smol_timeout::progress::{Progress, ProgressExt};
+//! use std::pin::pin;
+//! use std::time::Duration;
+//!
+//! # future::block_on(async {
+//! #
+//! let foo = async {
+//!     Timer::after(Duration::from_millis(250)).await;
+//!     42
+//! };
+//!
+//! let mut ticks = 0;
+//! let mut progress = pin!(foo.with_progress(Duration::from_millis(100)));
+//! let output = loop {
+//!     match progress.next().await {
+//!         Some(Progress::Pending(elapsed)) => {
+//!             ticks += 1;
+//!             assert!(elapsed >= Duration::from_millis(100) * ticks);
+//!         }
+//!         Some(Progress::Done(output)) => break output,
+//!         None => unreachable!(),
+//!     }
+//! };
+//!
+//! assert_eq!(output, 42);
+//! assert_eq!(ticks, 2);
+//! #
+//! # });
+//! ```
+
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                                          Imports                                           │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+use crate::{clock, Timer};
+use core::future::Future;
+use core::pin::Pin;
+use core::task::{Context, Poll};
+use core::time::Duration;
+use futures_core::stream::Stream;
+use pin_project_lite::pin_project;
+use std::time::Instant;
+
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                                      enum Progress<T>                                      │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+/// An item yielded by a [`WithProgress`] stream.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Progress<T> {
+    /// The future is still pending, after this much time.
+    Pending(Duration),
+    /// The future completed with this output.
+    Done(T),
+}
+
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                                  struct WithProgress<Fut>                                  │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+pin_project! {
+    /// A stream yielding a [`Progress::Pending`] tick every interval while its future is
+    /// pending, then a [`Progress::Done`] with its output.
+    ///
+    /// Created by [`ProgressExt::with_progress`].
+    #[derive(Debug)]
+    #[must_use = "streams do nothing unless polled"]
+    pub struct WithProgress<Fut> {
+        #[pin]
+        future: Fut,
+        timer: Timer,
+        interval: Duration,
+        start: Instant,
+        // The instant of the next tick.
+        next: Instant,
+        done: bool,
+    }
+}
+
+impl<Fut> WithProgress<Fut> {
+    /// Returns the interval between two ticks.
+    pub fn interval(&self) -> Duration {
+        self.interval
+    }
+}
+
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                                 trait ProgressExt: Future                                  │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+/// An extension trait for [`Future`]s that provides a way to create [`WithProgress`] streams.
+pub trait ProgressExt: Future {
+    /// Given a [`Duration`], creates and returns a new [`WithProgress`] stream that will yield a
+    /// [`Progress::Pending`] tick every interval until the future completes, then a
+    /// [`Progress::Done`] with its output.
+    fn with_progress(self, interval: Duration) -> WithProgress<Self>
+    where
+        Self: Sized,
+    {
+        let start = clock::now();
+
+        WithProgress {
+            future: self,
+            timer: Timer::at(start + interval),
+            interval,
+            start,
+            next: start + interval,
+            done: false,
+        }
+    }
+}
+
+impl<Fut: Future> ProgressExt for Fut {}
+
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                             impl Stream for WithProgress<Fut>                              │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+impl<Fut: Future> Stream for WithProgress<Fut> {
+    type Item = Progress<Fut::Output>;
+
+    fn poll_next(self: Pin<&mut Self>, ctx: &mut Context) -> Poll<Option<Self::Item>> {
+        let this = self.project();
+
+        if *this.done {
+            return Poll::Ready(None);
+        }
+
+        if let Poll::Ready(output) = this.future.poll(ctx) {
+            *this.done = true;
+            return Poll::Ready(Some(Progress::Done(output)));
+        }
+
+        if Pin::new(&mut *this.timer).poll(ctx).is_pending() {
+            return Poll::Pending;
+        }
+
+        *this.next += *this.interval;
+        this.timer.set_at(*this.next);
+
+        Poll::Ready(Some(Progress::Pending(clock::now() - *this.start)))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        if self.done {
+            (0, Some(0))
+        } else {
+            (1, None)
+        }
+    }
+}
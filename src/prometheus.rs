@@ -0,0 +1,187 @@
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                                                                                            │ *
+ * │ This Source Code Form is subject to the terms of the Mozilla Public                        │ *
+ * │ License, v. 2.0. If a copy of the MPL was not distributed with this                        │ *
+ * │ file, You can obtain one at http://mozilla.org/MPL/2.0/.                                   │ *
+ * │                                                                                            │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                                       Documentation                                        │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+//! Statistics of the [`NamedTimeout`]s, rendered in the Prometheus text exposition format, for
+//! applications not using the [`metrics`](https://docs.rs/metrics) ecosystem.
+//!
+//! With the `prometheus` feature, the outcome of every [`NamedTimeout`] is aggregated per name
+//! into:
+//!
+//! - a `timeout_completed_total` counter, of the futures that completed in time,
+//! - a `timeout_expired_total` counter, of the timers that completed first,
+//! - a `timeout_elapsed_seconds` histogram, of the time elapsed between the creation of the
+//!   timeouts and their completion (see [`BUCKETS`]).
+//!
+//! [`render`] returns them in the text format, e.g. to serve them on a `/metrics` endpoint.
+//!
+//! ## Example
+//!
+//! ```rust
+//! # use futures_lite::future;
+//! use smol_timeout::named::NamedTimeoutExt;
+//! use smol_timeout::prometheus;
+//! use std::time::Duration;
+//!
+//! # future::block_on(async {
+//! #
+//! let foo = future::pending::<()>().timeout_named(Duration::from_millis(100), "db.get_user");
+//! assert!(foo.await.is_err());
+//!
+//! let rendered = prometheus::render();
+//! assert!(rendered.contains("timeout_expired_total{name=\"db.get_user\"} 1\n"));
+//! assert!(rendered.contains("timeout_elapsed_seconds_count{name=\"db.get_user\"} 1\n"));
+//! #
+//! # });
+//! ```
+//!
+//! [`NamedTimeout`]: crate::named::NamedTimeout
+
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                                          Imports                                           │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+use core::fmt::Write;
+use core::time::Duration;
+use std::collections::BTreeMap;
+use std::string::String;
+use std::sync::Mutex;
+
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                                        struct Stats                                        │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+/// The upper bounds, in seconds, of the buckets of the `timeout_elapsed_seconds` histogram.
+pub const BUCKETS: [f64; 12] = [
+    0.001, 0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1., 2.5, 5., 10.,
+];
+
+#[derive(Default)]
+struct Stats {
+    completed: u64,
+    expired: u64,
+    /// The number of observations of every bucket (not cumulative).
+    buckets: [u64; BUCKETS.len()],
+    sum: f64,
+}
+
+static STATS: Mutex<BTreeMap<&'static str, Stats>> = Mutex::new(BTreeMap::new());
+
+/// Records the outcome of the timeout with the provided name.
+pub(crate) fn record(name: &'static str, elapsed: Duration, expired: bool) {
+    let mut stats = STATS.lock().unwrap();
+    let stats = stats.entry(name).or_default();
+
+    if expired {
+        stats.expired += 1;
+    } else {
+        stats.completed += 1;
+    }
+
+    let secs = elapsed.as_secs_f64();
+    if let Some(bucket) = BUCKETS.iter().position(|bound| secs <= *bound) {
+        stats.buckets[bucket] += 1;
+    }
+
+    stats.sum += secs;
+}
+
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                                        fn render()                                         │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+/// Escapes a label value as required by the text exposition format.
+fn escape(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for c in value.chars() {
+        match c {
+            '\\' => escaped.push_str("\\\\"),
+            '"' => escaped.push_str("\\\""),
+            '\n' => escaped.push_str("\\n"),
+            c => escaped.push(c),
+        }
+    }
+
+    escaped
+}
+
+/// Renders the statistics of all the named timeouts in the Prometheus text exposition format.
+pub fn render() -> String {
+    let stats = STATS.lock().unwrap();
+    let mut out = String::new();
+
+    // Writing to a `String` can't fail.
+    let _ = writeln!(
+        out,
+        "# HELP timeout_completed_total Futures completed before their timeout."
+    );
+    let _ = writeln!(out, "# TYPE timeout_completed_total counter");
+    for (name, stats) in stats.iter() {
+        let _ = writeln!(
+            out,
+            "timeout_completed_total{{name=\"{}\"}} {}",
+            escape(name),
+            stats.completed
+        );
+    }
+
+    let _ = writeln!(
+        out,
+        "# HELP timeout_expired_total Timeouts expired before their future completed."
+    );
+    let _ = writeln!(out, "# TYPE timeout_expired_total counter");
+    for (name, stats) in stats.iter() {
+        let _ = writeln!(
+            out,
+            "timeout_expired_total{{name=\"{}\"}} {}",
+            escape(name),
+            stats.expired
+        );
+    }
+
+    let _ = writeln!(
+        out,
+        "# HELP timeout_elapsed_seconds Time elapsed until timeouts completed or expired."
+    );
+    let _ = writeln!(out, "# TYPE timeout_elapsed_seconds histogram");
+    for (name, stats) in stats.iter() {
+        let name = escape(name);
+
+        let mut cumulative = 0;
+        for (bound, count) in BUCKETS.iter().zip(stats.buckets.iter()) {
+            cumulative += count;
+            let _ = writeln!(
+                out,
+                "timeout_elapsed_seconds_bucket{{name=\"{}\",le=\"{}\"}} {}",
+                name, bound, cumulative
+            );
+        }
+
+        let count = stats.completed + stats.expired;
+        let _ = writeln!(
+            out,
+            "timeout_elapsed_seconds_bucket{{name=\"{}\",le=\"+Inf\"}} {}",
+            name, count
+        );
+        let _ = writeln!(
+            out,
+            "timeout_elapsed_seconds_sum{{name=\"{}\"}} {}",
+            name, stats.sum
+        );
+        let _ = writeln!(
+            out,
+            "timeout_elapsed_seconds_count{{name=\"{}\"}} {}",
+            name, count
+        );
+    }
+
+    out
+}
@@ -0,0 +1,114 @@
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                                                                                            │ *
+ * │ This Source Code Form is subject to the terms of the Mozilla Public                        │ *
+ * │ License, v. 2.0. If a copy of the MPL was not distributed with this                        │ *
+ * │ file, You can obtain one at http://mozilla.org/MPL/2.0/.                                   │ *
+ * │                                                                                            │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                                          Imports                                           │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+use core::sync::atomic::{AtomicBool, Ordering};
+use core::task::Waker;
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex};
+use std::task::Wake;
+
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                                     struct ReadyQueue                                      │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+#[derive(Debug, Default)]
+struct State {
+    /// The indices of the entries that were woken up and not polled since.
+    ready: VecDeque<usize>,
+    /// The waker of the task polling the entries.
+    waker: Option<Waker>,
+}
+
+/// A queue of the entries of a collection of futures that were woken up, so that the collection
+/// only polls those instead of all of its entries.
+///
+/// Every entry is polled with its own [`Waker`] (see [`ReadyQueue::entry`]), which pushes the
+/// entry's index into the queue and wakes the task polling the collection.
+#[derive(Debug, Default)]
+pub(crate) struct ReadyQueue {
+    state: Mutex<State>,
+}
+
+impl ReadyQueue {
+    /// Creates a new empty [`ReadyQueue`].
+    pub(crate) fn new() -> Arc<Self> {
+        Arc::new(ReadyQueue::default())
+    }
+
+    /// Registers the waker of the task polling the entries, replacing the previous one.
+    pub(crate) fn register(&self, waker: &Waker) {
+        let mut state = self.state.lock().unwrap();
+        match &state.waker {
+            Some(registered) if registered.will_wake(waker) => (),
+            _ => state.waker = Some(waker.clone()),
+        }
+    }
+
+    /// Removes and returns the index of the next entry that was woken up, if any.
+    pub(crate) fn pop(&self) -> Option<usize> {
+        self.state.lock().unwrap().ready.pop_front()
+    }
+
+    /// Creates and returns the [`ReadyEntry`] of the entry at the provided index, which is
+    /// queued so that it gets polled a first time.
+    pub(crate) fn entry(self: &Arc<Self>, index: usize) -> Arc<ReadyEntry> {
+        self.state.lock().unwrap().ready.push_back(index);
+
+        Arc::new(ReadyEntry {
+            queue: self.clone(),
+            index,
+            queued: AtomicBool::new(true),
+        })
+    }
+}
+
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                                     struct ReadyEntry                                      │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+/// The [`Waker`] of an entry of a [`ReadyQueue`].
+#[derive(Debug)]
+pub(crate) struct ReadyEntry {
+    queue: Arc<ReadyQueue>,
+    index: usize,
+    /// Whether the entry is in the queue, so that it is only pushed once per wakeup.
+    queued: AtomicBool,
+}
+
+impl ReadyEntry {
+    /// Marks the entry as no longer queued, before it is polled.
+    pub(crate) fn dequeued(&self) {
+        self.queued.store(false, Ordering::Release);
+    }
+}
+
+impl Wake for ReadyEntry {
+    fn wake(self: Arc<Self>) {
+        self.wake_by_ref();
+    }
+
+    fn wake_by_ref(self: &Arc<Self>) {
+        if self.queued.swap(true, Ordering::AcqRel) {
+            return;
+        }
+
+        let waker = {
+            let mut state = self.queue.state.lock().unwrap();
+            state.ready.push_back(self.index);
+            state.waker.clone()
+        };
+
+        if let Some(waker) = waker {
+            waker.wake();
+        }
+    }
+}
@@ -0,0 +1,344 @@
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                                                                                            │ *
+ * │ This Source Code Form is subject to the terms of the Mozilla Public                        │ *
+ * │ License, v. 2.0. If a copy of the MPL was not distributed with this                        │ *
+ * │ file, You can obtain one at http://mozilla.org/MPL/2.0/.                                   │ *
+ * │                                                                                            │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                                       Documentation                                        │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+//! A stale-while-revalidate cell, serving its current value instantly while it is refreshed in
+//! the background with a timeout.
+//!
+//! Reading a [`Refreshing`] cell never waits: it returns the current value, and if that value is
+//! older than the cell's freshness duration, it asks the cell's [`Refresher`] to refresh it. The
+//! refresher is a future meant to be spawned on an executor, which runs the refresh futures
+//! created by a factory one at a time, each bounded by a timeout. What happens when refreshes
+//! keep timing out is decided by an [`OnTimeouts`] policy: keep serving the stale value, evict
+//! it, or give up refreshing.
+//!
+//! ## Example
+//!
+//! ```rust
+//! use async_io::Timer;
+//! # use futures_lite::future;
+//! use smol_timeout::refresh::Refreshing;
+//! use std::time::Duration;
+//!
+//! # future::block_on(async {
+//! #
+//! let config = Refreshing::new("v1", Duration::from_millis(100));
+//!
+//! let refresher = config.refresher(
+//!     || async {
+//!         Timer::after(Duration::from_millis(50)).await;
+//!         "v2"
+//!     },
+//!     Duration::from_millis(250),
+//! );
+//!
+//! let reads = async {
+//!     assert_eq!(config.get(), Some("v1"));
+//!
+//!     // The value becomes stale, and is still served while it is refreshed...
+//!     Timer::after(Duration::from_millis(150)).await;
+//!     assert!(config.is_stale());
+//!     assert_eq!(config.get(), Some("v1"));
+//!
+//!     // ...until the refresh completes.
+//!     Timer::after(Duration::from_millis(100)).await;
+//!     assert_eq!(config.get(), Some("v2"));
+//! };
+//!
+//! future::or(reads, refresher).await;
+//! #
+//! # });
+//! ```
+
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                                          Imports                                           │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+use crate::{clock, Timer};
+use core::fmt;
+use core::future::Future;
+use core::pin::Pin;
+use core::task::{Context, Poll, Waker};
+use core::time::Duration;
+use pin_project_lite::pin_project;
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
+
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                                      enum OnTimeouts                                       │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+/// What a [`Refresher`] does once refreshes timed out a number of times in a row.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum OnTimeouts {
+    /// The stale value keeps being served, and is refreshed again on the next read.
+    #[default]
+    Serve,
+    /// Once the provided number of refreshes timed out in a row, the stale value is evicted and
+    /// reads return [`None`] until a refresh completes.
+    Evict(u32),
+    /// Once the provided number of refreshes timed out in a row, the [`Refresher`] completes and
+    /// the stale value keeps being served without ever being refreshed again.
+    GiveUp(u32),
+}
+
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                                    struct Refreshing<T>                                    │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+struct State<T> {
+    value: Option<T>,
+    /// The instant the value was last refreshed (or set), if it ever was.
+    refreshed: Option<Instant>,
+    /// Whether a read asked for a refresh that didn't start yet.
+    requested: bool,
+    /// Whether a refresh is running.
+    refreshing: bool,
+    /// The number of refreshes that timed out in a row.
+    timeouts: u32,
+    waker: Option<Waker>,
+}
+
+struct Inner<T> {
+    freshness: Duration,
+    state: Mutex<State<T>>,
+}
+
+/// A cell serving its current value instantly, and asking its [`Refresher`] to refresh it in
+/// the background once it is older than its freshness duration.
+///
+/// Cloning a [`Refreshing`] cell returns a new handle to the same cell.
+pub struct Refreshing<T> {
+    inner: Arc<Inner<T>>,
+}
+
+impl<T: Clone> Refreshing<T> {
+    /// Creates a new [`Refreshing`] cell holding `value`, which is considered fresh for
+    /// `freshness` from now.
+    pub fn new(value: T, freshness: Duration) -> Self {
+        Refreshing::with(Some(value), Some(clock::now()), freshness)
+    }
+
+    /// Creates a new empty [`Refreshing`] cell, whose value is refreshed on the first read and
+    /// then considered fresh for `freshness`.
+    pub fn empty(freshness: Duration) -> Self {
+        Refreshing::with(None, None, freshness)
+    }
+
+    fn with(value: Option<T>, refreshed: Option<Instant>, freshness: Duration) -> Self {
+        Refreshing {
+            inner: Arc::new(Inner {
+                freshness,
+                state: Mutex::new(State {
+                    value,
+                    refreshed,
+                    requested: false,
+                    refreshing: false,
+                    timeouts: 0,
+                    waker: None,
+                }),
+            }),
+        }
+    }
+
+    /// Returns the current value of the cell, or [`None`] if it is empty, asking for a refresh
+    /// if it is stale.
+    pub fn get(&self) -> Option<T> {
+        let mut state = self.inner.state.lock().unwrap();
+        if self.inner.is_stale(&state) && !state.refreshing && !state.requested {
+            state.requested = true;
+            if let Some(waker) = &state.waker {
+                waker.wake_by_ref();
+            }
+        }
+
+        state.value.clone()
+    }
+
+    /// Returns `true` if the cell is empty or its value is older than its freshness duration.
+    pub fn is_stale(&self) -> bool {
+        self.inner.is_stale(&self.inner.state.lock().unwrap())
+    }
+
+    /// Returns the time elapsed since the value was last refreshed, or [`None`] if it never was.
+    pub fn age(&self) -> Option<Duration> {
+        let state = self.inner.state.lock().unwrap();
+        state
+            .refreshed
+            .map(|refreshed| clock::now().saturating_duration_since(refreshed))
+    }
+
+    /// Returns the number of refreshes that timed out in a row.
+    pub fn timeouts(&self) -> u32 {
+        self.inner.state.lock().unwrap().timeouts
+    }
+
+    /// Creates and returns a new [`Refresher`] refreshing the cell with the futures returned by
+    /// `refresh`, each bounded by the provided duration, and keeping serving the stale value
+    /// when they time out.
+    ///
+    /// The refresher only makes progress while it is polled and should be spawned on an
+    /// executor. A cell is meant to have a single refresher.
+    pub fn refresher<F, Fut>(&self, refresh: F, after: Duration) -> Refresher<T, F, Fut>
+    where
+        F: FnMut() -> Fut,
+        Fut: Future<Output = T>,
+    {
+        Refresher {
+            inner: self.inner.clone(),
+            refresh,
+            running: None,
+            timer: Timer::never(),
+            after,
+            policy: OnTimeouts::default(),
+        }
+    }
+}
+
+impl<T> Inner<T> {
+    fn is_stale(&self, state: &State<T>) -> bool {
+        match (&state.value, state.refreshed) {
+            (Some(_), Some(refreshed)) => {
+                clock::now().saturating_duration_since(refreshed) >= self.freshness
+            }
+            _ => true,
+        }
+    }
+}
+
+impl<T> Clone for Refreshing<T> {
+    fn clone(&self) -> Self {
+        Refreshing {
+            inner: self.inner.clone(),
+        }
+    }
+}
+
+impl<T> fmt::Debug for Refreshing<T> {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        let state = self.inner.state.lock().unwrap();
+        fmt.debug_struct("Refreshing")
+            .field("freshness", &self.inner.freshness)
+            .field("stale", &self.inner.is_stale(&state))
+            .field("refreshing", &state.refreshing)
+            .field("timeouts", &state.timeouts)
+            .finish()
+    }
+}
+
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                                struct Refresher<T, F, Fut>                                 │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+pin_project! {
+    /// A future refreshing a [`Refreshing`] cell whenever a read finds it stale, with a timeout
+    /// for every refresh.
+    ///
+    /// Created by [`Refreshing::refresher`]. It never completes, unless its [`OnTimeouts`]
+    /// policy makes it give up.
+    #[must_use = "futures do nothing unless you `.await` or poll them"]
+    pub struct Refresher<T, F, Fut> {
+        inner: Arc<Inner<T>>,
+        refresh: F,
+        #[pin]
+        running: Option<Fut>,
+        timer: Timer,
+        after: Duration,
+        policy: OnTimeouts,
+    }
+}
+
+impl<T, F, Fut> Refresher<T, F, Fut> {
+    /// Sets the [`OnTimeouts`] policy deciding what happens when refreshes keep timing out.
+    pub fn on_timeouts(mut self, policy: OnTimeouts) -> Self {
+        self.policy = policy;
+        self
+    }
+}
+
+impl<T, F, Fut> fmt::Debug for Refresher<T, F, Fut> {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        fmt.debug_struct("Refresher")
+            .field("after", &self.after)
+            .field("policy", &self.policy)
+            .field("refreshing", &self.running.is_some())
+            .finish()
+    }
+}
+
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                            impl Future for Refresher<T, F, Fut>                            │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+impl<T, F, Fut> Future for Refresher<T, F, Fut>
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = T>,
+{
+    type Output = ();
+
+    fn poll(self: Pin<&mut Self>, ctx: &mut Context) -> Poll<()> {
+        let mut this = self.project();
+
+        loop {
+            if let Some(running) = this.running.as_mut().as_pin_mut() {
+                let value = match running.poll(ctx) {
+                    Poll::Ready(value) => Some(value),
+                    Poll::Pending if Pin::new(&mut *this.timer).poll(ctx).is_ready() => None,
+                    Poll::Pending => return Poll::Pending,
+                };
+
+                this.running.set(None);
+
+                let mut state = this.inner.state.lock().unwrap();
+                state.refreshing = false;
+
+                match value {
+                    Some(value) => {
+                        state.value = Some(value);
+                        state.refreshed = Some(clock::now());
+                        state.timeouts = 0;
+                    }
+                    None => {
+                        state.timeouts = state.timeouts.saturating_add(1);
+                        match *this.policy {
+                            OnTimeouts::Serve => (),
+                            OnTimeouts::Evict(max) if state.timeouts >= max => state.value = None,
+                            OnTimeouts::Evict(_) => (),
+                            OnTimeouts::GiveUp(max) if state.timeouts >= max => {
+                                state.waker = None;
+                                return Poll::Ready(());
+                            }
+                            OnTimeouts::GiveUp(_) => (),
+                        }
+                    }
+                }
+            }
+
+            let mut state = this.inner.state.lock().unwrap();
+            if !state.requested {
+                match &state.waker {
+                    Some(waker) if waker.will_wake(ctx.waker()) => (),
+                    _ => state.waker = Some(ctx.waker().clone()),
+                }
+
+                return Poll::Pending;
+            }
+
+            state.requested = false;
+            state.refreshing = true;
+            drop(state);
+
+            this.running.set(Some((this.refresh)()));
+            this.timer.set_after(*this.after);
+        }
+    }
+}
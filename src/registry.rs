@@ -0,0 +1,160 @@
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                                                                                            │ *
+ * │ This Source Code Form is subject to the terms of the Mozilla Public                        │ *
+ * │ License, v. 2.0. If a copy of the MPL was not distributed with this                        │ *
+ * │ file, You can obtain one at http://mozilla.org/MPL/2.0/.                                   │ *
+ * │                                                                                            │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                                       Documentation                                        │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+//! A process-wide registry of the pending [`NamedTimeout`]s, to find out what a stuck service
+//! is waiting on.
+//!
+//! With the `registry` feature, every [`NamedTimeout`] is registered from its creation until
+//! it is dropped, and [`snapshot`] returns all the registered timeouts, sorted by deadline.
+//! This costs a global lock per timeout, so it is meant for debugging.
+//!
+//! ## Example
+//!
+//! ```rust
+//! # use futures_lite::future;
+//! use smol_timeout::named::NamedTimeoutExt;
+//! use smol_timeout::registry;
+//! use std::time::Duration;
+//!
+//! let foo = future::pending::<()>().timeout_named(Duration::from_secs(10), "db.get_user");
+//!
+//! let snapshot = registry::snapshot();
+//! assert_eq!(snapshot.len(), 1);
+//! assert_eq!(snapshot[0].name(), "db.get_user");
+//!
+//! drop(foo);
+//! assert!(registry::snapshot().is_empty());
+//! ```
+//!
+//! [`NamedTimeout`]: crate::named::NamedTimeout
+
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                                          Imports                                           │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+use crate::clock;
+use core::time::Duration;
+use std::collections::BTreeMap;
+use std::string::String;
+use std::sync::Mutex;
+use std::thread;
+use std::time::Instant;
+use std::vec::Vec;
+
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                                   struct PendingTimeout                                    │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+/// A pending [`NamedTimeout`](crate::named::NamedTimeout), as returned by [`snapshot`].
+#[derive(Debug, Clone)]
+pub struct PendingTimeout {
+    name: &'static str,
+    created: Instant,
+    deadline: Instant,
+    thread: Option<String>,
+}
+
+impl PendingTimeout {
+    /// Returns the name of the timeout.
+    pub fn name(&self) -> &'static str {
+        self.name
+    }
+
+    /// Returns the instant the timeout was created at.
+    pub fn created(&self) -> Instant {
+        self.created
+    }
+
+    /// Returns the deadline of the timeout.
+    pub fn deadline(&self) -> Instant {
+        self.deadline
+    }
+
+    /// Returns the name of the thread the timeout was created on, if it has one, as a hint of
+    /// which task is waiting on it.
+    pub fn thread(&self) -> Option<&str> {
+        self.thread.as_deref()
+    }
+
+    /// Returns the time elapsed since the timeout was created.
+    pub fn waited(&self) -> Duration {
+        clock::now().saturating_duration_since(self.created)
+    }
+
+    /// Returns the time remaining until the deadline of the timeout, or zero if it elapsed.
+    pub fn remaining(&self) -> Duration {
+        self.deadline.saturating_duration_since(clock::now())
+    }
+}
+
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                                       fn snapshot()                                        │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+struct Registry {
+    timeouts: BTreeMap<u64, PendingTimeout>,
+    next_key: u64,
+}
+
+static REGISTRY: Mutex<Registry> = Mutex::new(Registry {
+    timeouts: BTreeMap::new(),
+    next_key: 0,
+});
+
+/// Returns all the pending named timeouts, sorted by deadline.
+pub fn snapshot() -> Vec<PendingTimeout> {
+    let mut timeouts = REGISTRY
+        .lock()
+        .unwrap()
+        .timeouts
+        .values()
+        .cloned()
+        .collect::<Vec<_>>();
+
+    timeouts.sort_by_key(|timeout| timeout.deadline);
+    timeouts
+}
+
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                                    struct Registration                                     │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+/// The registration of a pending timeout, removed from the registry once dropped.
+#[derive(Debug)]
+pub(crate) struct Registration {
+    key: u64,
+}
+
+impl Registration {
+    /// Registers a timeout with the provided name and deadline.
+    pub(crate) fn new(name: &'static str, created: Instant, deadline: Instant) -> Self {
+        let timeout = PendingTimeout {
+            name,
+            created,
+            deadline,
+            thread: thread::current().name().map(String::from),
+        };
+
+        let mut registry = REGISTRY.lock().unwrap();
+        let key = registry.next_key;
+        registry.next_key += 1;
+        registry.timeouts.insert(key, timeout);
+
+        Registration { key }
+    }
+}
+
+impl Drop for Registration {
+    fn drop(&mut self) {
+        REGISTRY.lock().unwrap().timeouts.remove(&self.key);
+    }
+}
@@ -0,0 +1,202 @@
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                                                                                            │ *
+ * │ This Source Code Form is subject to the terms of the Mozilla Public                        │ *
+ * │ License, v. 2.0. If a copy of the MPL was not distributed with this                        │ *
+ * │ file, You can obtain one at http://mozilla.org/MPL/2.0/.                                   │ *
+ * │                                                                                            │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                                       Documentation                                        │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+//! An unordered set of labelled futures, each with its own timeout.
+//!
+//! ## Example
+//!
+//! ```rust
+//! use async_io::Timer;
+//! # use futures_lite::future;
+//! use futures_lite::StreamExt;
+//! use smol_timeout::set::TimeoutSet;
+//! use smol_timeout::Elapsed;
+//! use std::time::Duration;
+//!
+//! # future::block_on(async {
+//! #
+//! let sleep = |millis| async move {
+//!     Timer::after(Duration::from_millis(millis)).await;
+//!     millis
+//! };
+//!
+//! let mut set = TimeoutSet::new();
+//! set.insert("foo", sleep(250), Duration::from_millis(100));
+//! set.insert("bar", sleep(50), Duration::from_millis(100));
+//!
+//! assert_eq!(set.next().await, Some(("bar", Ok(50))));
+//! assert_eq!(set.next().await, Some(("foo", Err(Elapsed::new()))));
+//! assert_eq!(set.next().await, None);
+//! #
+//! # });
+//! ```
+
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                                          Imports                                           │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+use crate::ready_queue::{ReadyEntry, ReadyQueue};
+use crate::{Elapsed, Timeout, TimeoutExt};
+use core::fmt;
+use core::future::Future;
+use core::pin::Pin;
+use core::task::{Context, Poll, Waker};
+use core::time::Duration;
+use futures_core::stream::Stream;
+use std::boxed::Box;
+use std::sync::Arc;
+use std::vec::Vec;
+
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                                 struct TimeoutSet<L, Fut>                                  │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+struct Entry<L, Fut: Future> {
+    label: L,
+    future: Pin<Box<Timeout<Fut>>>,
+    ready: Arc<ReadyEntry>,
+    /// The waker the future is polled with, queueing the entry when woken up.
+    waker: Waker,
+}
+
+/// An unordered set of futures, each inserted with a label and its own timeout, that is also a
+/// [`Stream`] yielding every future's label and result as soon as it completes or times out.
+///
+/// A future's result is [`Elapsed`] if it timed out. Futures are dropped as soon as their
+/// result is yielded, so that entries which timed out don't accumulate. Like
+/// `FuturesUnordered`, every future is polled with its own waker, so that only the futures that
+/// were woken up are polled again. The stream ends whenever the set is empty, and more futures
+/// can be inserted afterwards.
+pub struct TimeoutSet<L, Fut: Future> {
+    entries: Vec<Option<Entry<L, Fut>>>,
+    /// The indices of the vacant slots of `entries`.
+    free: Vec<usize>,
+    len: usize,
+    queue: Arc<ReadyQueue>,
+}
+
+impl<L, Fut: Future> TimeoutSet<L, Fut> {
+    /// Creates a new empty [`TimeoutSet`].
+    pub fn new() -> Self {
+        TimeoutSet {
+            entries: Vec::new(),
+            free: Vec::new(),
+            len: 0,
+            queue: ReadyQueue::new(),
+        }
+    }
+
+    /// Inserts `future` into the set with the provided label, timing out if it doesn't complete
+    /// before the provided duration.
+    pub fn insert(&mut self, label: L, future: Fut, after: Duration) {
+        let index = self.free.pop().unwrap_or(self.entries.len());
+        let ready = self.queue.entry(index);
+
+        let entry = Entry {
+            label,
+            future: Box::pin(future.timeout(after)),
+            waker: Waker::from(ready.clone()),
+            ready,
+        };
+
+        match self.entries.get_mut(index) {
+            Some(slot) => *slot = Some(entry),
+            None => self.entries.push(Some(entry)),
+        }
+
+        self.len += 1;
+    }
+
+    /// Returns the number of futures in the set.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Returns `true` if the set contains no futures.
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+}
+
+impl<L, Fut: Future> Default for TimeoutSet<L, Fut> {
+    fn default() -> Self {
+        TimeoutSet::new()
+    }
+}
+
+// The futures are boxed.
+impl<L, Fut: Future> Unpin for TimeoutSet<L, Fut> {}
+
+impl<L: fmt::Debug, Fut: Future> fmt::Debug for TimeoutSet<L, Fut> {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        fmt.debug_list()
+            .entries(self.entries.iter().flatten().map(|entry| &entry.label))
+            .finish()
+    }
+}
+
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                             impl Stream for TimeoutSet<L, Fut>                             │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+impl<L, Fut: Future> Stream for TimeoutSet<L, Fut> {
+    type Item = (L, Result<Fut::Output, Elapsed>);
+
+    fn poll_next(self: Pin<&mut Self>, ctx: &mut Context) -> Poll<Option<Self::Item>> {
+        let this = self.get_mut();
+
+        if this.len == 0 {
+            return Poll::Ready(None);
+        }
+
+        this.queue.register(ctx.waker());
+
+        // Polling at most as many futures as the set contains before yielding, so that futures
+        // waking themselves up can't starve the task.
+        for _ in 0..this.len {
+            let index = match this.queue.pop() {
+                Some(index) => index,
+                None => return Poll::Pending,
+            };
+
+            // The entry may have been removed since it was woken up.
+            let entry = match this.entries.get_mut(index) {
+                Some(Some(entry)) => entry,
+                _ => continue,
+            };
+
+            entry.ready.dequeued();
+            let poll = entry
+                .future
+                .as_mut()
+                .poll(&mut Context::from_waker(&entry.waker));
+
+            if let Poll::Ready(output) = poll {
+                let entry = this.entries[index]
+                    .take()
+                    .expect("the entry was just polled");
+
+                this.free.push(index);
+                this.len -= 1;
+
+                return Poll::Ready(Some((entry.label, output.ok_or(Elapsed::new()))));
+            }
+        }
+
+        ctx.waker().wake_by_ref();
+        Poll::Pending
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.len, Some(self.len))
+    }
+}
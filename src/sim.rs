@@ -0,0 +1,780 @@
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                                                                                            │ *
+ * │ This Source Code Form is subject to the terms of the Mozilla Public                        │ *
+ * │ License, v. 2.0. If a copy of the MPL was not distributed with this                        │ *
+ * │ file, You can obtain one at http://mozilla.org/MPL/2.0/.                                   │ *
+ * │                                                                                            │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                                       Documentation                                        │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+//! A harness running futures under simulated time, so that timeouts and retries spanning
+//! minutes can be tested in milliseconds.
+//!
+//! [`run`] runs a future (and whatever it polls, e.g. a local executor) on the current thread
+//! under a virtual clock, which starts at the real time the simulation started and only
+//! advances when the future can't make progress: it then jumps straight to the earliest
+//! deadline of the pending timers, and fires them. Timers thus never sleep for real, and the
+//! (virtual) time at which everything happens is deterministic.
+//!
+//! Each simulation is driven by a seed, from which [`random`] draws its numbers (e.g. for
+//! jitter or backoff strategies) and which decides the order in which timers firing at the same
+//! instant wake their tasks up. [`run`] takes its seed from the `SMOL_TIMEOUT_SEED` environment
+//! variable if it is set, or picks a random one otherwise, and prints it if the simulation
+//! panics, so that a failure can be replayed with [`run_seeded`] (or by setting the variable).
+//!
+//! With the `sim` feature, the timers of this crate (e.g. the deadline of a
+//! [`TimeoutExt::timeout`], or the sleeps of a supervisor or of a throttle) are this module's
+//! [`Timer`], and compute their deadlines from [`now`]: they follow the [`Clock`] installed on
+//! the thread they are created on, by [`run`] or by [`test_util`](crate::test_util), and are
+//! [`async_io::Timer`]s otherwise. Enabling the feature thus doesn't change any timer of a
+//! program that never installs a clock. A simulated [`Timer`] keeps following its clock
+//! whichever thread it is then polled on, and is a [`DeadlineSource`], so that a [`Timeout`]
+//! (see [`timeout`]) follows the clock it was given. Deadlines computed from [`Instant::now`]
+//! don't follow the virtual clock, and should be computed from [`now`] instead. [`run_dual`]
+//! runs a test under both real and simulated time to catch code depending on the real passing
+//! of time.
+//!
+//! [`TimeoutExt::timeout`]: crate::TimeoutExt::timeout
+//!
+//! ## Example
+//!
+//! ```rust
+//! use smol_timeout::sim::{self, Timer};
+//! use smol_timeout::TimeoutExt;
+//! use std::time::{Duration, Instant};
+//!
+//! let start = Instant::now();
+//!
+//! let (output, elapsed) = sim::run(async {
+//!     let foo = async {
+//!         Timer::after(Duration::from_secs(600)).await;
+//!         24
+//!     };
+//!
+//!     assert_eq!(foo.timeout(Duration::from_secs(60)).await, None);
+//!
+//!     let bar = async {
+//!         Timer::after(Duration::from_secs(60)).await;
+//!         42
+//!     };
+//!
+//!     bar.timeout(Duration::from_secs(600)).await
+//! });
+//!
+//! assert_eq!(output, Some(42));
+//! assert_eq!(elapsed, Duration::from_secs(120));
+//! assert!(start.elapsed() < Duration::from_secs(10));
+//! ```
+
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                                          Imports                                           │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+use crate::{deadline, DeadlineSource};
+use core::cell::RefCell;
+use core::fmt;
+use core::future::Future;
+use core::hash::{BuildHasher, Hasher};
+use core::mem;
+use core::pin::Pin;
+use core::sync::atomic::{AtomicBool, Ordering};
+use core::task::{Context, Poll, Waker};
+use core::time::Duration;
+use std::collections::hash_map::RandomState;
+use std::collections::BTreeMap;
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::task::Wake;
+use std::thread::{self, Thread};
+use std::thread_local;
+use std::time::Instant;
+use std::vec::Vec;
+
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                                        struct Clock                                        │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+struct State {
+    /// The virtual time.
+    now: Instant,
+    /// The wakers of the registered timers, sorted by deadline.
+    timers: BTreeMap<(Instant, u64), Waker>,
+    next_key: u64,
+    seed: u64,
+    /// The state of the random number generator.
+    rng: u64,
+    /// The thread of the simulation driving the clock if it is driven by [`run`], rather than
+    /// only advanced manually, which is unparked whenever a new timer is registered.
+    driver: Option<Thread>,
+    /// Whether the virtual time only advances when asked to.
+    paused: bool,
+    /// Whether the simulation driving the clock ended, after which its timers turn into real
+    /// ones.
+    ended: bool,
+}
+
+impl State {
+    /// Returns the next number of the random number generator (SplitMix64).
+    fn random(&mut self) -> u64 {
+        self.rng = self.rng.wrapping_add(0x9e37_79b9_7f4a_7c15);
+
+        let mut z = self.rng;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+
+    /// Advances the virtual time to the earliest deadline of the registered timers, and removes
+    /// and returns the wakers of the timers whose deadline elapsed, or [`None`] if there are no
+    /// registered timers.
+    fn advance(&mut self) -> Option<Vec<Waker>> {
+        let earliest = self.timers.keys().next()?.0;
+        self.now = self.now.max(earliest);
+
+        Some(self.fire())
+    }
+
+    /// Removes and returns the wakers of the timers whose deadline elapsed.
+    fn fire(&mut self) -> Vec<Waker> {
+        let mut wakers = Vec::new();
+        while let Some(entry) = self.timers.first_entry() {
+            if entry.key().0 > self.now {
+                break;
+            }
+
+            wakers.push(entry.remove());
+        }
+
+        // The timers firing at the same instant are woken up in a seeded random order.
+        for i in (1..wakers.len()).rev() {
+            let j = (self.random() % (i as u64 + 1)) as usize;
+            wakers.swap(i, j);
+        }
+
+        wakers
+    }
+}
+
+/// A virtual clock, shared by the simulated [`Timer`]s following it.
+///
+/// Every simulated timer captures the clock it was created with, and keeps following it
+/// whichever thread it is polled on (e.g. by a multi-threaded executor, or on a blocking pool).
+/// A clock is either driven by [`run`], which makes it the clock of the current thread for the
+/// duration of the simulation, or created with [`Clock::new`] and only moves forward when
+/// [advanced](Clock::advance).
+///
+/// Cloning a [`Clock`] returns a new handle to the same clock.
+///
+/// ## Example
+///
+/// ```rust
+/// # use futures_lite::future;
+/// use smol_timeout::sim::Clock;
+/// use std::thread;
+/// use std::time::Duration;
+///
+/// let clock = Clock::new();
+/// let start = clock.now();
+///
+/// let timer = clock.timer_after(Duration::from_secs(60));
+/// let waiter = thread::spawn(move || future::block_on(timer));
+///
+/// clock.advance(Duration::from_secs(60));
+/// assert_eq!(waiter.join().unwrap(), start + Duration::from_secs(60));
+/// ```
+#[derive(Clone)]
+pub struct Clock {
+    state: Arc<Mutex<State>>,
+}
+
+thread_local! {
+    /// The clock of the simulation running on this thread, if any.
+    static CURRENT: RefCell<Option<Clock>> = const { RefCell::new(None) };
+}
+
+impl Clock {
+    /// Creates a new virtual clock starting at the real time, which only moves forward when
+    /// [advanced](Clock::advance), and whose random number generator is randomly seeded.
+    pub fn new() -> Self {
+        Clock::with(RandomState::new().build_hasher().finish(), None)
+    }
+
+    fn with(seed: u64, driver: Option<Thread>) -> Self {
+        let paused = driver.is_none();
+        Clock {
+            state: Arc::new(Mutex::new(State {
+                now: Instant::now(),
+                timers: BTreeMap::new(),
+                next_key: 0,
+                seed,
+                rng: seed,
+                driver,
+                paused,
+                ended: false,
+            })),
+        }
+    }
+
+    /// Returns the clock of the simulation running on the current thread, if any.
+    pub fn current() -> Option<Clock> {
+        CURRENT.with(|current| current.borrow().clone())
+    }
+
+    /// Returns the virtual time.
+    pub fn now(&self) -> Instant {
+        self.lock().now
+    }
+
+    /// Returns the seed of the clock's random number generator.
+    pub fn seed(&self) -> u64 {
+        self.lock().seed
+    }
+
+    /// Moves the clock forward by the provided duration, waking up the timers whose deadline it
+    /// passes.
+    pub fn advance(&self, by: Duration) {
+        let wakers = {
+            let mut state = self.lock();
+            state.now += by;
+            state.fire()
+        };
+
+        wakers.into_iter().for_each(Waker::wake);
+    }
+
+    /// Creates a timer following this clock, completing after the provided duration.
+    pub fn timer_after(&self, after: Duration) -> Timer {
+        self.timer_at(deadline::deadline_after(self.now(), after))
+    }
+
+    /// Creates a timer following this clock, completing at the provided deadline.
+    pub fn timer_at(&self, deadline: Instant) -> Timer {
+        Timer::on(Some(self), Some(deadline))
+    }
+
+    /// Given a [`Duration`], creates and returns a new [`Timeout`] that will poll both the
+    /// future and a timer following this clock that will complete after the provided duration,
+    /// and return the future's output or [`None`] if the timer completes first.
+    #[track_caller]
+    pub fn timeout<Fut: Future>(&self, future: Fut, after: Duration) -> Timeout<Fut> {
+        crate::Timeout::armed(future, self.timer_after(after), after)
+    }
+
+    fn lock(&self) -> MutexGuard<'_, State> {
+        self.state.lock().unwrap()
+    }
+
+    /// Marks the simulation driving the clock as ended, and wakes its timers up so that they
+    /// turn into real ones.
+    fn end(&self) {
+        let timers = {
+            let mut state = self.lock();
+            state.ended = true;
+            state.driver = None;
+            mem::take(&mut state.timers)
+        };
+
+        timers.into_values().for_each(Waker::wake);
+    }
+}
+
+impl Default for Clock {
+    fn default() -> Self {
+        Clock::new()
+    }
+}
+
+impl fmt::Debug for Clock {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        let state = self.lock();
+        fmt.debug_struct("Clock")
+            .field("now", &state.now)
+            .field("seed", &state.seed)
+            .field("paused", &state.paused)
+            .field("timers", &state.timers.len())
+            .finish()
+    }
+}
+
+/// Returns whether a simulation is running on the current thread.
+pub fn is_simulated() -> bool {
+    CURRENT.with(|current| current.borrow().is_some())
+}
+
+/// Returns the virtual time if a simulation is running on the current thread, or
+/// [`Instant::now`] otherwise.
+pub fn now() -> Instant {
+    match Clock::current() {
+        Some(clock) => clock.now(),
+        None => Instant::now(),
+    }
+}
+
+/// Returns the seed of the simulation running on the current thread, if any.
+pub fn seed() -> Option<u64> {
+    Clock::current().map(|clock| clock.seed())
+}
+
+/// Returns a random number, drawn from the seeded random number generator of the simulation
+/// running on the current thread if any, so that it is reproducible, or from a randomly seeded
+/// hasher otherwise.
+///
+/// ## Example
+///
+/// ```rust
+/// use smol_timeout::sim;
+///
+/// let jitters = || sim::run_seeded(42, async { [sim::random() % 100, sim::random() % 100] }).0;
+/// assert_eq!(jitters(), jitters());
+/// ```
+pub fn random() -> u64 {
+    match Clock::current() {
+        Some(clock) => clock.lock().random(),
+        None => RandomState::new().build_hasher().finish(),
+    }
+}
+
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                                        struct Timer                                        │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+#[derive(Debug)]
+enum Inner {
+    Real(async_io::Timer),
+    Virtual {
+        clock: Clock,
+        /// The key under which the timer is registered, if it is.
+        key: Option<u64>,
+    },
+}
+
+/// A timer following the virtual clock of the simulation it was created in (or the [`Clock`]
+/// it was created with), and backed by an [`async_io::Timer`] otherwise.
+///
+/// A virtual timer keeps following its clock when it is reset, and whichever thread it is
+/// polled on. Once the simulation driving its clock ends, it is turned into an
+/// [`async_io::Timer`] with the same deadline (which, as the clock returns to the real time, may
+/// be further away than it was on the virtual clock).
+#[derive(Debug)]
+#[must_use = "futures do nothing unless you `.await` or poll them"]
+pub struct Timer {
+    inner: Inner,
+    deadline: Option<Instant>,
+}
+
+impl Timer {
+    /// Creates a timer that never completes.
+    pub fn never() -> Self {
+        Timer::on(Clock::current().as_ref(), None)
+    }
+
+    /// Creates a timer completing after the provided duration.
+    pub fn after(after: Duration) -> Self {
+        Timer::at(deadline::deadline_after(now(), after))
+    }
+
+    /// Creates a timer completing at the provided deadline.
+    pub fn at(deadline: Instant) -> Self {
+        Timer::on(Clock::current().as_ref(), Some(deadline))
+    }
+
+    /// Creates a timer following the provided clock, or the real time.
+    fn on(clock: Option<&Clock>, deadline: Option<Instant>) -> Self {
+        let inner = match (clock, deadline) {
+            (Some(clock), _) => Inner::Virtual {
+                clock: clock.clone(),
+                key: None,
+            },
+            (None, Some(deadline)) => Inner::Real(async_io::Timer::at(deadline)),
+            (None, None) => Inner::Real(async_io::Timer::never()),
+        };
+
+        Timer { inner, deadline }
+    }
+
+    /// Resets the timer to complete after the provided duration.
+    pub fn set_after(&mut self, after: Duration) {
+        let now = DeadlineSource::now(self);
+        self.set_at(deadline::deadline_after(now, after));
+    }
+
+    /// Resets the timer to complete at the provided deadline.
+    pub fn set_at(&mut self, deadline: Instant) {
+        self.deregister();
+        if let Inner::Real(timer) = &mut self.inner {
+            timer.set_at(deadline);
+        }
+
+        self.deadline = Some(deadline);
+    }
+
+    fn deregister(&mut self) {
+        if let (Inner::Virtual { clock, key }, Some(deadline)) = (&mut self.inner, self.deadline) {
+            if let Some(key) = key.take() {
+                // The waker is dropped outside of the lock, as dropping it may drop other timers.
+                let waker = clock.lock().timers.remove(&(deadline, key));
+                drop(waker);
+            }
+        }
+    }
+}
+
+impl From<async_io::Timer> for Timer {
+    fn from(timer: async_io::Timer) -> Self {
+        Timer {
+            inner: Inner::Real(timer),
+            deadline: None,
+        }
+    }
+}
+
+impl Future for Timer {
+    type Output = Instant;
+
+    fn poll(mut self: Pin<&mut Self>, ctx: &mut Context) -> Poll<Instant> {
+        let this = &mut *self;
+        let (clock, key, deadline) = match (&mut this.inner, this.deadline) {
+            (Inner::Real(timer), _) => return Pin::new(timer).poll(ctx),
+            (Inner::Virtual { clock, key }, Some(deadline)) => (clock, key, deadline),
+            (Inner::Virtual { .. }, None) => return Poll::Pending,
+        };
+
+        let mut state = clock.lock();
+        if state.ended {
+            drop(state);
+            this.inner = Inner::Real(async_io::Timer::at(deadline));
+            return self.poll(ctx);
+        }
+
+        // The replaced or removed wakers are dropped outside of the lock, as dropping them may
+        // drop other timers.
+        if state.now >= deadline {
+            let now = state.now;
+            let waker = key
+                .take()
+                .and_then(|key| state.timers.remove(&(deadline, key)));
+
+            drop(state);
+            drop(waker);
+            return Poll::Ready(now);
+        }
+
+        let registered = key.is_some();
+        let key = *key.get_or_insert_with(|| {
+            state.next_key += 1;
+            state.next_key
+        });
+
+        let waker = state.timers.insert((deadline, key), ctx.waker().clone());
+
+        // Letting the simulation know about the new timer, in case it is waiting for one (e.g.
+        // because the timer is polled on another thread).
+        if !registered {
+            if let Some(driver) = &state.driver {
+                driver.unpark();
+            }
+        }
+
+        drop(state);
+        drop(waker);
+        Poll::Pending
+    }
+}
+
+impl DeadlineSource for Timer {
+    fn now(&self) -> Instant {
+        match &self.inner {
+            Inner::Real(_) => Instant::now(),
+            Inner::Virtual { clock, .. } => clock.now(),
+        }
+    }
+
+    fn deadline(&self) -> Option<Instant> {
+        self.deadline
+    }
+
+    fn set_at(&mut self, at: Instant) {
+        Timer::set_at(self, at);
+    }
+
+    fn set_never(&mut self) {
+        self.deregister();
+        if let Inner::Real(timer) = &mut self.inner {
+            *timer = async_io::Timer::never();
+        }
+
+        self.deadline = None;
+    }
+
+    fn fork(&self) -> Self {
+        match &self.inner {
+            Inner::Real(_) => Timer::on(None, self.deadline),
+            Inner::Virtual { clock, .. } => Timer::on(Some(clock), self.deadline),
+        }
+    }
+}
+
+impl Drop for Timer {
+    fn drop(&mut self) {
+        self.deregister();
+    }
+}
+
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                                     type Timeout<Fut>                                      │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+/// A [`Timeout`](crate::Timeout) whose deadline is a simulated [`Timer`], and which thus follows
+/// the virtual clock when created inside a simulation.
+///
+/// Created by [`timeout`].
+pub type Timeout<Fut> = crate::Timeout<Fut, Timer>;
+
+/// Given a [`Duration`], creates and returns a new [`Timeout`] that will poll both the future
+/// and a simulated [`Timer`] that will complete after the provided duration, and return the
+/// future's output or [`None`] if the timer completes first.
+#[track_caller]
+pub fn timeout<Fut: Future>(future: Fut, after: Duration) -> Timeout<Fut> {
+    crate::Timeout::armed(future, Timer::after(after), after)
+}
+
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                                          fn run()                                          │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+struct Unparker {
+    woken: AtomicBool,
+    thread: Thread,
+}
+
+impl Wake for Unparker {
+    fn wake(self: Arc<Self>) {
+        self.wake_by_ref();
+    }
+
+    fn wake_by_ref(self: &Arc<Self>) {
+        self.woken.store(true, Ordering::Release);
+        self.thread.unpark();
+    }
+}
+
+/// Ends the simulation of the current thread once dropped, printing its seed if its future
+/// panicked.
+struct Guard {
+    clock: Clock,
+}
+
+impl Drop for Guard {
+    fn drop(&mut self) {
+        if thread::panicking() {
+            std::eprintln!(
+                "simulation panicked, replay it with `SMOL_TIMEOUT_SEED={}`",
+                self.clock.seed()
+            );
+        }
+
+        let _ = CURRENT.try_with(|current| current.borrow_mut().take());
+        self.clock.end();
+    }
+}
+
+/// Runs a future to completion on the current thread under simulated time, returning its
+/// output alongside the virtual duration the run took.
+///
+/// The seed of the simulation is read from the `SMOL_TIMEOUT_SEED` environment variable if it
+/// is set, or picked randomly otherwise, and printed if the future panics.
+///
+/// Whenever the future can't make progress, the virtual clock jumps to the earliest deadline of
+/// the pending virtual timers and fires them. If there are none (or the clock is paused), the
+/// thread is parked until the future is woken up by something else (e.g. another thread), or a
+/// new timer following the clock is polled.
+///
+/// ## Panics
+///
+/// Panics if a simulation is already running on the current thread, or if
+/// `SMOL_TIMEOUT_SEED` is set but isn't a valid `u64`.
+pub fn run<Fut: Future>(future: Fut) -> (Fut::Output, Duration) {
+    let seed = match std::env::var("SMOL_TIMEOUT_SEED") {
+        Ok(seed) => seed.parse().expect("`SMOL_TIMEOUT_SEED` must be a `u64`"),
+        Err(_) => RandomState::new().build_hasher().finish(),
+    };
+
+    run_seeded(seed, future)
+}
+
+/// Runs a future to completion on the current thread under simulated time driven by the
+/// provided seed, returning its output alongside the virtual duration the run took.
+///
+/// ## Panics
+///
+/// Panics if a simulation is already running on the current thread.
+pub fn run_seeded<Fut: Future>(seed: u64, future: Fut) -> (Fut::Output, Duration) {
+    let clock = Clock::with(seed, Some(thread::current()));
+    let start = clock.now();
+    CURRENT.with(|current| {
+        let mut current = current.borrow_mut();
+        assert!(
+            current.is_none(),
+            "a simulation is already running on this thread"
+        );
+
+        *current = Some(clock.clone());
+    });
+
+    let _guard = Guard {
+        clock: clock.clone(),
+    };
+    let mut future = core::pin::pin!(future);
+
+    let unparker = Arc::new(Unparker {
+        woken: AtomicBool::new(true),
+        thread: thread::current(),
+    });
+    let waker = Waker::from(unparker.clone());
+    let mut ctx = Context::from_waker(&waker);
+
+    loop {
+        if unparker.woken.swap(false, Ordering::AcqRel) {
+            if let Poll::Ready(output) = future.as_mut().poll(&mut ctx) {
+                return (output, clock.now().saturating_duration_since(start));
+            }
+
+            continue;
+        }
+
+        let wakers = {
+            let mut state = clock.lock();
+            if state.paused {
+                None
+            } else {
+                state.advance()
+            }
+        };
+
+        match wakers {
+            Some(wakers) => wakers.into_iter().for_each(Waker::wake),
+            // Nothing simulated can wake the future until a new timer is registered or the clock
+            // is resumed.
+            None => thread::park(),
+        }
+    }
+}
+
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                                       fn run_dual()                                        │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+/// Runs the future returned by `body` twice, once against real timers and once under simulated
+/// time, and returns its output after checking that both runs returned the same one.
+///
+/// Code whose outcome differs between the two runs depends on the real passing of time in a way
+/// the virtual clock doesn't capture, e.g. by computing deadlines from [`Instant::now`] rather
+/// than from [`now`], or by waiting on timers that don't follow the virtual clock.
+///
+/// ## Panics
+///
+/// Panics if the outputs of the runs differ, or under the same conditions as [`run`].
+///
+/// ## Example
+///
+/// ```rust
+/// use smol_timeout::sim::{self, Timer};
+/// use smol_timeout::TimeoutExt;
+/// use std::cell::RefCell;
+/// use std::time::{Duration, Instant};
+///
+/// let runs = RefCell::new(Vec::new());
+///
+/// let output = sim::run_dual(|| async {
+///     let start = Instant::now();
+///     let foo = async {
+///         Timer::after(Duration::from_millis(250)).await;
+///         24
+///     };
+///
+///     let output = foo.timeout(Duration::from_millis(100)).await;
+///     runs.borrow_mut().push(start.elapsed());
+///     output
+/// });
+///
+/// assert_eq!(output, None);
+///
+/// // The real run waited for the timeout, while the simulated one took no real time.
+/// let runs = runs.into_inner();
+/// assert!(runs[0] >= Duration::from_millis(100));
+/// assert!(runs[1] < Duration::from_millis(50));
+/// ```
+pub fn run_dual<F, Fut>(mut body: F) -> Fut::Output
+where
+    F: FnMut() -> Fut,
+    Fut: Future,
+    Fut::Output: PartialEq + fmt::Debug,
+{
+    let real = async_io::block_on(body());
+    let (simulated, _) = run(body());
+
+    assert!(
+        real == simulated,
+        "the outputs under real and simulated time differ:\n     real: {:?}\nsimulated: {:?}",
+        real,
+        simulated,
+    );
+
+    simulated
+}
+
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                                         fn pause()                                         │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+/// Pauses the virtual clock of the current thread, starting one if no simulation is running.
+#[cfg(feature = "test-util")]
+pub(crate) fn pause() {
+    CURRENT.with(|current| {
+        let mut current = current.borrow_mut();
+        match &*current {
+            Some(clock) => clock.lock().paused = true,
+            None => *current = Some(Clock::new()),
+        }
+    });
+}
+
+/// Advances the virtual clock of the current thread if it is paused, and fires the timers whose
+/// deadline elapsed.
+#[cfg(feature = "test-util")]
+pub(crate) fn advance(by: Duration) {
+    if let Some(clock) = Clock::current() {
+        if clock.lock().paused {
+            clock.advance(by);
+        }
+    }
+}
+
+/// Resumes the virtual clock of the current thread, either letting the simulation advance it
+/// again or, if it was only started by [`pause`], ending it.
+#[cfg(feature = "test-util")]
+pub(crate) fn resume() {
+    let ended = CURRENT.with(|current| {
+        let mut current = current.borrow_mut();
+        match &*current {
+            Some(clock) => {
+                let mut state = clock.lock();
+                if state.driver.is_some() {
+                    state.paused = false;
+                    None
+                } else {
+                    drop(state);
+                    current.take()
+                }
+            }
+            None => None,
+        }
+    });
+
+    // The remaining timers are woken up to turn into real ones.
+    if let Some(ended) = ended {
+        ended.end();
+    }
+}
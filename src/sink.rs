@@ -0,0 +1,272 @@
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                                                                                            │ *
+ * │ This Source Code Form is subject to the terms of the Mozilla Public                        │ *
+ * │ License, v. 2.0. If a copy of the MPL was not distributed with this                        │ *
+ * │ file, You can obtain one at http://mozilla.org/MPL/2.0/.                                   │ *
+ * │                                                                                            │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                                       Documentation                                        │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+//! Graceful closes of [`Sink`]s within a deadline, for clean shutdowns of outbound pipelines.
+//!
+//! With the `futures-sink` feature, [`SinkTimeoutExt`] provides a way to close a sink (i.e.
+//! flush it and then close it) for at most a duration, and a way to first send it the items
+//! still buffered by the caller, reporting how many of them were lost if the deadline fires.
+//!
+//! ## Example
+//!
+//! ```rust
+//! # use futures_lite::future;
+//! use futures_sink::Sink;
+//! use smol_timeout::sink::SinkTimeoutExt;
+//! use std::pin::Pin;
+//! use std::task::{Context, Poll};
+//! use std::time::Duration;
+//!
+//! /// A sink whose peer stops accepting items after the first two.
+//! struct Stalling(usize);
+//!
+//! impl Sink<u32> for Stalling {
+//!     type Error = ();
+//!
+//!     fn poll_ready(self: Pin<&mut Self>, _: &mut Context) -> Poll<Result<(), ()>> {
+//!         if self.0 < 2 { Poll::Ready(Ok(())) } else { Poll::Pending }
+//!     }
+//!
+//!     fn start_send(mut self: Pin<&mut Self>, _: u32) -> Result<(), ()> {
+//!         self.0 += 1;
+//!         Ok(())
+//!     }
+//!
+//!     fn poll_flush(self: Pin<&mut Self>, _: &mut Context) -> Poll<Result<(), ()>> {
+//!         Poll::Ready(Ok(()))
+//!     }
+//!
+//!     fn poll_close(self: Pin<&mut Self>, _: &mut Context) -> Poll<Result<(), ()>> {
+//!         Poll::Ready(Ok(()))
+//!     }
+//! }
+//!
+//! # future::block_on(async {
+//! #
+//! let mut sink = Stalling(0);
+//!
+//! let drained = sink
+//!     .drain_within(vec![1, 2, 3, 4, 5], Duration::from_millis(100))
+//!     .await
+//!     .unwrap();
+//!
+//! assert_eq!(drained.sent(), 2);
+//! assert_eq!(drained.lost(), 3);
+//! assert!(!drained.is_closed());
+//! #
+//! # });
+//! ```
+
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                                          Imports                                           │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+use crate::Timer;
+use core::fmt;
+use core::future::Future;
+use core::marker::PhantomData;
+use core::pin::Pin;
+use core::task::{Context, Poll};
+use core::time::Duration;
+use futures_sink::Sink;
+
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                                       struct Drained                                       │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+/// The outcome of [`SinkTimeoutExt::drain_within`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct Drained {
+    sent: usize,
+    lost: usize,
+    closed: bool,
+}
+
+impl Drained {
+    /// Returns the number of items sent into the sink.
+    ///
+    /// Items sent into a sink which didn't close in time may still have been lost in its own
+    /// buffer.
+    pub fn sent(&self) -> usize {
+        self.sent
+    }
+
+    /// Returns the number of items which couldn't be sent into the sink before the deadline,
+    /// and were dropped.
+    pub fn lost(&self) -> usize {
+        self.lost
+    }
+
+    /// Returns whether the sink was closed before the deadline.
+    pub fn is_closed(&self) -> bool {
+        self.closed
+    }
+}
+
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                                 trait SinkTimeoutExt<Item>                                 │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+/// An extension trait for [`Sink`]s that provides a way to close them within a deadline.
+pub trait SinkTimeoutExt<Item>: Sink<Item> + Unpin + Sized {
+    /// Flushes and closes the sink, waiting for at most the provided duration, and returns the
+    /// result of closing it or [`None`] if the duration elapsed first.
+    fn close_timeout(&mut self, after: Duration) -> CloseTimeout<'_, Self, Item> {
+        CloseTimeout {
+            sink: self,
+            timer: Timer::after(after),
+            _item: PhantomData,
+        }
+    }
+
+    /// Sends all the provided items into the sink, then flushes and closes it, for at most the
+    /// provided duration, and returns how many items were sent and lost.
+    ///
+    /// The items which couldn't be sent before the duration elapsed are dropped.
+    fn drain_within<I>(
+        &mut self,
+        items: I,
+        after: Duration,
+    ) -> DrainWithin<'_, Self, Item, I::IntoIter>
+    where
+        I: IntoIterator<Item = Item>,
+    {
+        DrainWithin {
+            sink: self,
+            items: items.into_iter(),
+            next: None,
+            sent: 0,
+            timer: Timer::after(after),
+        }
+    }
+}
+
+impl<S: Sink<Item> + Unpin, Item> SinkTimeoutExt<Item> for S {}
+
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                              struct CloseTimeout<'a, S, Item>                              │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+/// A future closing a [`Sink`] with a timeout (see [`SinkTimeoutExt::close_timeout`]).
+#[must_use = "futures do nothing unless you `.await` or poll them"]
+pub struct CloseTimeout<'a, S, Item> {
+    sink: &'a mut S,
+    timer: Timer,
+    _item: PhantomData<fn(Item)>,
+}
+
+impl<S, Item> fmt::Debug for CloseTimeout<'_, S, Item> {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        fmt.debug_struct("CloseTimeout").finish()
+    }
+}
+
+impl<S: Sink<Item> + Unpin, Item> Future for CloseTimeout<'_, S, Item> {
+    type Output = Option<Result<(), S::Error>>;
+
+    fn poll(mut self: Pin<&mut Self>, ctx: &mut Context) -> Poll<Self::Output> {
+        if let Poll::Ready(res) = Pin::new(&mut *self.sink).poll_close(ctx) {
+            return Poll::Ready(Some(res));
+        }
+
+        if Pin::new(&mut self.timer).poll(ctx).is_ready() {
+            return Poll::Ready(None);
+        }
+
+        Poll::Pending
+    }
+}
+
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                             struct DrainWithin<'a, S, Item, I>                             │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+/// A future sending items into a [`Sink`] and closing it with a timeout (see
+/// [`SinkTimeoutExt::drain_within`]).
+#[must_use = "futures do nothing unless you `.await` or poll them"]
+pub struct DrainWithin<'a, S, Item, I> {
+    sink: &'a mut S,
+    items: I,
+    /// The item waiting for the sink to be ready, if any.
+    next: Option<Item>,
+    sent: usize,
+    timer: Timer,
+}
+
+// The items are never pinned.
+impl<S, Item, I> Unpin for DrainWithin<'_, S, Item, I> {}
+
+impl<S, Item, I> fmt::Debug for DrainWithin<'_, S, Item, I> {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        fmt.debug_struct("DrainWithin")
+            .field("sent", &self.sent)
+            .finish()
+    }
+}
+
+impl<S, Item, I> DrainWithin<'_, S, Item, I>
+where
+    S: Sink<Item> + Unpin,
+    I: Iterator<Item = Item>,
+{
+    /// Returns `Pending` or, if the timer completed, how many items were sent and lost.
+    fn expire(&mut self, ctx: &mut Context) -> Poll<Result<Drained, S::Error>> {
+        if Pin::new(&mut self.timer).poll(ctx).is_pending() {
+            return Poll::Pending;
+        }
+
+        let lost = usize::from(self.next.take().is_some()) + self.items.by_ref().count();
+
+        Poll::Ready(Ok(Drained {
+            sent: self.sent,
+            lost,
+            closed: false,
+        }))
+    }
+}
+
+impl<S, Item, I> Future for DrainWithin<'_, S, Item, I>
+where
+    S: Sink<Item> + Unpin,
+    I: Iterator<Item = Item>,
+{
+    type Output = Result<Drained, S::Error>;
+
+    fn poll(self: Pin<&mut Self>, ctx: &mut Context) -> Poll<Self::Output> {
+        let this = self.get_mut();
+
+        while let Some(item) = this.next.take().or_else(|| this.items.next()) {
+            match Pin::new(&mut *this.sink).poll_ready(ctx) {
+                Poll::Ready(Ok(())) => {
+                    Pin::new(&mut *this.sink).start_send(item)?;
+                    this.sent += 1;
+                }
+                Poll::Ready(Err(err)) => return Poll::Ready(Err(err)),
+                Poll::Pending => {
+                    this.next = Some(item);
+                    return this.expire(ctx);
+                }
+            }
+        }
+
+        match Pin::new(&mut *this.sink).poll_close(ctx) {
+            Poll::Ready(Ok(())) => Poll::Ready(Ok(Drained {
+                sent: this.sent,
+                lost: 0,
+                closed: true,
+            })),
+            Poll::Ready(Err(err)) => Poll::Ready(Err(err)),
+            Poll::Pending => this.expire(ctx),
+        }
+    }
+}
@@ -0,0 +1,158 @@
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                                                                                            │ *
+ * │ This Source Code Form is subject to the terms of the Mozilla Public                        │ *
+ * │ License, v. 2.0. If a copy of the MPL was not distributed with this                        │ *
+ * │ file, You can obtain one at http://mozilla.org/MPL/2.0/.                                   │ *
+ * │                                                                                            │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                                       Documentation                                        │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+//! A timeout with a smaller memory footprint than [`Timeout`](crate::Timeout), for code holding
+//! large numbers of pending timeouts.
+//!
+//! A [`Timeout`](crate::Timeout) embeds a whole [`Timer`]. A [`SlimTimeout`] only embeds its
+//! deadline and a pointer to a [`Timer`], which is allocated the first time the future returns
+//! [`Poll::Pending`], so that futures completing immediately don't allocate at all.
+//!
+//! ## Example
+//!
+//! ```rust
+//! use async_io::Timer;
+//! # use futures_lite::future;
+//! use smol_timeout::slim::SlimTimeoutExt;
+//! use std::time::Duration;
+//!
+//! # future::block_on(async {
+//! #
+//! let foo = async {
+//!     Timer::after(Duration::from_millis(250)).await;
+//!     24
+//! };
+//!
+//! assert_eq!(foo.slim_timeout(Duration::from_millis(100)).await, None);
+//!
+//! let bar = async {
+//!     Timer::after(Duration::from_millis(100)).await;
+//!     42
+//! };
+//!
+//! assert_eq!(bar.slim_timeout(Duration::from_millis(250)).await, Some(42));
+//! #
+//! # });
+//! ```
+
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                                          Imports                                           │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+use crate::Timer;
+use crate::{clock, deadline};
+use core::future::Future;
+use core::pin::Pin;
+use core::task::{Context, Poll};
+use core::time::Duration;
+use pin_project_lite::pin_project;
+use std::boxed::Box;
+use std::time::Instant;
+
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                                  struct SlimTimeout<Fut>                                   │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+pin_project! {
+    /// A future polling another future and, once it returned [`Poll::Pending`], a boxed
+    /// [`Timer`] that will complete at a specified deadline, and returning the future's output or
+    /// [`None`] if the timer completes first.
+    #[derive(Debug)]
+    #[must_use = "futures do nothing unless you `.await` or poll them"]
+    pub struct SlimTimeout<Fut> {
+        #[pin]
+        future: Fut,
+        deadline: Instant,
+        timer: Option<Box<Timer>>,
+    }
+}
+
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                                trait SlimTimeoutExt: Future                                │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+/// An extension trait for [`Future`]s that provides a way to create [`SlimTimeout`]s.
+pub trait SlimTimeoutExt: Future {
+    /// Given a [`Duration`], creates and returns a new [`SlimTimeout`] that will poll both the
+    /// future and a [`Timer`] that will complete after the provided duration, and return the
+    /// future's output or [`None`] if the timer completes first.
+    ///
+    /// ## Example
+    ///
+    /// ```rust
+    /// # use futures_lite::future;
+    /// use smol_timeout::slim::SlimTimeoutExt;
+    /// use std::time::Duration;
+    ///
+    /// # future::block_on(async {
+    /// #
+    /// let foo = future::pending::<()>().slim_timeout(Duration::from_millis(100));
+    /// assert_eq!(foo.await, None);
+    /// #
+    /// # })
+    /// ```
+    fn slim_timeout(self, after: Duration) -> SlimTimeout<Self>
+    where
+        Self: Sized,
+    {
+        self.slim_timeout_at(deadline::deadline_after(clock::now(), after))
+    }
+
+    /// Given an [`Instant`], creates and returns a new [`SlimTimeout`] that will poll both the
+    /// future and a [`Timer`] that will complete at the provided deadline, and return the
+    /// future's output or [`None`] if the timer completes first.
+    fn slim_timeout_at(self, deadline: Instant) -> SlimTimeout<Self>
+    where
+        Self: Sized,
+    {
+        SlimTimeout {
+            future: self,
+            deadline,
+            timer: None,
+        }
+    }
+}
+
+impl<Fut: Future> SlimTimeoutExt for Fut {}
+
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                              impl Future for SlimTimeout<Fut>                              │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+impl<Fut: Future> Future for SlimTimeout<Fut> {
+    type Output = Option<Fut::Output>;
+
+    fn poll(self: Pin<&mut Self>, ctx: &mut Context) -> Poll<Self::Output> {
+        let this = self.project();
+
+        if let Some(timer) = this.timer {
+            if Pin::new(&mut **timer).poll(ctx).is_ready() {
+                return Poll::Ready(None);
+            }
+        }
+
+        if let Poll::Ready(output) = this.future.poll(ctx) {
+            return Poll::Ready(Some(output));
+        }
+
+        if this.timer.is_none() {
+            let mut timer = Box::new(Timer::at(*this.deadline));
+            if Pin::new(&mut *timer).poll(ctx).is_ready() {
+                return Poll::Ready(None);
+            }
+
+            *this.timer = Some(timer);
+        }
+
+        Poll::Pending
+    }
+}
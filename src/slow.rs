@@ -0,0 +1,182 @@
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                                                                                            │ *
+ * │ This Source Code Form is subject to the terms of the Mozilla Public                        │ *
+ * │ License, v. 2.0. If a copy of the MPL was not distributed with this                        │ *
+ * │ file, You can obtain one at http://mozilla.org/MPL/2.0/.                                   │ *
+ * │                                                                                            │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                                       Documentation                                        │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+//! A way to detect futures blocking the thread polling them.
+//!
+//! A future whose `poll` blocks (e.g. on synchronous I/O or a heavy computation) stalls every
+//! other task of its executor thread, and can't be interrupted by a timeout since it doesn't
+//! yield. A [`SlowPoll`] measures the duration of every `poll` of its future, and calls a
+//! callback with it whenever it exceeds a threshold.
+//!
+//! ## Example
+//!
+//! ```rust
+//! # use futures_lite::future;
+//! use smol_timeout::slow::SlowPollExt;
+//! use std::thread;
+//! use std::time::Duration;
+//!
+//! # future::block_on(async {
+//! #
+//! let mut slow = Vec::new();
+//!
+//! let blocking = async {
+//!     thread::sleep(Duration::from_millis(50));
+//!     42
+//! };
+//!
+//! let blocking = blocking.on_slow_poll(Duration::from_millis(10), |duration| slow.push(duration));
+//! assert_eq!(blocking.await, 42);
+//!
+//! assert_eq!(slow.len(), 1);
+//! assert!(slow[0] >= Duration::from_millis(50));
+//! #
+//! # });
+//! ```
+
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                                          Imports                                           │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+use crate::clock::Stamp;
+use core::fmt;
+use core::future::Future;
+use core::pin::Pin;
+use core::task::{Context, Poll};
+use core::time::Duration;
+use pin_project_lite::pin_project;
+
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                                  struct SlowPoll<Fut, F>                                   │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+pin_project! {
+    /// A future polling another future and calling a callback with the duration of every `poll`
+    /// exceeding a threshold.
+    #[must_use = "futures do nothing unless you `.await` or poll them"]
+    pub struct SlowPoll<Fut, F> {
+        #[pin]
+        future: Fut,
+        threshold: Duration,
+        callback: F,
+    }
+}
+
+impl<Fut, F> fmt::Debug for SlowPoll<Fut, F> {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        fmt.debug_struct("SlowPoll")
+            .field("threshold", &self.threshold)
+            .finish()
+    }
+}
+
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                                 trait SlowPollExt: Future                                  │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+/// An extension trait for [`Future`]s that provides a way to create [`SlowPoll`]s.
+pub trait SlowPollExt: Future {
+    /// Given a threshold and a callback, creates and returns a new [`SlowPoll`] that will poll
+    /// the future and call the callback with the duration of every `poll` exceeding the
+    /// threshold.
+    fn on_slow_poll<F>(self, threshold: Duration, callback: F) -> SlowPoll<Self, F>
+    where
+        Self: Sized,
+        F: FnMut(Duration),
+    {
+        SlowPoll {
+            future: self,
+            threshold,
+            callback,
+        }
+    }
+
+    /// Given a threshold and a name, creates and returns a new [`SlowPoll`] that will poll the
+    /// future and log a warning including the name for every `poll` exceeding the threshold.
+    ///
+    /// Only available with the `log` feature.
+    #[cfg(feature = "log")]
+    fn log_slow_polls(
+        self,
+        threshold: Duration,
+        name: &'static str,
+    ) -> SlowPoll<Self, SlowPollLogger>
+    where
+        Self: Sized,
+    {
+        SlowPoll {
+            future: self,
+            threshold,
+            callback: SlowPollLogger { name },
+        }
+    }
+}
+
+impl<Fut: Future> SlowPollExt for Fut {}
+
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                              impl Future for SlowPoll<Fut, F>                              │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+impl<Fut: Future, F: SlowPollCallback> Future for SlowPoll<Fut, F> {
+    type Output = Fut::Output;
+
+    fn poll(self: Pin<&mut Self>, ctx: &mut Context) -> Poll<Self::Output> {
+        let this = self.project();
+
+        let start = Stamp::now();
+        let poll = this.future.poll(ctx);
+
+        let duration = start.elapsed();
+        if duration > *this.threshold {
+            this.callback.call(duration);
+        }
+
+        poll
+    }
+}
+
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                                   trait SlowPollCallback                                   │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+/// The callbacks [`SlowPoll`]s can call, implemented by closures and [`SlowPollLogger`].
+pub trait SlowPollCallback {
+    /// Called with the duration of a `poll` exceeding the threshold.
+    fn call(&mut self, duration: Duration);
+}
+
+impl<F: FnMut(Duration)> SlowPollCallback for F {
+    fn call(&mut self, duration: Duration) {
+        self(duration)
+    }
+}
+
+/// A [`SlowPollCallback`] logging a warning for every `poll` exceeding the threshold (see
+/// [`SlowPollExt::log_slow_polls`]).
+///
+/// Only available with the `log` feature.
+#[cfg(feature = "log")]
+#[derive(Debug, Clone, Copy)]
+pub struct SlowPollLogger {
+    name: &'static str,
+}
+
+#[cfg(feature = "log")]
+impl SlowPollCallback for SlowPollLogger {
+    fn call(&mut self, duration: Duration) {
+        ::log::warn!(
+            target: "smol_timeout",
+            "a poll of `{}` blocked for {:?}", self.name, duration
+        );
+    }
+}
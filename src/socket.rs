@@ -0,0 +1,206 @@
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                                                                                            │ *
+ * │ This Source Code Form is subject to the terms of the Mozilla Public                        │ *
+ * │ License, v. 2.0. If a copy of the MPL was not distributed with this                        │ *
+ * │ file, You can obtain one at http://mozilla.org/MPL/2.0/.                                   │ *
+ * │                                                                                            │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                                       Documentation                                        │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+//! Sockets whose reads and writes time out after default durations.
+//!
+//! With the `futures-io` feature, a [`TimeoutSocket`] wraps an [`AsyncRead`] and/or
+//! [`AsyncWrite`] (e.g. an [`Async<TcpStream>`] or an `Async<UnixStream>`)
+//! with a read and a write timeout set once at construction, like std's
+//! [`set_read_timeout`](std::net::TcpStream::set_read_timeout) and
+//! [`set_write_timeout`](std::net::TcpStream::set_write_timeout): every read, write, flush or
+//! close that doesn't complete within its timeout fails with an [`io::Error`] of kind
+//! [`TimedOut`](io::ErrorKind::TimedOut).
+//!
+//! [`Async<TcpStream>`]: https://docs.rs/async-io/latest/async_io/struct.Async.html
+//!
+//! The timer of an operation is only armed once the socket isn't ready, and is dropped as soon
+//! as the operation completes, so that idle sockets don't keep timers registered.
+//!
+//! ## Example
+//!
+//! ```rust
+//! use async_io::Async;
+//! # use futures_lite::future;
+//! use futures_lite::AsyncReadExt;
+//! use smol_timeout::socket::TimeoutSocket;
+//! use std::io;
+//! use std::net::{TcpListener, TcpStream};
+//! use std::time::Duration;
+//!
+//! # future::block_on(async {
+//! #
+//! let listener = Async::<TcpListener>::bind(([127, 0, 0, 1], 0))?;
+//! let stream = Async::<TcpStream>::connect(listener.get_ref().local_addr()?).await?;
+//! let (_peer, _) = listener.accept().await?;
+//!
+//! let mut stream = TimeoutSocket::new(stream, Some(Duration::from_millis(100)), None);
+//!
+//! let mut buf = [0; 16];
+//! let err = stream.read(&mut buf).await.unwrap_err();
+//! assert_eq!(err.kind(), io::ErrorKind::TimedOut);
+//! #
+//! # io::Result::Ok(())
+//! # }).unwrap();
+//! ```
+
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                                          Imports                                           │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+use crate::{Elapsed, Timer};
+use core::future::Future;
+use core::pin::Pin;
+use core::task::{Context, Poll};
+use core::time::Duration;
+use futures_io::{AsyncRead, AsyncWrite};
+use pin_project_lite::pin_project;
+use std::io;
+
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                                  struct TimeoutSocket<S>                                   │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+pin_project! {
+    /// A socket whose reads and writes fail with an [`io::Error`] of kind
+    /// [`TimedOut`](io::ErrorKind::TimedOut) if they don't complete within its read and write
+    /// timeouts.
+    ///
+    /// Flushes and closes are bounded by the write timeout.
+    #[derive(Debug)]
+    pub struct TimeoutSocket<S> {
+        #[pin]
+        io: S,
+        read_timeout: Option<Duration>,
+        write_timeout: Option<Duration>,
+        // The timer of the pending read, if any.
+        read_timer: Option<Timer>,
+        // The timer of the pending write, flush or close, if any.
+        write_timer: Option<Timer>,
+    }
+}
+
+impl<S> TimeoutSocket<S> {
+    /// Wraps the socket, bounding its reads and writes with the provided timeouts (or leaving
+    /// them unbounded, if [`None`]).
+    pub fn new(io: S, read_timeout: Option<Duration>, write_timeout: Option<Duration>) -> Self {
+        TimeoutSocket {
+            io,
+            read_timeout,
+            write_timeout,
+            read_timer: None,
+            write_timer: None,
+        }
+    }
+
+    /// Returns the read timeout.
+    pub fn read_timeout(&self) -> Option<Duration> {
+        self.read_timeout
+    }
+
+    /// Sets the read timeout, which applies from the next read on.
+    pub fn set_read_timeout(&mut self, timeout: Option<Duration>) {
+        self.read_timeout = timeout;
+        self.read_timer = None;
+    }
+
+    /// Returns the write timeout.
+    pub fn write_timeout(&self) -> Option<Duration> {
+        self.write_timeout
+    }
+
+    /// Sets the write timeout, which applies from the next write, flush or close on.
+    pub fn set_write_timeout(&mut self, timeout: Option<Duration>) {
+        self.write_timeout = timeout;
+        self.write_timer = None;
+    }
+
+    /// Returns a reference to the socket.
+    pub fn get_ref(&self) -> &S {
+        &self.io
+    }
+
+    /// Returns a mutable reference to the socket.
+    pub fn get_mut(&mut self) -> &mut S {
+        &mut self.io
+    }
+
+    /// Consumes the [`TimeoutSocket`], returning the socket.
+    pub fn into_inner(self) -> S {
+        self.io
+    }
+}
+
+/// Bounds the provided poll of an operation with the timer of the operation, armed for the
+/// provided timeout if it isn't yet.
+fn bound<T>(
+    poll: Poll<io::Result<T>>,
+    timeout: Option<Duration>,
+    timer: &mut Option<Timer>,
+    ctx: &mut Context,
+) -> Poll<io::Result<T>> {
+    if poll.is_ready() {
+        *timer = None;
+        return poll;
+    }
+
+    let after = match timeout {
+        Some(after) => after,
+        None => return Poll::Pending,
+    };
+
+    let pending = Pin::new(timer.get_or_insert_with(|| Timer::after(after)))
+        .poll(ctx)
+        .is_pending();
+
+    if pending {
+        return Poll::Pending;
+    }
+
+    *timer = None;
+    Poll::Ready(Err(Elapsed::new().into()))
+}
+
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                      impl AsyncRead + AsyncWrite for TimeoutSocket<S>                      │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+impl<S: AsyncRead> AsyncRead for TimeoutSocket<S> {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        ctx: &mut Context,
+        buf: &mut [u8],
+    ) -> Poll<io::Result<usize>> {
+        let this = self.project();
+        let poll = this.io.poll_read(ctx, buf);
+        bound(poll, *this.read_timeout, this.read_timer, ctx)
+    }
+}
+
+impl<S: AsyncWrite> AsyncWrite for TimeoutSocket<S> {
+    fn poll_write(self: Pin<&mut Self>, ctx: &mut Context, buf: &[u8]) -> Poll<io::Result<usize>> {
+        let this = self.project();
+        let poll = this.io.poll_write(ctx, buf);
+        bound(poll, *this.write_timeout, this.write_timer, ctx)
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, ctx: &mut Context) -> Poll<io::Result<()>> {
+        let this = self.project();
+        let poll = this.io.poll_flush(ctx);
+        bound(poll, *this.write_timeout, this.write_timer, ctx)
+    }
+
+    fn poll_close(self: Pin<&mut Self>, ctx: &mut Context) -> Poll<io::Result<()>> {
+        let this = self.project();
+        let poll = this.io.poll_close(ctx);
+        bound(poll, *this.write_timeout, this.write_timer, ctx)
+    }
+}
@@ -0,0 +1,267 @@
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                                                                                            │ *
+ * │ This Source Code Form is subject to the terms of the Mozilla Public                        │ *
+ * │ License, v. 2.0. If a copy of the MPL was not distributed with this                        │ *
+ * │ file, You can obtain one at http://mozilla.org/MPL/2.0/.                                   │ *
+ * │                                                                                            │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                                       Documentation                                        │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+//! Timeouts collecting statistics about how their future was polled, to tell a slow dependency
+//! from a starved executor.
+//!
+//! A [`StatsTimeout`] counts how many times its future was polled and woken up, and measures
+//! the time spent inside its `poll` as well as the scheduling latency (the time between a
+//! wakeup and the following poll), returning them as [`PollStats`] alongside the future's
+//! output. A future that timed out after few wakeups and little time spent polling was waiting
+//! on something slow; one that was mostly polled or woken up a lot was busy itself; and high
+//! scheduling latencies point at an overloaded executor rather than at the future.
+//!
+//! ## Example
+//!
+//! ```rust
+//! use async_io::Timer;
+//! # use futures_lite::future;
+//! use smol_timeout::stats::StatsTimeoutExt;
+//! use std::time::Duration;
+//!
+//! # future::block_on(async {
+//! #
+//! let foo = async {
+//!     Timer::after(Duration::from_millis(250)).await;
+//!     24
+//! };
+//!
+//! let (output, stats) = foo.timeout_with_stats(Duration::from_millis(100)).await;
+//! assert_eq!(output, None);
+//! assert_eq!(stats.polls(), 2);
+//! assert_eq!(stats.wakeups(), 1);
+//! assert!(stats.max_scheduling() <= stats.scheduling());
+//!
+//! let bar = async {
+//!     Timer::after(Duration::from_millis(100)).await;
+//!     42
+//! };
+//!
+//! let (output, stats) = bar.timeout_with_stats(Duration::from_millis(250)).await;
+//! assert_eq!(output, Some(42));
+//! assert_eq!(stats.polls(), 2);
+//! #
+//! # });
+//! ```
+
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                                          Imports                                           │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+use crate::clock::Stamp;
+use crate::Timer;
+use core::fmt;
+use core::future::Future;
+use core::pin::Pin;
+use core::sync::atomic::{AtomicU64, Ordering};
+use core::task::{Context, Poll, Waker};
+use core::time::Duration;
+use pin_project_lite::pin_project;
+use std::sync::{Arc, Mutex};
+use std::task::Wake;
+
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                                      struct PollStats                                      │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+/// Statistics about how the future of a [`StatsTimeout`] was polled.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct PollStats {
+    polls: u64,
+    busy: Duration,
+    wakeups: u64,
+    scheduling: Duration,
+    max_scheduling: Duration,
+}
+
+impl PollStats {
+    /// Returns the number of times the future was polled.
+    pub fn polls(&self) -> u64 {
+        self.polls
+    }
+
+    /// Returns the cumulative time spent inside the future's `poll`.
+    pub fn busy(&self) -> Duration {
+        self.busy
+    }
+
+    /// Returns the number of times the future (or its timer) woke up its task.
+    pub fn wakeups(&self) -> u64 {
+        self.wakeups
+    }
+
+    /// Returns the cumulative scheduling latency, i.e. the time elapsed between the task being
+    /// woken up and it polling the future, over all the wakeups.
+    pub fn scheduling(&self) -> Duration {
+        self.scheduling
+    }
+
+    /// Returns the highest scheduling latency of a single wakeup.
+    pub fn max_scheduling(&self) -> Duration {
+        self.max_scheduling
+    }
+}
+
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                                       struct Counter                                       │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+#[derive(Default)]
+struct Registered {
+    waker: Option<Waker>,
+    /// The instant of the first wakeup since the last poll.
+    woken: Option<Stamp>,
+}
+
+/// A waker counting and timestamping its wakeups before passing them on to the waker of the
+/// task.
+#[derive(Default)]
+pub(crate) struct Counter {
+    wakeups: AtomicU64,
+    registered: Mutex<Registered>,
+}
+
+impl Counter {
+    /// Registers the waker of the task, returning the scheduling latency of the wakeup that
+    /// led to this poll, if any.
+    pub(crate) fn register(&self, waker: &Waker) -> Option<Duration> {
+        let mut registered = self.registered.lock().unwrap();
+        match &registered.waker {
+            Some(registered) if registered.will_wake(waker) => (),
+            _ => registered.waker = Some(waker.clone()),
+        }
+
+        registered.woken.take().map(|woken| woken.elapsed())
+    }
+
+    /// Returns the number of wakeups so far.
+    pub(crate) fn wakeups(&self) -> u64 {
+        self.wakeups.load(Ordering::Relaxed)
+    }
+}
+
+impl Wake for Counter {
+    fn wake(self: Arc<Self>) {
+        self.wake_by_ref();
+    }
+
+    fn wake_by_ref(self: &Arc<Self>) {
+        self.wakeups.fetch_add(1, Ordering::Relaxed);
+
+        let mut registered = self.registered.lock().unwrap();
+        registered.woken.get_or_insert_with(Stamp::now);
+        if let Some(waker) = &registered.waker {
+            waker.wake_by_ref();
+        }
+    }
+}
+
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                                  struct StatsTimeout<Fut>                                  │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+pin_project! {
+    /// A future polling both another future and a [`Timer`] that will complete after a specified
+    /// timeout, and returning the future's output or [`None`] if the timer completes first,
+    /// alongside [`PollStats`] about how the future was polled.
+    #[must_use = "futures do nothing unless you `.await` or poll them"]
+    pub struct StatsTimeout<Fut> {
+        #[pin]
+        future: Fut,
+        timer: Timer,
+        counter: Arc<Counter>,
+        waker: Waker,
+        stats: PollStats,
+    }
+}
+
+impl<Fut> StatsTimeout<Fut> {
+    /// Returns the statistics collected so far.
+    pub fn stats(&self) -> PollStats {
+        PollStats {
+            wakeups: self.counter.wakeups(),
+            ..self.stats
+        }
+    }
+}
+
+impl<Fut> fmt::Debug for StatsTimeout<Fut> {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        fmt.debug_struct("StatsTimeout")
+            .field("stats", &self.stats())
+            .finish()
+    }
+}
+
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                               trait StatsTimeoutExt: Future                                │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+/// An extension trait for [`Future`]s that provides a way to create [`StatsTimeout`]s.
+pub trait StatsTimeoutExt: Future {
+    /// Given a [`Duration`], creates and returns a new [`StatsTimeout`] that will poll both the
+    /// future and a [`Timer`] that will complete after the provided duration, and return the
+    /// future's output or [`None`] if the timer completes first, alongside [`PollStats`].
+    fn timeout_with_stats(self, after: Duration) -> StatsTimeout<Self>
+    where
+        Self: Sized,
+    {
+        let counter = Arc::new(Counter::default());
+
+        StatsTimeout {
+            future: self,
+            timer: Timer::after(after),
+            waker: Waker::from(counter.clone()),
+            counter,
+            stats: PollStats::default(),
+        }
+    }
+}
+
+impl<Fut: Future> StatsTimeoutExt for Fut {}
+
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                             impl Future for StatsTimeout<Fut>                              │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+impl<Fut: Future> Future for StatsTimeout<Fut> {
+    type Output = (Option<Fut::Output>, PollStats);
+
+    fn poll(self: Pin<&mut Self>, ctx: &mut Context) -> Poll<Self::Output> {
+        let this = self.project();
+
+        if let Some(latency) = this.counter.register(ctx.waker()) {
+            this.stats.scheduling += latency;
+            this.stats.max_scheduling = this.stats.max_scheduling.max(latency);
+        }
+
+        let mut ctx = Context::from_waker(this.waker);
+
+        let start = Stamp::now();
+        let poll = this.future.poll(&mut ctx);
+        this.stats.polls += 1;
+        this.stats.busy += start.elapsed();
+
+        let output = match poll {
+            Poll::Ready(output) => Some(output),
+            Poll::Pending if Pin::new(this.timer).poll(&mut ctx).is_ready() => None,
+            Poll::Pending => return Poll::Pending,
+        };
+
+        let stats = PollStats {
+            wakeups: this.counter.wakeups(),
+            ..*this.stats
+        };
+
+        Poll::Ready((output, stats))
+    }
+}
@@ -0,0 +1,460 @@
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                                                                                            │ *
+ * │ This Source Code Form is subject to the terms of the Mozilla Public                        │ *
+ * │ License, v. 2.0. If a copy of the MPL was not distributed with this                        │ *
+ * │ file, You can obtain one at http://mozilla.org/MPL/2.0/.                                   │ *
+ * │                                                                                            │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                                       Documentation                                        │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+//! A supervisor restarting futures that time out or fail.
+//!
+//! Before each restart, the supervisor waits for a backoff delay computed by its
+//! [`RestartPolicy`], unless the failed run provides its own delay (e.g. parsed from a
+//! `Retry-After` header or a throttling error, see [`Supervisor::retry_after`]). With a total
+//! budget (see [`Supervisor::budget`]), runs and delays are bounded by what's left of it.
+//!
+//! ## Example
+//!
+//! ```rust
+//! use async_io::Timer;
+//! # use futures_lite::future;
+//! use smol_timeout::supervisor::{RestartPolicy, Supervisor};
+//! use std::time::Duration;
+//!
+//! # future::block_on(async {
+//! #
+//! let mut runs = 0;
+//!
+//! let supervisor = Supervisor::new(|| {
+//!     runs += 1;
+//!     let run = runs;
+//!
+//!     async move {
+//!         if run < 3 {
+//!             Timer::after(Duration::from_millis(250)).await;
+//!         }
+//!
+//!         Ok::<_, ()>(run)
+//!     }
+//! })
+//! .timeout(Duration::from_millis(100))
+//! .policy(RestartPolicy::new(5, Duration::from_secs(10)));
+//!
+//! assert_eq!(supervisor.run().await.ok(), Some(3));
+//! #
+//! # });
+//! ```
+
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                                          Imports                                           │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+use crate::{clock, deadline, TimeoutExt, Timer};
+use core::fmt;
+use core::future::Future;
+use core::time::Duration;
+use std::boxed::Box;
+use std::collections::VecDeque;
+
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                                    struct RestartPolicy                                    │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+/// The policy used by a [`Supervisor`] to decide whether and when to restart a run that timed
+/// out or failed.
+///
+/// A supervisor gives up once more than `max_restarts` restarts would happen within `window`.
+/// Before each restart, it waits for a backoff delay that starts at the initial backoff and
+/// doubles with every restart still inside the window, up to the maximum backoff.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RestartPolicy {
+    max_restarts: usize,
+    window: Duration,
+    backoff: Duration,
+    max_backoff: Duration,
+}
+
+impl RestartPolicy {
+    /// Creates a new [`RestartPolicy`] allowing at most `max_restarts` restarts within `window`,
+    /// without any backoff between restarts.
+    pub fn new(max_restarts: usize, window: Duration) -> Self {
+        RestartPolicy {
+            max_restarts,
+            window,
+            backoff: Duration::from_secs(0),
+            max_backoff: Duration::from_secs(0),
+        }
+    }
+
+    /// Sets the backoff delay waited before the first restart of a window, and the maximum delay
+    /// it can double up to.
+    pub fn backoff(mut self, initial: Duration, max: Duration) -> Self {
+        self.backoff = initial;
+        self.max_backoff = max;
+        self
+    }
+
+    /// Returns the delay to wait before a restart, given the number of restarts that already
+    /// happened within the window.
+    fn delay(&self, recent: usize) -> Duration {
+        let factor = 1u32.checked_shl(recent as u32).unwrap_or(u32::MAX);
+        self.backoff
+            .checked_mul(factor)
+            .map_or(self.max_backoff, |delay| delay.min(self.max_backoff))
+    }
+}
+
+impl Default for RestartPolicy {
+    /// Allows 3 restarts within 5 seconds, with a backoff starting at 100 milliseconds and up to
+    /// 1 second.
+    fn default() -> Self {
+        RestartPolicy::new(3, Duration::from_secs(5))
+            .backoff(Duration::from_millis(100), Duration::from_secs(1))
+    }
+}
+
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                                      enum Failure<E>                                       │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+/// The reason a supervised run didn't complete successfully.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Failure<E> {
+    /// The run didn't complete before the supervisor's timeout.
+    TimedOut,
+    /// The run completed with an error.
+    Failed(E),
+}
+
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                                     enum Transition<E>                                     │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+/// A transition of a [`Supervisor`], passed to its hook (see [`Supervisor::on_transition`]).
+#[derive(Debug)]
+pub enum Transition<'a, E> {
+    /// A run is starting. `attempt` starts at `0` and is incremented for every restart.
+    Started { attempt: usize },
+    /// A run didn't complete successfully.
+    Stopped {
+        attempt: usize,
+        failure: &'a Failure<E>,
+    },
+    /// The run is going to be restarted after `delay`.
+    Restarting { attempt: usize, delay: Duration },
+    /// The restart policy was exhausted and the supervisor is giving up.
+    GaveUp { attempt: usize },
+}
+
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                                      struct GaveUp<E>                                      │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+/// The error returned by [`Supervisor::run`] once its [`RestartPolicy`] is exhausted.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GaveUp<E> {
+    /// The number of runs that were started.
+    pub runs: usize,
+    /// Why the last run didn't complete successfully.
+    pub last: Failure<E>,
+}
+
+impl<E: fmt::Debug> fmt::Display for GaveUp<E> {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            fmt,
+            "supervisor gave up after {} runs (last: {:?})",
+            self.runs, self.last
+        )
+    }
+}
+
+impl<E: fmt::Debug> std::error::Error for GaveUp<E> {}
+
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                                     struct Supervisor                                      │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+/// A supervisor running the futures created by a factory one after the other, restarting them
+/// whenever they time out or fail, until one of them succeeds or its [`RestartPolicy`] is
+/// exhausted.
+///
+/// ## Example
+///
+/// ```rust
+/// # use futures_lite::future;
+/// use smol_timeout::supervisor::{Failure, RestartPolicy, Supervisor, Transition};
+/// use std::time::Duration;
+///
+/// # future::block_on(async {
+/// #
+/// let supervisor = Supervisor::new(|| async { Err::<(), _>("oops") })
+///     .policy(RestartPolicy::new(2, Duration::from_secs(10)))
+///     .on_transition(|transition| {
+///         if let Transition::GaveUp { attempt } = transition {
+///             assert_eq!(attempt, 2);
+///         }
+///     });
+///
+/// let err = supervisor.run().await.unwrap_err();
+/// assert_eq!(err.runs, 3);
+/// assert_eq!(err.last, Failure::Failed("oops"));
+/// #
+/// # });
+/// ```
+pub struct Supervisor<F, E> {
+    factory: F,
+    timeout: Option<Duration>,
+    policy: RestartPolicy,
+    budget: Option<Duration>,
+    #[allow(clippy::type_complexity)]
+    hook: Option<Box<dyn FnMut(Transition<E>) + Send>>,
+    #[allow(clippy::type_complexity)]
+    retry_after: Option<Box<dyn FnMut(&Failure<E>) -> Option<Duration> + Send>>,
+}
+
+impl<F, Fut, T, E> Supervisor<F, E>
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = Result<T, E>>,
+{
+    /// Creates a new [`Supervisor`] running the futures returned by `factory`, without any
+    /// timeout and with the default [`RestartPolicy`].
+    pub fn new(factory: F) -> Self {
+        Supervisor {
+            factory,
+            timeout: None,
+            policy: RestartPolicy::default(),
+            budget: None,
+            hook: None,
+            retry_after: None,
+        }
+    }
+
+    /// Sets the duration after which a run is considered to have timed out and is restarted.
+    pub fn timeout(mut self, after: Duration) -> Self {
+        self.timeout = Some(after);
+        self
+    }
+
+    /// Sets the [`RestartPolicy`] used to decide whether and when to restart runs.
+    pub fn policy(mut self, policy: RestartPolicy) -> Self {
+        self.policy = policy;
+        self
+    }
+
+    /// Sets the total duration the supervisor can spend running and restarting the futures,
+    /// after which it gives up.
+    ///
+    /// Runs are cut short at the end of the budget, and delays are bounded by what's left of it:
+    /// if a delay would exhaust the budget, the supervisor only waits until its end and makes a
+    /// last restart then, whose future is polled once. The supervisor gives up once a run fails
+    /// with nothing left of the budget.
+    ///
+    /// ## Example
+    ///
+    /// ```rust
+    /// # use futures_lite::future;
+    /// use smol_timeout::supervisor::{Failure, RestartPolicy, Supervisor};
+    /// use std::time::{Duration, Instant};
+    ///
+    /// # future::block_on(async {
+    /// #
+    /// let mut runs = 0;
+    ///
+    /// let supervisor = Supervisor::new(|| {
+    ///     runs += 1;
+    ///     let run = runs;
+    ///
+    ///     async move {
+    ///         if run < 2 {
+    ///             return Err(Duration::from_secs(10));
+    ///         }
+    ///
+    ///         Ok(run)
+    ///     }
+    /// })
+    /// .policy(RestartPolicy::new(5, Duration::from_secs(10)))
+    /// .budget(Duration::from_millis(200))
+    /// .retry_after(|failure| match failure {
+    ///     Failure::Failed(retry_after) => Some(*retry_after),
+    ///     Failure::TimedOut => None,
+    /// });
+    ///
+    /// // The `Retry-After` of 10s is cut short at the end of the budget.
+    /// let start = Instant::now();
+    /// assert_eq!(supervisor.run().await.ok(), Some(2));
+    /// assert!(start.elapsed() >= Duration::from_millis(200));
+    /// assert!(start.elapsed() < Duration::from_secs(10));
+    /// #
+    /// # });
+    /// ```
+    pub fn budget(mut self, total: Duration) -> Self {
+        self.budget = Some(total);
+        self
+    }
+
+    /// Sets a hook called with every failure, which can return a delay to wait before the next
+    /// restart instead of the backoff computed by the [`RestartPolicy`] (e.g. the delay of a
+    /// `Retry-After` header or of a throttling error).
+    ///
+    /// The delay is still cut short at the end of the budget (see [`Supervisor::budget`]), and the
+    /// [`RestartPolicy`] still decides whether to restart at all.
+    ///
+    /// ## Example
+    ///
+    /// ```rust
+    /// # use futures_lite::future;
+    /// use smol_timeout::supervisor::{Failure, RestartPolicy, Supervisor};
+    /// use std::time::{Duration, Instant};
+    ///
+    /// enum Error {
+    ///     Throttled { retry_after: Duration },
+    /// }
+    ///
+    /// # future::block_on(async {
+    /// #
+    /// let mut runs = 0;
+    ///
+    /// let supervisor = Supervisor::new(|| {
+    ///     runs += 1;
+    ///     let run = runs;
+    ///
+    ///     async move {
+    ///         if run < 2 {
+    ///             let retry_after = Duration::from_millis(100);
+    ///             return Err(Error::Throttled { retry_after });
+    ///         }
+    ///
+    ///         Ok(run)
+    ///     }
+    /// })
+    /// .policy(RestartPolicy::new(5, Duration::from_secs(10)))
+    /// .budget(Duration::from_secs(1))
+    /// .retry_after(|failure| match failure {
+    ///     Failure::Failed(Error::Throttled { retry_after }) => Some(*retry_after),
+    ///     Failure::TimedOut => None,
+    /// });
+    ///
+    /// let start = Instant::now();
+    /// assert_eq!(supervisor.run().await.ok(), Some(2));
+    /// assert!(start.elapsed() >= Duration::from_millis(100));
+    /// #
+    /// # });
+    /// ```
+    pub fn retry_after<H>(mut self, hook: H) -> Self
+    where
+        H: FnMut(&Failure<E>) -> Option<Duration> + Send + 'static,
+    {
+        self.retry_after = Some(Box::new(hook));
+        self
+    }
+
+    /// Sets a hook called for every [`Transition`] of the supervisor, e.g. to log them.
+    pub fn on_transition<H>(mut self, hook: H) -> Self
+    where
+        H: FnMut(Transition<E>) + Send + 'static,
+    {
+        self.hook = Some(Box::new(hook));
+        self
+    }
+
+    /// Runs the futures created by the factory until one of them succeeds, returning its output,
+    /// or until the [`RestartPolicy`] or the budget is exhausted, returning a [`GaveUp`] error.
+    pub async fn run(mut self) -> Result<T, GaveUp<E>> {
+        let mut restarts = VecDeque::new();
+        let mut attempt = 0;
+        let end = self
+            .budget
+            .map(|budget| deadline::deadline_after(clock::now(), budget));
+
+        loop {
+            self.emit(Transition::Started { attempt });
+
+            let timeout = match (self.timeout, end) {
+                (Some(after), Some(end)) => Some(after.min(deadline::remaining_until(end))),
+                (None, Some(end)) => Some(deadline::remaining_until(end)),
+                (timeout, None) => timeout,
+            };
+
+            let run = (self.factory)();
+            let failure = match timeout {
+                Some(after) => match run.timeout(after).await {
+                    Some(Ok(output)) => return Ok(output),
+                    Some(Err(err)) => Failure::Failed(err),
+                    None => Failure::TimedOut,
+                },
+                None => match run.await {
+                    Ok(output) => return Ok(output),
+                    Err(err) => Failure::Failed(err),
+                },
+            };
+
+            self.emit(Transition::Stopped {
+                attempt,
+                failure: &failure,
+            });
+
+            let now = clock::now();
+            while let Some(restart) = restarts.front() {
+                if now.duration_since(*restart) < self.policy.window {
+                    break;
+                }
+
+                restarts.pop_front();
+            }
+
+            let mut delay = match &mut self.retry_after {
+                Some(hook) => hook(&failure),
+                None => None,
+            }
+            .unwrap_or_else(|| self.policy.delay(restarts.len()));
+
+            let mut exhausted = restarts.len() >= self.policy.max_restarts;
+            if let Some(end) = end {
+                // A delay longer than what's left of the budget is cut short, so that a last
+                // restart is made at the end of the budget.
+                let remaining = deadline::remaining_until(end);
+                exhausted |= remaining == Duration::from_secs(0);
+                delay = delay.min(remaining);
+            }
+
+            if exhausted {
+                self.emit(Transition::GaveUp { attempt });
+                return Err(GaveUp {
+                    runs: attempt + 1,
+                    last: failure,
+                });
+            }
+
+            self.emit(Transition::Restarting { attempt, delay });
+
+            if delay > Duration::from_secs(0) {
+                Timer::after(delay).await;
+            }
+
+            restarts.push_back(clock::now());
+            attempt += 1;
+        }
+    }
+
+    fn emit(&mut self, transition: Transition<E>) {
+        if let Some(hook) = &mut self.hook {
+            hook(transition);
+        }
+    }
+}
+
+impl<F, E> fmt::Debug for Supervisor<F, E> {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        fmt.debug_struct("Supervisor")
+            .field("timeout", &self.timeout)
+            .field("policy", &self.policy)
+            .field("budget", &self.budget)
+            .finish()
+    }
+}
@@ -0,0 +1,240 @@
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                                                                                            │ *
+ * │ This Source Code Form is subject to the terms of the Mozilla Public                        │ *
+ * │ License, v. 2.0. If a copy of the MPL was not distributed with this                        │ *
+ * │ file, You can obtain one at http://mozilla.org/MPL/2.0/.                                   │ *
+ * │                                                                                            │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                                       Documentation                                        │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+//! Timeouts for spawned [`Task`]s that cancel the task itself when they fire.
+//!
+//! ## Example
+//!
+//! ```rust
+//! use async_executor::Executor;
+//! use async_io::Timer;
+//! # use futures_lite::future;
+//! use smol_timeout::task::{TaskExt, TaskOutcome};
+//! use std::time::Duration;
+//!
+//! let ex = Executor::new();
+//!
+//! # future::block_on(ex.run(async {
+//! #
+//! let foo = ex.spawn(async {
+//!     Timer::after(Duration::from_millis(250)).await;
+//!     24
+//! });
+//!
+//! let foo = foo.cancel_after(Duration::from_millis(100));
+//! assert!(matches!(foo.await, TaskOutcome::TimedOut));
+//!
+//! let bar = ex.spawn(async {
+//!     Timer::after(Duration::from_millis(100)).await;
+//!     42
+//! });
+//!
+//! let bar = bar.cancel_after(Duration::from_millis(250));
+//! assert!(matches!(bar.await, TaskOutcome::Completed(42)));
+//! #
+//! # }));
+//! ```
+
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                                          Imports                                           │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+use crate::Timer;
+use async_task::{FallibleTask, Task};
+use core::any::Any;
+use core::future::Future;
+use core::pin::Pin;
+use core::task::{Context, Poll};
+use core::time::Duration;
+use std::boxed::Box;
+use std::panic::{self, AssertUnwindSafe};
+
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                                    enum TaskOutcome<T>                                     │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+/// The outcome of a [`TimedTask`].
+pub enum TaskOutcome<T> {
+    /// The task completed before the timer and returned its output.
+    Completed(T),
+    /// The timer completed first and the task was cancelled.
+    TimedOut,
+    /// The task panicked and its [`Task`] was configured to propagate panics (see
+    /// [`async_task::Builder::propagate_panic`]). Contains the panic payload.
+    Panicked(Box<dyn Any + Send>),
+    /// The task was closed without producing an output, either because it panicked without
+    /// propagating the panic, or because it was cancelled by something other than the timeout
+    /// (e.g. its executor being dropped).
+    Closed,
+}
+
+impl<T> TaskOutcome<T> {
+    /// Returns the task's output if it completed, or [`None`] otherwise.
+    pub fn completed(self) -> Option<T> {
+        match self {
+            TaskOutcome::Completed(output) => Some(output),
+            _ => None,
+        }
+    }
+
+    /// Returns `true` if the task was cancelled because the timer completed first.
+    pub fn is_timed_out(&self) -> bool {
+        matches!(self, TaskOutcome::TimedOut)
+    }
+}
+
+impl<T: core::fmt::Debug> core::fmt::Debug for TaskOutcome<T> {
+    fn fmt(&self, fmt: &mut core::fmt::Formatter) -> core::fmt::Result {
+        match self {
+            TaskOutcome::Completed(output) => fmt.debug_tuple("Completed").field(output).finish(),
+            TaskOutcome::TimedOut => fmt.write_str("TimedOut"),
+            TaskOutcome::Panicked(_) => fmt.debug_tuple("Panicked").finish(),
+            TaskOutcome::Closed => fmt.write_str("Closed"),
+        }
+    }
+}
+
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                                   struct TimedTask<T, M>                                   │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+/// A future polling both a [`Task`] and a [`Timer`] that will complete after a specified timeout,
+/// cancelling the task if the timer completes first.
+///
+/// Unlike wrapping a [`Task`] in a [`Timeout`](crate::Timeout), which only stops awaiting it,
+/// this cancels the task itself so that it stops running on its executor. Dropping a
+/// [`TimedTask`] also cancels the task, like dropping a [`Task`] does.
+///
+/// ## Example
+///
+/// ```rust
+/// use async_executor::Executor;
+/// use async_io::Timer;
+/// # use futures_lite::future;
+/// use smol_timeout::task::TaskExt;
+/// use std::time::Duration;
+///
+/// let ex = Executor::new();
+///
+/// # future::block_on(ex.run(async {
+/// #
+/// let foo = ex.spawn(async {
+///     Timer::after(Duration::from_millis(250)).await;
+///     24
+/// });
+///
+/// let foo = foo.cancel_after(Duration::from_millis(100));
+/// assert_eq!(foo.await.completed(), None);
+///
+/// let bar = ex.spawn(async {
+///     Timer::after(Duration::from_millis(100)).await;
+///     42
+/// });
+///
+/// let bar = bar.cancel_after(Duration::from_millis(250));
+/// assert_eq!(bar.await.completed(), Some(42));
+/// #
+/// # }))
+/// ```
+#[derive(Debug)]
+pub struct TimedTask<T, M = ()> {
+    task: Option<FallibleTask<T, M>>,
+    timer: Timer,
+}
+
+impl<T, M> TimedTask<T, M> {
+    /// Given a [`Task`] and a [`Duration`], creates and returns a new [`TimedTask`] that will
+    /// cancel the task if it doesn't complete before the provided duration.
+    pub fn new(task: Task<T, M>, after: Duration) -> Self {
+        TimedTask {
+            task: Some(task.fallible()),
+            timer: Timer::after(after),
+        }
+    }
+}
+
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                                       trait TaskExt                                        │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+/// An extension trait for [`Task`]s that provides a way to create [`TimedTask`]s.
+pub trait TaskExt<T, M> {
+    /// Given a [`Duration`], creates and returns a new [`TimedTask`] that will poll both the task
+    /// and a [`Timer`] that will complete after the provided duration, and cancel the task if
+    /// the timer completes first.
+    ///
+    /// ## Example
+    ///
+    /// ```rust
+    /// use async_executor::Executor;
+    /// use async_io::Timer;
+    /// # use futures_lite::future;
+    /// use smol_timeout::task::TaskExt;
+    /// use std::time::Duration;
+    ///
+    /// let ex = Executor::new();
+    ///
+    /// # future::block_on(ex.run(async {
+    /// #
+    /// let foo = ex.spawn(async {
+    ///     Timer::after(Duration::from_millis(250)).await;
+    ///     24
+    /// });
+    ///
+    /// let foo = foo.cancel_after(Duration::from_millis(100));
+    /// assert!(foo.await.is_timed_out());
+    /// #
+    /// # }))
+    /// ```
+    fn cancel_after(self, after: Duration) -> TimedTask<T, M>;
+}
+
+impl<T, M> TaskExt<T, M> for Task<T, M> {
+    fn cancel_after(self, after: Duration) -> TimedTask<T, M> {
+        TimedTask::new(self, after)
+    }
+}
+
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                              impl Future for TimedTask<T, M>                               │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+impl<T, M> Future for TimedTask<T, M> {
+    type Output = TaskOutcome<T>;
+
+    fn poll(mut self: Pin<&mut Self>, ctx: &mut Context) -> Poll<Self::Output> {
+        let task = match &mut self.task {
+            Some(task) => task,
+            None => panic!("`TimedTask` polled after completion"),
+        };
+
+        // The task's output takes precedence over the timer, so that a task which already
+        // completed isn't reported as timed out.
+        let polled = panic::catch_unwind(AssertUnwindSafe(|| Pin::new(task).poll(ctx)));
+        let outcome = match polled {
+            Ok(Poll::Ready(Some(output))) => TaskOutcome::Completed(output),
+            Ok(Poll::Ready(None)) => TaskOutcome::Closed,
+            Err(payload) => TaskOutcome::Panicked(payload),
+            Ok(Poll::Pending) => {
+                if Pin::new(&mut self.timer).poll(ctx).is_pending() {
+                    return Poll::Pending;
+                }
+
+                TaskOutcome::TimedOut
+            }
+        };
+
+        // Dropping the task cancels it if it is still running.
+        self.task = None;
+        Poll::Ready(outcome)
+    }
+}
@@ -0,0 +1,92 @@
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                                                                                            │ *
+ * │ This Source Code Form is subject to the terms of the Mozilla Public                        │ *
+ * │ License, v. 2.0. If a copy of the MPL was not distributed with this                        │ *
+ * │ file, You can obtain one at http://mozilla.org/MPL/2.0/.                                   │ *
+ * │                                                                                            │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                                       Documentation                                        │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+//! Manual control over the clock followed by this crate's timers, for deterministic tests.
+//!
+//! [`pause_time`] freezes the clock of the current thread, after which time only moves forward
+//! when [`advance`] is called, firing the timers whose deadline it passes; [`resume`] returns to
+//! the normal clock. The clock is the one of [`crate::sim`], which all the timers of this crate
+//! follow when they are created on the thread whose clock was paused (e.g. the deadline of a
+//! [`TimeoutExt::timeout`], the delays of a [`Supervisor`] or the sleeps of a [`TimerWheel`]),
+//! wherever they are then polled, as long as their deadlines aren't computed from
+//! [`Instant::now`](std::time::Instant::now).
+//!
+//! Inside [`sim::run`], pausing stops the simulation from advancing the clock
+//! by itself, and resuming lets it do so again.
+//!
+//! [`TimeoutExt::timeout`]: crate::TimeoutExt::timeout
+//! [`Supervisor`]: crate::supervisor::Supervisor
+//! [`TimerWheel`]: crate::wheel::TimerWheel
+//!
+//! ## Example
+//!
+//! ```rust
+//! # use futures_lite::future;
+//! use smol_timeout::wheel::TimerWheel;
+//! use smol_timeout::{test_util, TimeoutExt};
+//! use std::time::Duration;
+//!
+//! test_util::pause_time();
+//!
+//! let mut foo = Box::pin(future::pending::<()>().timeout(Duration::from_secs(60)));
+//! assert_eq!(future::block_on(future::poll_once(&mut foo)), None);
+//!
+//! let wheel = TimerWheel::new(Duration::from_secs(1));
+//! let mut bar = Box::pin(wheel.timeout(future::pending::<()>(), Duration::from_secs(90)));
+//! assert_eq!(future::block_on(future::poll_once(&mut bar)), None);
+//!
+//! test_util::advance(Duration::from_secs(30));
+//! assert_eq!(future::block_on(future::poll_once(&mut foo)), None);
+//!
+//! test_util::advance(Duration::from_secs(31));
+//! assert_eq!(future::block_on(future::poll_once(&mut foo)), Some(None));
+//! assert_eq!(future::block_on(future::poll_once(&mut bar)), None);
+//!
+//! test_util::advance(Duration::from_secs(30));
+//! assert_eq!(future::block_on(future::poll_once(&mut bar)), Some(None));
+//!
+//! test_util::resume();
+//! ```
+
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                                          Imports                                           │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+use crate::sim;
+use core::time::Duration;
+
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                                      fn pause_time()                                       │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+/// Freezes the clock of the current thread, so that it only moves forward with [`advance`].
+///
+/// The timers created afterwards on this thread follow the paused clock, wherever they are
+/// polled. The ones created before keep following the clock they were created with.
+pub fn pause_time() {
+    sim::pause();
+}
+
+/// Moves the paused clock of the current thread forward by the provided duration, waking up the
+/// timers whose deadline it passes.
+///
+/// Does nothing if the clock of the current thread isn't paused.
+pub fn advance(by: Duration) {
+    sim::advance(by);
+}
+
+/// Returns the clock of the current thread to the real time.
+///
+/// The timers that were following the paused clock turn into real timers with the same deadline.
+pub fn resume() {
+    sim::resume();
+}
@@ -0,0 +1,145 @@
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                                                                                            │ *
+ * │ This Source Code Form is subject to the terms of the Mozilla Public                        │ *
+ * │ License, v. 2.0. If a copy of the MPL was not distributed with this                        │ *
+ * │ file, You can obtain one at http://mozilla.org/MPL/2.0/.                                   │ *
+ * │                                                                                            │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                                       Documentation                                        │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+//! A way to limit how often a future is polled.
+//!
+//! Some futures have an expensive `poll` (e.g. polling-based FFI handles, which query their
+//! underlying resource every time), and are woken up far more often than they can make progress.
+//! A [`Throttle`] polls its future at most once per interval: wakeups arriving sooner after the
+//! last poll are absorbed, and the future is polled once the interval elapsed instead.
+//!
+//! ## Example
+//!
+//! ```rust
+//! # use futures_lite::future;
+//! use smol_timeout::throttle::ThrottleExt;
+//! use std::cell::Cell;
+//! use std::time::{Duration, Instant};
+//!
+//! # future::block_on(async {
+//! #
+//! let polls = Cell::new(0);
+//!
+//! let busy = future::poll_fn(|ctx| {
+//!     polls.set(polls.get() + 1);
+//!     if polls.get() == 5 {
+//!         return std::task::Poll::Ready(42);
+//!     }
+//!
+//!     // Ask to be polled again right away.
+//!     ctx.waker().wake_by_ref();
+//!     std::task::Poll::Pending
+//! });
+//!
+//! let start = Instant::now();
+//! assert_eq!(busy.throttle(Duration::from_millis(20)).await, 42);
+//! assert!(start.elapsed() >= Duration::from_millis(80));
+//! #
+//! # });
+//! ```
+
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                                          Imports                                           │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+use crate::clock;
+use crate::Timer;
+use core::future::Future;
+use core::pin::Pin;
+use core::task::{Context, Poll};
+use core::time::Duration;
+use pin_project_lite::pin_project;
+use std::time::Instant;
+
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                                    struct Throttle<Fut>                                    │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+pin_project! {
+    /// A future polling another future at most once per interval, absorbing the wakeups
+    /// arriving sooner.
+    #[derive(Debug)]
+    #[must_use = "futures do nothing unless you `.await` or poll them"]
+    pub struct Throttle<Fut> {
+        #[pin]
+        future: Fut,
+        timer: Timer,
+        interval: Duration,
+        // The instant the future was last polled.
+        last: Option<Instant>,
+        // Whether the timer is set to the end of the current interval.
+        armed: bool,
+    }
+}
+
+impl<Fut> Throttle<Fut> {
+    /// Returns the minimum interval between two polls of the future.
+    pub fn interval(&self) -> Duration {
+        self.interval
+    }
+}
+
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                                 trait ThrottleExt: Future                                  │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+/// An extension trait for [`Future`]s that provides a way to create [`Throttle`]s.
+pub trait ThrottleExt: Future {
+    /// Given a [`Duration`], creates and returns a new [`Throttle`] that will poll the future at
+    /// most once per the provided interval, delaying the polls requested by wakeups arriving
+    /// sooner until the interval elapsed.
+    fn throttle(self, interval: Duration) -> Throttle<Self>
+    where
+        Self: Sized,
+    {
+        Throttle {
+            future: self,
+            timer: Timer::never(),
+            interval,
+            last: None,
+            armed: false,
+        }
+    }
+}
+
+impl<Fut: Future> ThrottleExt for Fut {}
+
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                               impl Future for Throttle<Fut>                                │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+impl<Fut: Future> Future for Throttle<Fut> {
+    type Output = Fut::Output;
+
+    fn poll(self: Pin<&mut Self>, ctx: &mut Context) -> Poll<Self::Output> {
+        let this = self.project();
+
+        if let Some(last) = *this.last {
+            let next = last + *this.interval;
+            if clock::now() < next {
+                if !*this.armed {
+                    *this.armed = true;
+                    this.timer.set_at(next);
+                }
+
+                // The wakeup is absorbed, and the future polled once the timer fires.
+                if Pin::new(&mut *this.timer).poll(ctx).is_pending() {
+                    return Poll::Pending;
+                }
+            }
+        }
+
+        *this.armed = false;
+        *this.last = Some(clock::now());
+        this.future.poll(ctx)
+    }
+}
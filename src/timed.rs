@@ -0,0 +1,103 @@
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                                                                                            │ *
+ * │ This Source Code Form is subject to the terms of the Mozilla Public                        │ *
+ * │ License, v. 2.0. If a copy of the MPL was not distributed with this                        │ *
+ * │ file, You can obtain one at http://mozilla.org/MPL/2.0/.                                   │ *
+ * │                                                                                            │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                                       Documentation                                        │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+//! A way to measure how long a future takes to complete.
+//!
+//! A [`Timed`] future returns the output of its future alongside the duration elapsed between
+//! its first poll and its completion, measured using the crate's clock (see [`crate::clock`]).
+//!
+//! ## Example
+//!
+//! ```rust
+//! use async_io::Timer;
+//! # use futures_lite::future;
+//! use smol_timeout::timed::TimedExt;
+//! use std::time::Duration;
+//!
+//! # future::block_on(async {
+//! #
+//! let foo = async {
+//!     Timer::after(Duration::from_millis(100)).await;
+//!     42
+//! };
+//!
+//! let (output, elapsed) = foo.timed().await;
+//! assert_eq!(output, 42);
+//! assert!(elapsed >= Duration::from_millis(100));
+//! #
+//! # });
+//! ```
+
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                                          Imports                                           │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+use crate::clock::Stamp;
+use core::future::Future;
+use core::pin::Pin;
+use core::task::{Context, Poll};
+use core::time::Duration;
+use pin_project_lite::pin_project;
+
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                                     struct Timed<Fut>                                      │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+pin_project! {
+    /// A future polling another future, and returning its output alongside the duration
+    /// elapsed between its first poll and its completion.
+    #[derive(Debug)]
+    #[must_use = "futures do nothing unless you `.await` or poll them"]
+    pub struct Timed<Fut> {
+        #[pin]
+        future: Fut,
+        start: Option<Stamp>,
+    }
+}
+
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                                   trait TimedExt: Future                                   │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+/// An extension trait for [`Future`]s that provides a way to create [`Timed`]s.
+pub trait TimedExt: Future {
+    /// Creates and returns a new [`Timed`] that will poll the future and return its output
+    /// alongside the duration elapsed between its first poll and its completion.
+    fn timed(self) -> Timed<Self>
+    where
+        Self: Sized,
+    {
+        Timed {
+            future: self,
+            start: None,
+        }
+    }
+}
+
+impl<Fut: Future> TimedExt for Fut {}
+
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                                 impl Future for Timed<Fut>                                 │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+impl<Fut: Future> Future for Timed<Fut> {
+    type Output = (Fut::Output, Duration);
+
+    fn poll(self: Pin<&mut Self>, ctx: &mut Context) -> Poll<Self::Output> {
+        let this = self.project();
+        let start = *this.start.get_or_insert_with(Stamp::now);
+
+        this.future
+            .poll(ctx)
+            .map(|output| (output, start.elapsed()))
+    }
+}
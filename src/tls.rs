@@ -0,0 +1,114 @@
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                                                                                            │ *
+ * │ This Source Code Form is subject to the terms of the Mozilla Public                        │ *
+ * │ License, v. 2.0. If a copy of the MPL was not distributed with this                        │ *
+ * │ file, You can obtain one at http://mozilla.org/MPL/2.0/.                                   │ *
+ * │                                                                                            │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                                       Documentation                                        │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+//! TLS handshakes (using [`futures_rustls`]) with a deadline.
+//!
+//! A peer that opens a connection but never completes the TLS handshake holds it (and its
+//! buffers) for as long as the handshake is awaited. With the `futures-rustls` feature,
+//! [`connect_timeout`] and [`accept_timeout`] bound the client and server handshakes, failing
+//! with an [`io::Error`] of kind [`TimedOut`](io::ErrorKind::TimedOut) if they don't complete in
+//! time.
+//!
+//! When the deadline fires first, the handshake is dropped along with the stream, which closes
+//! the connection and discards the half-established session: nothing of it can be reused.
+//!
+//! ## Example
+//!
+//! ```rust
+//! use async_io::Async;
+//! # use futures_lite::future;
+//! use futures_rustls::pki_types::ServerName;
+//! use futures_rustls::rustls::{ClientConfig, RootCertStore};
+//! use futures_rustls::TlsConnector;
+//! use smol_timeout::tls;
+//! use std::convert::TryFrom;
+//! use std::io;
+//! use std::net::{TcpListener, TcpStream};
+//! use std::sync::Arc;
+//! use std::time::Duration;
+//!
+//! # future::block_on(async {
+//! #
+//! let config = ClientConfig::builder()
+//!     .with_root_certificates(RootCertStore::empty())
+//!     .with_no_client_auth();
+//! let connector = TlsConnector::from(Arc::new(config));
+//!
+//! // The server accepts the connection, but never answers the handshake.
+//! let listener = Async::<TcpListener>::bind(([127, 0, 0, 1], 0))?;
+//! let stream = Async::<TcpStream>::connect(listener.get_ref().local_addr()?).await?;
+//! let (_server, _) = listener.accept().await?;
+//!
+//! let domain = ServerName::try_from("example.com").unwrap();
+//! let err = tls::connect_timeout(&connector, domain, stream, Duration::from_millis(100))
+//!     .await
+//!     .unwrap_err();
+//!
+//! assert_eq!(err.kind(), io::ErrorKind::TimedOut);
+//! #
+//! # io::Result::Ok(())
+//! # }).unwrap();
+//! ```
+
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                                          Imports                                           │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+use crate::{Elapsed, TimeoutExt};
+use core::time::Duration;
+use futures_io::{AsyncRead, AsyncWrite};
+use futures_rustls::pki_types::ServerName;
+use futures_rustls::{client, server, TlsAcceptor, TlsConnector};
+use std::io;
+
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                                    fn connect_timeout()                                    │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+/// Performs the client side of a TLS handshake over the stream, returning the established TLS
+/// stream or an [`io::Error`] of kind [`TimedOut`](io::ErrorKind::TimedOut) if the handshake
+/// doesn't complete before the provided duration.
+pub async fn connect_timeout<IO>(
+    connector: &TlsConnector,
+    domain: ServerName<'static>,
+    stream: IO,
+    after: Duration,
+) -> io::Result<client::TlsStream<IO>>
+where
+    IO: AsyncRead + AsyncWrite + Unpin,
+{
+    match connector.connect(domain, stream).timeout(after).await {
+        Some(res) => res,
+        None => Err(Elapsed::new().into()),
+    }
+}
+
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                                    fn accept_timeout()                                     │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+/// Performs the server side of a TLS handshake over the stream, returning the established TLS
+/// stream or an [`io::Error`] of kind [`TimedOut`](io::ErrorKind::TimedOut) if the handshake
+/// doesn't complete before the provided duration.
+pub async fn accept_timeout<IO>(
+    acceptor: &TlsAcceptor,
+    stream: IO,
+    after: Duration,
+) -> io::Result<server::TlsStream<IO>>
+where
+    IO: AsyncRead + AsyncWrite + Unpin,
+{
+    match acceptor.accept(stream).timeout(after).await {
+        Some(res) => res,
+        None => Err(Elapsed::new().into()),
+    }
+}
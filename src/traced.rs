@@ -0,0 +1,122 @@
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                                                                                            │ *
+ * │ This Source Code Form is subject to the terms of the Mozilla Public                        │ *
+ * │ License, v. 2.0. If a copy of the MPL was not distributed with this                        │ *
+ * │ file, You can obtain one at http://mozilla.org/MPL/2.0/.                                   │ *
+ * │                                                                                            │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                                       Documentation                                        │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+//! Timeouts remembering where they were created, so that their errors point at the call site
+//! which set the budget.
+//!
+//! A [`TracedTimeout`] records the location of the call to
+//! [`timeout_traced`](TracedTimeoutExt::timeout_traced) creating it, and, with the `backtrace`
+//! feature, captures a [`Backtrace`] (only when enabled by the `RUST_BACKTRACE` or
+//! `RUST_LIB_BACKTRACE` environment variables). Both are attached to the [`Elapsed`] error
+//! returned if its timer completes first (see [`Elapsed::traced`]).
+//!
+//! ## Example
+//!
+//! ```rust
+//! # use futures_lite::future;
+//! use smol_timeout::traced::TracedTimeoutExt;
+//! use std::time::Duration;
+//!
+//! # future::block_on(async {
+//! #
+//! let foo = future::pending::<()>().timeout_traced(Duration::from_millis(100));
+//! let line = line!() - 1;
+//!
+//! let err = foo.await.unwrap_err();
+//! assert_eq!(err.location().unwrap().line(), line);
+//! assert_eq!(err.location().unwrap().file(), file!());
+//! #
+//! # });
+//! ```
+//!
+//! [`Backtrace`]: std::backtrace::Backtrace
+
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                                          Imports                                           │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+use crate::{Elapsed, Timer};
+use core::future::Future;
+use core::pin::Pin;
+use core::task::{Context, Poll};
+use core::time::Duration;
+use pin_project_lite::pin_project;
+
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                                 struct TracedTimeout<Fut>                                  │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+pin_project! {
+    /// A future polling both another future and a [`Timer`] that will complete after a specified
+    /// timeout, and returning the future's output or a traced [`Elapsed`] error if the timer
+    /// completes first.
+    #[derive(Debug)]
+    #[must_use = "futures do nothing unless you `.await` or poll them"]
+    pub struct TracedTimeout<Fut> {
+        #[pin]
+        future: Fut,
+        timer: Timer,
+        error: Option<Elapsed>,
+    }
+}
+
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                               trait TracedTimeoutExt: Future                               │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+/// An extension trait for [`Future`]s that provides a way to create [`TracedTimeout`]s.
+pub trait TracedTimeoutExt: Future {
+    /// Given a [`Duration`], creates and returns a new [`TracedTimeout`] that will poll both the
+    /// future and a [`Timer`] that will complete after the provided duration, and return the
+    /// future's output or an [`Elapsed`] error pointing at the caller if the timer completes
+    /// first.
+    #[track_caller]
+    fn timeout_traced(self, after: Duration) -> TracedTimeout<Self>
+    where
+        Self: Sized,
+    {
+        TracedTimeout {
+            future: self,
+            timer: Timer::after(after),
+            error: Some(Elapsed::traced()),
+        }
+    }
+}
+
+impl<Fut: Future> TracedTimeoutExt for Fut {}
+
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                             impl Future for TracedTimeout<Fut>                             │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+impl<Fut: Future> Future for TracedTimeout<Fut> {
+    type Output = Result<Fut::Output, Elapsed>;
+
+    fn poll(self: Pin<&mut Self>, ctx: &mut Context) -> Poll<Self::Output> {
+        let this = self.project();
+
+        if let Poll::Ready(output) = this.future.poll(ctx) {
+            return Poll::Ready(Ok(output));
+        }
+
+        if Pin::new(this.timer).poll(ctx).is_ready() {
+            let error = this
+                .error
+                .take()
+                .expect("`TracedTimeout` polled after completion");
+
+            return Poll::Ready(Err(error));
+        }
+
+        Poll::Pending
+    }
+}
@@ -0,0 +1,386 @@
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                                                                                            │ *
+ * │ This Source Code Form is subject to the terms of the Mozilla Public                        │ *
+ * │ License, v. 2.0. If a copy of the MPL was not distributed with this                        │ *
+ * │ file, You can obtain one at http://mozilla.org/MPL/2.0/.                                   │ *
+ * │                                                                                            │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                                       Documentation                                        │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+//! Timeouts for blocking closures running on the [`blocking`] thread pool.
+//!
+//! A blocking closure can't be cancelled, so when the timeout fires first, the closure is left
+//! running detached on the thread pool and its output is either dropped or passed to a
+//! completion callback once it eventually returns.
+//!
+//! ## Example
+//!
+//! ```rust
+//! # use futures_lite::future;
+//! use smol_timeout::unblock::unblock_with_timeout;
+//! use std::thread;
+//! use std::time::Duration;
+//!
+//! # future::block_on(async {
+//! #
+//! let foo = unblock_with_timeout(Duration::from_millis(100), || {
+//!     thread::sleep(Duration::from_millis(250));
+//!     24
+//! });
+//!
+//! assert_eq!(foo.await, None);
+//!
+//! let bar = unblock_with_timeout(Duration::from_millis(250), || {
+//!     thread::sleep(Duration::from_millis(100));
+//!     42
+//! });
+//!
+//! assert_eq!(bar.await, Some(42));
+//! #
+//! # });
+//! ```
+//!
+//! Blocking iterators can also be drained on the thread pool as a [`Stream`] bounded by both a
+//! per-item timeout and an overall deadline using [`unblock_iter`].
+
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                                          Imports                                           │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+use crate::{TimeoutExt, Timer};
+use blocking::Unblock;
+use core::fmt;
+use core::future::Future;
+use core::mem;
+use core::pin::Pin;
+use core::task::{Context, Poll};
+use core::time::Duration;
+use futures_core::Stream;
+use std::sync::{Arc, Mutex};
+
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                                        enum Slot<C>                                        │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+/// The state shared between a blocking closure and the future awaiting it.
+enum Slot<C> {
+    /// The closure is running and its output hasn't been abandoned yet.
+    Waiting,
+    /// The timeout fired first, and the closure's output must be passed to the callback (if any).
+    Abandoned(Option<C>),
+    /// The closure returned and its output will be returned by its task.
+    Finished,
+}
+
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                                 fn unblock_with_timeout()                                  │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+/// Runs a blocking closure on the [`blocking`] thread pool (see [`blocking::unblock`]),
+/// returning its output or [`None`] if it doesn't return before the provided duration.
+///
+/// When the timeout fires first, the closure keeps running detached and its output is dropped
+/// once it returns. See [`unblock_with_timeout_then`] to handle it instead.
+///
+/// ## Example
+///
+/// ```rust
+/// # use futures_lite::future;
+/// use smol_timeout::unblock::unblock_with_timeout;
+/// use std::thread;
+/// use std::time::Duration;
+///
+/// # future::block_on(async {
+/// #
+/// let foo = unblock_with_timeout(Duration::from_millis(100), || {
+///     thread::sleep(Duration::from_millis(250));
+///     24
+/// });
+///
+/// assert_eq!(foo.await, None);
+/// #
+/// # })
+/// ```
+pub async fn unblock_with_timeout<T, F>(after: Duration, f: F) -> Option<T>
+where
+    T: Send + 'static,
+    F: FnOnce() -> T + Send + 'static,
+{
+    unblock(after, f, None::<fn(T)>).await
+}
+
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                               fn unblock_with_timeout_then()                               │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+/// Runs a blocking closure on the [`blocking`] thread pool (see [`blocking::unblock`]),
+/// returning its output or [`None`] if it doesn't return before the provided duration.
+///
+/// When the timeout fires first, the closure keeps running detached and `on_complete` is called
+/// with its output once it returns, on the thread that ran it. `on_complete` is never called if
+/// the output was returned.
+///
+/// ## Example
+///
+/// ```rust
+/// # use futures_lite::future;
+/// use smol_timeout::unblock::unblock_with_timeout_then;
+/// use std::sync::mpsc;
+/// use std::thread;
+/// use std::time::Duration;
+///
+/// # future::block_on(async {
+/// #
+/// let (sender, receiver) = mpsc::channel();
+///
+/// let foo = unblock_with_timeout_then(
+///     Duration::from_millis(100),
+///     || {
+///         thread::sleep(Duration::from_millis(250));
+///         24
+///     },
+///     move |late| sender.send(late).unwrap(),
+/// );
+///
+/// assert_eq!(foo.await, None);
+/// assert_eq!(receiver.recv().unwrap(), 24);
+/// #
+/// # })
+/// ```
+pub async fn unblock_with_timeout_then<T, F, C>(after: Duration, f: F, on_complete: C) -> Option<T>
+where
+    T: Send + 'static,
+    F: FnOnce() -> T + Send + 'static,
+    C: FnOnce(T) + Send + 'static,
+{
+    unblock(after, f, Some(on_complete)).await
+}
+
+async fn unblock<T, F, C>(after: Duration, f: F, on_complete: Option<C>) -> Option<T>
+where
+    T: Send + 'static,
+    F: FnOnce() -> T + Send + 'static,
+    C: FnOnce(T) + Send + 'static,
+{
+    let slot = Arc::new(Mutex::new(Slot::<C>::Waiting));
+
+    let mut task = {
+        let slot = slot.clone();
+
+        blocking::unblock(move || {
+            let output = f();
+
+            let mut slot = slot.lock().unwrap();
+            match mem::replace(&mut *slot, Slot::Finished) {
+                Slot::Abandoned(on_complete) => {
+                    drop(slot);
+                    if let Some(on_complete) = on_complete {
+                        on_complete(output);
+                    }
+
+                    None
+                }
+                _ => Some(output),
+            }
+        })
+    };
+
+    if let Some(output) = (&mut task).timeout(after).await {
+        return output;
+    }
+
+    let finished = {
+        let mut slot = slot.lock().unwrap();
+        match *slot {
+            Slot::Finished => true,
+            _ => {
+                *slot = Slot::Abandoned(on_complete);
+                false
+            }
+        }
+    };
+
+    if finished {
+        // The closure returned right after the timer fired, so its output is on its way.
+        return task.await;
+    }
+
+    task.detach();
+    None
+}
+
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                                        enum Expired                                        │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+/// The error yielded by a [`TimeboxedIter`] when it expires.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Expired {
+    /// The iterator didn't yield its next item before the per-item timeout.
+    Item,
+    /// The overall deadline was reached before the iterator was exhausted.
+    Deadline,
+}
+
+impl fmt::Display for Expired {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Expired::Item => fmt.write_str("iterator didn't yield an item in time"),
+            Expired::Deadline => fmt.write_str("iterator wasn't exhausted before the deadline"),
+        }
+    }
+}
+
+impl std::error::Error for Expired {}
+
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                                  struct TimeboxedIter<I>                                   │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+/// A [`Stream`] draining a blocking [`Iterator`] on the [`blocking`] thread pool, with both a
+/// per-item timeout and an overall deadline.
+///
+/// Both timers start the first time the stream is polled, and the per-item timeout restarts
+/// every time the next item is requested, so that the time the consumer spends between two
+/// items isn't counted against the iterator. At most one item is computed ahead of the consumer.
+///
+/// When either expires, the stream yields a single [`Expired`] error and then ends. The iterator
+/// itself can't be interrupted, so an item that is being computed when the stream expires is
+/// dropped once it is ready.
+///
+/// ## Example
+///
+/// ```rust
+/// use futures_lite::{future, StreamExt};
+/// use smol_timeout::unblock::{unblock_iter, Expired};
+/// use std::thread;
+/// use std::time::Duration;
+///
+/// # future::block_on(async {
+/// #
+/// let iter = (0..).map(|i| {
+///     thread::sleep(Duration::from_millis(if i < 2 { 10 } else { 250 }));
+///     i
+/// });
+///
+/// let stream = unblock_iter(iter, Duration::from_millis(100), Duration::from_secs(1));
+/// let items = stream.collect::<Vec<_>>().await;
+///
+/// assert_eq!(items, [Ok(0), Ok(1), Err(Expired::Item)]);
+/// #
+/// # })
+/// ```
+#[derive(Debug)]
+pub struct TimeboxedIter<I: Iterator> {
+    iter: Option<Unblock<I>>,
+    per_item: Duration,
+    total: Duration,
+    item_timer: Timer,
+    deadline: Timer,
+    /// Whether the stream was polled and its deadline armed.
+    started: bool,
+    /// Whether an item was requested and the per-item timer armed for it.
+    requested: bool,
+}
+
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                                     fn unblock_iter()                                      │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+/// Given a blocking [`Iterator`], creates and returns a new [`TimeboxedIter`] draining it on the
+/// [`blocking`] thread pool, expiring if an item takes longer than `per_item` to be yielded, or
+/// if the iterator isn't exhausted within `total`.
+///
+/// ## Example
+///
+/// ```rust
+/// use async_io::Timer;
+/// use futures_lite::{future, StreamExt};
+/// use smol_timeout::unblock::unblock_iter;
+/// use std::time::Duration;
+///
+/// # future::block_on(async {
+/// #
+/// let mut stream = unblock_iter(0..3, Duration::from_millis(100), Duration::from_millis(200));
+///
+/// // Neither the time before the first poll nor the time spent between items counts.
+/// Timer::after(Duration::from_millis(250)).await;
+/// assert_eq!(stream.next().await, Some(Ok(0)));
+///
+/// Timer::after(Duration::from_millis(150)).await;
+/// assert_eq!(stream.next().await, Some(Ok(1)));
+/// #
+/// # })
+/// ```
+pub fn unblock_iter<I>(iter: I, per_item: Duration, total: Duration) -> TimeboxedIter<I>
+where
+    I: Iterator + Send + 'static,
+    I::Item: Send + 'static,
+{
+    TimeboxedIter {
+        iter: Some(Unblock::with_capacity(1, iter)),
+        per_item,
+        total,
+        item_timer: Timer::never(),
+        deadline: Timer::never(),
+        started: false,
+        requested: false,
+    }
+}
+
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                              impl Stream for TimeboxedIter<I>                              │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+impl<I> Stream for TimeboxedIter<I>
+where
+    I: Iterator + Send + 'static,
+    I::Item: Send + 'static,
+{
+    type Item = Result<I::Item, Expired>;
+
+    fn poll_next(mut self: Pin<&mut Self>, ctx: &mut Context) -> Poll<Option<Self::Item>> {
+        let this = &mut *self;
+        let iter = match &mut this.iter {
+            Some(iter) => iter,
+            None => return Poll::Ready(None),
+        };
+
+        if !this.started {
+            this.started = true;
+            this.deadline.set_after(this.total);
+        }
+
+        if !this.requested {
+            this.requested = true;
+            this.item_timer.set_after(this.per_item);
+        }
+
+        if Pin::new(&mut this.deadline).poll(ctx).is_ready() {
+            this.iter = None;
+            return Poll::Ready(Some(Err(Expired::Deadline)));
+        }
+
+        match Pin::new(iter).poll_next(ctx) {
+            Poll::Ready(Some(item)) => {
+                this.requested = false;
+                Poll::Ready(Some(Ok(item)))
+            }
+            Poll::Ready(None) => {
+                this.iter = None;
+                Poll::Ready(None)
+            }
+            Poll::Pending => {
+                if Pin::new(&mut this.item_timer).poll(ctx).is_pending() {
+                    return Poll::Pending;
+                }
+
+                this.iter = None;
+                Poll::Ready(Some(Err(Expired::Item)))
+            }
+        }
+    }
+}
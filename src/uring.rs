@@ -0,0 +1,372 @@
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                                                                                            │ *
+ * │ This Source Code Form is subject to the terms of the Mozilla Public                        │ *
+ * │ License, v. 2.0. If a copy of the MPL was not distributed with this                        │ *
+ * │ file, You can obtain one at http://mozilla.org/MPL/2.0/.                                   │ *
+ * │                                                                                            │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                                       Documentation                                        │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+//! Timeouts armed using [`io_uring`]'s timeout operations, for applications whose I/O doesn't
+//! go through [`async_io`]'s reactor.
+//!
+//! A [`UringTimers`] owns an io_uring instance, on which every [`UringSleep`] submits a timeout
+//! operation, removed again if the sleep is dropped before it fires. A single thread waits for
+//! the completions of the instance and wakes the sleeps that fired, so that deadlines don't
+//! require [`async_io`]'s reactor at all.
+//!
+//! Only available on Linux, with the `io-uring` feature.
+//!
+//! ## Example
+//!
+//! ```rust
+//! use async_io::Timer;
+//! # use futures_lite::future;
+//! use smol_timeout::uring::UringTimers;
+//! use std::time::Duration;
+//!
+//! # future::block_on(async {
+//! #
+//! let timers = UringTimers::new().unwrap();
+//!
+//! let foo = async {
+//!     Timer::after(Duration::from_millis(250)).await;
+//!     24
+//! };
+//!
+//! assert_eq!(timers.timeout(foo, Duration::from_millis(100)).await, None);
+//!
+//! let bar = async {
+//!     Timer::after(Duration::from_millis(100)).await;
+//!     42
+//! };
+//!
+//! assert_eq!(timers.timeout(bar, Duration::from_millis(250)).await, Some(42));
+//! #
+//! # });
+//! ```
+
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                                          Imports                                           │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+use core::fmt;
+use core::future::Future;
+use core::pin::Pin;
+use core::task::{Context, Poll, Waker};
+use core::time::Duration;
+use io_uring::{opcode, squeue, types, IoUring};
+use pin_project_lite::pin_project;
+use std::io;
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::vec::Vec;
+
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                                       struct Driver                                        │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+/// The number of entries of the submission queue.
+const ENTRIES: u32 = 256;
+/// The user data of the operations whose completion is ignored.
+const IGNORED: u64 = u64::MAX;
+/// The user data of the operation asking the driver thread to exit.
+const SHUTDOWN: u64 = u64::MAX - 1;
+
+enum Slot {
+    Vacant,
+    Waiting(Option<Waker>),
+    Fired,
+    /// The sleep was dropped and its timeout is being removed.
+    Cancelled,
+}
+
+/// The slots of the pending timeouts, indexed by the user data of their operation. A slot is
+/// only reused once the completion of its timeout was received.
+struct Slots {
+    slots: Vec<Slot>,
+    free: Vec<usize>,
+}
+
+struct Driver {
+    ring: IoUring,
+    /// Serializes the accesses to the submission queue.
+    submission: Mutex<()>,
+    slots: Mutex<Slots>,
+}
+
+impl Driver {
+    fn push(&self, entry: squeue::Entry) -> io::Result<()> {
+        let _guard = self.submission.lock().unwrap();
+
+        loop {
+            // SAFETY: the submission queue is only accessed while `submission` is locked. The
+            // queue is synchronized when dropped.
+            let pushed = unsafe { self.ring.submission_shared().push(&entry).is_ok() };
+
+            // Submitting even if the queue was full, to make some room.
+            self.ring.submit()?;
+            if pushed {
+                return Ok(());
+            }
+        }
+    }
+
+    fn run(&self) {
+        loop {
+            if let Err(err) = self.ring.submitter().submit_and_wait(1) {
+                if err.kind() == io::ErrorKind::Interrupted {
+                    continue;
+                }
+
+                panic!("failed to wait for io_uring completions: {}", err);
+            }
+
+            let mut wakers = Vec::new();
+            let mut shutdown = false;
+            {
+                let mut slots = self.slots.lock().unwrap();
+
+                // SAFETY: the completion queue is only accessed by the driver thread.
+                for cqe in unsafe { self.ring.completion_shared() } {
+                    let key = match cqe.user_data() {
+                        IGNORED => continue,
+                        SHUTDOWN => {
+                            shutdown = true;
+                            continue;
+                        }
+                        key => key as usize,
+                    };
+
+                    match core::mem::replace(&mut slots.slots[key], Slot::Fired) {
+                        Slot::Waiting(waker) => wakers.extend(waker),
+                        Slot::Cancelled => {
+                            slots.slots[key] = Slot::Vacant;
+                            slots.free.push(key);
+                        }
+                        Slot::Vacant | Slot::Fired => unreachable!(),
+                    }
+                }
+            }
+
+            for waker in wakers {
+                waker.wake();
+            }
+
+            if shutdown {
+                return;
+            }
+        }
+    }
+}
+
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                                     struct UringTimers                                     │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+/// Asks the driver thread to exit once the timers and all their sleeps are dropped.
+struct Shared {
+    driver: Arc<Driver>,
+}
+
+impl Drop for Shared {
+    fn drop(&mut self) {
+        let shutdown = opcode::Nop::new().build().user_data(SHUTDOWN);
+        let _ = self.driver.push(shutdown);
+    }
+}
+
+/// A factory of timers armed using io_uring timeout operations, driven by a dedicated thread.
+///
+/// Cloning a [`UringTimers`] returns a new handle to the same io_uring instance.
+#[derive(Clone)]
+pub struct UringTimers {
+    shared: Arc<Shared>,
+}
+
+impl UringTimers {
+    /// Creates a new [`UringTimers`], setting up a new io_uring instance and spawning the thread
+    /// driving it, or returns an error if io_uring isn't supported or allowed.
+    pub fn new() -> io::Result<Self> {
+        let driver = Arc::new(Driver {
+            ring: IoUring::new(ENTRIES)?,
+            submission: Mutex::new(()),
+            slots: Mutex::new(Slots {
+                slots: Vec::new(),
+                free: Vec::new(),
+            }),
+        });
+
+        {
+            let driver = driver.clone();
+
+            thread::Builder::new()
+                .name("smol-timeout-uring".into())
+                .spawn(move || driver.run())?;
+        }
+
+        Ok(UringTimers {
+            shared: Arc::new(Shared { driver }),
+        })
+    }
+
+    /// Creates and returns a new [`UringSleep`] future that will complete after the provided
+    /// duration.
+    ///
+    /// ## Panics
+    ///
+    /// Panics if submitting the timeout operation fails.
+    pub fn sleep(&self, after: Duration) -> UringSleep {
+        let driver = &self.shared.driver;
+
+        let key = {
+            let mut slots = driver.slots.lock().unwrap();
+            match slots.free.pop() {
+                Some(key) => {
+                    slots.slots[key] = Slot::Waiting(None);
+                    key
+                }
+                None => {
+                    slots.slots.push(Slot::Waiting(None));
+                    slots.slots.len() - 1
+                }
+            }
+        };
+
+        // The kernel copies the timespec when the operation is submitted.
+        let timespec = types::Timespec::new()
+            .sec(after.as_secs())
+            .nsec(after.subsec_nanos());
+
+        let timeout = opcode::Timeout::new(&timespec)
+            .build()
+            .user_data(key as u64);
+
+        driver
+            .push(timeout)
+            .expect("failed to submit an io_uring timeout");
+
+        UringSleep {
+            shared: self.shared.clone(),
+            key,
+        }
+    }
+
+    /// Given a future and a [`Duration`], creates and returns a new [`UringTimeout`] that will
+    /// poll both the future and a [`UringSleep`] that will complete after the provided duration,
+    /// and return the future's output or [`None`] if the sleep completes first.
+    pub fn timeout<Fut: Future>(&self, future: Fut, after: Duration) -> UringTimeout<Fut> {
+        UringTimeout {
+            future,
+            sleep: self.sleep(after),
+        }
+    }
+}
+
+impl fmt::Debug for UringTimers {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        fmt.debug_struct("UringTimers").finish()
+    }
+}
+
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                                     struct UringSleep                                      │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+/// A future completing once its io_uring timeout operation completes.
+///
+/// Dropping a [`UringSleep`] before it completes removes its timeout operation.
+#[must_use = "futures do nothing unless you `.await` or poll them"]
+pub struct UringSleep {
+    shared: Arc<Shared>,
+    key: usize,
+}
+
+impl Future for UringSleep {
+    type Output = ();
+
+    fn poll(self: Pin<&mut Self>, ctx: &mut Context) -> Poll<()> {
+        let mut slots = self.shared.driver.slots.lock().unwrap();
+        match &mut slots.slots[self.key] {
+            Slot::Fired => Poll::Ready(()),
+            Slot::Waiting(waker) => {
+                match waker {
+                    Some(waker) if waker.will_wake(ctx.waker()) => (),
+                    _ => *waker = Some(ctx.waker().clone()),
+                }
+
+                Poll::Pending
+            }
+            Slot::Vacant | Slot::Cancelled => unreachable!(),
+        }
+    }
+}
+
+impl Drop for UringSleep {
+    fn drop(&mut self) {
+        let driver = &self.shared.driver;
+
+        {
+            let mut slots = driver.slots.lock().unwrap();
+            if let Slot::Fired = slots.slots[self.key] {
+                slots.slots[self.key] = Slot::Vacant;
+                slots.free.push(self.key);
+                return;
+            }
+
+            slots.slots[self.key] = Slot::Cancelled;
+        }
+
+        // The slot is freed once the timeout completes as cancelled (or fired).
+        let remove = opcode::TimeoutRemove::new(self.key as u64)
+            .build()
+            .user_data(IGNORED);
+
+        let _ = driver.push(remove);
+    }
+}
+
+impl fmt::Debug for UringSleep {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        fmt.debug_struct("UringSleep").finish()
+    }
+}
+
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                                  struct UringTimeout<Fut>                                  │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+pin_project! {
+    /// A future polling both another future and a [`UringSleep`], and returning the future's
+    /// output or [`None`] if the sleep completes first.
+    ///
+    /// Created by [`UringTimers::timeout`].
+    #[derive(Debug)]
+    #[must_use = "futures do nothing unless you `.await` or poll them"]
+    pub struct UringTimeout<Fut> {
+        #[pin]
+        future: Fut,
+        sleep: UringSleep,
+    }
+}
+
+impl<Fut: Future> Future for UringTimeout<Fut> {
+    type Output = Option<Fut::Output>;
+
+    fn poll(self: Pin<&mut Self>, ctx: &mut Context) -> Poll<Self::Output> {
+        let this = self.project();
+
+        if Pin::new(this.sleep).poll(ctx).is_ready() {
+            return Poll::Ready(None);
+        }
+
+        if let Poll::Ready(output) = this.future.poll(ctx) {
+            return Poll::Ready(Some(output));
+        }
+
+        Poll::Pending
+    }
+}
@@ -0,0 +1,89 @@
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                                                                                            │ *
+ * │ This Source Code Form is subject to the terms of the Mozilla Public                        │ *
+ * │ License, v. 2.0. If a copy of the MPL was not distributed with this                        │ *
+ * │ file, You can obtain one at http://mozilla.org/MPL/2.0/.                                   │ *
+ * │                                                                                            │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                                          Imports                                           │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+use core::sync::atomic::{AtomicU64, Ordering};
+use core::task::Waker;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::task::Wake;
+
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                                      struct WakerSet                                       │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+/// A set of wakers, used to share a single future or timer between multiple tasks: the shared
+/// future is polled with the set's own [`Waker`] (see [`WakerSet::waker`]), which wakes all the
+/// tasks registered in the set.
+///
+/// Registered wakers are removed once woken, so tasks need to register again every time they
+/// are polled.
+#[derive(Debug, Default)]
+pub(crate) struct WakerSet {
+    wakers: Mutex<HashMap<u64, Waker>>,
+    next_key: AtomicU64,
+}
+
+impl WakerSet {
+    /// Creates a new empty [`WakerSet`].
+    pub(crate) fn new() -> Arc<Self> {
+        Arc::new(WakerSet::default())
+    }
+
+    /// Returns a new key to register wakers with.
+    pub(crate) fn key(&self) -> u64 {
+        self.next_key.fetch_add(1, Ordering::Relaxed)
+    }
+
+    /// Registers a waker under the provided key, replacing any waker already registered under
+    /// it.
+    pub(crate) fn register(&self, key: u64, waker: &Waker) {
+        let mut wakers = self.wakers.lock().unwrap();
+        match wakers.get_mut(&key) {
+            Some(registered) => {
+                if !registered.will_wake(waker) {
+                    *registered = waker.clone();
+                }
+            }
+            None => {
+                wakers.insert(key, waker.clone());
+            }
+        }
+    }
+
+    /// Removes the waker registered under the provided key, if any.
+    pub(crate) fn remove(&self, key: u64) {
+        self.wakers.lock().unwrap().remove(&key);
+    }
+
+    /// Wakes and removes all the registered wakers.
+    pub(crate) fn wake_all(&self) {
+        let wakers = core::mem::take(&mut *self.wakers.lock().unwrap());
+        for waker in wakers.into_values() {
+            waker.wake();
+        }
+    }
+
+    /// Returns a [`Waker`] waking all the registered wakers.
+    pub(crate) fn waker(self: &Arc<Self>) -> Waker {
+        Waker::from(self.clone())
+    }
+}
+
+impl Wake for WakerSet {
+    fn wake(self: Arc<Self>) {
+        self.wake_all();
+    }
+
+    fn wake_by_ref(self: &Arc<Self>) {
+        self.wake_all();
+    }
+}
@@ -0,0 +1,340 @@
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                                                                                            │ *
+ * │ This Source Code Form is subject to the terms of the Mozilla Public                        │ *
+ * │ License, v. 2.0. If a copy of the MPL was not distributed with this                        │ *
+ * │ file, You can obtain one at http://mozilla.org/MPL/2.0/.                                   │ *
+ * │                                                                                            │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                                       Documentation                                        │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+//! Sleeps and intervals following the wall clock, for durations long enough that the monotonic
+//! clock drifts away from it.
+//!
+//! Sleeping for an hour on the monotonic clock doesn't mean that the wall clock moved by an hour:
+//! the two drift apart (e.g. while the clock is slewed by NTP, or while the system is
+//! suspended), and an interval that accumulates monotonic sleeps ends up firing minutes away
+//! from the wall-clock instants it was meant to. A [`WallInterval`] computes every tick from
+//! the wall clock instead (at a fixed wall-clock start plus a number of periods), while still
+//! sleeping on the monotonic [`Timer`]: it converts the time left on the wall clock into a
+//! monotonic sleep, and re-checks the wall clock when it wakes up, sleeping again if the tick
+//! isn't due yet. Long sleeps are also split to re-check the wall clock regularly, so that steps
+//! of the wall clock are picked up before the tick is due.
+//!
+//! The conversions between the two clocks are also available on their own: [`deadline_at`]
+//! and [`system_time_at`] convert between a [`SystemTime`] and the [`Instant`] it is expected
+//! at on the monotonic clock (for the current readings of both clocks), and a [`SleepUntil`]
+//! future sleeps until a [`SystemTime`], re-validating the wall clock whenever it wakes up.
+//!
+//! ## Example
+//!
+//! ```rust
+//! # use futures_lite::future;
+//! use futures_lite::StreamExt;
+//! use smol_timeout::wall;
+//! use std::time::{Duration, SystemTime};
+//!
+//! # future::block_on(async {
+//! #
+//! let start = SystemTime::now() + Duration::from_millis(100);
+//! let mut interval = wall::interval_at(start, Duration::from_millis(100));
+//!
+//! assert_eq!(interval.next().await, Some(start));
+//! assert_eq!(interval.next().await, Some(start + Duration::from_millis(100)));
+//! assert!(SystemTime::now() >= start + Duration::from_millis(100));
+//! #
+//! # });
+//! ```
+
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                                          Imports                                           │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+use crate::{clock, deadline, Timer};
+use core::convert::TryFrom;
+use core::future::Future;
+use core::pin::Pin;
+use core::task::{Context, Poll};
+use core::time::Duration;
+use futures_core::stream::Stream;
+use std::time::{Instant, SystemTime};
+
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                                        Conversions                                         │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+/// Returns the monotonic deadline at which the wall clock is expected to reach `target`, given
+/// the current readings of both clocks, or now if `target` is in the past.
+///
+/// The deadline is only an estimate, as the clocks can drift apart or the wall clock can be
+/// stepped before it is reached: see [`SleepUntil`] to re-validate it on wakeup.
+///
+/// ## Example
+///
+/// ```rust
+/// use smol_timeout::wall;
+/// use std::time::{Duration, Instant, SystemTime};
+///
+/// let target = SystemTime::now() + Duration::from_secs(60);
+/// let deadline = wall::deadline_at(target);
+///
+/// let remaining = deadline.saturating_duration_since(Instant::now());
+/// assert!(remaining > Duration::from_secs(59) && remaining <= Duration::from_secs(60));
+/// ```
+pub fn deadline_at(target: SystemTime) -> Instant {
+    let now = clock::now();
+    match target.duration_since(SystemTime::now()) {
+        Ok(wait) => deadline::deadline_after(now, wait),
+        Err(_) => now,
+    }
+}
+
+/// Returns the wall-clock instant corresponding to the monotonic `deadline`, given the current
+/// readings of both clocks.
+///
+/// This is the inverse of [`deadline_at`], with the same caveats.
+///
+/// ## Example
+///
+/// ```rust
+/// use smol_timeout::wall;
+/// use std::time::{Duration, Instant, SystemTime};
+///
+/// let ago = wall::system_time_at(Instant::now() - Duration::from_secs(60));
+///
+/// let elapsed = SystemTime::now().duration_since(ago).unwrap();
+/// assert!(elapsed >= Duration::from_secs(60) && elapsed < Duration::from_secs(61));
+/// ```
+pub fn system_time_at(deadline: Instant) -> SystemTime {
+    let now = clock::now();
+    let wall = SystemTime::now();
+
+    if deadline >= now {
+        wall + (deadline - now)
+    } else {
+        wall - (now - deadline)
+    }
+}
+
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                                     struct SleepUntil                                      │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+/// A future sleeping on the monotonic [`Timer`] until the wall clock reaches a target, and
+/// returning the wall-clock instant it woke up at.
+///
+/// Whenever its timer fires, the sleep re-checks the wall clock and sleeps again if the target
+/// isn't reached yet (e.g. because the wall clock was stepped back or is being slewed). Long
+/// sleeps are split so that the wall clock is re-checked at least once per resync duration,
+/// picking up steps of the wall clock (e.g. forward, or after the system was suspended) before
+/// the target.
+///
+/// Created by [`sleep_until`].
+///
+/// ## Example
+///
+/// ```rust
+/// # use futures_lite::future;
+/// use smol_timeout::wall;
+/// use std::time::{Duration, SystemTime};
+///
+/// # future::block_on(async {
+/// #
+/// let target = SystemTime::now() + Duration::from_millis(100);
+///
+/// let woke = wall::sleep_until(target).resync(Duration::from_millis(30)).await;
+/// assert!(woke >= target);
+/// #
+/// # });
+/// ```
+#[derive(Debug)]
+#[must_use = "futures do nothing unless you `.await` or poll them"]
+pub struct SleepUntil {
+    timer: Timer,
+    target: SystemTime,
+    resync: Duration,
+}
+
+impl SleepUntil {
+    /// The default longest duration slept before re-checking the wall clock.
+    pub const DEFAULT_RESYNC: Duration = Duration::from_secs(60);
+
+    /// Sets the longest duration slept on the monotonic clock before re-checking the wall clock,
+    /// which defaults to [`SleepUntil::DEFAULT_RESYNC`].
+    ///
+    /// ## Panics
+    ///
+    /// Panics if `resync` is zero.
+    pub fn resync(mut self, resync: Duration) -> Self {
+        assert!(
+            resync > Duration::from_secs(0),
+            "a sleep's resync duration can't be zero"
+        );
+
+        self.resync = resync;
+        self.arm();
+        self
+    }
+
+    /// Returns the wall-clock instant the sleep completes at.
+    pub fn target(&self) -> SystemTime {
+        self.target
+    }
+
+    /// Resets the sleep to complete at a new wall-clock target.
+    pub fn set(&mut self, target: SystemTime) {
+        self.target = target;
+        self.arm();
+    }
+
+    /// Sets the timer to the target, or to the next time the wall clock should be re-checked.
+    fn arm(&mut self) {
+        let deadline = deadline_at(self.target);
+        self.timer
+            .set_at(deadline.min(deadline::deadline_after(clock::now(), self.resync)));
+    }
+}
+
+/// Creates and returns a new [`SleepUntil`] future completing once the wall clock reaches
+/// `target`.
+pub fn sleep_until(target: SystemTime) -> SleepUntil {
+    let mut sleep = SleepUntil {
+        timer: Timer::never(),
+        target,
+        resync: SleepUntil::DEFAULT_RESYNC,
+    };
+
+    sleep.arm();
+    sleep
+}
+
+impl Future for SleepUntil {
+    type Output = SystemTime;
+
+    fn poll(self: Pin<&mut Self>, ctx: &mut Context) -> Poll<SystemTime> {
+        let this = self.get_mut();
+
+        loop {
+            if Pin::new(&mut this.timer).poll(ctx).is_pending() {
+                return Poll::Pending;
+            }
+
+            // The timer may fire before the target on the wall clock, e.g. after a clock step.
+            let now = SystemTime::now();
+            if now >= this.target {
+                return Poll::Ready(now);
+            }
+
+            this.arm();
+        }
+    }
+}
+
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                                    struct WallInterval                                     │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+/// A stream ticking at a wall-clock start and then once per period on the wall clock, and
+/// yielding the wall-clock instant every tick was due at.
+///
+/// Ticks missed by more than a period (e.g. while the system was suspended) are skipped: the
+/// interval yields the last one that was due, and then keeps ticking on its schedule.
+///
+/// Created by [`interval`] and [`interval_at`].
+#[derive(Debug)]
+#[must_use = "streams do nothing unless polled"]
+pub struct WallInterval {
+    /// A sleep until the next tick.
+    sleep: SleepUntil,
+    period: Duration,
+}
+
+impl WallInterval {
+    /// The default longest duration slept before re-checking the wall clock.
+    pub const DEFAULT_RESYNC: Duration = SleepUntil::DEFAULT_RESYNC;
+
+    /// Sets the longest duration slept on the monotonic clock before re-checking the wall clock,
+    /// which defaults to [`WallInterval::DEFAULT_RESYNC`].
+    ///
+    /// ## Panics
+    ///
+    /// Panics if `resync` is zero.
+    pub fn resync(mut self, resync: Duration) -> Self {
+        self.sleep = self.sleep.resync(resync);
+        self
+    }
+
+    /// Returns the period of the interval.
+    pub fn period(&self) -> Duration {
+        self.period
+    }
+
+    /// Returns the wall-clock instant the next tick is due at.
+    pub fn next_tick(&self) -> SystemTime {
+        self.sleep.target()
+    }
+}
+
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                                       fn interval()                                        │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+/// Creates and returns a new [`WallInterval`] ticking once per `period` on the wall clock,
+/// starting one period from now.
+///
+/// ## Panics
+///
+/// Panics if `period` is zero.
+pub fn interval(period: Duration) -> WallInterval {
+    interval_at(SystemTime::now() + period, period)
+}
+
+/// Creates and returns a new [`WallInterval`] ticking at `start` and then once per `period` on
+/// the wall clock.
+///
+/// ## Panics
+///
+/// Panics if `period` is zero.
+pub fn interval_at(start: SystemTime, period: Duration) -> WallInterval {
+    assert!(
+        period > Duration::from_secs(0),
+        "an interval's period can't be zero"
+    );
+
+    WallInterval {
+        sleep: sleep_until(start),
+        period,
+    }
+}
+
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                                impl Stream for WallInterval                                │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+impl Stream for WallInterval {
+    type Item = SystemTime;
+
+    fn poll_next(self: Pin<&mut Self>, ctx: &mut Context) -> Poll<Option<SystemTime>> {
+        let this = self.get_mut();
+
+        let now = match Pin::new(&mut this.sleep).poll(ctx) {
+            Poll::Ready(now) => now,
+            Poll::Pending => return Poll::Pending,
+        };
+
+        let due = this.sleep.target();
+        let late = now.duration_since(due).unwrap_or_default();
+        let missed = late.as_nanos() / this.period.as_nanos();
+
+        let periods = this.period.as_nanos() * (missed + 1);
+        this.sleep
+            .set(due + Duration::from_nanos(u64::try_from(periods).unwrap_or(u64::MAX)));
+
+        let skipped = this.period.as_nanos() * missed;
+        let tick = due + Duration::from_nanos(u64::try_from(skipped).unwrap_or(u64::MAX));
+
+        Poll::Ready(Some(tick))
+    }
+}
@@ -0,0 +1,237 @@
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                                                                                            │ *
+ * │ This Source Code Form is subject to the terms of the Mozilla Public                        │ *
+ * │ License, v. 2.0. If a copy of the MPL was not distributed with this                        │ *
+ * │ file, You can obtain one at http://mozilla.org/MPL/2.0/.                                   │ *
+ * │                                                                                            │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                                       Documentation                                        │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+//! A timer backend for WASI targets, on which `async-io`'s reactor isn't available.
+//!
+//! With the `wasi` feature, the timeouts of this crate use this module's [`Timer`] when
+//! compiled for WASI (preview 1 or preview 2), so that [`TimeoutExt`](crate::TimeoutExt) and
+//! the other timeouts work unchanged. The timers record their deadline, read from the monotonic
+//! clock, in a thread-local list, and are fired by [`block_on`], which waits for the earliest
+//! deadline with `poll_oneoff` (or the `wasi:clocks/monotonic-clock` subscription on preview 2)
+//! when its future can't make progress.
+//!
+//! The timers only fire while the thread they were created on is running [`block_on`].
+//!
+//! ## Example
+//!
+//! ```rust,ignore
+//! use smol_timeout::wasi::{self, Timer};
+//! use smol_timeout::TimeoutExt;
+//! use std::time::Duration;
+//!
+//! wasi::block_on(async {
+//!     let foo = async {
+//!         Timer::after(Duration::from_millis(250)).await;
+//!         24
+//!     };
+//!
+//!     assert_eq!(foo.timeout(Duration::from_millis(100)).await, None);
+//! });
+//! ```
+
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                                          Imports                                           │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+use crate::deadline;
+use core::cell::RefCell;
+use core::future::Future;
+use core::pin::Pin;
+use core::sync::atomic::{AtomicBool, Ordering};
+use core::task::{Context, Poll, Waker};
+use core::time::Duration;
+use std::collections::BTreeMap;
+use std::sync::Arc;
+use std::task::Wake;
+use std::thread;
+use std::thread_local;
+use std::time::Instant;
+use std::vec::Vec;
+
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                                       struct Reactor                                       │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+#[derive(Default)]
+struct Reactor {
+    /// The wakers of the registered timers, sorted by deadline.
+    timers: BTreeMap<(Instant, u64), Waker>,
+    next_key: u64,
+}
+
+thread_local! {
+    static REACTOR: RefCell<Reactor> = RefCell::new(Reactor::default());
+}
+
+impl Reactor {
+    /// Wakes and removes the timers whose deadline elapsed, and returns the earliest deadline of
+    /// the remaining ones.
+    fn fire(&mut self, now: Instant) -> Option<Instant> {
+        let mut wakers = Vec::new();
+        while let Some(entry) = self.timers.first_entry() {
+            if entry.key().0 > now {
+                break;
+            }
+
+            wakers.push(entry.remove());
+        }
+
+        for waker in wakers {
+            waker.wake();
+        }
+
+        self.timers.keys().next().map(|(deadline, _)| *deadline)
+    }
+}
+
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                                        struct Timer                                        │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+/// A future completing at a deadline, mirroring the API of `async-io`'s `Timer`.
+#[derive(Debug)]
+#[must_use = "futures do nothing unless you `.await` or poll them"]
+pub struct Timer {
+    deadline: Option<Instant>,
+    /// The key under which the timer is registered, if it is.
+    key: Option<u64>,
+}
+
+impl Timer {
+    /// Creates a timer that never completes.
+    pub fn never() -> Self {
+        Timer {
+            deadline: None,
+            key: None,
+        }
+    }
+
+    /// Creates a timer completing after the provided duration.
+    pub fn after(after: Duration) -> Self {
+        Timer::at(deadline::deadline_after(Instant::now(), after))
+    }
+
+    /// Creates a timer completing at the provided deadline.
+    pub fn at(deadline: Instant) -> Self {
+        Timer {
+            deadline: Some(deadline),
+            key: None,
+        }
+    }
+
+    /// Resets the timer to complete after the provided duration.
+    pub fn set_after(&mut self, after: Duration) {
+        self.set_at(deadline::deadline_after(Instant::now(), after));
+    }
+
+    /// Resets the timer to complete at the provided deadline.
+    pub fn set_at(&mut self, deadline: Instant) {
+        self.deregister();
+        self.deadline = Some(deadline);
+    }
+
+    fn deregister(&mut self) {
+        if let (Some(deadline), Some(key)) = (self.deadline, self.key.take()) {
+            let _ =
+                REACTOR.try_with(|reactor| reactor.borrow_mut().timers.remove(&(deadline, key)));
+        }
+    }
+}
+
+impl Future for Timer {
+    type Output = Instant;
+
+    fn poll(mut self: Pin<&mut Self>, ctx: &mut Context) -> Poll<Instant> {
+        let deadline = match self.deadline {
+            Some(deadline) => deadline,
+            None => return Poll::Pending,
+        };
+
+        let now = Instant::now();
+        if now >= deadline {
+            self.deregister();
+            return Poll::Ready(now);
+        }
+
+        let key = self.key;
+        self.key = Some(REACTOR.with(|reactor| {
+            let mut reactor = reactor.borrow_mut();
+            let key = key.unwrap_or_else(|| {
+                reactor.next_key += 1;
+                reactor.next_key
+            });
+
+            reactor.timers.insert((deadline, key), ctx.waker().clone());
+            key
+        }));
+
+        Poll::Pending
+    }
+}
+
+impl Drop for Timer {
+    fn drop(&mut self) {
+        self.deregister();
+    }
+}
+
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                                       fn block_on()                                        │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+struct Unparker {
+    woken: AtomicBool,
+}
+
+impl Wake for Unparker {
+    fn wake(self: Arc<Self>) {
+        self.wake_by_ref();
+    }
+
+    fn wake_by_ref(self: &Arc<Self>) {
+        self.woken.store(true, Ordering::Release);
+    }
+}
+
+/// Runs a future to completion on the current thread, firing the timers of this thread and
+/// sleeping until the earliest of their deadlines whenever the future can't make progress.
+pub fn block_on<Fut: Future>(future: Fut) -> Fut::Output {
+    let mut future = core::pin::pin!(future);
+
+    let unparker = Arc::new(Unparker {
+        woken: AtomicBool::new(true),
+    });
+    let waker = Waker::from(unparker.clone());
+    let mut ctx = Context::from_waker(&waker);
+
+    loop {
+        if unparker.woken.swap(false, Ordering::AcqRel) {
+            if let Poll::Ready(output) = future.as_mut().poll(&mut ctx) {
+                return output;
+            }
+
+            continue;
+        }
+
+        let earliest = REACTOR.with(|reactor| reactor.borrow_mut().fire(Instant::now()));
+        if unparker.woken.load(Ordering::Acquire) {
+            continue;
+        }
+
+        match earliest {
+            // Sleeping uses the monotonic clock subscription of `poll_oneoff`.
+            Some(deadline) => thread::sleep(crate::deadline::remaining_until(deadline)),
+            // Nothing can wake the future anymore.
+            None => thread::park(),
+        }
+    }
+}
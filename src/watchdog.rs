@@ -0,0 +1,219 @@
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                                                                                            │ *
+ * │ This Source Code Form is subject to the terms of the Mozilla Public                        │ *
+ * │ License, v. 2.0. If a copy of the MPL was not distributed with this                        │ *
+ * │ file, You can obtain one at http://mozilla.org/MPL/2.0/.                                   │ *
+ * │                                                                                            │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                                       Documentation                                        │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+//! A watchdog running a whole program's main future and aborting the process if it stops
+//! ticking a [`Heartbeat`].
+//!
+//! The watchdog runs on its own thread, so that it fires even when the executor running the main
+//! future is completely stalled (e.g. because a task is blocking it), which a [`Timer`]-based
+//! timeout can't detect.
+//!
+//! [`Timer`]: async_io::Timer
+//!
+//! ## Example
+//!
+//! ```rust
+//! use async_io::Timer;
+//! use smol_timeout::watchdog::{self, Heartbeat};
+//! use std::time::Duration;
+//!
+//! let heartbeat = Heartbeat::new();
+//!
+//! let main = {
+//!     let heartbeat = heartbeat.clone();
+//!
+//!     async move {
+//!         for _ in 0..3 {
+//!             Timer::after(Duration::from_millis(50)).await;
+//!             heartbeat.tick();
+//!         }
+//!
+//!         42
+//!     }
+//! };
+//!
+//! let output = watchdog::run_with_watchdog(main, heartbeat, Duration::from_millis(250));
+//! assert_eq!(output, 42);
+//! ```
+
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                                          Imports                                           │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+use crate::clock::Stamp;
+use core::future::Future;
+use core::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use core::time::Duration;
+use std::eprintln;
+use std::process;
+use std::sync::Arc;
+use std::thread;
+
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                                      struct Heartbeat                                      │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+/// A heartbeat that a future watched by a watchdog must [`tick`](Heartbeat::tick) periodically.
+///
+/// Cloning a [`Heartbeat`] returns a new handle to the same heartbeat.
+#[derive(Debug, Clone)]
+pub struct Heartbeat {
+    inner: Arc<Inner>,
+}
+
+#[derive(Debug)]
+struct Inner {
+    start: Stamp,
+    /// The number of nanoseconds between `start` and the last tick.
+    last: AtomicU64,
+}
+
+impl Heartbeat {
+    /// Creates a new [`Heartbeat`], considered to have ticked when it is created.
+    pub fn new() -> Self {
+        Heartbeat {
+            inner: Arc::new(Inner {
+                start: Stamp::now(),
+                last: AtomicU64::new(0),
+            }),
+        }
+    }
+
+    /// Ticks the heartbeat, signalling any watchdog watching it that progress is being made.
+    pub fn tick(&self) {
+        let nanos = self.inner.start.elapsed().as_nanos() as u64;
+        self.inner.last.fetch_max(nanos, Ordering::Relaxed);
+    }
+
+    /// Returns the duration since the heartbeat last ticked.
+    pub fn since_last_tick(&self) -> Duration {
+        let last = Duration::from_nanos(self.inner.last.load(Ordering::Relaxed));
+        self.inner.start.elapsed().saturating_sub(last)
+    }
+}
+
+impl Default for Heartbeat {
+    fn default() -> Self {
+        Heartbeat::new()
+    }
+}
+
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                                   fn run_with_watchdog()                                   │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+/// Blocks the current thread on `main` (using [`async_io::block_on`]) while a watchdog thread
+/// aborts the process if `heartbeat` doesn't tick for longer than `limit`, and returns `main`'s
+/// output.
+///
+/// See [`run_with_watchdog_handler`] to invoke a handler instead of aborting the process.
+///
+/// ## Example
+///
+/// ```rust
+/// use futures_lite::future;
+/// use smol_timeout::watchdog::{self, Heartbeat};
+/// use std::time::Duration;
+///
+/// let heartbeat = Heartbeat::new();
+///
+/// let output = watchdog::run_with_watchdog(future::ready(42), heartbeat, Duration::from_secs(1));
+/// assert_eq!(output, 42);
+/// ```
+pub fn run_with_watchdog<Fut: Future>(
+    main: Fut,
+    heartbeat: Heartbeat,
+    limit: Duration,
+) -> Fut::Output {
+    run_with_watchdog_handler(main, heartbeat, limit, |stalled| {
+        eprintln!(
+            "watchdog: heartbeat didn't tick for {:?}, aborting",
+            stalled
+        );
+        process::abort();
+    })
+}
+
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                               fn run_with_watchdog_handler()                               │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+/// Blocks the current thread on `main` (using [`async_io::block_on`]) while a watchdog thread
+/// invokes `handler` if `heartbeat` doesn't tick for longer than `limit`, and returns `main`'s
+/// output.
+///
+/// `handler` is invoked at most once, on the watchdog thread, with the duration since the
+/// heartbeat last ticked. The watchdog stops watching after invoking it, while `main` keeps
+/// running.
+///
+/// ## Example
+///
+/// ```rust
+/// use std::sync::mpsc;
+/// use smol_timeout::watchdog::{self, Heartbeat};
+/// use std::time::Duration;
+///
+/// let heartbeat = Heartbeat::new();
+/// let (sender, receiver) = mpsc::channel();
+///
+/// let main = async {
+///     // Blocks the executor, which a watchdog detects.
+///     std::thread::sleep(Duration::from_millis(250));
+/// };
+///
+/// watchdog::run_with_watchdog_handler(main, heartbeat, Duration::from_millis(100), move |_| {
+///     sender.send(()).unwrap();
+/// });
+///
+/// assert!(receiver.try_recv().is_ok());
+/// ```
+pub fn run_with_watchdog_handler<Fut, H>(
+    main: Fut,
+    heartbeat: Heartbeat,
+    limit: Duration,
+    handler: H,
+) -> Fut::Output
+where
+    Fut: Future,
+    H: FnOnce(Duration) + Send + 'static,
+{
+    let done = Arc::new(AtomicBool::new(false));
+
+    let watchdog = {
+        let done = done.clone();
+
+        thread::Builder::new()
+            .name("smol-timeout-watchdog".into())
+            .spawn(move || loop {
+                let stalled = heartbeat.since_last_tick();
+                if done.load(Ordering::Acquire) {
+                    return;
+                }
+
+                if stalled >= limit {
+                    handler(stalled);
+                    return;
+                }
+
+                thread::park_timeout(limit - stalled);
+            })
+            .expect("failed to spawn the watchdog thread")
+    };
+
+    let output = crate::block_on(main);
+
+    done.store(true, Ordering::Release);
+    watchdog.thread().unpark();
+    let _ = watchdog.join();
+
+    output
+}
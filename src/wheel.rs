@@ -0,0 +1,678 @@
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                                                                                            │ *
+ * │ This Source Code Form is subject to the terms of the Mozilla Public                        │ *
+ * │ License, v. 2.0. If a copy of the MPL was not distributed with this                        │ *
+ * │ file, You can obtain one at http://mozilla.org/MPL/2.0/.                                   │ *
+ * │                                                                                            │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                                       Documentation                                        │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+//! A hierarchical timer wheel, for applications with large numbers of concurrent timeouts.
+//!
+//! Every [`Timer`](async_io::Timer) is registered with the reactor of [`async_io`], which keeps
+//! them sorted in a single map. A [`TimerWheel`] instead keeps its timers in buckets of ticks,
+//! where inserting and cancelling a timer are constant-time operations, and is driven by a
+//! single thread only waking up on the ticks of its occupied buckets.
+//!
+//! The trade off is precision: a wheel's timers complete on the first tick following their
+//! deadline, so up to one tick late.
+//!
+//! ## Example
+//!
+//! ```rust
+//! use async_io::Timer;
+//! # use futures_lite::future;
+//! use smol_timeout::wheel::TimerWheel;
+//! use std::time::Duration;
+//!
+//! # future::block_on(async {
+//! #
+//! let wheel = TimerWheel::new(Duration::from_millis(10));
+//!
+//! let foo = async {
+//!     Timer::after(Duration::from_millis(250)).await;
+//!     24
+//! };
+//!
+//! assert_eq!(wheel.timeout(foo, Duration::from_millis(100)).await, None);
+//!
+//! let bar = async {
+//!     Timer::after(Duration::from_millis(100)).await;
+//!     42
+//! };
+//!
+//! assert_eq!(wheel.timeout(bar, Duration::from_millis(250)).await, Some(42));
+//! #
+//! # });
+//! ```
+
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                                          Imports                                           │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+use crate::{clock, deadline, DeadlineSource, Timeout};
+use core::fmt;
+use core::future::Future;
+use core::pin::Pin;
+use core::task::{Context, Poll, Waker};
+use core::time::Duration;
+use std::sync::{Arc, Mutex, OnceLock, Weak};
+use std::thread::{self, Thread};
+use std::time::Instant;
+use std::vec::Vec;
+
+#[cfg(all(feature = "sim", not(target_os = "wasi")))]
+use crate::sim;
+
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                                        struct Wheel                                        │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+/// The number of bits of a tick used to index the slots of a level.
+const SLOT_BITS: u32 = 6;
+/// The number of slots of a level.
+const SLOTS: usize = 1 << SLOT_BITS;
+/// The number of levels, each covering [`SLOTS`] times more ticks than the previous one.
+const LEVELS: usize = 6;
+/// The index used as a null link.
+const NIL: usize = usize::MAX;
+
+struct Entry {
+    /// The tick on which the entry completes.
+    deadline: u64,
+    waker: Option<Waker>,
+    fired: bool,
+    /// The slot (as `level * SLOTS + slot`) whose list contains the entry, or [`NIL`].
+    slot: usize,
+    prev: usize,
+    next: usize,
+}
+
+/// The state of a [`TimerWheel`], where entries live in a slab and are linked into per-slot
+/// doubly linked lists, so that they can be inserted and removed in constant time.
+struct Wheel {
+    /// The last tick that was processed.
+    now: u64,
+    entries: Vec<Entry>,
+    free: Vec<usize>,
+    /// The heads of the lists of each slot of each level.
+    heads: Vec<usize>,
+    /// The slots of each level whose list isn't empty, as one bit per slot.
+    occupied: [u64; LEVELS],
+    /// The number of entries that haven't fired yet.
+    waiting: usize,
+    /// The tick until which the driver is parked ([`u64::MAX`] if until it is unparked).
+    parked: u64,
+}
+
+impl Wheel {
+    fn new() -> Self {
+        Wheel {
+            now: 0,
+            entries: Vec::new(),
+            free: Vec::new(),
+            heads: std::vec![NIL; LEVELS * SLOTS],
+            occupied: [0; LEVELS],
+            waiting: 0,
+            parked: u64::MAX,
+        }
+    }
+
+    /// Inserts a new entry completing on the provided tick, and returns its key.
+    fn insert(&mut self, deadline: u64) -> usize {
+        let entry = Entry {
+            deadline,
+            waker: None,
+            fired: false,
+            slot: NIL,
+            prev: NIL,
+            next: NIL,
+        };
+
+        let key = match self.free.pop() {
+            Some(key) => {
+                self.entries[key] = entry;
+                key
+            }
+            None => {
+                self.entries.push(entry);
+                self.entries.len() - 1
+            }
+        };
+
+        self.waiting += 1;
+        self.schedule(key);
+        key
+    }
+
+    /// Removes an entry, whether it fired or not.
+    fn remove(&mut self, key: usize) {
+        if !self.entries[key].fired {
+            self.unlink(key);
+            self.waiting -= 1;
+        }
+
+        self.entries[key].waker = None;
+        self.free.push(key);
+    }
+
+    /// Links a waiting entry into the slot matching its deadline, or fires it if its deadline
+    /// was already processed.
+    fn schedule(&mut self, key: usize) -> Option<Waker> {
+        let deadline = self.entries[key].deadline;
+        if deadline <= self.now {
+            return self.fire(key);
+        }
+
+        // The level is the one of the most significant group of bits that differs between the
+        // deadline and the current tick.
+        let significant = 63 - ((deadline ^ self.now) | (SLOTS as u64 - 1)).leading_zeros();
+        let level = (significant / SLOT_BITS) as usize;
+
+        let slot = if level < LEVELS {
+            level * SLOTS + ((deadline >> (level as u32 * SLOT_BITS)) as usize & (SLOTS - 1))
+        } else {
+            // Too far away: parking the entry in the last slot of the last level to be
+            // processed, from which it will be rescheduled.
+            let level = LEVELS - 1;
+            let current = (self.now >> (level as u32 * SLOT_BITS)) as usize;
+            level * SLOTS + (current.wrapping_sub(1) & (SLOTS - 1))
+        };
+
+        let head = self.heads[slot];
+        if head != NIL {
+            self.entries[head].prev = key;
+        }
+
+        let entry = &mut self.entries[key];
+        entry.slot = slot;
+        entry.prev = NIL;
+        entry.next = head;
+        self.heads[slot] = key;
+        self.occupied[slot / SLOTS] |= 1 << (slot % SLOTS);
+
+        None
+    }
+
+    fn unlink(&mut self, key: usize) {
+        let Entry {
+            slot, prev, next, ..
+        } = self.entries[key];
+
+        if prev == NIL {
+            self.heads[slot] = next;
+            if next == NIL {
+                self.occupied[slot / SLOTS] &= !(1 << (slot % SLOTS));
+            }
+        } else {
+            self.entries[prev].next = next;
+        }
+
+        if next != NIL {
+            self.entries[next].prev = prev;
+        }
+
+        self.entries[key].slot = NIL;
+    }
+
+    fn fire(&mut self, key: usize) -> Option<Waker> {
+        let entry = &mut self.entries[key];
+        entry.fired = true;
+        entry.slot = NIL;
+        self.waiting -= 1;
+        entry.waker.take()
+    }
+
+    /// Unlinks all the entries of a slot, and returns the head of their list.
+    fn take(&mut self, slot: usize) -> usize {
+        let head = self.heads[slot];
+        self.heads[slot] = NIL;
+        self.occupied[slot / SLOTS] &= !(1 << (slot % SLOTS));
+        head
+    }
+
+    /// Returns the first tick after the current one on which an occupied slot is processed,
+    /// either firing its entries or rescheduling them to a lower level, or [`None`] if the wheel
+    /// has no entries. Nothing happens on the ticks before it.
+    fn next_tick(&self) -> Option<u64> {
+        let mut next = None;
+        for level in 0..LEVELS {
+            let occupied = self.occupied[level];
+            if occupied == 0 {
+                continue;
+            }
+
+            // The slots of a level are processed in turn, once per rotation of the level.
+            let shift = level as u32 * SLOT_BITS;
+            let current = (self.now >> shift) as u32 & (SLOTS as u32 - 1);
+            let ahead = occupied.rotate_right((current + 1) % SLOTS as u32);
+            let distance = u64::from(ahead.trailing_zeros()) + 1;
+
+            let tick = ((self.now >> shift) + distance) << shift;
+            next = Some(next.map_or(tick, |next: u64| next.min(tick)));
+        }
+
+        next
+    }
+
+    /// Processes all the ticks up to `now`, jumping over the ticks on which nothing happens,
+    /// and pushing the wakers of the entries that fired.
+    fn advance(&mut self, now: u64, wakers: &mut Vec<Waker>) {
+        while self.now < now {
+            let tick = match self.next_tick() {
+                Some(tick) if tick <= now => tick,
+                _ => {
+                    self.now = now;
+                    return;
+                }
+            };
+
+            self.now = tick;
+
+            // Rescheduling the entries of the higher levels whose slot is reached, starting
+            // with the highest.
+            for level in (1..LEVELS).rev() {
+                let shift = level as u32 * SLOT_BITS;
+                if tick & ((1 << shift) - 1) != 0 {
+                    continue;
+                }
+
+                let slot = level * SLOTS + ((tick >> shift) as usize & (SLOTS - 1));
+                let mut key = self.take(slot);
+                while key != NIL {
+                    let next = self.entries[key].next;
+                    wakers.extend(self.schedule(key));
+                    key = next;
+                }
+            }
+
+            let mut key = self.take(tick as usize & (SLOTS - 1));
+            while key != NIL {
+                let next = self.entries[key].next;
+                wakers.extend(self.fire(key));
+                key = next;
+            }
+        }
+    }
+}
+
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                                     struct TimerWheel                                      │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+struct Inner {
+    start: Instant,
+    tick: Duration,
+    wheel: Mutex<Wheel>,
+    driver: OnceLock<Thread>,
+    /// The simulated clock the wheel follows if it was created inside a simulation, in which
+    /// case it isn't driven by a thread but by its sleeps (see [`Sleep::poll`]).
+    #[cfg(all(feature = "sim", not(target_os = "wasi")))]
+    clock: Option<sim::Clock>,
+}
+
+impl Inner {
+    /// Returns the current instant on the clock the wheel follows.
+    fn now(&self) -> Instant {
+        #[cfg(all(feature = "sim", not(target_os = "wasi")))]
+        if let Some(clock) = &self.clock {
+            return clock.now();
+        }
+
+        clock::now()
+    }
+
+    /// Returns whether the wheel follows a simulated clock, and thus has no driver thread.
+    fn is_simulated(&self) -> bool {
+        #[cfg(all(feature = "sim", not(target_os = "wasi")))]
+        return self.clock.is_some();
+
+        #[cfg(not(all(feature = "sim", not(target_os = "wasi"))))]
+        false
+    }
+
+    /// Returns the first tick that is at or after `deadline`, so that entries never complete
+    /// before their deadline.
+    fn tick_of(&self, deadline: Instant) -> u64 {
+        let since = deadline.saturating_duration_since(self.start);
+        let tick = self.tick.as_nanos();
+        since.as_nanos().div_ceil(tick) as u64
+    }
+
+    /// Returns the last tick that is at or before now.
+    fn current_tick(&self) -> u64 {
+        let since = self.now().saturating_duration_since(self.start);
+        (since.as_nanos() / self.tick.as_nanos()) as u64
+    }
+
+    /// Inserts a new entry completing on the first tick following `deadline`, spawning or waking
+    /// up the driver if needed, and returns its key.
+    fn register(self: &Arc<Self>, deadline: Instant) -> usize {
+        let tick = self.tick_of(deadline);
+
+        let (key, wake) = {
+            let mut wheel = self.wheel.lock().unwrap();
+
+            // The driver only needs to be woken up if it is parked past the new entry's slot.
+            let key = wheel.insert(tick);
+            let wake = wheel.next_tick().is_some_and(|next| next < wheel.parked);
+            (key, wake)
+        };
+
+        if self.is_simulated() {
+            return key;
+        }
+
+        let driver = self.driver.get_or_init(|| spawn_driver(self));
+        if wake {
+            driver.unpark();
+        }
+
+        key
+    }
+}
+
+impl Drop for Inner {
+    fn drop(&mut self) {
+        // Letting the driver thread notice that the wheel is gone.
+        if let Some(driver) = self.driver.get() {
+            driver.unpark();
+        }
+    }
+}
+
+/// A hierarchical timer wheel, whose timers complete on the first tick following their
+/// deadline.
+///
+/// The wheel is driven by a thread, spawned the first time a timer is created, which sleeps
+/// until the next tick on which one of its timers completes (or is moved closer to completing,
+/// for timers far away), and exits once the wheel and all its timers are dropped. A wheel created inside a simulation (with the `sim` feature,
+/// see [`crate::sim`]) follows its virtual clock instead, and is advanced by its sleeps.
+///
+/// Cloning a [`TimerWheel`] returns a new handle to the same wheel.
+#[derive(Clone)]
+pub struct TimerWheel {
+    inner: Arc<Inner>,
+}
+
+impl TimerWheel {
+    /// Creates a new [`TimerWheel`] with the provided tick duration.
+    ///
+    /// ## Panics
+    ///
+    /// Panics if `tick` is zero.
+    pub fn new(tick: Duration) -> Self {
+        assert!(
+            tick > Duration::from_secs(0),
+            "a wheel's tick can't be zero"
+        );
+
+        TimerWheel {
+            inner: Arc::new(Inner {
+                start: clock::now(),
+                tick,
+                wheel: Mutex::new(Wheel::new()),
+                driver: OnceLock::new(),
+                #[cfg(all(feature = "sim", not(target_os = "wasi")))]
+                clock: sim::Clock::current(),
+            }),
+        }
+    }
+
+    /// Returns the duration of a tick of the wheel.
+    pub fn tick(&self) -> Duration {
+        self.inner.tick
+    }
+
+    /// Returns the number of timers of the wheel that didn't complete yet.
+    pub fn len(&self) -> usize {
+        self.inner.wheel.lock().unwrap().waiting
+    }
+
+    /// Returns `true` if all the timers of the wheel completed.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Creates and returns a new [`Sleep`] future that will complete on the first tick following
+    /// the provided duration.
+    pub fn sleep(&self, after: Duration) -> Sleep {
+        self.sleep_until(deadline::deadline_after(self.inner.now(), after))
+    }
+
+    /// Creates and returns a new [`Sleep`] future that will complete on the first tick following
+    /// the provided deadline.
+    pub fn sleep_until(&self, deadline: Instant) -> Sleep {
+        Sleep {
+            key: self.inner.register(deadline),
+            inner: self.inner.clone(),
+            deadline,
+            #[cfg(all(feature = "sim", not(target_os = "wasi")))]
+            timer: None,
+        }
+    }
+
+    /// Given a future and a [`Duration`], creates and returns a new [`WheelTimeout`] that will
+    /// poll both the future and a [`Sleep`] of this wheel that will complete after the provided
+    /// duration, and return the future's output or [`None`] if the sleep completes first.
+    #[track_caller]
+    pub fn timeout<Fut: Future>(&self, future: Fut, after: Duration) -> WheelTimeout<Fut> {
+        Timeout::armed(future, self.sleep(after), after)
+    }
+}
+
+impl fmt::Debug for TimerWheel {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        fmt.debug_struct("TimerWheel")
+            .field("tick", &self.inner.tick)
+            .field("len", &self.len())
+            .finish()
+    }
+}
+
+/// Spawns the thread driving a wheel, which only keeps a weak reference to it.
+fn spawn_driver(inner: &Arc<Inner>) -> Thread {
+    let inner = Arc::downgrade(inner);
+
+    thread::Builder::new()
+        .name("smol-timeout-wheel".into())
+        .spawn(move || drive(inner))
+        .expect("failed to spawn the timer wheel thread")
+        .thread()
+        .clone()
+}
+
+fn drive(inner: Weak<Inner>) {
+    let mut wakers = Vec::new();
+
+    loop {
+        let inner = match inner.upgrade() {
+            Some(inner) => inner,
+            None => return,
+        };
+
+        let now = inner.current_tick();
+        let next = {
+            let mut wheel = inner.wheel.lock().unwrap();
+            wheel.advance(now, &mut wakers);
+
+            // Parking until the next tick on which something happens, however far away.
+            let next = wheel.next_tick();
+            wheel.parked = next.unwrap_or(u64::MAX);
+            next
+        };
+
+        for waker in wakers.drain(..) {
+            waker.wake();
+        }
+
+        match next {
+            Some(next) => {
+                let ticks = (inner.tick.as_nanos() as u64).saturating_mul(next);
+                let next = deadline::deadline_after(inner.start, Duration::from_nanos(ticks));
+                let sleep = deadline::remaining_until(next);
+                drop(inner);
+                thread::park_timeout(sleep);
+            }
+            None => {
+                drop(inner);
+                thread::park();
+            }
+        }
+    }
+}
+
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                                        struct Sleep                                        │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+/// A future completing on the first tick of a [`TimerWheel`] following a deadline.
+///
+/// A [`Sleep`] is a [`DeadlineSource`]: rearming it moves it to another slot of its wheel, and
+/// forking it registers a new sleep with the same wheel. Dropping a [`Sleep`] removes it from its
+/// wheel.
+#[must_use = "futures do nothing unless you `.await` or poll them"]
+pub struct Sleep {
+    inner: Arc<Inner>,
+    key: usize,
+    deadline: Instant,
+    /// The simulated timer firing on the tick the sleep completes on, if its wheel follows a
+    /// simulated clock.
+    #[cfg(all(feature = "sim", not(target_os = "wasi")))]
+    timer: Option<sim::Timer>,
+}
+
+impl Sleep {
+    /// Advances a wheel following a simulated clock up to its current tick, and returns whether
+    /// the sleep completed, once its simulated timer fired if it didn't.
+    #[cfg(all(feature = "sim", not(target_os = "wasi")))]
+    fn poll_simulated(&mut self, clock: &sim::Clock, ctx: &mut Context) -> bool {
+        let mut wakers = Vec::new();
+        let (fired, tick) = {
+            let mut wheel = self.inner.wheel.lock().unwrap();
+            wheel.advance(self.inner.current_tick(), &mut wakers);
+
+            let entry = &wheel.entries[self.key];
+            (entry.fired, entry.deadline)
+        };
+
+        for waker in wakers.drain(..) {
+            waker.wake();
+        }
+
+        if fired {
+            return true;
+        }
+
+        let inner = &self.inner;
+        let timer = self.timer.get_or_insert_with(|| {
+            let nanos = (inner.tick.as_nanos() as u64).saturating_mul(tick);
+            clock.timer_at(deadline::deadline_after(
+                inner.start,
+                Duration::from_nanos(nanos),
+            ))
+        });
+
+        if Pin::new(timer).poll(ctx).is_pending() {
+            return false;
+        }
+
+        let mut wheel = self.inner.wheel.lock().unwrap();
+        wheel.advance(self.inner.current_tick(), &mut wakers);
+        drop(wheel);
+
+        for waker in wakers {
+            waker.wake();
+        }
+
+        true
+    }
+}
+
+impl Future for Sleep {
+    type Output = ();
+
+    fn poll(self: Pin<&mut Self>, ctx: &mut Context) -> Poll<()> {
+        // Without a driver thread, a wheel following a simulated clock is advanced by its sleeps,
+        // which are woken up by simulated timers firing on the ticks they complete on.
+        #[cfg(all(feature = "sim", not(target_os = "wasi")))]
+        if let Some(clock) = self.inner.clock.clone() {
+            let this = self.get_mut();
+            return match this.poll_simulated(&clock, ctx) {
+                true => Poll::Ready(()),
+                false => Poll::Pending,
+            };
+        }
+
+        let mut wheel = self.inner.wheel.lock().unwrap();
+        let entry = &mut wheel.entries[self.key];
+        if entry.fired {
+            return Poll::Ready(());
+        }
+
+        match &entry.waker {
+            Some(waker) if waker.will_wake(ctx.waker()) => (),
+            _ => entry.waker = Some(ctx.waker().clone()),
+        }
+
+        Poll::Pending
+    }
+}
+
+impl DeadlineSource for Sleep {
+    fn now(&self) -> Instant {
+        self.inner.now()
+    }
+
+    fn deadline(&self) -> Option<Instant> {
+        Some(self.deadline)
+    }
+
+    fn set_at(&mut self, at: Instant) {
+        self.inner.wheel.lock().unwrap().remove(self.key);
+        self.key = self.inner.register(at);
+        self.deadline = at;
+
+        #[cfg(all(feature = "sim", not(target_os = "wasi")))]
+        {
+            self.timer = None;
+        }
+    }
+
+    fn fork(&self) -> Self {
+        Sleep {
+            key: self.inner.register(self.deadline),
+            inner: self.inner.clone(),
+            deadline: self.deadline,
+            #[cfg(all(feature = "sim", not(target_os = "wasi")))]
+            timer: None,
+        }
+    }
+}
+
+impl Drop for Sleep {
+    fn drop(&mut self) {
+        self.inner.wheel.lock().unwrap().remove(self.key);
+    }
+}
+
+impl fmt::Debug for Sleep {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        fmt.debug_struct("Sleep")
+            .field("deadline", &self.deadline)
+            .finish()
+    }
+}
+
+/* ┌────────────────────────────────────────────────────────────────────────────────────────────┐ *\
+ * │                                   type WheelTimeout<Fut>                                   │ *
+\* └────────────────────────────────────────────────────────────────────────────────────────────┘ */
+
+/// A [`Timeout`] whose deadline is a [`Sleep`] of a [`TimerWheel`], and which can thus be
+/// shortened, restarted and cloned like any other.
+///
+/// Created by [`TimerWheel::timeout`].
+pub type WheelTimeout<Fut> = Timeout<Fut, Sleep>;